@@ -0,0 +1,343 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use russh::keys::PrivateKeyWithHashAlg;
+use russh::keys::ssh_key::private::Ed25519Keypair;
+use russh::keys::ssh_key::{PrivateKey, PublicKey};
+use russh::{ChannelMsg, client};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Between mouse reports; real mice report at 60-1000 Hz.
+const STEP: Duration = Duration::from_millis(16);
+/// With no target on screen this long we are on the menu or the results,
+/// and start the next round.
+const NO_TARGET_RESTART: Duration = Duration::from_secs(3);
+/// SGR mouse reports, as xterm sends them with `?1006h`.
+const MOUSE_MOVE: u8 = 35;
+const MOUSE_LEFT: u8 = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Aim {
+    /// Reaction times, curved paths and misses of a decent player
+    Human,
+    /// Instant, perfectly straight and regular; the anticheat should refuse it
+    Robot,
+}
+
+#[derive(Debug)]
+pub struct BotConfig {
+    pub addr: String,
+    pub play_for: Duration,
+    pub aim: Aim,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// What one session measured.
+#[derive(Debug, Default)]
+pub struct SessionReport {
+    /// From opening the TCP connection to the shell starting.
+    pub connect: Duration,
+    /// From sending a mouse report to the next bytes from the server.
+    pub input_latency: Vec<Duration>,
+    /// Between consecutive chunks of output.
+    pub frame_gaps: Vec<Duration>,
+    pub bytes: u64,
+    pub clicks: u32,
+    pub error: Option<String>,
+}
+
+struct Client;
+
+impl client::Handler for Client {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, _key: &PublicKey) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Plays as a fresh player with its own key until `config.play_for` is up.
+pub async fn run(id: usize, config: Arc<BotConfig>) -> SessionReport {
+    let mut report = SessionReport::default();
+    if let Err(e) = play(id, &config, &mut report).await {
+        report.error = Some(format!("{e:#}"));
+    }
+    report
+}
+
+async fn play(id: usize, config: &BotConfig, report: &mut SessionReport) -> Result<()> {
+    let started = Instant::now();
+    let key = PrivateKey::from(Ed25519Keypair::from_seed(&rand::random()));
+    let mut session = client::connect(
+        Arc::new(client::Config::default()),
+        config.addr.as_str(),
+        Client,
+    )
+    .await
+    .context("Cannot connect")?;
+    let auth = session
+        .authenticate_publickey("bench", PrivateKeyWithHashAlg::new(Arc::new(key), None))
+        .await?;
+    anyhow::ensure!(auth.success(), "Key refused");
+    let channel = session.channel_open_session().await?;
+    let (cols, rows) = (config.cols, config.rows);
+    channel
+        .request_pty(false, "xterm-256color", cols.into(), rows.into(), 0, 0, &[])
+        .await?;
+    channel.request_shell(false).await?;
+    report.connect = started.elapsed();
+
+    let (mut reader, writer) = channel.split();
+    let mut screen = vt100::Parser::new(rows, cols, 0);
+    let mut hand = Hand::new(config.aim, (cols / 2, rows / 2));
+    let mut script = Script::Naming;
+    let mut last_output: Option<Instant> = None;
+    let mut pending_input: Option<Instant> = None;
+    let mut step = tokio::time::interval(STEP);
+    let deadline = tokio::time::sleep(config.play_for);
+    tokio::pin!(deadline);
+
+    loop {
+        let mut send = Vec::new();
+        tokio::select! {
+            _ = &mut deadline => break,
+            msg = reader.wait() => match msg {
+                Some(ChannelMsg::Data { data }) => {
+                    let now = Instant::now();
+                    screen.process(&data);
+                    report.bytes += data.len() as u64;
+                    if let Some(last) = last_output.replace(now) {
+                        report.frame_gaps.push(now - last);
+                    }
+                    if let Some(sent) = pending_input.take() {
+                        report.input_latency.push(now - sent);
+                    }
+                }
+                Some(ChannelMsg::Eof | ChannelMsg::Close) | None => {
+                    anyhow::bail!("Server closed the session")
+                }
+                Some(_) => {}
+            },
+            _ = step.tick() => {
+                let now = Instant::now();
+                script.advance(id, now, last_output.is_some(), &mut send);
+                if script == Script::Playing {
+                    let target = find_target(screen.screen());
+                    hand.step(now, target, &mut send, &mut report.clicks);
+                    if hand.idle_for(now) >= NO_TARGET_RESTART {
+                        hand.reset_idle(now);
+                        // `r` restarts from the results, a click starts from the menu
+                        send.extend_from_slice(b"r");
+                        click(&mut send, cols / 2, rows / 2);
+                    }
+                }
+            }
+        }
+        if !send.is_empty() {
+            writer.data(&send[..]).await?;
+            pending_input.get_or_insert_with(Instant::now);
+        }
+    }
+
+    writer.data(&b"q"[..]).await.ok();
+    session
+        .disconnect(russh::Disconnect::ByApplication, "", "en")
+        .await
+        .ok();
+    Ok(())
+}
+
+/// Gets a new account past the naming screen and tutorial.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Naming,
+    SkipTutorial(Instant),
+    Menu(Instant),
+    Playing,
+}
+
+impl Script {
+    fn advance(&mut self, id: usize, now: Instant, drawn: bool, send: &mut Vec<u8>) {
+        let pause = Duration::from_millis(500);
+        *self = match *self {
+            Self::Naming if drawn => {
+                // the screen may hold a suggested name
+                send.extend_from_slice(&[0x7f; 16]);
+                let name = format!("bench{id}x{:04x}", rand::random::<u16>());
+                send.extend_from_slice(&name.as_bytes()[..name.len().min(15)]);
+                send.push(b'\r');
+                Self::SkipTutorial(now + pause)
+            }
+            Self::SkipTutorial(at) if now >= at => {
+                send.push(0x1b);
+                Self::Menu(now + pause)
+            }
+            Self::Menu(at) if now >= at => Self::Playing,
+            other => other,
+        };
+    }
+}
+
+/// Moves the pointer towards targets the way `aim` says.
+struct Hand {
+    aim: Aim,
+    rng: StdRng,
+    pos: (f32, f32),
+    target: Option<(u16, u16)>,
+    /// Start and end of the current movement, and where it started from.
+    motion: Option<(Instant, Duration, (f32, f32))>,
+    /// Perpendicular bend of the current path, in cells.
+    bend: f32,
+    /// Horizontal error of the current click, in cells.
+    miss: f32,
+    clicked: bool,
+    last_target_seen: Instant,
+}
+
+impl Hand {
+    fn new(aim: Aim, pos: (u16, u16)) -> Self {
+        Self {
+            aim,
+            rng: StdRng::from_os_rng(),
+            pos: (pos.0.into(), pos.1.into()),
+            target: None,
+            motion: None,
+            bend: 0.0,
+            miss: 0.0,
+            clicked: false,
+            last_target_seen: Instant::now(),
+        }
+    }
+
+    fn idle_for(&self, now: Instant) -> Duration {
+        now - self.last_target_seen
+    }
+
+    fn reset_idle(&mut self, now: Instant) {
+        self.last_target_seen = now;
+    }
+
+    fn step(
+        &mut self,
+        now: Instant,
+        seen: Option<(u16, u16)>,
+        send: &mut Vec<u8>,
+        clicks: &mut u32,
+    ) {
+        if seen.is_some() {
+            self.last_target_seen = now;
+        }
+        if seen != self.target {
+            self.target = seen;
+            self.clicked = false;
+            self.motion = seen.map(|_| {
+                let (reaction, travel) = match self.aim {
+                    Aim::Human => (
+                        self.rng.random_range(180..=380),
+                        self.rng.random_range(150..=350),
+                    ),
+                    Aim::Robot => (20, 0),
+                };
+                self.bend = match self.aim {
+                    Aim::Human => self.rng.random_range(-3.0..3.0),
+                    Aim::Robot => 0.0,
+                };
+                // now and then a player clicks just off the edge
+                self.miss = match self.aim {
+                    Aim::Human if self.rng.random_bool(0.1) => self.rng.random_range(-4.0..4.0),
+                    _ => 0.0,
+                };
+                (
+                    now + Duration::from_millis(reaction),
+                    Duration::from_millis(travel),
+                    self.pos,
+                )
+            });
+        }
+        let (Some(goal), Some((start, travel, from))) = (self.target, self.motion) else {
+            return;
+        };
+        if now < start || self.clicked {
+            return;
+        }
+
+        let goal = (f32::from(goal.0) + self.miss, f32::from(goal.1));
+        let t = if travel.is_zero() {
+            1.0
+        } else {
+            ((now - start).as_secs_f32() / travel.as_secs_f32()).min(1.0)
+        };
+        // ease in and out, bowed to one side, with a little tremor
+        let eased = t * t * (3.0 - 2.0 * t);
+        let bow = self.bend * (std::f32::consts::PI * t).sin();
+        let tremor = match self.aim {
+            Aim::Human if t < 1.0 => self.rng.random_range(-0.4..0.4),
+            _ => 0.0,
+        };
+        let (dx, dy) = (goal.0 - from.0, goal.1 - from.1);
+        let len = (dx * dx + dy * dy).sqrt().max(1.0);
+        self.pos = (
+            from.0 + dx * eased - dy / len * bow + tremor,
+            from.1 + dy * eased + dx / len * bow + tremor,
+        );
+        let (x, y) = (
+            self.pos.0.round().max(0.0) as u16,
+            self.pos.1.round().max(0.0) as u16,
+        );
+        mouse(send, MOUSE_MOVE, x, y, true);
+        if t >= 1.0 {
+            click(send, x, y);
+            *clicks += 1;
+            self.clicked = true;
+        }
+    }
+}
+
+fn mouse(send: &mut Vec<u8>, button: u8, x: u16, y: u16, pressed: bool) {
+    let end = if pressed { 'M' } else { 'm' };
+    send.extend_from_slice(format!("\x1b[<{button};{};{}{end}", x + 1, y + 1).as_bytes());
+}
+
+fn click(send: &mut Vec<u8>, x: u16, y: u16) {
+    mouse(send, MOUSE_LEFT, x, y, true);
+    mouse(send, MOUSE_LEFT, x, y, false);
+}
+
+/// Centre of the cells painted in the target color (red, in the default
+/// theme), if any are on screen.
+fn find_target(screen: &vt100::Screen) -> Option<(u16, u16)> {
+    let (rows, cols) = screen.size();
+    let (mut sum_x, mut sum_y, mut n) = (0u32, 0u32, 0u32);
+    for y in 0..rows {
+        for x in 0..cols {
+            let red = screen
+                .cell(y, x)
+                .is_some_and(|cell| matches!(cell.bgcolor(), vt100::Color::Idx(1 | 9)));
+            if red {
+                sum_x += u32::from(x);
+                sum_y += u32::from(y);
+                n += 1;
+            }
+        }
+    }
+    (n > 0).then(|| ((sum_x / n) as u16, (sum_y / n) as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_the_red_block() {
+        let mut parser = vt100::Parser::new(10, 20, 0);
+        parser.process(b"\x1b[3;5H\x1b[41m    \x1b[4;5H    \x1b[0m");
+        assert_eq!(find_target(parser.screen()), Some((5, 2)));
+
+        let mut parser = vt100::Parser::new(10, 20, 0);
+        parser.process(b"menu");
+        assert_eq!(find_target(parser.screen()), None);
+    }
+}
@@ -0,0 +1,135 @@
+mod bot;
+mod stats;
+
+use anyhow::Result;
+use bot::{Aim, BotConfig, SessionReport};
+use clap::Parser;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Opens many SSH sessions against a shootsh server, plays in each with a
+/// simulated mouse, and reports how the server kept up. Point it at a test
+/// instance: every session creates an account.
+#[derive(Parser, Debug)]
+#[command(version, about = "Load test for the shootsh SSH server")]
+struct Args {
+    /// Server to connect to
+    #[arg(long, default_value = "127.0.0.1:2222")]
+    addr: String,
+    /// Concurrent sessions
+    #[arg(short = 'n', long, default_value_t = 10)]
+    sessions: usize,
+    /// Seconds each session plays for
+    #[arg(short, long, default_value_t = 60)]
+    duration: u64,
+    /// Milliseconds between opening one session and the next
+    #[arg(long, default_value_t = 50)]
+    ramp_ms: u64,
+    /// How the simulated players move the mouse
+    #[arg(long, value_enum, default_value_t = Aim::Human)]
+    aim: Aim,
+    /// Terminal size of every session
+    #[arg(long, default_value_t = 120)]
+    cols: u16,
+    #[arg(long, default_value_t = 40)]
+    rows: u16,
+    /// PID of the server, when it runs on this machine, to report its CPU
+    /// and memory use
+    #[arg(long)]
+    pid: Option<u32>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    let config = Arc::new(BotConfig {
+        addr: args.addr.clone(),
+        play_for: Duration::from_secs(args.duration),
+        aim: args.aim,
+        cols: args.cols,
+        rows: args.rows,
+    });
+
+    println!(
+        "{} sessions against {} for {}s, {:?} aim",
+        args.sessions, args.addr, args.duration, args.aim
+    );
+    let started = Instant::now();
+    let first = args.pid.and_then(stats::sample);
+    let peak_rss = Arc::new(AtomicU64::new(0));
+    let sampler = args.pid.map(|pid| {
+        let peak_rss = Arc::clone(&peak_rss);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(s) = stats::sample(pid) else { break };
+                peak_rss.fetch_max(s.rss_kb, Ordering::Relaxed);
+            }
+        })
+    });
+
+    let mut handles = Vec::with_capacity(args.sessions);
+    for id in 0..args.sessions {
+        handles.push(tokio::spawn(bot::run(id, Arc::clone(&config))));
+        tokio::time::sleep(Duration::from_millis(args.ramp_ms)).await;
+    }
+
+    let mut reports = Vec::with_capacity(handles.len());
+    for handle in handles {
+        reports.push(handle.await?);
+    }
+    let elapsed = started.elapsed();
+    let last = args.pid.and_then(stats::sample);
+    if let Some(sampler) = sampler {
+        sampler.abort();
+    }
+    let peak_rss = peak_rss.load(Ordering::Relaxed);
+
+    print_report(&reports);
+    if let (Some(first), Some(last)) = (first, last) {
+        let cpu = (last.cpu - first.cpu).as_secs_f64() / elapsed.as_secs_f64();
+        println!(
+            "server      CPU {:.0}% of a core  RSS {} MiB (peak {} MiB)",
+            cpu * 100.0,
+            last.rss_kb / 1024,
+            peak_rss.max(last.rss_kb) / 1024
+        );
+    }
+    if args.aim == Aim::Robot {
+        println!("\nRobot aim: these runs should show up in `shootsh_ssh checks`.");
+    }
+    Ok(())
+}
+
+fn print_report(reports: &[SessionReport]) {
+    let failed: Vec<_> = reports.iter().filter_map(|r| r.error.as_ref()).collect();
+    println!(
+        "\nsessions    {} ok, {} failed",
+        reports.len() - failed.len(),
+        failed.len()
+    );
+    for error in failed.iter().take(5) {
+        println!("            {error}");
+    }
+
+    let mut connect: Vec<_> = reports
+        .iter()
+        .filter(|r| r.error.is_none())
+        .map(|r| r.connect)
+        .collect();
+    let mut latency: Vec<_> = reports
+        .iter()
+        .flat_map(|r| r.input_latency.clone())
+        .collect();
+    let mut gaps: Vec<_> = reports.iter().flat_map(|r| r.frame_gaps.clone()).collect();
+    let bytes: u64 = reports.iter().map(|r| r.bytes).sum();
+    let clicks: u32 = reports.iter().map(|r| r.clicks).sum();
+    println!("connect     {}", stats::summary(&mut connect));
+    println!("input→frame {}", stats::summary(&mut latency));
+    println!("frame gap   {}", stats::summary(&mut gaps));
+    println!("traffic     {} KiB received, {clicks} clicks", bytes / 1024);
+}
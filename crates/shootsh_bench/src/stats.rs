@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+/// Clock ticks per second in `/proc/<pid>/stat`; 100 on every mainstream
+/// Linux build.
+const CLK_TCK: f64 = 100.0;
+
+/// `p` in 0..=1; `sorted` must be in ascending order.
+pub fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[rank]
+}
+
+/// "p50 12ms  p95 30ms  p99 41ms" over `samples`, which it sorts.
+pub fn summary(samples: &mut [Duration]) -> String {
+    if samples.is_empty() {
+        return "no samples".to_string();
+    }
+    samples.sort();
+    let ms = |p| percentile(samples, p).as_millis();
+    format!(
+        "p50 {}ms  p95 {}ms  p99 {}ms  max {}ms  ({} samples)",
+        ms(0.5),
+        ms(0.95),
+        ms(0.99),
+        ms(1.0),
+        samples.len()
+    )
+}
+
+/// CPU time and resident memory of a process, from `/proc`.
+#[derive(Debug, Clone, Copy)]
+pub struct ProcSample {
+    pub cpu: Duration,
+    pub rss_kb: u64,
+}
+
+pub fn sample(pid: u32) -> Option<ProcSample> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    Some(ProcSample {
+        cpu: Duration::from_secs_f64(cpu_ticks(&stat)? as f64 / CLK_TCK),
+        rss_kb: rss_kb(&status)?,
+    })
+}
+
+/// utime + stime; the command name in parentheses may contain spaces.
+fn cpu_ticks(stat: &str) -> Option<u64> {
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+    Some(utime + stime)
+}
+
+fn rss_kb(status: &str) -> Option<u64> {
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proc_parsing_and_percentiles() {
+        let stat = "4242 (shootsh ssh) S 1 4242 4242 0 -1 4194560 900 0 0 0 150 50 0 0 20 0 9";
+        assert_eq!(cpu_ticks(stat), Some(200));
+        assert_eq!(rss_kb("Name:\tshootsh\nVmRSS:\t  51200 kB\n"), Some(51200));
+
+        let ms = Duration::from_millis;
+        let sorted: Vec<_> = (1..=100).map(ms).collect();
+        assert_eq!(percentile(&sorted, 0.5), ms(51));
+        assert_eq!(percentile(&sorted, 1.0), ms(100));
+        assert_eq!(percentile(&[], 0.5), Duration::ZERO);
+    }
+}
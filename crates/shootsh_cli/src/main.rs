@@ -1,5 +1,9 @@
+mod paths;
+mod sync;
+
 use anyhow::{Context, Result};
 use arc_swap::ArcSwap;
+use clap::{Parser, Subcommand, ValueEnum};
 use crossterm::{
     event::{self, Event, KeyCode, MouseButton, MouseEventKind},
     execute,
@@ -7,40 +11,175 @@ use crossterm::{
 };
 use ratatui::prelude::*;
 use rusqlite::Connection;
+use serde::Deserialize;
 use shootsh_core::Scene;
-use shootsh_core::db::DbCache;
+use shootsh_core::db::{ClientKind, DbCache, IDLE_TICK_INTERVAL, UserContext};
 use shootsh_core::{
-    Action, App,
+    Action, App, GameConfig, GameMode,
     db::{DbRequest, Repository},
-    domain, ui,
+    domain,
+    preferences::Language,
+    signing::SigningKey,
+    theme::ColorSupport,
+    ui,
 };
 use std::{
-    io,
+    io::{self, Write},
+    path::{Path, PathBuf},
     sync::Arc,
     time::{Duration, Instant},
 };
+use sync::{SyncArgs, UploadQueue};
 use tokio::sync::mpsc;
 
 const DEFAULT_MAX_USERS: i64 = 100_000;
+const DEFAULT_FPS: u32 = 60;
+const MAX_DURATION_SECS: u64 = 600;
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let conn = Connection::open("shootsh.db").context("Failed to open database")?;
-    let repo =
-        Repository::new(conn, DEFAULT_MAX_USERS).context("Failed to initialize repository")?;
-    let shared_cache = Arc::new(ArcSwap::from_pointee(repo.get_current_cache()));
-    let (db_tx, db_rx) = mpsc::channel::<DbRequest>(100);
+/// Plays shootsh in this terminal. Flags win over the TOML file given by
+/// `--config`, by default `$XDG_CONFIG_HOME/shootsh/config.toml`; its keys are
+/// the flag names with underscores.
+#[derive(Parser, Deserialize, Debug, Default)]
+#[command(version, about = "shootsh, played locally")]
+#[serde(default, deny_unknown_fields)]
+struct Args {
+    /// TOML file with any of the settings below
+    #[arg(long, env = "SHOOTSH_CONFIG")]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+    /// Database file for scores and settings [default:
+    /// $XDG_DATA_HOME/shootsh/shootsh.db]
+    #[arg(long, env = "SHOOTSH_DB")]
+    db: Option<PathBuf>,
+    /// Keep everything in memory; nothing is saved
+    #[arg(long)]
+    #[serde(skip)]
+    no_db: bool,
+    /// Play straight away without a name or any database; nothing is saved
+    /// and the leaderboards stay empty
+    #[arg(long)]
+    #[serde(skip)]
+    guest: bool,
+    /// Round length in seconds; such rounds are unranked practice
+    #[arg(short, long)]
+    duration: Option<u64>,
+    /// What starting from the menu plays [default: standard]
+    #[arg(short, long, value_enum)]
+    mode: Option<Mode>,
+    /// Same targets every round; such rounds are unranked practice
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Frames drawn per second [default: 60]
+    #[arg(long)]
+    fps: Option<u32>,
+    #[command(subcommand)]
+    #[serde(skip)]
+    command: Option<Command>,
+}
 
-    let user_context = repo
-        .get_or_create_user_context("local")
-        .context("Failed to get or create local user")?;
-    let mut app = App::new(user_context, db_tx, shared_cache.load_full());
+impl Args {
+    /// Flags merged over the config file, which has to exist if it was named.
+    fn load() -> Result<Self> {
+        let args = Self::parse();
+        let path = match &args.config {
+            Some(path) => path.clone(),
+            None => match paths::default_config().filter(|path| path.is_file()) {
+                Some(path) => path,
+                None => return Ok(args),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let file: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        Ok(Self {
+            db: args.db.or(file.db),
+            duration: args.duration.or(file.duration),
+            mode: args.mode.or(file.mode),
+            seed: args.seed.or(file.seed),
+            fps: args.fps.or(file.fps),
+            ..args
+        })
+    }
+
+    /// The database file, moving one left in the working directory by
+    /// earlier versions to the default place.
+    fn db_path(&self) -> PathBuf {
+        if let Some(path) = &self.db {
+            return path.clone();
+        }
+        let path = paths::default_db();
+        let legacy = paths::legacy_db();
+        match paths::migrate_db(&legacy, &path) {
+            Ok(true) => eprintln!("Moved {} to {}", legacy.display(), path.display()),
+            Ok(false) => {}
+            Err(e) => eprintln!("{e:#}; keeping scores in {}", legacy.display()),
+        }
+        if legacy.is_file() && !path.exists() {
+            return legacy;
+        }
+        path
+    }
+}
 
-    spawn_db_worker(repo, Arc::clone(&shared_cache), db_rx);
+#[derive(Subcommand, Debug)]
+enum Command {
+    Sync(SyncArgs),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    Standard,
+    Daily,
+    Practice,
+}
+
+impl From<Mode> for GameMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Standard => Self::Standard,
+            Mode::Daily => Self::DailyChallenge,
+            Mode::Practice => Self::Practice,
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::load()?;
+    let fps = args.fps.unwrap_or(DEFAULT_FPS);
+    anyhow::ensure!((1..=240).contains(&fps), "fps must be between 1 and 240");
+    anyhow::ensure!(
+        args.duration
+            .is_none_or(|secs| (1..=MAX_DURATION_SECS).contains(&secs)),
+        "duration must be between 1 and {MAX_DURATION_SECS} seconds"
+    );
+    if let Some(Command::Sync(sync)) = &args.command {
+        return sync::run(sync, &args.db_path()).await;
+    }
+    let shared_cache = Arc::new(ArcSwap::from_pointee(DbCache::default()));
+    let mut app = if args.guest {
+        App::offline(UserContext::guest("guest"), GameConfig::default())
+    } else {
+        open_app(&args, &shared_cache)?
+    };
+    app.color_support = detect_color_support();
+    app.locale_language = detect_language();
+    app.client = ClientKind::Local;
+    app.behavior_analyzer.set_latency(Duration::ZERO);
+    app.start_mode = args.mode.unwrap_or(Mode::Standard).into();
+    app.seed = args.seed;
+    app.round_length = args.duration.map(Duration::from_secs);
 
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, event::EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        event::EnableMouseCapture,
+        event::EnableBracketedPaste
+    )?;
 
     let panic_hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |panic_info| {
@@ -48,18 +187,21 @@ async fn main() -> Result<()> {
         let _ = execute!(
             io::stdout(),
             LeaveAlternateScreen,
-            event::DisableMouseCapture
+            event::DisableMouseCapture,
+            event::DisableBracketedPaste
         );
         panic_hook(panic_info);
     }));
 
     let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
-    let res = run_loop(&mut app, &mut terminal, shared_cache).await;
+    let tick_rate = Duration::from_secs(1) / fps;
+    let res = run_loop(&mut app, &mut terminal, shared_cache, tick_rate).await;
 
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        event::DisableMouseCapture
+        event::DisableMouseCapture,
+        event::DisableBracketedPaste
     )
     .ok();
     disable_raw_mode().ok();
@@ -67,6 +209,9 @@ async fn main() -> Result<()> {
     if let Err(e) = res {
         eprintln!("Application Error: {:?}", e);
     }
+    if let Some(takeout) = app.takeout.take() {
+        print!("{takeout}");
+    }
 
     Ok(())
 }
@@ -75,15 +220,15 @@ async fn run_loop<B: Backend>(
     app: &mut App,
     terminal: &mut Terminal<B>,
     shared_cache: Arc<ArcSwap<shootsh_core::db::DbCache>>,
+    tick_rate: Duration,
 ) -> Result<()>
 where
     <B as Backend>::Error: std::error::Error + Send + Sync + 'static,
 {
-    let tick_rate = Duration::from_millis(16);
     let mut last_tick = Instant::now();
 
     while !app.should_quit {
-        app.db_cache = shared_cache.load_full();
+        app.set_db_cache(shared_cache.load_full());
 
         if let Ok(size) = terminal.size() {
             app.screen_size = domain::Size {
@@ -95,6 +240,12 @@ where
         terminal.draw(|f| {
             ui::render(app, &app.db_cache, f);
         })?;
+        app.perf.record_frame(Instant::now());
+        if app.feedback.take_bell() {
+            let mut stdout = io::stdout();
+            stdout.write_all(b"\x07")?;
+            stdout.flush()?;
+        }
 
         let timeout = tick_rate.saturating_sub(last_tick.elapsed());
         if event::poll(timeout)? {
@@ -109,6 +260,7 @@ where
         }
 
         if last_tick.elapsed() >= tick_rate {
+            app.perf.record_tick(tick_rate, Instant::now());
             app.update_state(Action::Tick).0?;
             last_tick = Instant::now();
         }
@@ -116,6 +268,95 @@ where
     Ok(())
 }
 
+/// Plays as the local player, with a DB worker keeping `shared_cache` current.
+fn open_app(args: &Args, shared_cache: &Arc<ArcSwap<DbCache>>) -> Result<App> {
+    let signing_key = SigningKey::random();
+    let (repo, uploads) = open_repository(args)?;
+    let repo = repo.with_signing_key(signing_key.clone());
+    shared_cache.store(Arc::new(repo.get_current_cache()));
+    let (db_tx, db_rx) = mpsc::channel::<DbRequest>(100);
+
+    let user_context = repo
+        .get_or_create_user_context("local")
+        .context("Failed to get or create local user")?;
+    let mut app = App::new(
+        user_context,
+        db_tx,
+        shared_cache.load_full(),
+        GameConfig::default(),
+    );
+    app.signing_key = Some(signing_key);
+
+    spawn_db_worker(repo, uploads, Arc::clone(shared_cache), db_rx);
+    Ok(app)
+}
+
+/// `--no-db` (or `DB_BACKEND=memory`) skips the database file entirely. If the
+/// file can't be opened (e.g. a read-only filesystem) we fall back to memory
+/// rather than refusing to start. Standard runs are queued for `sync` beside
+/// the file.
+fn open_repository(args: &Args) -> Result<(Repository, Option<UploadQueue>)> {
+    if args.no_db || std::env::var("DB_BACKEND").as_deref() == Ok("memory") {
+        return Ok((Repository::in_memory(DEFAULT_MAX_USERS), None));
+    }
+    let db = args.db_path();
+    let opened = create_parent(&db)
+        .and_then(|_| Connection::open(&db).map_err(Into::into))
+        .with_context(|| format!("Failed to open database {}", db.display()))
+        .and_then(|conn| Repository::new(conn, DEFAULT_MAX_USERS));
+    match opened {
+        Ok(repo) => {
+            let uploads = UploadQueue::open(&db)
+                .inspect_err(|e| eprintln!("{e:#}; runs will not be queued for sync"))
+                .ok();
+            Ok((repo, uploads))
+        }
+        Err(e) => {
+            eprintln!("{e:#}; scores will not be saved this session");
+            Ok((Repository::in_memory(DEFAULT_MAX_USERS), None))
+        }
+    }
+}
+
+/// Makes the directory the database goes in, e.g. on the first run.
+fn create_parent(path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    Ok(())
+}
+
+/// `NO_COLOR` wins, then terminfo via `tput`, then the environment.
+fn detect_color_support() -> ColorSupport {
+    if std::env::var_os("NO_COLOR").is_some() {
+        return ColorSupport::Monochrome;
+    }
+
+    let colors = std::process::Command::new("tput")
+        .arg("colors")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .and_then(|s| s.trim().parse::<u32>().ok());
+
+    match colors {
+        Some(n) => ColorSupport::from_color_count(n),
+        None => ColorSupport::detect(
+            &std::env::var("TERM").unwrap_or_default(),
+            std::env::var("COLORTERM").ok().as_deref(),
+        ),
+    }
+}
+
+fn detect_language() -> Language {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .and_then(|value| Language::from_locale(&value))
+        .unwrap_or_default()
+}
+
 async fn handle_event(app: &mut App, event: Event) -> Result<()> {
     let captured = app.input_captured();
 
@@ -130,12 +371,15 @@ async fn handle_event(app: &mut App, event: Event) -> Result<()> {
                     KeyCode::Char('k') => Some(Action::RequestReset),
                     _ => None,
                 }
+            } else if key.code == KeyCode::F(3) {
+                Some(Action::ToggleDebug)
             } else if captured {
                 // when captured mode
                 match key.code {
                     KeyCode::Enter => Some(Action::SubmitInput),
                     KeyCode::Backspace => Some(Action::DeleteCharacter),
                     KeyCode::Esc => Some(Action::BackToMenu),
+                    KeyCode::Tab => Some(Action::ToggleLinkMode),
                     KeyCode::Char(c) => Some(Action::AppendCharacter(c)),
                     _ => None,
                 }
@@ -143,13 +387,29 @@ async fn handle_event(app: &mut App, event: Event) -> Result<()> {
                 match key.code {
                     KeyCode::Char('q') => Some(Action::Quit),
                     KeyCode::Char('r') => Some(Action::Restart),
+                    KeyCode::Char('c') => Some(Action::StartDailyChallenge),
+                    KeyCode::Char('u') => Some(Action::FindDuel),
+                    KeyCode::Char('t') => Some(Action::OpenChat),
+                    KeyCode::Char('v') => Some(Action::WatchReplay),
+                    KeyCode::Char('s') => Some(Action::OpenSettings),
+                    KeyCode::Char('p') => Some(Action::OpenProfile),
+                    KeyCode::Char('e') => Some(Action::ExportData),
+                    KeyCode::Char('d') => Some(Action::LinkDevice),
+                    KeyCode::Char('g') => Some(Action::CreateRecoveryCode),
+                    KeyCode::Char('m') => Some(Action::OpenCalibration),
+                    KeyCode::Char('?') => Some(Action::ToggleHelp),
                     KeyCode::Char('y') => Some(Action::ConfirmReset),
                     KeyCode::Char('n') => Some(Action::CancelReset),
 
-                    KeyCode::Char('h') => Some(Action::NavigateLeft),
-                    KeyCode::Char('l') => Some(Action::NavigateRight),
+                    KeyCode::Char('h') => Some(Action::PreviousTab),
+                    KeyCode::Char('l') => Some(Action::OpenLeaderboard),
+                    KeyCode::Char('a') => Some(Action::CycleSeason),
+                    KeyCode::Char('k') => Some(Action::NavigateUp),
+                    KeyCode::Char('j') => Some(Action::NavigateDown),
                     KeyCode::Left => Some(Action::NavigateLeft),
                     KeyCode::Right => Some(Action::NavigateRight),
+                    KeyCode::Up => Some(Action::NavigateUp),
+                    KeyCode::Down => Some(Action::NavigateDown),
 
                     KeyCode::Enter => Some(Action::SubmitInput),
                     KeyCode::Backspace => Some(Action::DeleteCharacter),
@@ -164,8 +424,11 @@ async fn handle_event(app: &mut App, event: Event) -> Result<()> {
             MouseEventKind::Moved | MouseEventKind::Drag(_) => {
                 Some(Action::MouseMove(m.column, m.row))
             }
+            MouseEventKind::ScrollUp => Some(Action::NavigateUp),
+            MouseEventKind::ScrollDown => Some(Action::NavigateDown),
             _ => None,
         },
+        Event::Paste(text) => Some(Action::Paste(text)),
         _ => None,
     };
 
@@ -179,8 +442,7 @@ async fn handle_event(app: &mut App, event: Event) -> Result<()> {
             match rx.await {
                 Ok(Ok(_)) => match current_scene {
                     Scene::Naming(state) => {
-                        app.user.name = Some(state.input.clone());
-                        app.change_scene(Scene::Menu);
+                        app.complete_naming(state.input);
                     }
                     Scene::ResetConfirmation => {
                         app.should_quit = true;
@@ -207,16 +469,35 @@ async fn handle_event(app: &mut App, event: Event) -> Result<()> {
 
 fn spawn_db_worker(
     repo: Repository,
+    uploads: Option<UploadQueue>,
     cache: Arc<ArcSwap<DbCache>>,
     mut rx: mpsc::Receiver<DbRequest>,
 ) {
+    let runtime = tokio::runtime::Handle::current();
     std::thread::spawn(move || {
-        while let Some(req) = rx.blocking_recv() {
-            match repo.handle_request(req) {
-                Some(new_cache) => {
-                    cache.store(Arc::new(new_cache));
-                }
-                None => {}
+        loop {
+            let new_cache =
+                match runtime.block_on(tokio::time::timeout(IDLE_TICK_INTERVAL, rx.recv())) {
+                    Ok(Some(req)) => {
+                        // practice and daily challenges are only ever ranked here
+                        if let (
+                            Some(uploads),
+                            DbRequest::SaveGame {
+                                mode: GameMode::Standard,
+                                replay,
+                                ..
+                            },
+                        ) = (&uploads, &req)
+                        {
+                            let _ = uploads.push(replay);
+                        }
+                        repo.handle_request(req)
+                    }
+                    Ok(None) => break,
+                    Err(_) => repo.run_idle(),
+                };
+            if let Some(new_cache) = new_cache {
+                cache.store(Arc::new(new_cache));
             }
         }
     });
@@ -0,0 +1,105 @@
+use anyhow::{Context, Result, bail};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "shootsh";
+const DB_FILE: &str = "shootsh.db";
+const CONFIG_FILE: &str = "config.toml";
+/// SQLite keeps uncheckpointed writes beside the database in these.
+const DB_SIDECARS: [&str; 2] = ["-wal", "-shm"];
+
+/// `$XDG_DATA_HOME/shootsh/shootsh.db`, or the platform's equivalent, e.g.
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows.
+pub fn default_db() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_DIR).join(DB_FILE))
+        .unwrap_or_else(|| PathBuf::from(DB_FILE))
+}
+
+/// `$XDG_CONFIG_HOME/shootsh/config.toml`, or the platform's equivalent.
+pub fn default_config() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_DIR).join(CONFIG_FILE))
+}
+
+/// Where earlier versions put the database: wherever they were run from.
+pub fn legacy_db() -> PathBuf {
+    PathBuf::from(DB_FILE)
+}
+
+/// Moves the database at `from` to `to`, unless `to` already has one. True
+/// if it was moved.
+pub fn migrate_db(from: &Path, to: &Path) -> Result<bool> {
+    if !from.is_file() || to.exists() {
+        return Ok(false);
+    }
+    checkpoint(from).with_context(|| format!("Failed to checkpoint {}", from.display()))?;
+    if let Some(dir) = to.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    move_file(from, to)?;
+    for suffix in DB_SIDECARS {
+        let sidecar = |path: &Path| PathBuf::from(format!("{}{suffix}", path.display()));
+        if sidecar(from).is_file() {
+            move_file(&sidecar(from), &sidecar(to))?;
+        }
+    }
+    Ok(true)
+}
+
+/// Writes everything in the write-ahead log back into the database file, so
+/// the file moves whole even if the sidecars were to go astray.
+fn checkpoint(path: &Path) -> Result<()> {
+    let conn = Connection::open(path)?;
+    let busy: i64 = conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+    if busy != 0 {
+        bail!("The database is in use; close other shootsh windows first");
+    }
+    Ok(())
+}
+
+/// A rename, or a copy when the data directory is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    std::fs::copy(from, to)
+        .and_then(|_| std::fs::remove_file(from))
+        .with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migration_moves_the_database_once() {
+        let dir = std::env::temp_dir().join(format!("shootsh-paths-{}", rand::random::<u64>()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let from = dir.join("shootsh.db");
+        let to = dir.join("data").join("shootsh").join("shootsh.db");
+        let scores = |path: &Path| -> i64 {
+            let conn = Connection::open(path).unwrap();
+            conn.query_row("SELECT COUNT(*) FROM scores", [], |row| row.get(0))
+                .unwrap()
+        };
+        let conn = Connection::open(&from).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn.execute_batch("CREATE TABLE scores (score INTEGER); INSERT INTO scores VALUES (120);")
+            .unwrap();
+
+        // the writes still in the log arrive too
+        assert!(migrate_db(&from, &to).unwrap());
+        drop(conn);
+        assert!(!from.exists());
+        assert!(!dir.join("shootsh.db-wal").exists());
+        assert_eq!(scores(&to), 1);
+
+        // a newer database is never overwritten
+        std::fs::write(&from, "old").unwrap();
+        assert!(!migrate_db(&from, &to).unwrap());
+        assert_eq!(scores(&to), 1);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use clap::Args;
+use rusqlite::Connection;
+use russh::keys::ssh_key::PublicKey;
+use russh::keys::{PrivateKey, PrivateKeyWithHashAlg};
+use russh::{ChannelMsg, client};
+use shootsh_core::db::{self, Verdict};
+use shootsh_core::replay::{self, MAX_BATCH_BYTES, MAX_BATCH_RUNS, Replay};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Keys tried, in order, without `--identity`.
+const DEFAULT_IDENTITIES: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+/// Uploads the standard runs played here to a shootsh server, where they are
+/// checked from their replays and ranked under the account of your SSH key.
+#[derive(Args, Debug)]
+pub struct SyncArgs {
+    /// Server to upload to, e.g. shoot.sh
+    #[arg(long)]
+    server: String,
+    #[arg(long, default_value_t = 22)]
+    port: u16,
+    /// Private key to log in with; defaults to the usual ones in ~/.ssh
+    #[arg(short, long)]
+    identity: Option<PathBuf>,
+}
+
+/// Standard runs not yet uploaded, kept beside the local boards.
+pub struct UploadQueue {
+    conn: Connection,
+}
+
+impl UploadQueue {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        // `sync` may be the first to open the file since an upgrade
+        db::migrate(&conn)?;
+        Ok(Self { conn })
+    }
+
+    pub fn push(&self, replay: &Replay) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO upload_queue (replay) VALUES (?1)",
+            [replay.compress()?],
+        )?;
+        Ok(())
+    }
+
+    /// Oldest first, as ids and compressed replays.
+    fn pending(&self) -> Result<Vec<(i64, Vec<u8>)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, replay FROM upload_queue ORDER BY id")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn remove(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM upload_queue WHERE id = ?1", [id])?;
+        Ok(())
+    }
+}
+
+struct Client {
+    host: String,
+    port: u16,
+}
+
+impl client::Handler for Client {
+    type Error = anyhow::Error;
+
+    async fn check_server_key(&mut self, key: &PublicKey) -> Result<bool> {
+        let known = russh::keys::check_known_hosts(&self.host, self.port, key)
+            .context("Host key differs from the one in known_hosts")?;
+        anyhow::ensure!(
+            known,
+            "{} is not in known_hosts yet; connect once with `ssh {}` first",
+            self.host,
+            self.host
+        );
+        Ok(true)
+    }
+}
+
+pub async fn run(args: &SyncArgs, db: &Path) -> Result<()> {
+    if !db.is_file() {
+        println!("Nothing to upload");
+        return Ok(());
+    }
+    let queue = UploadQueue::open(db)
+        .with_context(|| format!("Failed to open database {}", db.display()))?;
+    let pending = queue.pending()?;
+    if pending.is_empty() {
+        println!("Nothing to upload");
+        return Ok(());
+    }
+
+    let key = load_identity(args.identity.as_deref())?;
+    let client = Client {
+        host: args.server.clone(),
+        port: args.port,
+    };
+    let mut session = client::connect(
+        Arc::new(client::Config::default()),
+        (args.server.as_str(), args.port),
+        client,
+    )
+    .await
+    .with_context(|| format!("Cannot connect to {}", args.server))?;
+    let hash_alg = session.best_supported_rsa_hash().await?.flatten();
+    let auth = session
+        .authenticate_publickey(
+            "shootsh",
+            PrivateKeyWithHashAlg::new(Arc::new(key), hash_alg),
+        )
+        .await?;
+    anyhow::ensure!(auth.success(), "{} refused the key", args.server);
+
+    println!("Uploading {} runs to {}", pending.len(), args.server);
+    let mut tally: Vec<(String, usize)> = Vec::new();
+    for batch in batches(&pending) {
+        let blobs: Vec<&[u8]> = batch.iter().map(|(_, blob)| blob.as_slice()).collect();
+        let answer = upload(&session, replay::encode_batch(&blobs)).await?;
+        for ((id, _), line) in batch.iter().zip(answer.lines()) {
+            // whatever the server made of a run, sending it again won't change that
+            if Verdict::from_key(line.split(' ').next().unwrap_or_default()).is_none() {
+                anyhow::bail!("{}", answer.trim());
+            }
+            queue.remove(*id)?;
+            match tally.iter_mut().find(|(seen, _)| seen == line) {
+                Some((_, count)) => *count += 1,
+                None => tally.push((line.to_string(), 1)),
+            }
+        }
+        if answer.lines().count() < batch.len() {
+            anyhow::bail!("The server stopped answering; the rest stay queued");
+        }
+    }
+    for (line, count) in tally {
+        println!("  {count:>4}  {line}");
+    }
+
+    session
+        .disconnect(russh::Disconnect::ByApplication, "", "en")
+        .await
+        .ok();
+    Ok(())
+}
+
+/// Runs `upload` with the batch on stdin; the server's answer, a line per run.
+async fn upload(session: &client::Handle<Client>, payload: Vec<u8>) -> Result<String> {
+    let mut channel = session.channel_open_session().await?;
+    channel.exec(true, "upload").await?;
+    channel.data(&payload[..]).await?;
+    channel.eof().await?;
+
+    let mut output = Vec::new();
+    let mut status = None;
+    while let Some(msg) = channel.wait().await {
+        match msg {
+            ChannelMsg::Data { data } => output.extend_from_slice(&data),
+            ChannelMsg::ExitStatus { exit_status } => status = Some(exit_status),
+            _ => {}
+        }
+    }
+    let output = String::from_utf8_lossy(&output).into_owned();
+    anyhow::ensure!(status == Some(0), "{}", output.trim());
+    Ok(output)
+}
+
+/// Splits the queue into uploads the server takes in one go.
+fn batches(pending: &[(i64, Vec<u8>)]) -> Vec<&[(i64, Vec<u8>)]> {
+    let mut batches = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (i, (_, blob)) in pending.iter().enumerate() {
+        let size = blob.len() + 4;
+        if i > start && (i - start == MAX_BATCH_RUNS || bytes + size > MAX_BATCH_BYTES) {
+            batches.push(&pending[start..i]);
+            (start, bytes) = (i, 0);
+        }
+        bytes += size;
+    }
+    if start < pending.len() {
+        batches.push(&pending[start..]);
+    }
+    batches
+}
+
+fn load_identity(path: Option<&Path>) -> Result<PrivateKey> {
+    if let Some(path) = path {
+        return russh::keys::load_secret_key(path, None)
+            .with_context(|| format!("Cannot load {}", path.display()));
+    }
+    let ssh_dir = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join(".ssh"))
+        .context("No home directory to find a key in; pass --identity")?;
+    DEFAULT_IDENTITIES
+        .iter()
+        .map(|name| ssh_dir.join(name))
+        .find(|path| path.exists())
+        .context("No key in ~/.ssh; pass --identity")
+        .and_then(|path| {
+            russh::keys::load_secret_key(&path, None).with_context(|| {
+                format!(
+                    "Cannot load {} (is it passphrase-protected?)",
+                    path.display()
+                )
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batches_respect_the_run_limit() {
+        let pending: Vec<_> = (0..MAX_BATCH_RUNS as i64 + 1)
+            .map(|id| (id, vec![0; 10]))
+            .collect();
+        let batches = batches(&pending);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].len(), MAX_BATCH_RUNS);
+        assert_eq!(batches[1][0].0, MAX_BATCH_RUNS as i64);
+    }
+
+    #[test]
+    fn test_queue_keeps_runs_until_removed() {
+        let path =
+            std::env::temp_dir().join(format!("shootsh-uploads-{}.db", rand::random::<u64>()));
+        let queue = UploadQueue::open(&path).unwrap();
+        queue.push(&Replay::default()).unwrap();
+        queue.push(&Replay::default()).unwrap();
+        let pending = queue.pending().unwrap();
+        assert_eq!(pending.len(), 2);
+        queue.remove(pending[0].0).unwrap();
+        assert_eq!(queue.pending().unwrap().len(), 1);
+        drop(queue);
+        let _ = std::fs::remove_file(path);
+    }
+}
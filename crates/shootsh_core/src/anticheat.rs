@@ -1,45 +1,822 @@
 use crate::domain::{MouseTrace, Point};
+use crate::replay::{Replay, ReplayEvent};
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+#[derive(Debug, Clone)]
 pub struct AntiCheatConfig {
     pub min_reaction_time: Duration,
+    /// Most of a session's round trip taken off `min_reaction_time`; more
+    /// would let a client buy slack by answering probes late.
+    pub max_latency_slack: Duration,
+    /// Longest single cursor step, in cells, before it counts as a teleport.
+    pub max_jump_cells: u16,
+    /// Fastest believable cursor speed, averaged over `speed_window`.
+    pub max_cells_per_sec: f32,
+    /// Long enough that SSH batching several moves into one packet evens out.
+    pub speed_window: Duration,
+    /// Cursor moves arriving with less spread between them than this look scripted.
+    pub min_timing_jitter: Duration,
+    pub path_shape: PathShapeConfig,
+    /// Approach paths a cubic fits more closely than this, in cells, are
+    /// synthetic. Rounding to the grid alone leaves about 0.29.
+    pub min_curve_residual: f64,
+    /// Refused hits in a session before it has to pass a verification round.
+    pub verify_after_warnings: u32,
 }
 
 impl Default for AntiCheatConfig {
     fn default() -> Self {
         Self {
             min_reaction_time: Duration::from_millis(100),
+            max_latency_slack: Duration::from_millis(80),
+            max_jump_cells: 40,
+            max_cells_per_sec: 1500.0,
+            speed_window: Duration::from_millis(250),
+            min_timing_jitter: Duration::from_micros(200),
+            path_shape: PathShapeConfig::default(),
+            min_curve_residual: 0.4,
+            verify_after_warnings: 3,
         }
     }
 }
 
+/// How far a session's input travels. Slower links batch and drop cursor
+/// reports, so the server sees coarser, straighter paths than were drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LatencyClass {
+    /// Same machine, every report arrives.
+    Local,
+    Low,
+    /// Also assumed until a session is measured, as the most lenient class.
+    #[default]
+    High,
+}
+
+impl LatencyClass {
+    pub fn of(round_trip: Duration) -> Self {
+        if round_trip < Duration::from_millis(5) {
+            Self::Local
+        } else if round_trip < Duration::from_millis(80) {
+            Self::Low
+        } else {
+            Self::High
+        }
+    }
+}
+
+/// The link a run was played over, as the session measured it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Link {
+    pub latency: LatencyClass,
+    /// Zero until a probe comes back.
+    pub round_trip: Duration,
+}
+
+impl Link {
+    /// Played on the machine that recorded it, e.g. an offline run.
+    pub const LOCAL: Self = Self {
+        latency: LatencyClass::Local,
+        round_trip: Duration::ZERO,
+    };
+}
+
+/// How closely a player is watched, raised by the DB worker as their runs
+/// get flagged and lowered again after a streak of clean ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Strictness {
+    /// Only the cheap checks.
+    #[default]
+    Baseline,
+    /// Also the jitter and path analysis.
+    Full,
+    /// Like `Full`, and every run's replay is verified, not just top ones.
+    Replay,
+}
+
+/// Limits on how machine-like an approach path may look.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathShapeThresholds {
+    /// Displacement over path length above which a path is a ruler line.
+    pub max_straightness: f64,
+    /// Bits of entropy in the turning angles below which a path is too regular,
+    /// as a straight line or an evenly sampled spline is.
+    pub min_turn_entropy: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathShapeConfig {
+    pub local: PathShapeThresholds,
+    pub low_latency: PathShapeThresholds,
+    pub high_latency: PathShapeThresholds,
+}
+
+impl Default for PathShapeConfig {
+    fn default() -> Self {
+        Self {
+            local: PathShapeThresholds {
+                max_straightness: 0.99,
+                min_turn_entropy: 0.5,
+            },
+            low_latency: PathShapeThresholds {
+                max_straightness: 0.995,
+                min_turn_entropy: 0.3,
+            },
+            high_latency: PathShapeThresholds {
+                max_straightness: 1.0,
+                min_turn_entropy: 0.0,
+            },
+        }
+    }
+}
+
+impl PathShapeConfig {
+    pub fn for_class(&self, latency: LatencyClass) -> &PathShapeThresholds {
+        match latency {
+            LatencyClass::Local => &self.local,
+            LatencyClass::Low => &self.low_latency,
+            LatencyClass::High => &self.high_latency,
+        }
+    }
+}
+
+/// Impossible cursor movement spotted by `BehaviorAnalyzer::observe_move`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MotionViolation {
+    Teleport,
+    Speed,
+}
+
+impl MotionViolation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Teleport => "teleport",
+            Self::Speed => "speed",
+        }
+    }
+}
+
+/// Per-session cursor velocity state.
+struct MotionTracker {
+    max_jump_cells: Option<u16>,
+    max_cells_per_sec: Option<f32>,
+    window: Duration,
+    /// Moves within `window` of the newest one, oldest first.
+    recent: VecDeque<MouseTrace>,
+    /// Held until the next hit, which it refuses.
+    pending: Option<MotionViolation>,
+}
+
+impl MotionTracker {
+    fn observe(&mut self, trace: MouseTrace) -> Option<MotionViolation> {
+        let violation = self.check(&trace);
+        self.recent.push_back(trace);
+        while let Some(oldest) = self.recent.front() {
+            if trace.time.duration_since(oldest.time) <= self.window {
+                break;
+            }
+            self.recent.pop_front();
+        }
+        if violation.is_some() {
+            self.pending = violation;
+        }
+        violation
+    }
+
+    fn check(&self, trace: &MouseTrace) -> Option<MotionViolation> {
+        let last = self.recent.back()?;
+        let jump = distance(last.pos, trace.pos);
+        if self.max_jump_cells.is_some_and(|max| jump > max as u32) {
+            return Some(MotionViolation::Teleport);
+        }
+        let max_speed = self.max_cells_per_sec?;
+        let oldest = self.recent.front()?;
+        let span = trace.time.duration_since(oldest.time);
+        // a shorter span is dominated by network batching, not by the player
+        if span < self.window / 2 {
+            return None;
+        }
+        let path: u32 = self
+            .recent
+            .iter()
+            .zip(self.recent.iter().skip(1).chain([trace]))
+            .map(|(a, b)| distance(a.pos, b.pos))
+            .sum();
+        (path as f32 / span.as_secs_f32() > max_speed).then_some(MotionViolation::Speed)
+    }
+}
+
+fn distance(a: Point, b: Point) -> u32 {
+    a.x.abs_diff(b.x) as u32 + a.y.abs_diff(b.y) as u32
+}
+
+/// A click on the target, with the cursor path since it spawned.
+pub struct Interaction<'a> {
+    pub history: &'a VecDeque<MouseTrace>,
+    pub spawn_time: Instant,
+    pub click_pos: Point,
+    pub latency: LatencyClass,
+    /// Measured terminal round trip; zero until a probe comes back.
+    pub round_trip: Duration,
+    /// The session has been refused before; costly checks only look at these.
+    pub suspect: bool,
+}
+
+/// One heuristic in the `BehaviorAnalyzer` pipeline.
+pub trait Check: Send + Sync {
+    /// Stable identifier, used to disable the check and in logs.
+    fn name(&self) -> &'static str;
+    /// Lowest strictness the check runs at.
+    fn min_strictness(&self) -> Strictness {
+        Strictness::Baseline
+    }
+    /// `false` refuses the hit.
+    fn is_legit(&self, interaction: &Interaction) -> bool;
+    /// What the check measured and the threshold it held it to, for tuning.
+    /// `None` when the interaction told it nothing.
+    fn measure(&self, _interaction: &Interaction) -> Option<Measurement> {
+        None
+    }
+}
+
+/// One value a check measured, in the check's own unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub value: f64,
+    pub limit: f64,
+}
+
+/// A hit a check refused, kept for telemetry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CheckEvent {
+    pub check: &'static str,
+    /// `None` for the movement checks, which judge moves rather than hits.
+    pub measurement: Option<Measurement>,
+}
+
+/// Refuses hits faster than a human could react, or with no cursor trace at all.
+pub struct ReactionTimeCheck {
+    pub min_reaction_time: Duration,
+    pub max_latency_slack: Duration,
+}
+
+impl Check for ReactionTimeCheck {
+    fn name(&self) -> &'static str {
+        "reaction_time"
+    }
+
+    fn is_legit(&self, interaction: &Interaction) -> bool {
+        self.measure(interaction)
+            .is_some_and(|m| m.value >= m.limit)
+    }
+
+    /// Milliseconds from the spawn to the last cursor report, against a
+    /// limit lowered by the session's round trip. Slow links deliver cursor
+    /// reports in bursts, so the server's clock says little about the player's.
+    fn measure(&self, interaction: &Interaction) -> Option<Measurement> {
+        let last_trace = interaction.history.back()?;
+        let slack = interaction.round_trip.min(self.max_latency_slack);
+        Some(Measurement {
+            value: last_trace
+                .time
+                .duration_since(interaction.spawn_time)
+                .as_secs_f64()
+                * 1000.0,
+            limit: self.min_reaction_time.saturating_sub(slack).as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Refuses hits whose cursor trace arrived on a near-perfect clock. Terminals
+/// and SSH add jitter to real mouse input; scripts tend to sleep a fixed step.
+pub struct TimingRegularityCheck {
+    pub min_jitter: Duration,
+}
+
+impl TimingRegularityCheck {
+    /// Fewer intervals than this say too little about the input source.
+    const MIN_INTERVALS: usize = 8;
+}
+
+impl Check for TimingRegularityCheck {
+    fn name(&self) -> &'static str {
+        "timing_regularity"
+    }
+
+    fn min_strictness(&self) -> Strictness {
+        Strictness::Full
+    }
+
+    fn is_legit(&self, interaction: &Interaction) -> bool {
+        self.measure(interaction).is_none_or(|m| m.value >= m.limit)
+    }
+
+    /// Standard deviation of the report intervals, in microseconds.
+    fn measure(&self, interaction: &Interaction) -> Option<Measurement> {
+        let intervals: Vec<f64> = interaction
+            .history
+            .iter()
+            .zip(interaction.history.iter().skip(1))
+            .map(|(a, b)| b.time.duration_since(a.time).as_secs_f64())
+            .collect();
+        if intervals.len() < Self::MIN_INTERVALS {
+            return None;
+        }
+        let n = intervals.len() as f64;
+        let mean = intervals.iter().sum::<f64>() / n;
+        // moves batched into one packet share a timestamp; that's the network, not a clock
+        if mean < 0.001 {
+            return None;
+        }
+        let variance = intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>() / n;
+        Some(Measurement {
+            value: variance.sqrt() * 1e6,
+            limit: self.min_jitter.as_secs_f64() * 1e6,
+        })
+    }
+}
+
+/// Refuses approach paths that are ruler-straight or turn with machine-like
+/// regularity. Real hands overshoot, correct and wobble on the way in.
+pub struct PathShapeCheck {
+    pub config: PathShapeConfig,
+}
+
+impl PathShapeCheck {
+    /// Fewer cursor steps than this are a flick, which can be straight.
+    const MIN_STEPS: usize = 8;
+    const TURN_BINS: usize = 8;
+
+    /// Straightness and turn entropy of the path, if it is long enough to
+    /// judge and not along a row or column.
+    fn shape(history: &VecDeque<MouseTrace>) -> Option<(f64, f64)> {
+        let steps: Vec<(f64, f64)> = history
+            .iter()
+            .zip(history.iter().skip(1))
+            .map(|(a, b)| {
+                (
+                    f64::from(b.pos.x) - f64::from(a.pos.x),
+                    f64::from(b.pos.y) - f64::from(a.pos.y),
+                )
+            })
+            .filter(|&(dx, dy)| dx != 0.0 || dy != 0.0)
+            .collect();
+        if steps.len() < Self::MIN_STEPS {
+            return None;
+        }
+        // cursor reports are whole cells, so a hand sweeping along a row or
+        // column draws a ruler line as well; the timing check judges those
+        if steps.iter().all(|&(_, dy)| dy == 0.0) || steps.iter().all(|&(dx, _)| dx == 0.0) {
+            return None;
+        }
+
+        let length: f64 = steps.iter().map(|(dx, dy)| dx.hypot(*dy)).sum();
+        let (x, y) = steps
+            .iter()
+            .fold((0.0, 0.0), |(x, y), (dx, dy)| (x + dx, y + dy));
+
+        let mut bins = [0u32; Self::TURN_BINS];
+        for (a, b) in steps.iter().zip(steps.iter().skip(1)) {
+            let turn = (b.1.atan2(b.0) - a.1.atan2(a.0)).rem_euclid(std::f64::consts::TAU);
+            let bin = (turn / std::f64::consts::TAU * Self::TURN_BINS as f64).round() as usize;
+            bins[bin % Self::TURN_BINS] += 1;
+        }
+        let turns = (steps.len() - 1) as f64;
+        let entropy: f64 = bins
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = f64::from(n) / turns;
+                -p * p.log2()
+            })
+            .sum();
+        Some((x.hypot(y) / length, entropy))
+    }
+}
+
+impl Check for PathShapeCheck {
+    fn name(&self) -> &'static str {
+        "path_shape"
+    }
+
+    fn min_strictness(&self) -> Strictness {
+        Strictness::Full
+    }
+
+    fn is_legit(&self, interaction: &Interaction) -> bool {
+        let thresholds = self.config.for_class(interaction.latency);
+        Self::shape(interaction.history).is_none_or(|(straightness, entropy)| {
+            straightness <= thresholds.max_straightness && entropy >= thresholds.min_turn_entropy
+        })
+    }
+
+    /// Straightness when it is over the limit, otherwise turn entropy in bits.
+    fn measure(&self, interaction: &Interaction) -> Option<Measurement> {
+        let thresholds = self.config.for_class(interaction.latency);
+        let (straightness, entropy) = Self::shape(interaction.history)?;
+        Some(if straightness > thresholds.max_straightness {
+            Measurement {
+                value: straightness,
+                limit: thresholds.max_straightness,
+            }
+        } else {
+            Measurement {
+                value: entropy,
+                limit: thresholds.min_turn_entropy,
+            }
+        })
+    }
+}
+
+/// Refuses approach paths that a single smooth curve explains almost exactly,
+/// which is what "humanizer" bots draw: Bezier curves without genuine noise.
+/// Only runs for suspect sessions, as fitting every hit isn't free.
+pub struct CurveFitCheck {
+    pub min_residual: f64,
+}
+
+impl CurveFitCheck {
+    /// A cubic needs this many points before a close fit means anything.
+    const MIN_POINTS: usize = 12;
+    /// Cells the path must stray from the straight line between its ends;
+    /// a cubic fits any near-straight approach, so those tell nothing.
+    const MIN_BEND: f64 = 2.0;
+
+    /// Farthest any point lies from the chord between the first and last.
+    fn bend(history: &VecDeque<MouseTrace>) -> Option<f64> {
+        let (first, last) = (history.front()?.pos, history.back()?.pos);
+        let (x0, y0) = (f64::from(first.x), f64::from(first.y));
+        let (dx, dy) = (f64::from(last.x) - x0, f64::from(last.y) - y0);
+        let chord = dx.hypot(dy);
+        if chord == 0.0 {
+            return None;
+        }
+        history
+            .iter()
+            .map(|t| {
+                ((f64::from(t.pos.x) - x0) * dy - (f64::from(t.pos.y) - y0) * dx).abs() / chord
+            })
+            .max_by(f64::total_cmp)
+    }
+
+    /// RMS distance per axis between the path and the closest cubic through
+    /// it, fitted over time and over report order; bots may ease either.
+    /// `None` for paths too short or too straight to judge.
+    fn residual(history: &VecDeque<MouseTrace>) -> Option<f64> {
+        if history.len() < Self::MIN_POINTS || Self::bend(history)? < Self::MIN_BEND {
+            return None;
+        }
+        let start = history.front()?.time;
+        let times: Vec<f64> = history
+            .iter()
+            .map(|t| t.time.duration_since(start).as_secs_f64())
+            .collect();
+        let order: Vec<f64> = (0..history.len()).map(|i| i as f64).collect();
+        let xs: Vec<f64> = history.iter().map(|t| f64::from(t.pos.x)).collect();
+        let ys: Vec<f64> = history.iter().map(|t| f64::from(t.pos.y)).collect();
+        [times, order]
+            .iter()
+            .filter_map(|ts| {
+                let ex = cubic_residuals(ts, &xs)?;
+                let ey = cubic_residuals(ts, &ys)?;
+                Some(((ex + ey) / (2 * xs.len()) as f64).sqrt())
+            })
+            .min_by(f64::total_cmp)
+    }
+}
+
+impl Check for CurveFitCheck {
+    fn name(&self) -> &'static str {
+        "curve_fit"
+    }
+
+    fn min_strictness(&self) -> Strictness {
+        Strictness::Full
+    }
+
+    fn is_legit(&self, interaction: &Interaction) -> bool {
+        self.measure(interaction).is_none_or(|m| m.value >= m.limit)
+    }
+
+    /// Fit residual in cells.
+    fn measure(&self, interaction: &Interaction) -> Option<Measurement> {
+        if !interaction.suspect {
+            return None;
+        }
+        Some(Measurement {
+            value: Self::residual(interaction.history)?,
+            limit: self.min_residual,
+        })
+    }
+}
+
+/// Sum of squared residuals of the least-squares cubic through `(ts, vs)`,
+/// or `None` when the parameters don't spread enough to fit one.
+fn cubic_residuals(ts: &[f64], vs: &[f64]) -> Option<f64> {
+    let span = ts.last()? - ts.first()?;
+    if span <= 0.0 {
+        return None;
+    }
+    let ts: Vec<f64> = ts.iter().map(|t| (t - ts[0]) / span).collect();
+    // normal equations, augmented with the right-hand side
+    let mut m = [[0.0f64; 5]; 4];
+    for (t, v) in ts.iter().zip(vs) {
+        let powers = [1.0, *t, t * t, t * t * t];
+        for (row, p) in m.iter_mut().zip(powers) {
+            for (cell, q) in row.iter_mut().zip(powers) {
+                *cell += p * q;
+            }
+            row[4] += p * v;
+        }
+    }
+    for col in 0..4 {
+        let pivot = (col..4).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+        if m[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        m.swap(col, pivot);
+        let pivot_row = m[col];
+        for (i, row) in m.iter_mut().enumerate() {
+            if i != col {
+                let factor = row[col] / pivot_row[col];
+                for (cell, p) in row.iter_mut().zip(pivot_row).skip(col) {
+                    *cell -= factor * p;
+                }
+            }
+        }
+    }
+    let coeffs: Vec<f64> = (0..4).map(|i| m[i][4] / m[i][i]).collect();
+    Some(
+        ts.iter()
+            .zip(vs)
+            .map(|(t, v)| {
+                let fit = coeffs[0] + t * (coeffs[1] + t * (coeffs[2] + t * coeffs[3]));
+                (v - fit).powi(2)
+            })
+            .sum(),
+    )
+}
+
+/// Runs its checks in order; the first one that fails refuses the hit.
+/// Cursor movement is checked as it arrives, and a violation refuses the next hit.
 pub struct BehaviorAnalyzer {
-    config: AntiCheatConfig,
+    checks: Vec<Box<dyn Check>>,
+    motion: MotionTracker,
+    latency: LatencyClass,
+    round_trip: Duration,
+    strictness: Strictness,
+    /// Refused hits since the session last passed verification.
+    warnings: u32,
+    verify_after_warnings: Option<u32>,
+    /// Refusals since the run started, oldest first.
+    events: Vec<CheckEvent>,
 }
 
 impl BehaviorAnalyzer {
+    /// Refusals kept per run; a run refused more often than this says enough.
+    const MAX_EVENTS: usize = 100;
+
+    /// The built-in checks, tuned by `config`.
     pub fn new(config: AntiCheatConfig) -> Self {
-        Self { config }
+        let mut analyzer = Self::empty()
+            .with_check(ReactionTimeCheck {
+                min_reaction_time: config.min_reaction_time,
+                max_latency_slack: config.max_latency_slack,
+            })
+            .with_check(TimingRegularityCheck {
+                min_jitter: config.min_timing_jitter,
+            })
+            .with_check(PathShapeCheck {
+                config: config.path_shape,
+            })
+            .with_check(CurveFitCheck {
+                min_residual: config.min_curve_residual,
+            });
+        analyzer.motion.max_jump_cells = Some(config.max_jump_cells);
+        analyzer.motion.max_cells_per_sec = Some(config.max_cells_per_sec);
+        analyzer.motion.window = config.speed_window;
+        analyzer.verify_after_warnings = Some(config.verify_after_warnings);
+        analyzer
+    }
+
+    /// Accepts every hit.
+    pub fn empty() -> Self {
+        Self {
+            checks: Vec::new(),
+            motion: MotionTracker {
+                max_jump_cells: None,
+                max_cells_per_sec: None,
+                window: AntiCheatConfig::default().speed_window,
+                recent: VecDeque::new(),
+                pending: None,
+            },
+            latency: LatencyClass::default(),
+            round_trip: Duration::ZERO,
+            // unknown players get every check until the DB says otherwise
+            strictness: Strictness::Full,
+            warnings: 0,
+            verify_after_warnings: None,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn with_check(mut self, check: impl Check + 'static) -> Self {
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Also accepts "teleport" and "speed" for the movement checks.
+    pub fn without_check(mut self, name: &str) -> Self {
+        self.checks.retain(|c| c.name() != name);
+        if name == MotionViolation::Teleport.name() {
+            self.motion.max_jump_cells = None;
+        }
+        if name == MotionViolation::Speed.name() {
+            self.motion.max_cells_per_sec = None;
+        }
+        self
+    }
+
+    /// Feeds one cursor move; returns a violation as soon as it is seen.
+    pub fn observe_move(&mut self, trace: MouseTrace) -> Option<MotionViolation> {
+        self.motion.observe(trace)
+    }
+
+    /// Fits the thresholds to the session's link, e.g. each time it is measured.
+    pub fn set_latency(&mut self, round_trip: Duration) {
+        self.latency = LatencyClass::of(round_trip);
+        self.round_trip = round_trip;
+    }
+
+    /// Picks the checks to run, e.g. from the player's record once it loads.
+    pub fn set_strictness(&mut self, strictness: Strictness) {
+        self.strictness = strictness;
+    }
+
+    pub fn strictness(&self) -> Strictness {
+        self.strictness
+    }
+
+    pub fn link(&self) -> Link {
+        Link {
+            latency: self.latency,
+            round_trip: self.round_trip,
+        }
+    }
+
+    fn active_checks(&self) -> impl Iterator<Item = &dyn Check> {
+        self.checks
+            .iter()
+            .map(|c| c.as_ref())
+            .filter(|c| c.min_strictness() <= self.strictness)
+    }
+
+    /// Forgets the movement and refusals seen so far, e.g. when a new run starts.
+    pub fn reset_motion(&mut self) {
+        self.motion.recent.clear();
+        self.motion.pending = None;
+        self.events.clear();
+    }
+
+    /// Refusals since the run started, e.g. to store with its submission.
+    pub fn take_events(&mut self) -> Vec<CheckEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Name of the first check that refuses the interaction.
+    pub fn failed_check(&self, interaction: &Interaction) -> Option<&'static str> {
+        if let Some(violation) = self.motion.pending {
+            return Some(violation.name());
+        }
+        self.active_checks()
+            .find(|c| !c.is_legit(interaction))
+            .map(|c| c.name())
+    }
+
+    /// Judges a hit on the target; any pending movement violation is used up.
+    pub fn judge(&mut self, interaction: &Interaction) -> Result<(), &'static str> {
+        let failed = self.failed_check(interaction);
+        self.motion.pending = None;
+        for check in self.active_checks() {
+            if let Some(m) = check.measure(interaction) {
+                tracing::debug!(
+                    check = check.name(),
+                    value = m.value,
+                    limit = m.limit,
+                    "Measured hit"
+                );
+            }
+        }
+        if let Some(check) = failed {
+            self.warnings += 1;
+            let measurement = self
+                .checks
+                .iter()
+                .find(|c| c.name() == check)
+                .and_then(|c| c.measure(interaction));
+            tracing::info!(
+                check,
+                value = measurement.map(|m| m.value),
+                limit = measurement.map(|m| m.limit),
+                latency = ?self.latency,
+                round_trip_ms = self.round_trip.as_millis() as u64,
+                "Anticheat refused a hit"
+            );
+            if self.events.len() < Self::MAX_EVENTS {
+                self.events.push(CheckEvent { check, measurement });
+            }
+            // a refusal earns the rest of the session the full analysis
+            self.strictness = self.strictness.max(Strictness::Full);
+        }
+        failed.map_or(Ok(()), Err)
+    }
+
+    /// Whether the session has been refused at all since it was last verified.
+    pub fn is_suspect(&self) -> bool {
+        self.warnings > 0
+    }
+
+    /// Whether the session has been refused often enough to be challenged.
+    pub fn needs_verification(&self) -> bool {
+        self.verify_after_warnings
+            .is_some_and(|limit| self.warnings >= limit)
+    }
+
+    /// Called once a verification round is over, whatever its outcome.
+    pub fn clear_warnings(&mut self) {
+        self.warnings = 0;
     }
 
     pub fn is_legit_interaction(
-        &self,
+        &mut self,
         history: &VecDeque<MouseTrace>,
         spawn_time: Instant,
-        _click_pos: Point,
+        click_pos: Point,
     ) -> bool {
-        let last_trace = match history.back() {
-            Some(t) => t,
-            None => return false,
-        };
+        self.judge(&Interaction {
+            history,
+            spawn_time,
+            click_pos,
+            latency: self.latency,
+            round_trip: self.round_trip,
+            suspect: self.is_suspect(),
+        })
+        .is_ok()
+    }
+}
+
+/// Replays a recorded run through a fresh pipeline, so a score can be judged
+/// without trusting the client that played it. Every `Score` frame marks a
+/// counted hit on the preceding click, judged as over `link`, as it was live.
+/// Returns the name of the first failure.
+pub fn verify_replay(
+    config: &AntiCheatConfig,
+    replay: &Replay,
+    claimed_score: u32,
+    link: Link,
+) -> Result<(), &'static str> {
+    let mut analyzer = BehaviorAnalyzer::new(config.clone());
+    let start = Instant::now();
+    let mut history = VecDeque::new();
+    let mut spawn_time = start;
+    let mut last_click = None;
+    let mut score = 0;
 
-        // check reaction speed
-        if last_trace.time.duration_since(spawn_time) < self.config.min_reaction_time {
-            return false;
+    for frame in &replay.frames {
+        let at = start + Duration::from_millis(frame.at_ms.into());
+        match frame.event {
+            ReplayEvent::TargetSpawn(_) => {
+                spawn_time = at;
+                history.clear();
+            }
+            ReplayEvent::Resize(_) => {}
+            ReplayEvent::MouseMove(pos) => {
+                let trace = MouseTrace { pos, time: at };
+                let _ = analyzer.observe_move(trace);
+                history.push_back(trace);
+            }
+            ReplayEvent::MouseClick(pos) => {
+                history.push_back(MouseTrace { pos, time: at });
+                last_click = Some(pos);
+            }
+            ReplayEvent::Score(new_score) => {
+                analyzer.judge(&Interaction {
+                    history: &history,
+                    spawn_time,
+                    click_pos: last_click.take().ok_or("hit_without_click")?,
+                    latency: link.latency,
+                    round_trip: link.round_trip,
+                    suspect: analyzer.is_suspect(),
+                })?;
+                score = new_score;
+            }
         }
-        true
     }
+
+    if score != claimed_score {
+        return Err("score_mismatch");
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -48,7 +825,7 @@ mod tests {
 
     #[test]
     fn test_human_reaction_speed() {
-        let v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
         let spawn = Instant::now();
         let history = VecDeque::from(vec![MouseTrace {
             pos: Point { x: 1, y: 1 },
@@ -59,7 +836,7 @@ mod tests {
 
     #[test]
     fn test_bot_reaction_speed() {
-        let v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
         let spawn = Instant::now();
         let history = VecDeque::from(vec![MouseTrace {
             pos: Point { x: 1, y: 1 },
@@ -67,4 +844,229 @@ mod tests {
         }]);
         assert!(!v.is_legit_interaction(&history, spawn, Point { x: 1, y: 1 }));
     }
+
+    #[test]
+    fn test_round_trip_lowers_reaction_limit() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let spawn = Instant::now();
+        let after = |ms| {
+            VecDeque::from(vec![MouseTrace {
+                pos: Point { x: 1, y: 1 },
+                time: spawn + Duration::from_millis(ms),
+            }])
+        };
+        v.set_latency(Duration::from_millis(150));
+        assert!(v.is_legit_interaction(&after(50), spawn, Point { x: 1, y: 1 }));
+        // the slack is capped, so a slow link can't excuse any speed
+        assert!(!v.is_legit_interaction(&after(15), spawn, Point { x: 1, y: 1 }));
+        v.set_latency(Duration::from_millis(2));
+        assert!(!v.is_legit_interaction(&after(50), spawn, Point { x: 1, y: 1 }));
+    }
+
+    #[test]
+    fn test_replay_is_judged_over_the_live_round_trip() {
+        let config = AntiCheatConfig::default();
+        let mut live = BehaviorAnalyzer::new(config.clone());
+        live.set_latency(Duration::from_millis(150));
+        let spawn = Instant::now();
+        let history = VecDeque::from([trace(10, spawn + Duration::from_millis(50))]);
+        assert!(live.is_legit_interaction(&history, spawn, Point { x: 10, y: 1 }));
+
+        let mut replay = Replay::default();
+        let at = Duration::from_millis;
+        replay.record(at(0), ReplayEvent::TargetSpawn(Point { x: 10, y: 1 }));
+        replay.record(at(50), ReplayEvent::MouseMove(Point { x: 10, y: 1 }));
+        replay.record(at(50), ReplayEvent::MouseClick(Point { x: 10, y: 1 }));
+        replay.record(at(50), ReplayEvent::Score(100));
+        assert_eq!(verify_replay(&config, &replay, 100, live.link()), Ok(()));
+        assert_eq!(
+            verify_replay(&config, &replay, 100, Link::LOCAL),
+            Err("reaction_time")
+        );
+    }
+
+    fn trace(x: u16, at: Instant) -> MouseTrace {
+        MouseTrace {
+            pos: Point { x, y: 1 },
+            time: at,
+        }
+    }
+
+    #[test]
+    fn test_teleport_refuses_next_hit() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let spawn = Instant::now();
+        assert_eq!(v.observe_move(trace(1, spawn)), None);
+        assert_eq!(
+            v.observe_move(trace(100, spawn + Duration::from_millis(300))),
+            Some(MotionViolation::Teleport)
+        );
+        let history = VecDeque::from([trace(100, spawn + Duration::from_millis(300))]);
+        assert!(!v.is_legit_interaction(&history, spawn, Point { x: 100, y: 1 }));
+        // the violation only costs one hit
+        assert!(v.is_legit_interaction(&history, spawn, Point { x: 100, y: 1 }));
+        assert!(!v.needs_verification());
+    }
+
+    #[test]
+    fn test_superhuman_speed() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let start = Instant::now();
+        // 30 cells every 10ms is 3000 cells/s
+        let verdicts: Vec<_> = (0..20)
+            .map(|i| {
+                v.observe_move(trace(
+                    i % 2 * 30,
+                    start + Duration::from_millis(i as u64 * 10),
+                ))
+            })
+            .collect();
+        assert!(verdicts.contains(&Some(MotionViolation::Speed)));
+    }
+
+    #[test]
+    fn test_verify_replay() {
+        let mut replay = Replay::default();
+        let at = Duration::from_millis;
+        replay.record(at(0), ReplayEvent::TargetSpawn(Point { x: 10, y: 5 }));
+        replay.record(at(200), ReplayEvent::MouseMove(Point { x: 10, y: 5 }));
+        replay.record(at(250), ReplayEvent::MouseClick(Point { x: 10, y: 5 }));
+        replay.record(at(250), ReplayEvent::Score(100));
+        replay.record(at(250), ReplayEvent::TargetSpawn(Point { x: 30, y: 5 }));
+        let config = AntiCheatConfig::default();
+        let local = Link::LOCAL;
+        assert_eq!(verify_replay(&config, &replay, 100, local), Ok(()));
+        assert_eq!(
+            verify_replay(&config, &replay, 500, local),
+            Err("score_mismatch")
+        );
+
+        // a click 20ms after the spawn is too fast for a human
+        replay.record(at(270), ReplayEvent::MouseClick(Point { x: 30, y: 5 }));
+        replay.record(at(270), ReplayEvent::Score(200));
+        assert_eq!(
+            verify_replay(&config, &replay, 200, local),
+            Err("reaction_time")
+        );
+    }
+
+    #[test]
+    fn test_clockwork_input_is_refused() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let spawn = Instant::now();
+        let steps = |jitter: fn(u64) -> u64| -> VecDeque<MouseTrace> {
+            (0..12)
+                .map(|i| {
+                    trace(
+                        i as u16,
+                        spawn + Duration::from_micros(200_000 + i * 10_000 + jitter(i)),
+                    )
+                })
+                .collect()
+        };
+        let scripted = steps(|i| i % 2 * 50);
+        assert!(!v.is_legit_interaction(&scripted, spawn, Point { x: 11, y: 1 }));
+        let human = steps(|i| i * 7919 % 6000);
+        assert!(v.is_legit_interaction(&human, spawn, Point { x: 11, y: 1 }));
+    }
+
+    #[test]
+    fn test_ruler_straight_path_is_refused() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        v.set_latency(Duration::ZERO);
+        let spawn = Instant::now();
+        let path = |y: fn(u16) -> u16| -> VecDeque<MouseTrace> {
+            (0..12u16)
+                .map(|i| MouseTrace {
+                    pos: Point { x: i, y: y(i) },
+                    time: spawn
+                        + Duration::from_millis(200 + u64::from(i) * 13 + u64::from(i * i % 7)),
+                })
+                .collect()
+        };
+        let ruler = path(|i| i);
+        assert!(!v.is_legit_interaction(&ruler, spawn, Point { x: 11, y: 11 }));
+        // a row is all a terminal reports of a hand sweeping straight across it
+        let sweep = path(|_| 5);
+        assert!(v.is_legit_interaction(&sweep, spawn, Point { x: 11, y: 5 }));
+        let wobbly = path(|i| [5, 6, 6, 5, 4, 5, 7, 6, 6, 5, 5, 4][i as usize]);
+        assert!(v.is_legit_interaction(&wobbly, spawn, Point { x: 11, y: 4 }));
+
+        // over a slow link the same ruler line may just be dropped reports
+        v.set_latency(Duration::from_millis(150));
+        assert!(v.is_legit_interaction(&ruler, spawn, Point { x: 11, y: 11 }));
+
+        // players with a clean record only get the cheap checks
+        v.set_latency(Duration::ZERO);
+        v.set_strictness(Strictness::Baseline);
+        assert!(v.is_legit_interaction(&ruler, spawn, Point { x: 11, y: 11 }));
+    }
+
+    #[test]
+    fn test_repeated_warnings_call_for_verification() {
+        let mut v = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        let history = VecDeque::new();
+        for _ in 0..3 {
+            assert!(!v.needs_verification());
+            assert!(!v.is_legit_interaction(&history, Instant::now(), Point { x: 1, y: 1 }));
+        }
+        assert!(v.needs_verification());
+        let events = v.take_events();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].check, "reaction_time");
+        assert_eq!(events[0].measurement, None);
+        v.clear_warnings();
+        assert!(!v.needs_verification());
+    }
+
+    #[test]
+    fn test_bezier_path_is_refused_once_suspect() {
+        let spawn = Instant::now();
+        let path = |noise: fn(u64) -> f64| -> VecDeque<MouseTrace> {
+            (0..20u64)
+                .map(|i| {
+                    let t = i as f64 / 19.0;
+                    let u = 1.0 - t;
+                    // cubic Bezier from (0, 0) via (10, 30) and (40, -10) to (60, 10)
+                    let x = 3.0 * u * u * t * 10.0 + 3.0 * u * t * t * 40.0 + t * t * t * 60.0;
+                    let y = 3.0 * u * u * t * 30.0 - 3.0 * u * t * t * 10.0 + t * t * t * 10.0;
+                    MouseTrace {
+                        pos: Point {
+                            x: (x + noise(i)).round() as u16,
+                            y: (y + 10.0 + noise(i * 7)).round() as u16,
+                        },
+                        time: spawn + Duration::from_millis(200 + i * 15 + i * i % 11),
+                    }
+                })
+                .collect()
+        };
+        let bot = path(|_| 0.0);
+        let human = path(|i| (i * 7919 % 13) as f64 / 2.0 - 3.0);
+        let check = CurveFitCheck { min_residual: 0.4 };
+        let interaction = |history, suspect| Interaction {
+            history,
+            spawn_time: spawn,
+            click_pos: Point { x: 60, y: 20 },
+            latency: LatencyClass::Local,
+            round_trip: Duration::ZERO,
+            suspect,
+        };
+
+        assert!(check.is_legit(&interaction(&bot, false)));
+        assert!(!check.is_legit(&interaction(&bot, true)));
+        assert!(check.is_legit(&interaction(&human, true)));
+        // a straight approach fits any cubic, so it is left to the path shape check
+        let straight: VecDeque<_> = (0..20u64)
+            .map(|i| trace(i as u16 * 3, spawn + Duration::from_millis(200 + i * 15)))
+            .collect();
+        assert!(check.is_legit(&interaction(&straight, true)));
+    }
+
+    #[test]
+    fn test_checks_can_be_disabled() {
+        let mut v =
+            BehaviorAnalyzer::new(AntiCheatConfig::default()).without_check("reaction_time");
+        let history = VecDeque::new();
+        assert!(v.is_legit_interaction(&history, Instant::now(), Point { x: 1, y: 1 }));
+    }
 }
@@ -1,15 +1,66 @@
-use crate::anticheat::BehaviorAnalyzer;
-use crate::db::{DbCache, DbRequest, UserContext};
+use crate::anticheat::{AntiCheatConfig, BehaviorAnalyzer};
+use crate::chat::{CHAT_MAX_LEN, ChatBus, ChatMessage, ChatRejected};
+use crate::db::{
+    ACTIVITY_HISTORY_DAYS, ClientKind, DbCache, DbRequest, GameResult, LINK_CODE_LEN,
+    LINK_CODE_TTL, RECOVERY_CODE_LEN, RankingPeriod, SCORE_HISTORY_LEN, ScoreEntry, ScoresPage,
+    UserContext, UserRank,
+};
 use crate::domain::{
-    CombatStats, MAX_PLAYER_NAME_LEN, MouseTrace, PLAYING_TIME_SEC, Point, Size, Target,
+    CombatStats, GameConfig, GameMode, Heatmap, MAX_PLAYER_NAME_LEN, MouseTrace, Point, Size,
+    Target,
 };
+use crate::duel::{DuelLink, DuelTicket};
+use crate::i18n::Messages;
+use crate::preferences::{GameDuration, HitFeedback, Language, SettingsField};
+use crate::replay::{Replay, ReplayEvent};
+use crate::signing::{EventSigner, Signature, SigningKey};
+use crate::theme::ColorSupport;
 use anyhow::Result;
-use std::collections::VecDeque;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::mpsc;
+use tokio::sync::{broadcast, mpsc, oneshot};
 
 pub const RANKING_LIMIT: u32 = 10;
+pub const LEADERBOARD_PAGE_SIZE: u32 = 15;
+/// Linger on the final frame before returning to the menu.
+const REPLAY_END_HOLD: Duration = Duration::from_secs(1);
+const EFFECT_LIFETIME: Duration = Duration::from_millis(350);
+/// Time constant of the smooth cursor's easing toward the mouse.
+const CURSOR_EASING: Duration = Duration::from_millis(40);
+/// Cells of mouse travel required to finish the aiming step.
+const TUTORIAL_AIM_DISTANCE: u32 = 30;
+/// Scripted stationary targets, as fractions of the free area.
+const TUTORIAL_TARGETS: [(f64, f64); 3] = [(0.5, 0.5), (0.15, 0.3), (0.85, 0.7)];
+/// Calibration markers, as fractions of the screen.
+const CALIBRATION_MARKERS: [(f64, f64); 3] = [(0.2, 0.3), (0.8, 0.3), (0.5, 0.75)];
+/// Targets in a verification challenge; one of them may be missed.
+const VERIFY_ROUNDS: u32 = 5;
+/// How long a verification target stays up.
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(3);
+/// Rows taken by the verification prompt; targets appear below it.
+pub const VERIFY_PANEL_HEIGHT: u16 = 4;
+/// Corners verification targets appear in, as fractions of the free area.
+const VERIFY_CORNERS: [(f64, f64); 4] = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)];
+/// Minimum spacing between latency probes while the debug overlay is open.
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+/// An unanswered probe is abandoned after this long.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Length of the slide/fade between the menu, a game and its results.
+const TRANSITION_DURATION: Duration = Duration::from_millis(250);
+/// Length of the top-3 podium sequence on the game over screen.
+const PODIUM_DURATION: Duration = Duration::from_secs(3);
+/// How long the screen-edge flash stays up.
+const FEEDBACK_FLASH: Duration = Duration::from_millis(120);
+/// Menu inactivity before the attract demo starts.
+const ATTRACT_IDLE: Duration = Duration::from_secs(60);
+/// Cells the cursor moves per key press with keyboard aiming; cells are about twice as tall as wide.
+const KEYBOARD_AIM_STEP: (u16, u16) = (2, 1);
+/// Speed of the demo cursor, in cells per second.
+const DEMO_CURSOR_SPEED: f32 = 45.0;
 
 #[derive(Clone)]
 pub struct PlayingState {
@@ -18,6 +69,441 @@ pub struct PlayingState {
     pub mouse_history: VecDeque<MouseTrace>,
     pub last_target_spawn: Instant,
     pub scene_start: Instant,
+    pub mode: GameMode,
+    pub rng: StdRng,
+    /// Screen size the replay last recorded, which spawns are placed on.
+    pub screen: Size,
+    pub config: GameConfig,
+    pub last_milestone: Option<(u32, Instant)>,
+    pub replay: Replay,
+    /// Signs game events as they are recorded, when the host set a key.
+    pub signer: Option<EventSigner>,
+    /// Personal-best run to race against, standard mode only.
+    pub ghost: Option<Arc<Replay>>,
+    pub heatmap: Heatmap,
+    pub effects: Vec<Effect>,
+    /// Opponent and shared scores when this round is a duel.
+    pub duel: Option<DuelLink>,
+}
+
+impl PlayingState {
+    fn record(&mut self, elapsed: Duration, event: ReplayEvent) {
+        self.replay.record(elapsed, event);
+        if let Some(signer) = &mut self.signer {
+            signer.update(self.replay.frames.last().expect("just recorded"));
+        }
+    }
+
+    /// Places and records the next target, recording the screen size first
+    /// if the terminal was resized since the last spawn.
+    fn spawn_target(&mut self, screen: Size, elapsed: Duration) {
+        if screen != self.screen {
+            self.screen = screen;
+            self.record(elapsed, ReplayEvent::Resize(screen));
+        }
+        self.target = Target::new_random(screen, &self.config, &mut self.rng);
+        self.last_target_spawn = Instant::now();
+        let spawn = ReplayEvent::TargetSpawn(self.target.pos);
+        self.record(elapsed, spawn);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EffectKind {
+    HitBurst,
+    MissMarker,
+}
+
+/// Short-lived hit/miss feedback, dropped after `EFFECT_LIFETIME`.
+#[derive(Debug, Clone, Copy)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub pos: Point,
+    pub started: Instant,
+}
+
+impl Effect {
+    fn new(kind: EffectKind, pos: Point) -> Self {
+        Self {
+            kind,
+            pos,
+            started: Instant::now(),
+        }
+    }
+
+    /// 0.0 when spawned, 1.0 when about to expire.
+    pub fn progress(&self) -> f32 {
+        (self.started.elapsed().as_secs_f32() / EFFECT_LIFETIME.as_secs_f32()).min(1.0)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct GameOverState {
+    pub final_score: u32,
+    pub is_new_record: bool,
+    pub mode: GameMode,
+    pub heatmap: Heatmap,
+    /// Celebration played before the results when the run placed top 3.
+    pub podium: Option<Podium>,
+    pub duel: Option<DuelLink>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Podium {
+    pub rank: u32,
+    pub started: Instant,
+}
+
+impl Podium {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackEvent {
+    Hit,
+    ComboBreak,
+}
+
+/// Pending cues for the `HitFeedback` preference. The bell is raw output the
+/// renderer can't produce, so frontends drain it with `take_bell`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Feedback {
+    bell_pending: bool,
+    flash: Option<(FeedbackEvent, Instant)>,
+}
+
+impl Feedback {
+    fn signal(&mut self, style: HitFeedback, event: FeedbackEvent) {
+        match style {
+            HitFeedback::Off => {}
+            HitFeedback::Bell => self.bell_pending = true,
+            HitFeedback::Flash => self.flash = Some((event, Instant::now())),
+        }
+    }
+
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell_pending)
+    }
+
+    /// The event whose flash is still on screen, if any.
+    pub fn flash(&self) -> Option<FeedbackEvent> {
+        self.flash
+            .filter(|(_, at)| at.elapsed() < FEEDBACK_FLASH)
+            .map(|(event, _)| event)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransitionKind {
+    Slide,
+    Fade,
+}
+
+/// Animation over a freshly entered scene, cleared by `Action::Tick` once done.
+#[derive(Debug, Clone, Copy)]
+pub struct Transition {
+    pub kind: TransitionKind,
+    pub started: Instant,
+}
+
+impl Transition {
+    /// 0.0 when the scene was entered, 1.0 once finished.
+    pub fn progress(&self) -> f32 {
+        (self.started.elapsed().as_secs_f32() / TRANSITION_DURATION.as_secs_f32()).min(1.0)
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ReplayState {
+    pub replay: Arc<Replay>,
+    pub config: GameConfig,
+    pub started: Instant,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct SettingsState {
+    pub selected: usize,
+}
+
+impl SettingsState {
+    pub fn field(&self) -> SettingsField {
+        SettingsField::ALL[self.selected % SettingsField::ALL.len()]
+    }
+}
+
+/// Cursor position in fractional cells, trailing the last reported mouse cell.
+#[derive(Debug, Clone, Copy)]
+pub struct SmoothCursor {
+    pub x: f32,
+    pub y: f32,
+    updated: Instant,
+}
+
+impl SmoothCursor {
+    fn new() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            updated: Instant::now(),
+        }
+    }
+
+    /// Frame-rate independent exponential easing.
+    fn advance(&mut self, target: Point) {
+        let dt = self.updated.elapsed().as_secs_f32();
+        self.updated = Instant::now();
+
+        let alpha = 1.0 - (-dt / CURSOR_EASING.as_secs_f32()).exp();
+        self.x += (target.x as f32 - self.x) * alpha;
+        self.y += (target.y as f32 - self.y) * alpha;
+    }
+}
+
+/// Render loop instrumentation for the debug overlay, fed by the frontends.
+#[derive(Debug, Default)]
+pub struct PerfStats {
+    frames: VecDeque<Instant>,
+    last_tick: Option<Instant>,
+    /// How late the most recent tick fired relative to the tick rate.
+    pub tick_drift: Duration,
+    /// Terminal round trip, only measured by frontends that can probe it.
+    pub round_trip: Option<Duration>,
+    probe_sent: Option<Instant>,
+    last_probe: Option<Instant>,
+}
+
+impl PerfStats {
+    pub fn record_frame(&mut self, now: Instant) {
+        self.frames.push_back(now);
+        while let Some(&oldest) = self.frames.front() {
+            if now.duration_since(oldest) <= Duration::from_secs(1) {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn record_tick(&mut self, tick_rate: Duration, now: Instant) {
+        if let Some(last) = self.last_tick.replace(now) {
+            self.tick_drift = now.duration_since(last).saturating_sub(tick_rate);
+        }
+    }
+
+    /// Frames drawn during the last second.
+    pub fn fps(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns true when the frontend should send a new latency probe.
+    pub fn start_probe(&mut self, now: Instant) -> bool {
+        let due = match (self.probe_sent, self.last_probe) {
+            (Some(sent), _) => now.duration_since(sent) >= PROBE_TIMEOUT,
+            (None, Some(last)) => now.duration_since(last) >= PROBE_INTERVAL,
+            (None, None) => true,
+        };
+        if due {
+            self.probe_sent = Some(now);
+            self.last_probe = Some(now);
+        }
+        due
+    }
+
+    pub fn probe_pending(&self) -> bool {
+        self.probe_sent.is_some()
+    }
+
+    pub fn finish_probe(&mut self, now: Instant) {
+        if let Some(sent) = self.probe_sent.take() {
+            self.round_trip = Some(now.duration_since(sent));
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct LeaderboardState {
+    pub offset: u32,
+    pub entries: Vec<ScoreEntry>,
+    pub is_loading: bool,
+    /// Size of the whole board, if the backend reported it.
+    pub total: Option<u32>,
+    /// Index into `DbCache::past_seasons`; `None` shows the live boards.
+    pub season: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TutorialStep {
+    Aim,
+    Shoot,
+    Combo,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct TutorialState {
+    pub step: TutorialStep,
+    pub distance_moved: u32,
+    pub targets_hit: usize,
+    pub target: Option<Target>,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct CalibrationState {
+    /// Reported click minus marker position, one entry per marker clicked so far.
+    pub errors: Vec<(i32, i32)>,
+}
+
+impl CalibrationState {
+    pub fn marker_count(&self) -> usize {
+        CALIBRATION_MARKERS.len()
+    }
+}
+
+/// Challenge a session with repeated cheat warnings has to take before it
+/// sees its result. Targets appear in random corners after random delays,
+/// which a script replaying canned input can't anticipate.
+#[derive(Clone)]
+pub struct VerificationState {
+    pub target: Option<Target>,
+    /// When the next target appears, or when the shown one appeared.
+    pub due: Instant,
+    /// 0-based index of the current target.
+    pub round: u32,
+    pub passed: u32,
+    history: VecDeque<MouseTrace>,
+    rng: StdRng,
+    /// Shown once the challenge is over.
+    game_over: GameOverState,
+}
+
+impl PartialEq for VerificationState {
+    fn eq(&self, other: &Self) -> bool {
+        self.target == other.target && self.round == other.round
+    }
+}
+
+impl VerificationState {
+    fn new(game_over: GameOverState) -> Self {
+        let mut rng = StdRng::seed_from_u64(rand::random());
+        Self {
+            target: None,
+            due: Instant::now() + Self::delay(&mut rng),
+            round: 0,
+            passed: 0,
+            history: VecDeque::new(),
+            rng,
+            game_over,
+        }
+    }
+
+    fn delay(rng: &mut StdRng) -> Duration {
+        Duration::from_millis(rng.random_range(400..1500))
+    }
+
+    pub fn round_count(&self) -> u32 {
+        VERIFY_ROUNDS
+    }
+
+    fn next_round(&mut self) {
+        self.round += 1;
+        self.target = None;
+        self.due = Instant::now() + Self::delay(&mut self.rng);
+    }
+
+    fn is_over(&self) -> bool {
+        self.round >= VERIFY_ROUNDS
+    }
+
+    /// A target in a random corner of the screen below the prompt.
+    fn spawn_target(&mut self, screen: Size, config: &GameConfig) -> Target {
+        let (fx, fy) = VERIFY_CORNERS[self.rng.random_range(0..VERIFY_CORNERS.len())];
+        let below = Size {
+            height: screen.height.saturating_sub(VERIFY_PANEL_HEIGHT),
+            ..screen
+        };
+        let mut target = Target::at_fraction(below, config, fx, fy);
+        target.pos.y += VERIFY_PANEL_HEIGHT;
+        target
+    }
+}
+
+/// 1-based placing of `score` among the other players on a cached board,
+/// if it makes the top 3. The cache may still hold the player's own entry.
+fn podium_rank(scores: &[ScoreEntry], own_name: Option<&str>, score: u32) -> Option<u32> {
+    if score == 0 {
+        return None;
+    }
+    let ahead = scores
+        .iter()
+        .filter(|e| Some(e.name.as_str()) != own_name && e.score >= score)
+        .count() as u32;
+    (ahead < 3).then_some(ahead + 1)
+}
+
+/// Cell where the `index`-th calibration marker is drawn.
+pub fn calibration_marker(screen: Size, index: usize) -> Point {
+    let (fx, fy) = CALIBRATION_MARKERS[index % CALIBRATION_MARKERS.len()];
+    Point {
+        x: (screen.width.saturating_sub(1) as f64 * fx).round() as u16,
+        y: (screen.height.saturating_sub(1) as f64 * fy).round() as u16,
+    }
+}
+
+/// Self-playing attract loop. Nothing here is saved or replayed.
+#[derive(Clone)]
+pub struct DemoState {
+    pub target: Target,
+    pub cursor: (f32, f32),
+    pub combat_stats: CombatStats,
+    pub last_target_spawn: Instant,
+    last_step: Instant,
+    rng: StdRng,
+}
+
+impl PartialEq for DemoState {
+    fn eq(&self, other: &Self) -> bool {
+        self.target == other.target && self.cursor == other.cursor
+    }
+}
+
+impl DemoState {
+    /// Moves the cursor toward the target center and shoots once it arrives.
+    fn step(&mut self, screen: Size, config: &GameConfig, now: Instant) {
+        let dt = now.duration_since(self.last_step).as_secs_f32();
+        self.last_step = now;
+
+        if self
+            .target
+            .is_expired(self.last_target_spawn.elapsed(), &self.combat_stats, config)
+        {
+            self.combat_stats.register_miss();
+            self.respawn(screen, config, now);
+            return;
+        }
+
+        let goal_x = self.target.pos.x as f32 + self.target.visual_width as f32 / 2.0;
+        let goal_y = self.target.pos.y as f32 + self.target.visual_height as f32 / 2.0;
+        let (dx, dy) = (goal_x - self.cursor.0, goal_y - self.cursor.1);
+        // cells are about twice as tall as they are wide
+        let distance = dx.hypot(dy * 2.0);
+        let travel = DEMO_CURSOR_SPEED * dt;
+
+        if distance <= travel.max(0.5) {
+            self.cursor = (goal_x, goal_y);
+            self.combat_stats
+                .record_reaction(now.duration_since(self.last_target_spawn));
+            self.combat_stats.register_hit();
+            self.respawn(screen, config, now);
+        } else {
+            self.cursor.0 += dx / distance * travel;
+            self.cursor.1 += dy / distance * travel;
+        }
+    }
+
+    fn respawn(&mut self, screen: Size, config: &GameConfig, now: Instant) {
+        self.target = Target::new_random(screen, config, &mut self.rng);
+        self.last_target_spawn = now;
+    }
 }
 
 #[derive(Clone, PartialEq)]
@@ -25,6 +511,15 @@ pub struct NamingState {
     pub input: String,
     pub error: Option<String>,
     pub is_loading: bool,
+    /// Typing a link code from another device instead of a new name.
+    pub linking: bool,
+}
+
+/// Waiting for the lobby to find an opponent, then counting down to the round.
+#[derive(Clone, PartialEq)]
+pub struct DuelLobbyState {
+    pub since: Instant,
+    pub link: Option<DuelLink>,
 }
 
 #[derive(Clone, PartialEq)]
@@ -32,11 +527,17 @@ pub enum Scene {
     Naming(NamingState),
     Menu,
     Playing(Box<PlayingState>),
-    GameOver {
-        final_score: u32,
-        is_new_record: bool,
-    },
+    GameOver(GameOverState),
     ResetConfirmation,
+    Replay(Box<ReplayState>),
+    Settings(SettingsState),
+    Profile,
+    Tutorial(TutorialState),
+    Leaderboard(LeaderboardState),
+    Calibration(CalibrationState),
+    Demo(Box<DemoState>),
+    Verification(Box<VerificationState>),
+    DuelLobby(DuelLobbyState),
 }
 
 impl PartialEq for PlayingState {
@@ -51,33 +552,55 @@ pub type ActionResult = (
     Option<tokio::sync::oneshot::Receiver<Result<(), anyhow::Error>>>,
 );
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Which menu panel the arrow keys drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MenuFocus {
+    #[default]
+    Leaderboard,
+    Activity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum LeaderboardTab {
     Daily,
     Weekly,
+    #[default]
     AllTime,
-}
-
-impl Default for LeaderboardTab {
-    fn default() -> Self {
-        Self::AllTime
-    }
+    DailyChallenge,
 }
 
 impl LeaderboardTab {
+    pub const ALL: [Self; 4] = [
+        Self::Daily,
+        Self::Weekly,
+        Self::AllTime,
+        Self::DailyChallenge,
+    ];
+
     pub fn next(&self) -> Self {
         match self {
             Self::Daily => Self::Weekly,
             Self::Weekly => Self::AllTime,
-            Self::AllTime => Self::Daily,
+            Self::AllTime => Self::DailyChallenge,
+            Self::DailyChallenge => Self::Daily,
         }
     }
 
     pub fn prev(&self) -> Self {
         match self {
-            Self::Daily => Self::AllTime,
+            Self::Daily => Self::DailyChallenge,
             Self::Weekly => Self::Daily,
             Self::AllTime => Self::Weekly,
+            Self::DailyChallenge => Self::AllTime,
+        }
+    }
+
+    pub fn period(&self) -> RankingPeriod {
+        match self {
+            Self::Daily => RankingPeriod::Daily,
+            Self::Weekly => RankingPeriod::Weekly,
+            Self::AllTime => RankingPeriod::AllTime,
+            Self::DailyChallenge => RankingPeriod::DailyChallenge,
         }
     }
 }
@@ -86,18 +609,90 @@ pub struct App {
     pub user: UserContext,
     pub scene: Scene,
     pub db_cache: Arc<DbCache>,
-    pub db_tx: mpsc::Sender<DbRequest>,
+    /// The player's rank on each board, so they see themselves outside the
+    /// top 10; fetched again whenever the boards change.
+    pub own_ranks: HashMap<RankingPeriod, UserRank>,
+    /// `None` when nothing is stored, as in offline guest play; requests are dropped.
+    pub db_tx: Option<mpsc::Sender<DbRequest>>,
     pub mouse_pos: Point,
+    /// Added to every reported mouse cell; measured by the calibration scene.
+    pub mouse_offset: (i32, i32),
+    pub smooth_cursor: SmoothCursor,
     pub screen_size: Size,
     pub last_scene_change: Instant,
+    pub transition: Option<Transition>,
+    /// Last non-tick action, used to start the attract demo.
+    pub last_input: Instant,
     pub should_quit: bool,
-    behavior_analyzer: BehaviorAnalyzer,
+    /// Checks every hit on the target; hosts may swap in their own pipeline.
+    pub behavior_analyzer: BehaviorAnalyzer,
     pub last_cheat_warning: Option<Instant>,
     pub leaderboard_tab: LeaderboardTab,
+    pub menu_focus: MenuFocus,
+    /// Weeks the activity graph is scrolled back from the current one.
+    pub activity_scroll: u16,
+    pub show_help: bool,
+    pub show_debug: bool,
+    pub perf: PerfStats,
+    pub feedback: Feedback,
+    /// Viewers of this session, kept up to date by the frontend.
+    pub spectators: usize,
+    /// Time left before the frontend closes this idle session; shown as an overlay.
+    pub idle_warning: Option<Duration>,
+    /// Operator broadcast shown over every scene until dismissed.
+    pub announcement: Option<String>,
+    /// Arrow keys (or `hjkl`) move the cursor and space shoots, for
+    /// terminals that don't report the mouse. Set by the frontend.
+    pub keyboard_aim: bool,
+    pub color_support: ColorSupport,
+    /// Language reported by the client's locale, used when the preference is `Auto`.
+    pub locale_language: Language,
+    /// Recorded with every score submission in the audit log.
+    pub client: ClientKind,
+    /// Address the player connected from, set by the frontend; wrong link
+    /// codes count against it as well as against the key.
+    pub peer_ip: Option<IpAddr>,
+    /// Shared with the host's DB worker, which refuses runs whose game
+    /// events don't match their signature.
+    pub signing_key: Option<SigningKey>,
+    /// Matchmaking queue of the host; duels are only offered when set.
+    pub duel_tx: Option<mpsc::Sender<DuelTicket>>,
+    /// Line being typed into the menu chat; keys go there while it is set.
+    pub chat_input: Option<String>,
+    /// Latest line anyone sent, shown on the menu.
+    pub last_chat: Option<ChatMessage>,
+    /// Why the host refused the line still in `chat_input`.
+    pub chat_error: Option<ChatRejected>,
+    chat: Option<(Arc<ChatBus>, broadcast::Receiver<ChatMessage>)>,
+    base_config: GameConfig,
+    score_page_rx: Option<oneshot::Receiver<ScoresPage>>,
+    ranks_rx: Option<oneshot::Receiver<HashMap<RankingPeriod, UserRank>>>,
+    takeout_rx: Option<oneshot::Receiver<Result<String>>>,
+    link_code_rx: Option<oneshot::Receiver<Result<String>>>,
+    link_rx: Option<oneshot::Receiver<Result<UserContext>>>,
+    /// Code shown on the profile for linking another device, and when it was issued.
+    pub link_code: Option<(String, Instant)>,
+    recovery_code_rx: Option<oneshot::Receiver<Result<String>>>,
+    duel_rx: Option<oneshot::Receiver<DuelLink>>,
+    /// Fresh recovery code, shown in settings until the player leaves them.
+    pub recovery_code: Option<String>,
+    /// The player's data export; once it arrives the app quits and the
+    /// frontend prints it after restoring the terminal.
+    pub takeout: Option<String>,
+    /// The last export failed; said on the profile until the next try.
+    pub takeout_failed: bool,
+    /// What starting from the menu plays.
+    pub start_mode: GameMode,
+    /// Fixed seed for every round but the daily challenge; seeded rounds are practice.
+    pub seed: Option<u64>,
+    /// Round length chosen by the host over the player's preference; always practice.
+    pub round_length: Option<Duration>,
 }
 
 pub enum Action {
     AppendCharacter(char),
+    /// A bracketed paste, taken as typed but in one go.
+    Paste(String),
     DeleteCharacter,
     SubmitInput,
     MouseMove(u16, u16),
@@ -109,46 +704,317 @@ pub enum Action {
     ConfirmReset,
     CancelReset,
     Restart,
+    StartDailyChallenge,
+    FindDuel,
+    OpenChat,
+    WatchReplay,
+    OpenSettings,
+    OpenProfile,
+    ExportData,
+    LinkDevice,
+    CreateRecoveryCode,
+    ToggleLinkMode,
+    OpenLeaderboard,
+    /// `h`: the counterpart of `l`, a no-op on the menu where `l` opens the ranking
+    PreviousTab,
+    CycleSeason,
+    OpenCalibration,
+    ToggleHelp,
+    ToggleDebug,
     NavigateLeft,
     NavigateRight,
+    NavigateUp,
+    NavigateDown,
 }
 
 impl App {
-    pub fn new(user: UserContext, db_tx: mpsc::Sender<DbRequest>, db_cache: Arc<DbCache>) -> Self {
+    pub fn new(
+        user: UserContext,
+        db_tx: mpsc::Sender<DbRequest>,
+        db_cache: Arc<DbCache>,
+        base_config: GameConfig,
+    ) -> Self {
+        Self::build(user, Some(db_tx), db_cache, base_config)
+    }
+
+    /// Plays without a database: nothing is saved and the boards stay empty.
+    pub fn offline(user: UserContext, base_config: GameConfig) -> Self {
+        Self::build(user, None, Arc::default(), base_config)
+    }
+
+    fn build(
+        user: UserContext,
+        db_tx: Option<mpsc::Sender<DbRequest>>,
+        db_cache: Arc<DbCache>,
+        base_config: GameConfig,
+    ) -> Self {
         let initial_scene = if user.name.is_none() {
             Scene::Naming(NamingState {
                 input: String::new(),
                 error: None,
                 is_loading: false,
+                linking: false,
             })
         } else {
             Scene::Menu
         };
 
-        Self {
+        let mut behavior_analyzer = BehaviorAnalyzer::new(AntiCheatConfig::default());
+        behavior_analyzer.set_strictness(user.strictness);
+
+        let mut app = Self {
             user,
             scene: initial_scene,
             db_cache,
+            own_ranks: HashMap::new(),
             mouse_pos: Point { x: 0, y: 0 },
+            mouse_offset: (0, 0),
+            smooth_cursor: SmoothCursor::new(),
             screen_size: Size::default(),
             last_scene_change: Instant::now(),
+            transition: None,
+            last_input: Instant::now(),
             should_quit: false,
-            behavior_analyzer: BehaviorAnalyzer::new(Default::default()),
+            behavior_analyzer,
             last_cheat_warning: None,
             db_tx,
             leaderboard_tab: LeaderboardTab::default(),
+            menu_focus: MenuFocus::default(),
+            activity_scroll: 0,
+            show_help: false,
+            show_debug: false,
+            perf: PerfStats::default(),
+            feedback: Feedback::default(),
+            spectators: 0,
+            idle_warning: None,
+            announcement: None,
+            keyboard_aim: false,
+            color_support: ColorSupport::default(),
+            locale_language: Language::Auto,
+            client: ClientKind::default(),
+            peer_ip: None,
+            signing_key: None,
+            duel_tx: None,
+            chat_input: None,
+            last_chat: None,
+            chat_error: None,
+            chat: None,
+            base_config,
+            score_page_rx: None,
+            ranks_rx: None,
+            takeout_rx: None,
+            link_code_rx: None,
+            link_rx: None,
+            link_code: None,
+            recovery_code_rx: None,
+            duel_rx: None,
+            recovery_code: None,
+            takeout: None,
+            takeout_failed: false,
+            start_mode: GameMode::Standard,
+            seed: None,
+            round_length: None,
+        };
+        app.request_ranks();
+        app
+    }
+
+    /// Takes the host's latest boards; the player's own ranks follow them.
+    pub fn set_db_cache(&mut self, db_cache: Arc<DbCache>) {
+        if !Arc::ptr_eq(&self.db_cache, &db_cache) {
+            self.db_cache = db_cache;
+            self.request_ranks();
         }
     }
 
+    /// Replaces the default anticheat thresholds, keeping the player's strictness.
+    pub fn with_anticheat(mut self, config: AntiCheatConfig) -> Self {
+        let strictness = self.behavior_analyzer.strictness();
+        self.behavior_analyzer = BehaviorAnalyzer::new(config);
+        self.behavior_analyzer.set_strictness(strictness);
+        self
+    }
+
+    /// Joins the host's chat, which is offered on the menu from then on.
+    pub fn with_chat(mut self, bus: Arc<ChatBus>) -> Self {
+        let rx = bus.subscribe();
+        self.chat = Some((bus, rx));
+        self
+    }
+
+    pub fn has_chat(&self) -> bool {
+        self.chat.is_some()
+    }
+
+    pub fn messages(&self) -> &'static Messages {
+        Messages::for_language(
+            self.user
+                .preferences
+                .language
+                .or_detected(self.locale_language),
+        )
+    }
+
     pub fn input_captured(&self) -> bool {
-        matches!(self.scene, Scene::Naming(_))
+        matches!(self.scene, Scene::Naming(_)) || self.chat_input.is_some()
     }
 
     pub fn update_state(&mut self, action: Action) -> ActionResult {
+        // calibration needs the raw coordinates to measure the offset
+        let action = match action {
+            _ if matches!(self.scene, Scene::Calibration(_)) => action,
+            Action::MouseMove(x, y) => {
+                let (x, y) = self.apply_mouse_offset(x, y);
+                Action::MouseMove(x, y)
+            }
+            Action::MouseClick(x, y) => {
+                let (x, y) = self.apply_mouse_offset(x, y);
+                Action::MouseClick(x, y)
+            }
+            _ => action,
+        };
+        let action = if self.keyboard_aim {
+            self.keyboard_aim_action(action)
+        } else {
+            action
+        };
+
+        if !matches!(action, Action::Tick) {
+            self.last_input = Instant::now();
+            // any input ends the demo, except quitting outright
+            if matches!(self.scene, Scene::Demo(_)) && !matches!(action, Action::Quit) {
+                self.change_scene(Scene::Menu);
+                return (Ok(()), None);
+            }
+        }
+
+        // the help overlay swallows input until it is dismissed
+        if self.show_help {
+            match action {
+                Action::ToggleHelp | Action::BackToMenu => {
+                    self.show_help = false;
+                    return (Ok(()), None);
+                }
+                Action::Quit | Action::Tick | Action::MouseMove(..) | Action::ToggleDebug => {}
+                _ => return (Ok(()), None),
+            }
+        }
+        let Some(action) = self.chat_action(action) else {
+            return (Ok(()), None);
+        };
+        if self.announcement.is_some() && matches!(action, Action::SubmitInput | Action::BackToMenu)
+        {
+            self.announcement = None;
+            return (Ok(()), None);
+        }
+
         match action {
+            Action::ToggleHelp => {
+                self.show_help = true;
+                (Ok(()), None)
+            }
+            Action::ToggleDebug => {
+                self.show_debug = !self.show_debug;
+                (Ok(()), None)
+            }
             Action::Restart => {
-                if matches!(self.scene, Scene::Playing(_) | Scene::GameOver { .. }) {
-                    self.start_game();
+                let mode = match &self.scene {
+                    // a duel is played out, and a rematch goes back to the lobby
+                    Scene::Playing(state) if state.duel.is_some() => None,
+                    Scene::GameOver(state) if state.duel.is_some() => {
+                        self.handle_find_duel();
+                        None
+                    }
+                    Scene::Playing(state) => Some(state.mode),
+                    Scene::GameOver(state) => Some(state.mode),
+                    _ => None,
+                };
+                if let Some(mode) = mode {
+                    self.start_game(mode);
+                }
+                (Ok(()), None)
+            }
+            Action::StartDailyChallenge => {
+                if matches!(self.scene, Scene::Menu) {
+                    self.start_game(GameMode::DailyChallenge);
+                }
+                (Ok(()), None)
+            }
+            Action::FindDuel => {
+                self.handle_find_duel();
+                (Ok(()), None)
+            }
+            Action::OpenChat => {
+                if matches!(self.scene, Scene::Menu) && self.chat.is_some() {
+                    self.chat_input = Some(String::new());
+                    self.chat_error = None;
+                }
+                (Ok(()), None)
+            }
+            Action::WatchReplay => {
+                self.handle_watch_replay();
+                (Ok(()), None)
+            }
+            Action::OpenSettings => {
+                if matches!(self.scene, Scene::Menu) {
+                    self.change_scene(Scene::Settings(SettingsState { selected: 0 }));
+                }
+                (Ok(()), None)
+            }
+            Action::OpenLeaderboard => {
+                match self.scene {
+                    Scene::Menu => {
+                        self.change_scene(Scene::Leaderboard(LeaderboardState {
+                            offset: 0,
+                            entries: Vec::new(),
+                            is_loading: true,
+                            total: None,
+                            season: None,
+                        }));
+                        self.request_score_page(0);
+                    }
+                    // `l` keeps switching tabs wherever the ranking is shown
+                    _ => self.handle_navigate_right(),
+                }
+                (Ok(()), None)
+            }
+            Action::PreviousTab => {
+                if !matches!(self.scene, Scene::Menu) {
+                    self.handle_navigate_left();
+                }
+                (Ok(()), None)
+            }
+            Action::OpenProfile => {
+                if matches!(self.scene, Scene::Menu) {
+                    self.change_scene(Scene::Profile);
+                }
+                (Ok(()), None)
+            }
+            Action::ExportData => {
+                self.handle_export_data();
+                (Ok(()), None)
+            }
+            Action::LinkDevice => {
+                self.handle_link_device();
+                (Ok(()), None)
+            }
+            Action::CreateRecoveryCode => {
+                self.handle_create_recovery_code();
+                (Ok(()), None)
+            }
+            Action::ToggleLinkMode => {
+                if let Scene::Naming(state) = &mut self.scene
+                    && !state.is_loading
+                {
+                    state.linking = !state.linking;
+                    state.input.clear();
+                    state.error = None;
+                }
+                (Ok(()), None)
+            }
+            Action::OpenCalibration => {
+                if matches!(self.scene, Scene::Menu) {
+                    self.change_scene(Scene::Calibration(CalibrationState { errors: Vec::new() }));
                 }
                 (Ok(()), None)
             }
@@ -169,67 +1035,446 @@ impl App {
                 }
                 (Ok(()), None)
             }
-            Action::Tick => (self.handle_tick(), None),
-            Action::MouseMove(x, y) => {
-                self.handle_mouse_move(x, y);
-                (Ok(()), None)
+            Action::Tick => (self.handle_tick(), None),
+            Action::MouseMove(x, y) => {
+                self.handle_mouse_move(x, y);
+                (Ok(()), None)
+            }
+            Action::MouseClick(x, y) => (self.handle_click(x, y), None),
+            Action::CycleSeason => {
+                self.handle_cycle_season();
+                (Ok(()), None)
+            }
+            Action::NavigateLeft => {
+                self.handle_navigate_left();
+                (Ok(()), None)
+            }
+            Action::NavigateRight => {
+                self.handle_navigate_right();
+                (Ok(()), None)
+            }
+            Action::NavigateUp => {
+                self.handle_navigate_vertical(false);
+                (Ok(()), None)
+            }
+            Action::NavigateDown => {
+                self.handle_navigate_vertical(true);
+                (Ok(()), None)
+            }
+            Action::AppendCharacter(c) => (self.handle_append_char(c), None),
+            // escape sequences in the text are filtered out like any other symbol
+            Action::Paste(text) => (
+                text.chars().try_for_each(|c| self.handle_append_char(c)),
+                None,
+            ),
+            Action::DeleteCharacter => (self.handle_delete_char(), None),
+            Action::SubmitInput => {
+                if let Scene::Tutorial(state) = &self.scene {
+                    if state.step == TutorialStep::Combo {
+                        self.start_game(self.start_mode);
+                    }
+                    return (Ok(()), None);
+                }
+                (Ok(()), self.handle_submit_name())
+            }
+            Action::BackToMenu => {
+                // walking away from a challenge counts as failing it
+                if matches!(self.scene, Scene::Verification(_)) {
+                    self.report_verification(false);
+                }
+                // so is walking away from a duel, once it has been matched
+                let duel = match &self.scene {
+                    Scene::Playing(state) => state.duel.as_ref(),
+                    Scene::DuelLobby(state) => state.link.as_ref(),
+                    _ => None,
+                };
+                if let Some(duel) = duel {
+                    duel.board.forfeit(duel.side);
+                }
+                self.duel_rx = None;
+                self.recovery_code = None;
+                self.change_scene(Scene::Menu);
+                (Ok(()), None)
+            }
+        }
+    }
+
+    /// Edits the chat line while one is open; returns the actions it doesn't take.
+    fn chat_action(&mut self, action: Action) -> Option<Action> {
+        let Some(input) = &mut self.chat_input else {
+            return Some(action);
+        };
+        let mut push = |c: char| {
+            if !c.is_control() && input.chars().count() < CHAT_MAX_LEN {
+                input.push(c);
+            }
+        };
+        match action {
+            Action::AppendCharacter(c) => push(c),
+            Action::Paste(text) => text.chars().for_each(push),
+            Action::DeleteCharacter => {
+                input.pop();
+            }
+            Action::SubmitInput => self.submit_chat(),
+            Action::BackToMenu => {
+                self.chat_input = None;
+                self.chat_error = None;
+            }
+            other => return Some(other),
+        }
+        None
+    }
+
+    fn submit_chat(&mut self) {
+        let (Some(input), Some((bus, _))) = (self.chat_input.take(), &self.chat) else {
+            return;
+        };
+        let name = self.user.name.as_deref().unwrap_or_default();
+        self.chat_error = match bus.send(self.user.id, name, &input) {
+            Ok(()) | Err(ChatRejected::Empty) => None,
+            // keep the line so it can be fixed or sent again
+            Err(e) => {
+                self.chat_input = Some(input);
+                Some(e)
+            }
+        };
+    }
+
+    fn poll_chat(&mut self) {
+        let Some((_, rx)) = &mut self.chat else {
+            return;
+        };
+        loop {
+            match rx.try_recv() {
+                Ok(message) => self.last_chat = Some(message),
+                // only the latest line is shown, so skipped ones don't matter
+                Err(broadcast::error::TryRecvError::Lagged(_)) => {}
+                Err(_) => return,
             }
-            Action::MouseClick(x, y) => (self.handle_click(x, y), None),
-            Action::NavigateLeft => {
-                self.handle_navigate_left();
-                (Ok(()), None)
+        }
+    }
+
+    /// Turns cursor keys into steps of the cursor and space into a click
+    /// wherever there is something to shoot.
+    fn keyboard_aim_action(&self, action: Action) -> Action {
+        let aiming = matches!(
+            self.scene,
+            Scene::Playing(_) | Scene::Tutorial(_) | Scene::Verification(_)
+        );
+        let Point { x, y } = self.mouse_pos;
+        let (dx, dy) = KEYBOARD_AIM_STEP;
+        let max_x = self.screen_size.width.saturating_sub(1);
+        let max_y = self.screen_size.height.saturating_sub(1);
+        match action {
+            Action::AppendCharacter(' ')
+                if (aiming || matches!(self.scene, Scene::Menu)) && self.chat_input.is_none() =>
+            {
+                Action::MouseClick(x, y)
             }
-            Action::NavigateRight => {
-                self.handle_navigate_right();
-                (Ok(()), None)
+            // `h`/`l` switch tabs and open the leaderboard elsewhere
+            Action::NavigateLeft | Action::PreviousTab if aiming => {
+                Action::MouseMove(x.saturating_sub(dx), y)
             }
-            Action::AppendCharacter(c) => (self.handle_append_char(c), None),
-            Action::DeleteCharacter => (self.handle_delete_char(), None),
-            Action::SubmitInput => (Ok(()), self.handle_submit_name()),
-            Action::BackToMenu => {
-                self.change_scene(Scene::Menu);
-                (Ok(()), None)
+            Action::NavigateRight | Action::OpenLeaderboard if aiming => {
+                Action::MouseMove((x + dx).min(max_x), y)
+            }
+            Action::NavigateUp if aiming => Action::MouseMove(x, y.saturating_sub(dy)),
+            Action::NavigateDown if aiming => Action::MouseMove(x, (y + dy).min(max_y)),
+            other => other,
+        }
+    }
+
+    /// Prefills the naming screen, e.g. with the SSH login name. A name that
+    /// couldn't have been typed there is ignored; callers check it is free and
+    /// allowed first with `DbRequest::CheckUsername`.
+    pub fn suggest_name(&mut self, name: &str) {
+        let Scene::Naming(state) = &mut self.scene else {
+            return;
+        };
+        let typeable = !name.is_empty()
+            && name.chars().count() <= MAX_PLAYER_NAME_LEN
+            && name.chars().all(|c| c.is_ascii_alphanumeric());
+        if typeable && !state.linking && state.input.is_empty() {
+            state.input = name.to_string();
+        }
+    }
+
+    /// Applies the answer to a request `update_state` handed back: a stored
+    /// name, a reset account, or why the name was refused.
+    pub fn complete_request(&mut self, result: Result<()>) {
+        match (result, self.scene.clone()) {
+            (Ok(()), Scene::Naming(state)) => self.complete_naming(state.input),
+            (Ok(()), Scene::ResetConfirmation) => self.should_quit = true,
+            (Ok(()), _) => self.change_scene(Scene::Menu),
+            (Err(e), _) => {
+                if let Scene::Naming(state) = &mut self.scene {
+                    state.error = Some(e.to_string());
+                    state.is_loading = false;
+                }
             }
         }
     }
 
+    /// Called once the chosen name has been stored; first-timers get the tutorial.
+    pub fn complete_naming(&mut self, name: String) {
+        self.user.name = Some(name);
+        if self.user.sessions == 0 {
+            self.change_scene(Scene::Tutorial(TutorialState {
+                step: TutorialStep::Aim,
+                distance_moved: 0,
+                targets_hit: 0,
+                target: None,
+            }));
+        } else {
+            self.change_scene(Scene::Menu);
+        }
+    }
+
     pub fn change_scene(&mut self, new_scene: Scene) {
+        // a round fades in rather than slides, so each target is drawn where it is hit
+        let kind = match (&self.scene, &new_scene) {
+            (Scene::GameOver(_), Scene::Menu) => Some(TransitionKind::Slide),
+            (Scene::Menu | Scene::GameOver(_), Scene::Playing(_))
+            | (Scene::Playing(_), Scene::GameOver(_) | Scene::Menu) => Some(TransitionKind::Fade),
+            _ => None,
+        };
+        self.transition = kind
+            .filter(|_| !self.user.preferences.reduced_motion)
+            .map(|kind| Transition {
+                kind,
+                started: Instant::now(),
+            });
         self.scene = new_scene;
         self.last_scene_change = Instant::now();
+        self.chat_input = None;
+    }
+
+    fn start_game(&mut self, mode: GameMode) {
+        // a non-default round length or a known seed is not comparable on the leaderboards
+        let duration = self.user.preferences.game_duration;
+        let unranked = duration != GameDuration::default()
+            || self.round_length.is_some()
+            || self.seed.is_some()
+            || self.start_mode == GameMode::Practice;
+        let mode = match mode {
+            GameMode::Standard if unranked => GameMode::Practice,
+            GameMode::Practice if !unranked => GameMode::Standard,
+            other => other,
+        };
+
+        let seed = match self.seed {
+            Some(seed) if mode == GameMode::Practice => seed,
+            _ => mode.new_seed(),
+        };
+        let mut config = self.base_config.clone();
+        if mode == GameMode::Practice {
+            config.playing_time = self.round_length.unwrap_or(duration.as_duration());
+        }
+        let config = config.with_modifier(self.db_cache.weekly_modifier);
+        let ghost = match mode {
+            GameMode::Standard => self.user.best_replay.clone(),
+            GameMode::DailyChallenge | GameMode::Practice => None,
+        };
+        self.begin_round(mode, seed, config, ghost, None);
+    }
+
+    /// Starts a round on the seed the lobby picked; duels are unranked.
+    fn start_duel(&mut self, link: DuelLink) {
+        let config = self
+            .base_config
+            .clone()
+            .with_modifier(self.db_cache.weekly_modifier);
+        self.begin_round(GameMode::Practice, link.seed, config, None, Some(link));
     }
 
-    fn start_game(&mut self) {
-        let state = PlayingState {
-            target: Target::new_random(self.screen_size),
+    fn begin_round(
+        &mut self,
+        mode: GameMode,
+        seed: u64,
+        config: GameConfig,
+        ghost: Option<Arc<Replay>>,
+        duel: Option<DuelLink>,
+    ) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let target = Target::new_random(self.screen_size, &config, &mut rng);
+        let spawn = ReplayEvent::TargetSpawn(target.pos);
+        let mut state = PlayingState {
+            target,
             combat_stats: CombatStats::new(),
             mouse_history: VecDeque::from([MouseTrace::new(self.mouse_pos.x, self.mouse_pos.y)]),
             last_target_spawn: Instant::now(),
             scene_start: Instant::now(),
+            mode,
+            rng,
+            screen: self.screen_size,
+            config,
+            last_milestone: None,
+            replay: Replay {
+                seed: Some(seed),
+                ..Replay::default()
+            },
+            signer: self.signing_key.as_ref().map(EventSigner::new),
+            ghost,
+            heatmap: Heatmap::new(self.screen_size),
+            effects: Vec::new(),
+            duel,
         };
+        state.record(Duration::ZERO, ReplayEvent::Resize(self.screen_size));
+        state.record(Duration::ZERO, spawn);
+        self.behavior_analyzer.reset_motion();
         self.change_scene(Scene::Playing(Box::new(state)));
     }
 
-    fn end_game(&mut self, stats: CombatStats) -> Result<()> {
+    fn start_demo(&mut self) {
+        let mut rng = StdRng::seed_from_u64(rand::random());
+        let target = Target::new_random(self.screen_size, &self.base_config, &mut rng);
+        let now = Instant::now();
+        self.change_scene(Scene::Demo(Box::new(DemoState {
+            target,
+            cursor: (
+                self.screen_size.width as f32 / 2.0,
+                self.screen_size.height as f32 / 2.0,
+            ),
+            combat_stats: CombatStats::new(),
+            last_target_spawn: now,
+            last_step: now,
+            rng,
+        })));
+    }
+
+    /// Queues the player with the host's lobby and waits there for an opponent.
+    fn handle_find_duel(&mut self) {
+        let Some(duel_tx) = &self.duel_tx else {
+            return;
+        };
+        if !matches!(self.scene, Scene::Menu | Scene::GameOver(_)) {
+            return;
+        }
+        let (reply_tx, rx) = oneshot::channel();
+        let ticket = DuelTicket {
+            user_id: self.user.id,
+            name: self.user.name.clone().unwrap_or_default(),
+            reply_tx,
+        };
+        if duel_tx.try_send(ticket).is_ok() {
+            self.duel_rx = Some(rx);
+            self.change_scene(Scene::DuelLobby(DuelLobbyState {
+                since: Instant::now(),
+                link: None,
+            }));
+        }
+    }
+
+    fn poll_duel(&mut self) {
+        if let Some(rx) = &mut self.duel_rx {
+            match rx.try_recv() {
+                Ok(link) => {
+                    self.duel_rx = None;
+                    if let Scene::DuelLobby(state) = &mut self.scene {
+                        state.link = Some(link);
+                    }
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                // the lobby is gone, e.g. the server is shutting down
+                Err(oneshot::error::TryRecvError::Closed) => {
+                    self.duel_rx = None;
+                    self.change_scene(Scene::Menu);
+                }
+            }
+        }
+
+        let Scene::DuelLobby(state) = &self.scene else {
+            return;
+        };
+        if let Some(link) = state
+            .link
+            .as_ref()
+            .filter(|link| Instant::now() >= link.starts_at)
+        {
+            let link = link.clone();
+            self.start_duel(link);
+        }
+    }
+
+    fn handle_watch_replay(&mut self) {
+        if !matches!(self.scene, Scene::Menu) {
+            return;
+        }
+        if let Some(replay) = self.user.best_replay.clone() {
+            self.change_scene(Scene::Replay(Box::new(ReplayState {
+                replay,
+                config: self.base_config.clone(),
+                started: Instant::now(),
+            })));
+        }
+    }
+
+    fn end_game(
+        &mut self,
+        stats: CombatStats,
+        mode: GameMode,
+        replay: Replay,
+        signature: Option<Signature>,
+        heatmap: Heatmap,
+        duel: Option<DuelLink>,
+    ) -> Result<()> {
         let final_score = stats.current_score();
+        if let Some(duel) = &duel {
+            duel.board.set_score(duel.side, final_score);
+            duel.board.finish(duel.side);
+        }
+        let replay = Arc::new(replay);
+
+        let result = GameResult {
+            duration_ms: replay.duration().as_millis() as u64,
+            ..GameResult::from_stats(&stats)
+        };
 
-        let _ = self.db_tx.try_send(DbRequest::SaveGame {
+        let checks = self.behavior_analyzer.take_events();
+        let link = self.behavior_analyzer.link();
+        self.send_db(DbRequest::SaveGame {
             user_id: self.user.id,
-            score: final_score,
-            hits: stats.hit_count,
-            misses: stats.miss_count,
+            result,
+            mode,
+            replay: Arc::clone(&replay),
+            client: self.client,
+            signature,
+            checks,
+            latency: link.latency,
+            round_trip: link.round_trip,
         });
 
         // honestly, should wait db response and react.
-        // update high score
-        let is_new_record = final_score > self.user.high_score;
+        // update high score (daily challenge runs only rank on their own board)
+        let is_new_record = mode == GameMode::Standard && final_score > self.user.high_score;
         if is_new_record {
             self.user.high_score = final_score;
+            self.user.best_replay = Some(replay);
+        }
+        if mode == GameMode::DailyChallenge {
+            self.leaderboard_tab = LeaderboardTab::DailyChallenge;
         }
 
         // update stats
+        self.user.best_combo = self.user.best_combo.max(stats.best_combo());
         self.user.total_hits += stats.hit_count;
         self.user.total_misses += stats.miss_count;
         self.user.sessions += 1;
+        self.user.reaction_total_ms += stats.reaction_total_ms;
+        self.user.reaction_samples += stats.reaction_samples;
+        self.user.best_accuracy_bp = self
+            .user
+            .best_accuracy_bp
+            .max(result.accuracy_bp().unwrap_or(0));
+        if let Some(ms) = result.average_reaction_ms() {
+            self.user.best_reaction_ms = Some(self.user.best_reaction_ms.map_or(ms, |b| b.min(ms)));
+        }
+        if mode != GameMode::Practice {
+            self.user.score_history.push(final_score);
+            if self.user.score_history.len() > SCORE_HISTORY_LEN {
+                self.user.score_history.remove(0);
+            }
+        }
 
         // update activity
         let today = chrono::Utc::now().format("%Y-%m-%d").to_string();
@@ -245,48 +1490,408 @@ impl App {
             );
         }
 
-        self.change_scene(Scene::GameOver {
+        let board = match mode {
+            GameMode::Standard => Some(&self.db_cache.all_time_scores),
+            GameMode::DailyChallenge => Some(&self.db_cache.daily_challenge_scores),
+            GameMode::Practice => None,
+        };
+        let podium = board
+            .and_then(|scores| podium_rank(scores, self.user.name.as_deref(), final_score))
+            .map(|rank| Podium {
+                rank,
+                started: Instant::now(),
+            });
+
+        let game_over = GameOverState {
             final_score,
             is_new_record,
-        });
+            mode,
+            heatmap,
+            podium,
+            duel,
+        };
+        if self.behavior_analyzer.needs_verification() {
+            let state = VerificationState::new(game_over);
+            self.change_scene(Scene::Verification(Box::new(state)));
+        } else {
+            self.change_scene(Scene::GameOver(game_over));
+        }
 
         Ok(())
     }
 
+    /// Shows the result once the last verification target is done.
+    fn finish_verification(&mut self) {
+        let Scene::Verification(state) = &self.scene else {
+            return;
+        };
+        if !state.is_over() {
+            return;
+        }
+        let passed = state.passed + 1 >= VERIFY_ROUNDS;
+        let mut game_over = state.game_over.clone();
+        // the podium would have played out behind the challenge
+        if let Some(podium) = &mut game_over.podium {
+            podium.started = Instant::now();
+        }
+        self.report_verification(passed);
+        self.change_scene(Scene::GameOver(game_over));
+    }
+
+    /// Hands a request to the host's DB worker; false if there is none or it is busy.
+    fn send_db(&self, req: DbRequest) -> bool {
+        self.db_tx
+            .as_ref()
+            .is_some_and(|tx| tx.try_send(req).is_ok())
+    }
+
+    /// The outcome moves the player's trust; either way the warnings start over.
+    fn report_verification(&mut self, passed: bool) {
+        self.send_db(DbRequest::Verification {
+            user_id: self.user.id,
+            passed,
+        });
+        self.behavior_analyzer.clear_warnings();
+    }
+
     fn handle_tick(&mut self) -> Result<()> {
         if self
             .last_cheat_warning
-            .map_or(false, |t| t.elapsed() >= Duration::from_secs(2))
+            .is_some_and(|t| t.elapsed() >= Duration::from_secs(2))
         {
             self.last_cheat_warning = None;
         }
 
+        self.poll_score_page();
+        self.poll_ranks();
+        self.poll_takeout();
+        self.poll_link();
+        self.poll_duel();
+        self.poll_chat();
+        if self.transition.is_some_and(|t| t.progress() >= 1.0) {
+            self.transition = None;
+        }
+        self.smooth_cursor.advance(self.mouse_pos);
+
+        if matches!(self.scene, Scene::Menu)
+            && !self.show_help
+            && self.last_input.elapsed() >= ATTRACT_IDLE
+        {
+            self.start_demo();
+            return Ok(());
+        }
+
+        if let Scene::GameOver(state) = &mut self.scene
+            && state.podium.is_some_and(|p| p.elapsed() >= PODIUM_DURATION)
+        {
+            state.podium = None;
+        }
+
+        if let Scene::Demo(state) = &mut self.scene {
+            state.step(self.screen_size, &self.base_config, Instant::now());
+            return Ok(());
+        }
+
+        if let Scene::Verification(state) = &mut self.scene {
+            let now = Instant::now();
+            if state.target.is_none() && now >= state.due {
+                state.target = Some(state.spawn_target(self.screen_size, &self.base_config));
+                state.due = now;
+                state.history.clear();
+            } else if state.target.is_some() && now.duration_since(state.due) >= VERIFY_TIMEOUT {
+                state.next_round();
+            }
+            self.finish_verification();
+            return Ok(());
+        }
+
+        if let Scene::Replay(state) = &self.scene {
+            if state.started.elapsed() >= state.replay.duration() + REPLAY_END_HOLD {
+                self.change_scene(Scene::Menu);
+            }
+            return Ok(());
+        }
+
         if let Scene::Playing(state) = &mut self.scene {
+            state
+                .effects
+                .retain(|e| e.started.elapsed() < EFFECT_LIFETIME);
+
             // end game
-            if state.scene_start.elapsed() >= Duration::from_secs(PLAYING_TIME_SEC.into()) {
+            if state.scene_start.elapsed() >= state.config.playing_time {
                 let stats = state.combat_stats.clone();
-                return self.end_game(stats);
+                let mode = state.mode;
+                let replay = std::mem::take(&mut state.replay);
+                let signature = state.signer.take().map(EventSigner::finish);
+                let heatmap = std::mem::take(&mut state.heatmap);
+                let duel = state.duel.take();
+                return self.end_game(stats, mode, replay, signature, heatmap, duel);
+            }
+
+            if let Some(duel) = &state.duel {
+                duel.board
+                    .set_score(duel.side, state.combat_stats.current_score());
             }
 
             // respawn target
-            if state
-                .target
-                .is_expired(state.last_target_spawn.elapsed(), &state.combat_stats)
-            {
+            if state.target.is_expired(
+                state.last_target_spawn.elapsed(),
+                &state.combat_stats,
+                &state.config,
+            ) {
+                if state.combat_stats.current_combo() > 0 {
+                    self.feedback.signal(
+                        self.user.preferences.hit_feedback,
+                        FeedbackEvent::ComboBreak,
+                    );
+                }
                 state.combat_stats.register_miss();
-                state.target = Target::new_random(self.screen_size);
-                state.last_target_spawn = Instant::now();
+                let elapsed = state.scene_start.elapsed();
+                state.spawn_target(self.screen_size, elapsed);
                 state.mouse_history.clear();
             }
         }
         Ok(())
     }
 
+    fn request_score_page(&mut self, offset: u32) {
+        let season = match &self.scene {
+            Scene::Leaderboard(state) => {
+                state.season.and_then(|i| self.db_cache.past_seasons.get(i))
+            }
+            _ => None,
+        };
+        let (tx, rx) = oneshot::channel();
+        let req = match season {
+            Some(season) => DbRequest::GetSeasonScores {
+                season_id: season.id,
+                offset,
+                limit: LEADERBOARD_PAGE_SIZE,
+                reply_tx: tx,
+            },
+            None => DbRequest::GetScoresPage {
+                period: self.leaderboard_tab.period(),
+                offset,
+                limit: LEADERBOARD_PAGE_SIZE,
+                reply_tx: tx,
+            },
+        };
+        self.send_db(req);
+        self.score_page_rx = Some(rx);
+
+        if let Scene::Leaderboard(state) = &mut self.scene {
+            state.offset = offset;
+            state.is_loading = true;
+        }
+    }
+
+    fn poll_score_page(&mut self) {
+        let Some(rx) = &mut self.score_page_rx else {
+            return;
+        };
+        let page = match rx.try_recv() {
+            Ok(page) => page,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => ScoresPage::default(),
+        };
+        self.score_page_rx = None;
+
+        if let Scene::Leaderboard(state) = &mut self.scene {
+            state.entries = page.entries;
+            state.total = page.total;
+            state.is_loading = false;
+        }
+    }
+
+    fn request_ranks(&mut self) {
+        let (tx, rx) = oneshot::channel();
+        let sent = self.send_db(DbRequest::GetUserRanks {
+            user_id: self.user.id,
+            reply_tx: tx,
+        });
+        if sent {
+            self.ranks_rx = Some(rx);
+        }
+    }
+
+    fn poll_ranks(&mut self) {
+        let Some(rx) = &mut self.ranks_rx else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(ranks) => self.own_ranks = ranks,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {}
+        }
+        self.ranks_rx = None;
+    }
+
+    fn handle_export_data(&mut self) {
+        if !matches!(self.scene, Scene::Profile) || self.takeout_rx.is_some() {
+            return;
+        }
+        let (tx, rx) = oneshot::channel();
+        let sent = self.send_db(DbRequest::ExportUserData {
+            user_id: self.user.id,
+            fingerprint: self.user.fingerprint.clone(),
+            reply_tx: tx,
+        });
+        if sent {
+            self.takeout_rx = Some(rx);
+        }
+        self.takeout_failed = !sent;
+    }
+
+    fn poll_takeout(&mut self) {
+        let Some(rx) = &mut self.takeout_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => Err(anyhow::anyhow!("DB worker gone")),
+        };
+        self.takeout_rx = None;
+        match result {
+            Ok(json) => {
+                self.takeout = Some(json);
+                self.should_quit = true;
+            }
+            Err(e) => {
+                tracing::warn!(user_id = self.user.id, error = %e, "Data export failed");
+                self.takeout_failed = true;
+            }
+        }
+    }
+
+    fn handle_link_device(&mut self) {
+        if !matches!(self.scene, Scene::Profile) || self.link_code_rx.is_some() {
+            return;
+        }
+        let (tx, rx) = oneshot::channel();
+        let sent = self.send_db(DbRequest::CreateLinkCode {
+            user_id: self.user.id,
+            reply_tx: tx,
+        });
+        if sent {
+            self.link_code_rx = Some(rx);
+        }
+    }
+
+    fn handle_create_recovery_code(&mut self) {
+        if !matches!(self.scene, Scene::Settings(_)) || self.recovery_code_rx.is_some() {
+            return;
+        }
+        let (tx, rx) = oneshot::channel();
+        let sent = self.send_db(DbRequest::CreateRecoveryCode {
+            user_id: self.user.id,
+            reply_tx: tx,
+        });
+        if sent {
+            self.recovery_code_rx = Some(rx);
+        }
+    }
+
+    fn poll_link(&mut self) {
+        if let Some(rx) = &mut self.recovery_code_rx {
+            match rx.try_recv() {
+                Ok(code) => {
+                    self.recovery_code = code.ok();
+                    self.recovery_code_rx = None;
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.recovery_code_rx = None,
+            }
+        }
+        if let Some(rx) = &mut self.link_code_rx {
+            match rx.try_recv() {
+                Ok(code) => {
+                    self.link_code = code.ok().map(|code| (code, Instant::now()));
+                    self.link_code_rx = None;
+                }
+                Err(oneshot::error::TryRecvError::Empty) => {}
+                Err(oneshot::error::TryRecvError::Closed) => self.link_code_rx = None,
+            }
+        }
+        if self
+            .link_code
+            .as_ref()
+            .is_some_and(|(_, issued)| issued.elapsed() >= LINK_CODE_TTL)
+        {
+            self.link_code = None;
+        }
+
+        let Some(rx) = &mut self.link_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => Err(anyhow::anyhow!("DB worker gone")),
+        };
+        self.link_rx = None;
+        match result {
+            Ok(user) => {
+                self.behavior_analyzer.set_strictness(user.strictness);
+                self.user = user;
+                self.request_ranks();
+                self.change_scene(Scene::Menu);
+            }
+            Err(e) => {
+                if let Scene::Naming(state) = &mut self.scene {
+                    state.error = Some(e.to_string());
+                    state.is_loading = false;
+                }
+            }
+        }
+    }
+
+    fn apply_mouse_offset(&self, x: u16, y: u16) -> (u16, u16) {
+        let (dx, dy) = self.mouse_offset;
+        let max_x = self.screen_size.width.saturating_sub(1) as i32;
+        let max_y = self.screen_size.height.saturating_sub(1) as i32;
+        (
+            (x as i32 + dx).clamp(0, max_x) as u16,
+            (y as i32 + dy).clamp(0, max_y) as u16,
+        )
+    }
+
     fn handle_mouse_move(&mut self, x: u16, y: u16) {
+        let prev = self.mouse_pos;
         self.mouse_pos = Point { x, y };
 
+        if let Scene::Tutorial(state) = &mut self.scene
+            && state.step == TutorialStep::Aim
+        {
+            state.distance_moved += prev.x.abs_diff(x) as u32 + prev.y.abs_diff(y) as u32;
+            if state.distance_moved >= TUTORIAL_AIM_DISTANCE {
+                state.step = TutorialStep::Shoot;
+                let (fx, fy) = TUTORIAL_TARGETS[0];
+                state.target = Some(Target::at_fraction(
+                    self.screen_size,
+                    &self.base_config,
+                    fx,
+                    fy,
+                ));
+            }
+        }
+
+        if let Scene::Verification(state) = &mut self.scene {
+            let trace = MouseTrace::new(x, y);
+            let _ = self.behavior_analyzer.observe_move(trace);
+            state.history.push_back(trace);
+            if state.history.len() > 50 {
+                state.history.pop_front();
+            }
+        }
+
         if let Scene::Playing(state) = &mut self.scene {
-            state.mouse_history.push_back(MouseTrace::new(x, y));
+            let elapsed = state.scene_start.elapsed();
+            state.record(elapsed, ReplayEvent::MouseMove(Point { x, y }));
+            // a violation refuses the next hit, which shows the warning
+            let trace = MouseTrace::new(x, y);
+            let _ = self.behavior_analyzer.observe_move(trace);
+            state.mouse_history.push_back(trace);
             if state.mouse_history.len() > 50 {
                 state.mouse_history.pop_front();
             }
@@ -295,11 +1900,23 @@ impl App {
 
     fn handle_click(&mut self, x: u16, y: u16) -> Result<()> {
         match &mut self.scene {
-            Scene::Menu => self.start_game(),
+            Scene::Menu => self.start_game(self.start_mode),
             Scene::Playing(state) => {
+                let elapsed = state.scene_start.elapsed();
+                state.record(elapsed, ReplayEvent::MouseClick(Point { x, y }));
                 state.mouse_history.push_back(MouseTrace::new(x, y));
 
                 if !state.target.is_hit(x, y) {
+                    state
+                        .effects
+                        .push(Effect::new(EffectKind::MissMarker, Point { x, y }));
+                    state.heatmap.record(Point { x, y }, false);
+                    if state.combat_stats.current_combo() > 0 {
+                        self.feedback.signal(
+                            self.user.preferences.hit_feedback,
+                            FeedbackEvent::ComboBreak,
+                        );
+                    }
                     state.combat_stats.register_miss();
                     return Ok(());
                 }
@@ -310,20 +1927,98 @@ impl App {
                     Point { x, y },
                 );
 
+                state.heatmap.record(Point { x, y }, is_legit);
+
                 if is_legit {
-                    state.combat_stats.register_hit();
-                    state.target = Target::new_random(self.screen_size);
-                    state.last_target_spawn = Instant::now();
+                    let center = Point {
+                        x: state.target.pos.x + state.target.visual_width / 2,
+                        y: state.target.pos.y + state.target.visual_height / 2,
+                    };
+                    state
+                        .effects
+                        .push(Effect::new(EffectKind::HitBurst, center));
+                    state
+                        .combat_stats
+                        .record_reaction(state.last_target_spawn.elapsed());
+                    self.feedback
+                        .signal(self.user.preferences.hit_feedback, FeedbackEvent::Hit);
+                    if let Some(milestone) = state.combat_stats.register_hit() {
+                        state.last_milestone = Some((milestone, Instant::now()));
+                    }
+
+                    let elapsed = state.scene_start.elapsed();
+                    let score = state.combat_stats.current_score();
+                    state.record(elapsed, ReplayEvent::Score(score));
+                    state.spawn_target(self.screen_size, elapsed);
                     state.mouse_history.clear();
                 } else {
-                    state.combat_stats.register_miss();
+                    state
+                        .effects
+                        .push(Effect::new(EffectKind::MissMarker, Point { x, y }));
+                    state.combat_stats.register_flagged();
                     self.last_cheat_warning = Some(Instant::now());
                     state.mouse_history.clear();
                 }
             }
 
-            Scene::GameOver { .. } => {
-                if self.last_scene_change.elapsed() >= Duration::from_millis(500) {
+            // the first click skips the podium, the next one leaves
+            Scene::GameOver(state) if state.podium.is_some() => state.podium = None,
+            Scene::GameOver(_)
+                if self.last_scene_change.elapsed() >= Duration::from_millis(500) =>
+            {
+                self.change_scene(Scene::Menu);
+            }
+            Scene::Tutorial(state) => match state.step {
+                TutorialStep::Aim => {}
+                TutorialStep::Shoot => {
+                    if state.target.as_ref().is_some_and(|t| t.is_hit(x, y)) {
+                        state.targets_hit += 1;
+                        if state.targets_hit < TUTORIAL_TARGETS.len() {
+                            let (fx, fy) = TUTORIAL_TARGETS[state.targets_hit];
+                            state.target = Some(Target::at_fraction(
+                                self.screen_size,
+                                &self.base_config,
+                                fx,
+                                fy,
+                            ));
+                        } else {
+                            state.step = TutorialStep::Combo;
+                            state.target = None;
+                        }
+                    }
+                }
+                TutorialStep::Combo => self.start_game(self.start_mode),
+            },
+            Scene::Verification(state) => {
+                state.history.push_back(MouseTrace::new(x, y));
+                if state.target.as_ref().is_some_and(|t| t.is_hit(x, y)) {
+                    if self.behavior_analyzer.is_legit_interaction(
+                        &state.history,
+                        state.due,
+                        Point { x, y },
+                    ) {
+                        state.passed += 1;
+                    }
+                    state.next_round();
+                    self.finish_verification();
+                }
+            }
+            Scene::Calibration(state) => {
+                let marker = calibration_marker(self.screen_size, state.errors.len());
+                state
+                    .errors
+                    .push((x as i32 - marker.x as i32, y as i32 - marker.y as i32));
+
+                if state.errors.len() == CALIBRATION_MARKERS.len() {
+                    let n = state.errors.len() as f64;
+                    let (sx, sy) = state
+                        .errors
+                        .iter()
+                        .fold((0, 0), |(sx, sy), (ex, ey)| (sx + ex, sy + ey));
+                    self.mouse_offset = (
+                        -(sx as f64 / n).round() as i32,
+                        -(sy as f64 / n).round() as i32,
+                    );
                     self.change_scene(Scene::Menu);
                 }
             }
@@ -334,21 +2029,30 @@ impl App {
 
     fn handle_append_char(&mut self, c: char) -> Result<()> {
         if let Scene::Naming(state) = &mut self.scene {
+            let max_len = if state.linking {
+                RECOVERY_CODE_LEN
+            } else {
+                MAX_PLAYER_NAME_LEN
+            };
             if !state.is_loading
                 && c.is_ascii_alphanumeric()
-                && state.input.chars().count() < MAX_PLAYER_NAME_LEN
+                && state.input.chars().count() < max_len
             {
-                state.input.push(c);
+                state.input.push(if state.linking {
+                    c.to_ascii_uppercase()
+                } else {
+                    c
+                });
             }
         }
         Ok(())
     }
 
     fn handle_delete_char(&mut self) -> Result<()> {
-        if let Scene::Naming(state) = &mut self.scene {
-            if !state.is_loading {
-                state.input.pop();
-            }
+        if let Scene::Naming(state) = &mut self.scene
+            && !state.is_loading
+        {
+            state.input.pop();
         }
         Ok(())
     }
@@ -362,13 +2066,45 @@ impl App {
             }
 
             let trimmed = state.input.trim().to_string();
+            if state.linking {
+                // answered through `poll_link`, not the frontend's naming reply;
+                // the length tells a link code from a recovery code
+                let (tx, rx) = oneshot::channel();
+                let fingerprint = self.user.fingerprint.clone();
+                let req = match trimmed.len() {
+                    LINK_CODE_LEN => Some(DbRequest::RedeemLinkCode {
+                        fingerprint,
+                        peer_ip: self.peer_ip,
+                        code: trimmed,
+                        reply_tx: tx,
+                    }),
+                    RECOVERY_CODE_LEN => Some(DbRequest::RedeemRecoveryCode {
+                        fingerprint,
+                        code: trimmed,
+                        reply_tx: tx,
+                    }),
+                    _ => None,
+                };
+                // `state` holds the scene, so this can't go through send_db
+                let sent = req.is_some_and(|req| {
+                    self.db_tx
+                        .as_ref()
+                        .is_some_and(|tx| tx.try_send(req).is_ok())
+                });
+                if sent {
+                    state.is_loading = true;
+                    state.error = None;
+                    self.link_rx = Some(rx);
+                }
+                return None;
+            }
             if !trimmed.is_empty() {
                 let (tx, rx) = tokio::sync::oneshot::channel();
 
                 state.is_loading = true;
                 state.error = None;
 
-                let _ = self.db_tx.try_send(DbRequest::UpdateUsername {
+                self.send_db(DbRequest::UpdateUsername {
                     user_id: self.user.id,
                     new_name: trimmed,
                     reply_tx: tx,
@@ -385,12 +2121,11 @@ impl App {
     ) -> Option<tokio::sync::oneshot::Receiver<Result<(), anyhow::Error>>> {
         let (tx, rx) = tokio::sync::oneshot::channel();
 
-        let send_result = self.db_tx.try_send(DbRequest::DeleteUser {
+        let sent = self.send_db(DbRequest::DeleteUser {
             user_id: self.user.id,
             reply_tx: tx,
         });
-
-        if send_result.is_err() {
+        if !sent {
             return None;
         }
 
@@ -399,17 +2134,111 @@ impl App {
 
     fn handle_navigate_left(&mut self) {
         match &self.scene {
-            Scene::Menu | Scene::GameOver { .. } => {
+            Scene::Menu if self.menu_focus == MenuFocus::Activity => {
+                let max = (ACTIVITY_HISTORY_DAYS / 7) as u16 - crate::ui::WEEKS_TO_DISPLAY;
+                self.activity_scroll = (self.activity_scroll + 1).min(max);
+            }
+            Scene::Menu | Scene::GameOver(_) => {
+                self.leaderboard_tab = self.leaderboard_tab.prev();
+            }
+            Scene::Leaderboard(_) => {
                 self.leaderboard_tab = self.leaderboard_tab.prev();
+                self.show_live_boards();
+                self.request_score_page(0);
+            }
+            Scene::Settings(state) => {
+                state.field().cycle(&mut self.user.preferences, false);
+                self.save_preferences();
             }
             _ => {}
         }
     }
 
+    /// Saves right away so quitting from the settings screen keeps the change.
+    fn save_preferences(&self) {
+        self.send_db(DbRequest::SavePreferences {
+            user_id: self.user.id,
+            preferences: self.user.preferences,
+        });
+    }
+
     fn handle_navigate_right(&mut self) {
         match &self.scene {
-            Scene::Menu | Scene::GameOver { .. } => {
+            Scene::Menu if self.menu_focus == MenuFocus::Activity => {
+                self.activity_scroll = self.activity_scroll.saturating_sub(1);
+            }
+            Scene::Menu | Scene::GameOver(_) => {
+                self.leaderboard_tab = self.leaderboard_tab.next();
+            }
+            Scene::Leaderboard(_) => {
                 self.leaderboard_tab = self.leaderboard_tab.next();
+                self.show_live_boards();
+                self.request_score_page(0);
+            }
+            Scene::Settings(state) => {
+                state.field().cycle(&mut self.user.preferences, true);
+                self.save_preferences();
+            }
+            _ => {}
+        }
+    }
+
+    /// Live boards, then each archived season from newest to oldest.
+    fn handle_cycle_season(&mut self) {
+        let count = self.db_cache.past_seasons.len();
+        let Scene::Leaderboard(state) = &mut self.scene else {
+            return;
+        };
+        if count == 0 {
+            return;
+        }
+        state.season = match state.season {
+            None => Some(0),
+            Some(i) if i + 1 < count => Some(i + 1),
+            Some(_) => None,
+        };
+        self.request_score_page(0);
+    }
+
+    fn show_live_boards(&mut self) {
+        if let Scene::Leaderboard(state) = &mut self.scene {
+            state.season = None;
+        }
+    }
+
+    fn handle_navigate_vertical(&mut self, down: bool) {
+        match &mut self.scene {
+            // the activity graph sits below the leaderboard
+            Scene::Menu => {
+                self.menu_focus = if down {
+                    MenuFocus::Activity
+                } else {
+                    MenuFocus::Leaderboard
+                };
+            }
+            Scene::Settings(state) => {
+                let len = SettingsField::ALL.len();
+                state.selected = if down {
+                    (state.selected + 1) % len
+                } else {
+                    (state.selected + len - 1) % len
+                };
+            }
+            Scene::Leaderboard(state) if !state.is_loading => {
+                // without a total, a short page means there is nothing further down
+                let next = state.offset + LEADERBOARD_PAGE_SIZE;
+                let has_more = match state.total {
+                    Some(total) => next < total,
+                    None => state.entries.len() as u32 == LEADERBOARD_PAGE_SIZE,
+                };
+                let offset = if down {
+                    has_more.then_some(next)
+                } else {
+                    (state.offset > 0).then(|| state.offset.saturating_sub(LEADERBOARD_PAGE_SIZE))
+                };
+                if let Some(offset) = offset {
+                    self.request_score_page(offset);
+                }
             }
             _ => {}
         }
@@ -0,0 +1,102 @@
+use crate::names::NameFilter;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+pub const CHAT_MAX_LEN: usize = 60;
+/// Least time between two lines from one player.
+const CHAT_COOLDOWN: Duration = Duration::from_secs(3);
+/// Lines a slow session may fall behind before it skips ahead.
+const CHAT_BACKLOG: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChatMessage {
+    pub name: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRejected {
+    Empty,
+    TooFast,
+    Offensive,
+}
+
+/// Relays chat lines to every session on the host, after the name filter's
+/// blocked words and a per-player cooldown.
+pub struct ChatBus {
+    tx: broadcast::Sender<ChatMessage>,
+    filter: NameFilter,
+    last_sent: Mutex<HashMap<i64, Instant>>,
+}
+
+impl ChatBus {
+    pub fn new(filter: NameFilter) -> Self {
+        Self {
+            tx: broadcast::channel(CHAT_BACKLOG).0,
+            filter,
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChatMessage> {
+        self.tx.subscribe()
+    }
+
+    /// Strips control characters (no escape sequences reach other terminals),
+    /// caps the length and sends the line to every subscriber, the sender included.
+    pub fn send(&self, user_id: i64, name: &str, text: &str) -> Result<(), ChatRejected> {
+        let text: String = text
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(CHAT_MAX_LEN)
+            .collect();
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ChatRejected::Empty);
+        }
+        if self.filter.is_offensive(text) {
+            return Err(ChatRejected::Offensive);
+        }
+
+        let now = Instant::now();
+        let mut last_sent = self.last_sent.lock().unwrap();
+        if last_sent
+            .get(&user_id)
+            .is_some_and(|t| now.duration_since(*t) < CHAT_COOLDOWN)
+        {
+            return Err(ChatRejected::TooFast);
+        }
+        last_sent.retain(|_, t| now.duration_since(*t) < CHAT_COOLDOWN);
+        last_sent.insert(user_id, now);
+        drop(last_sent);
+
+        // nobody listening is fine
+        let _ = self.tx.send(ChatMessage {
+            name: name.to_string(),
+            text: text.to_string(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_filters_and_limits() {
+        let bus = ChatBus::new(NameFilter::default());
+        let mut rx = bus.subscribe();
+
+        assert_eq!(bus.send(1, "alice", " \x1b\x07 "), Err(ChatRejected::Empty));
+        assert_eq!(bus.send(1, "alice", "sh1t"), Err(ChatRejected::Offensive));
+        assert_eq!(bus.send(1, "alice", "gg\x07 all"), Ok(()));
+        assert_eq!(bus.send(1, "alice", "again"), Err(ChatRejected::TooFast));
+        assert_eq!(bus.send(2, "bob", "gg"), Ok(()));
+
+        assert_eq!(rx.try_recv().unwrap().text, "gg all");
+        assert_eq!(rx.try_recv().unwrap().name, "bob");
+    }
+}
@@ -1,5 +1,89 @@
+use crate::anticheat::{
+    AntiCheatConfig, CheckEvent, LatencyClass, Link, Strictness, verify_replay,
+};
+use crate::domain::{CombatStats, GameConfig, GameMode, WeeklyModifier};
+use crate::names::{NameFilter, NameRejected};
+use crate::preferences::{GameDuration, PreferenceOption, Preferences};
+use crate::region::Region;
+use crate::replay::Replay;
+use crate::signing::{self, Signature, SigningKey};
 use anyhow::Result;
-use rusqlite::{Connection, params};
+use rand::Rng;
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+mod audit;
+mod backup;
+mod cluster;
+mod export;
+mod memory;
+mod migrations;
+mod outliers;
+#[cfg(feature = "postgres")]
+mod postgres;
+mod review;
+mod seasons;
+mod sqlite;
+mod takeout;
+mod trust;
+mod upload;
+
+pub use audit::{AuditEntry, CheckRecord, ClientKind, Submission, Verdict};
+pub use backup::{backup_database, restore_database, verify_integrity};
+pub use cluster::{CLUSTER_SYNC_INTERVAL, ClusterEvent, ClusterRecord};
+pub use export::ExportFormat;
+pub use memory::MemoryStore;
+pub use migrations::run as migrate;
+pub use outliers::{Distribution, GameStats, OutlierPolicy};
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresStore;
+pub use review::{REVIEW_MIN_FLAGGED_CLICKS, ReplaySummary, ReviewItem};
+pub use sqlite::SqliteStore;
+pub use trust::{MAX_TRUST, ShadowedUser, Trust, TrustPolicy};
+
+use cluster::Cluster;
+
+/// Number of recent scores kept per user for the profile sparkline.
+pub const SCORE_HISTORY_LEN: usize = 30;
+/// Leaderboard rows kept in `DbCache`; further pages go through `DbRequest::GetScoresPage`.
+pub const CACHED_PAGE_SIZE: u32 = 10;
+/// Most recently seen players whose latest percentile is kept in `DbCache`.
+const RANKED_USERS_LIMIT: usize = 256;
+/// Days of activity loaded per user; the menu graph can scroll back this far.
+pub const ACTIVITY_HISTORY_DAYS: u32 = 53 * 7;
+/// How long a device link code can be redeemed.
+pub const LINK_CODE_TTL: Duration = Duration::from_secs(10 * 60);
+pub const LINK_CODE_LEN: usize = 6;
+/// Recovery codes never expire, so they get far more entropy than link codes.
+pub const RECOVERY_CODE_LEN: usize = 16;
+/// Wrong link codes a key may try per `LINK_CODE_TTL` before it is turned
+/// away; short codes are only safe while guessing them stays slow.
+pub const MAX_LINK_FAILURES: u32 = 5;
+/// No 0/O or 1/I, so codes survive being read off another screen.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// Runs that place within this many all-time ranks keep their full replay.
+pub const REPLAY_RANKS: u32 = 10;
+/// Compressed replays larger than this are not kept.
+pub const MAX_REPLAY_BYTES: usize = 256 * 1024;
+/// How often the worker checks whether a season has ended.
+const SEASON_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How long an idle worker waits for a request before calling `Repository::run_idle`,
+/// so boards roll over at midnight even when nobody is playing.
+pub const IDLE_TICK_INTERVAL: Duration = Duration::from_secs(30);
+/// Boards with fewer entries don't report a percentile; "top 50%" of two
+/// players says nothing.
+pub const PERCENTILE_MIN_ENTRIES: u32 = 10;
+/// How long the population's game stats are reused by the outlier check;
+/// they are aggregated over every game in the mode, and one run barely moves them.
+const POPULATION_STATS_TTL: Duration = Duration::from_secs(5 * 60);
+/// Runs with fewer shots don't count towards best accuracy, so a single
+/// lucky click can't set a 100% record.
+pub const BEST_ACCURACY_MIN_SHOTS: u32 = 10;
 
 #[derive(Debug, Clone)]
 pub struct ActivityDay {
@@ -16,14 +100,170 @@ pub struct UserContext {
     pub total_hits: u32,
     pub total_misses: u32,
     pub sessions: u32,
+    pub best_combo: u32,
     pub user_activity: Vec<ActivityDay>,
+    pub best_replay: Option<Arc<Replay>>,
+    pub preferences: Preferences,
+    pub reaction_total_ms: u64,
+    pub reaction_samples: u32,
+    /// Oldest first, at most `SCORE_HISTORY_LEN` entries.
+    pub score_history: Vec<u32>,
+    /// Best single-run accuracy in basis points (0..=10000).
+    pub best_accuracy_bp: u32,
+    /// Fastest single-run average reaction time.
+    pub best_reaction_ms: Option<u64>,
+    /// Filled in by the repository from the player's trust; stores leave it at the default.
+    pub strictness: Strictness,
 }
 
-#[derive(Debug, Clone)]
+impl UserContext {
+    pub fn average_reaction_ms(&self) -> Option<u64> {
+        (self.reaction_samples > 0).then(|| self.reaction_total_ms / self.reaction_samples as u64)
+    }
+
+    /// Lifetime hit percentage.
+    pub fn accuracy(&self) -> Option<f64> {
+        let shots = self.total_hits + self.total_misses;
+        (shots > 0).then(|| self.total_hits as f64 / shots as f64 * 100.0)
+    }
+
+    /// A named player with no history, for play that is never stored.
+    pub fn guest(name: &str) -> Self {
+        Self {
+            id: 0,
+            fingerprint: String::new(),
+            name: Some(name.to_string()),
+            high_score: 0,
+            total_hits: 0,
+            total_misses: 0,
+            sessions: 0,
+            best_combo: 0,
+            user_activity: Vec::new(),
+            best_replay: None,
+            preferences: Preferences::default(),
+            reaction_total_ms: 0,
+            reaction_samples: 0,
+            score_history: Vec::new(),
+            best_accuracy_bp: 0,
+            best_reaction_ms: None,
+            strictness: Strictness::default(),
+        }
+    }
+}
+
+/// Outcome of a single run as persisted by `DbRequest::SaveGame`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GameResult {
+    pub score: u32,
+    pub hits: u32,
+    pub misses: u32,
+    pub best_combo: u32,
+    pub reaction_total_ms: u64,
+    pub reaction_samples: u32,
+    /// How long the run actually lasted.
+    pub duration_ms: u64,
+    pub flagged_clicks: u32,
+}
+
+impl GameResult {
+    pub fn from_stats(stats: &CombatStats) -> Self {
+        Self {
+            score: stats.current_score(),
+            hits: stats.hit_count,
+            misses: stats.miss_count,
+            best_combo: stats.best_combo(),
+            reaction_total_ms: stats.reaction_total_ms,
+            reaction_samples: stats.reaction_samples,
+            duration_ms: 0,
+            flagged_clicks: stats.flagged_count,
+        }
+    }
+
+    /// Accuracy in basis points, `None` below `BEST_ACCURACY_MIN_SHOTS`.
+    pub fn accuracy_bp(&self) -> Option<u32> {
+        let shots = self.hits + self.misses;
+        (shots >= BEST_ACCURACY_MIN_SHOTS).then(|| self.hits * 10_000 / shots)
+    }
+
+    pub fn average_reaction_ms(&self) -> Option<u64> {
+        (self.reaction_samples > 0).then(|| self.reaction_total_ms / self.reaction_samples as u64)
+    }
+}
+
+/// One finished run from the `games` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameRecord {
+    pub id: i64,
+    pub mode: GameMode,
+    pub score: u32,
+    pub hits: u32,
+    pub misses: u32,
+    pub duration_ms: u64,
+    pub reaction_ms: Option<u64>,
+    pub played_at: String,
+}
+
+/// A player's position on one leaderboard, 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UserRank {
+    pub rank: u32,
+    pub score: u32,
+}
+
+/// One page of a leaderboard as shown by the full leaderboard scene.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScoresPage {
+    pub entries: Vec<ScoreEntry>,
+    /// Entries on the whole board, when the backend can tell cheaply.
+    pub total: Option<u32>,
+}
+
+/// Where a player's latest run landed, e.g. "top 7% today".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScorePercentile {
+    pub score: u32,
+    pub period: RankingPeriod,
+    /// 1..=100, smaller is better.
+    pub top_percent: u32,
+}
+
+/// An operator-defined season. `ends_on` is exclusive: the all-time board is
+/// archived and reset once that UTC date arrives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Season {
+    pub id: i64,
+    pub name: String,
+    /// `%Y-%m-%d`
+    pub starts_on: String,
+    /// `%Y-%m-%d`
+    pub ends_on: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScoreEntry {
     pub name: String,
     pub score: u32,
     pub created_at: String,
+    pub region: Option<Region>,
+}
+
+/// Finished duels between two players, counted from the first one's side.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeadToHead {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl HeadToHead {
+    /// The same record from the opponent's side.
+    pub fn flipped(self) -> Self {
+        Self {
+            wins: self.losses,
+            losses: self.wins,
+            draws: self.draws,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -31,27 +271,237 @@ pub struct DbCache {
     pub daily_scores: Vec<ScoreEntry>,
     pub weekly_scores: Vec<ScoreEntry>,
     pub all_time_scores: Vec<ScoreEntry>,
+    pub daily_challenge_scores: Vec<ScoreEntry>,
+    pub weekly_modifier: Option<WeeklyModifier>,
+    /// Operator announcement shown on the menu.
+    pub motd: Option<String>,
+    /// Latest ranked run of recently active players, by user id.
+    pub score_percentiles: HashMap<i64, ScorePercentile>,
+    /// Archived seasons, most recent first.
+    pub past_seasons: Vec<Season>,
+    /// Key fingerprints an operator banned; refused at login.
+    pub banned_keys: HashSet<String>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum RankingPeriod {
     Daily,
     Weekly,
     AllTime,
+    DailyChallenge,
+}
+
+impl RankingPeriod {
+    pub const ALL: [Self; 4] = [
+        Self::Daily,
+        Self::Weekly,
+        Self::AllTime,
+        Self::DailyChallenge,
+    ];
+}
+
+/// Returned by `ScoreStore::update_username` when another player has the name.
+#[derive(Debug)]
+pub struct UsernameTaken;
+
+impl std::fmt::Display for UsernameTaken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Username already taken")
+    }
+}
+
+impl std::error::Error for UsernameTaken {}
+
+/// Users, stats and leaderboards as seen by `Repository`. Only ever called
+/// from the DB worker thread, so implementations need not be `Sync`.
+pub trait ScoreStore {
+    fn find_user(&self, fingerprint: &str) -> Result<Option<UserContext>>;
+    fn count_users(&self) -> Result<i64>;
+    /// Deletes users who never scored and haven't been created or played
+    /// within `inactive_days`. Returns how many were removed.
+    fn prune_inactive_users(&self, inactive_days: u32) -> Result<usize>;
+    fn create_user(&self, fingerprint: &str) -> Result<i64>;
+    fn update_username(&self, user_id: i64, name: &str) -> Result<()>;
+    fn is_username_taken(&self, name: &str) -> Result<bool>;
+    fn delete_user(&self, user_id: i64) -> Result<()>;
+    /// Lets another key log in as `user_id`; `find_user` then resolves it too.
+    fn add_user_key(&self, user_id: i64, fingerprint: &str) -> Result<()>;
+    /// Makes `fingerprint` the user's only key, dropping linked ones.
+    fn replace_user_keys(&self, user_id: i64, fingerprint: &str) -> Result<()>;
+    /// Stores the hash of the user's recovery code; `None` removes it.
+    fn set_recovery_code(&self, user_id: i64, code_hash: Option<&str>) -> Result<()>;
+    fn find_recovery_code(&self, code_hash: &str) -> Result<Option<i64>>;
+    /// Stores a link code's hash for `ttl`, replacing the user's earlier code.
+    /// False if another live code has the same hash.
+    fn put_link_code(&self, user_id: i64, code_hash: &str, ttl: Duration) -> Result<bool>;
+    /// Uses up the code; the account it was issued for, if it was still live.
+    fn take_link_code(&self, code_hash: &str) -> Result<Option<i64>>;
+    /// Wrong link codes `fingerprint` tried within the last `window`; see
+    /// `link_failure_keys` for the other keys failures are counted under.
+    fn link_failures(&self, fingerprint: &str, window: Duration) -> Result<u32>;
+    /// Also forgets failures older than `window`, whoever made them.
+    fn add_link_failure(&self, fingerprint: &str, window: Duration) -> Result<()>;
+
+    fn save_game(&self, user_id: i64, result: &GameResult) -> Result<()>;
+    /// Daily challenge runs count towards lifetime stats and activity,
+    /// but rank only on the challenge board, never on the regular ones.
+    fn save_daily_challenge(&self, user_id: i64, result: &GameResult) -> Result<()>;
+    /// Lifetime stats and activity only, no leaderboard entry.
+    fn save_practice(&self, user_id: i64, result: &GameResult) -> Result<()>;
+    /// Keeps only the highest-scoring run per user.
+    fn save_best_replay(&self, user_id: i64, score: u32, replay: &Replay) -> Result<()>;
+    fn save_preferences(&self, user_id: i64, prefs: &Preferences) -> Result<()>;
+    /// Appends the run to the per-game history, whatever the mode, and returns its game id.
+    fn record_game(&self, user_id: i64, mode: GameMode, result: &GameResult) -> Result<i64>;
+    /// Most recent runs first.
+    fn get_games(&self, user_id: i64, limit: u32) -> Result<Vec<GameRecord>>;
+    /// Distributions over recorded games in `mode`, for everyone or one user.
+    fn game_stats(&self, mode: GameMode, user_id: Option<i64>) -> Result<GameStats>;
+    /// Stores a `Replay::compress` blob for `game_id`.
+    fn save_replay(&self, game_id: i64, data: &[u8]) -> Result<()>;
+    fn get_replay(&self, game_id: i64) -> Result<Option<Vec<u8>>>;
+    /// Drops replays of games scoring below `min_score` or no longer recorded.
+    fn prune_replays(&self, min_score: u32) -> Result<usize>;
+    /// Append-only; entries outlive the user so disputes can still be settled.
+    fn append_audit(&self, user_id: i64, submission: &Submission) -> Result<()>;
+    /// Newest first.
+    fn get_audit_log(&self, user_id: Option<i64>, limit: u32) -> Result<Vec<AuditEntry>>;
+    /// Append-only, like the audit log.
+    fn append_check_events(
+        &self,
+        user_id: i64,
+        game_id: Option<i64>,
+        events: &[CheckEvent],
+    ) -> Result<()>;
+    /// Newest first.
+    fn get_check_events(&self, check: Option<&str>, limit: u32) -> Result<Vec<CheckRecord>>;
+    /// Held submissions, and flagged ones with at least `min_flagged_clicks`
+    /// refused hits, with no later decision on their game, oldest first.
+    fn get_review_queue(&self, limit: u32, min_flagged_clicks: u32) -> Result<Vec<AuditEntry>>;
+    fn get_game(&self, game_id: i64) -> Result<Option<GameRecord>>;
+    /// Puts an approved held run on the boards it still belongs to.
+    fn publish_game(&self, game_id: i64) -> Result<()>;
+    /// Takes a rejected run off the boards; entries it set fall back to the
+    /// player's best other ranked run of the same period. Fails for a game
+    /// the audit log never flagged.
+    fn retract_game(&self, game_id: i64) -> Result<()>;
+    /// `None` for unknown users.
+    fn get_trust(&self, user_id: i64) -> Result<Option<Trust>>;
+    fn set_trust(&self, user_id: i64, trust: Trust) -> Result<()>;
+    /// Lowest trust first.
+    fn get_shadowed_users(&self) -> Result<Vec<ShadowedUser>>;
+
+    /// Shadowed users are left out of every public board and count.
+    fn get_top_scores(
+        &self,
+        period: RankingPeriod,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>>;
+    /// `None` when the player has no score on that leaderboard yet. A
+    /// shadowed player is ranked among the visible ones as if listed.
+    fn get_user_rank(&self, user_id: i64, period: RankingPeriod) -> Result<Option<UserRank>>;
+    /// (entries strictly above `score`, all entries) on the current board.
+    fn count_scores(&self, period: RankingPeriod, score: u32) -> Result<(u32, u32)>;
+
+    /// Modifier scheduled for the current `%Y-%W` week, if the operator set one.
+    fn get_weekly_modifier(&self) -> Result<Option<WeeklyModifier>>;
+    /// Schedules `modifier` for `week` (formatted as `%Y-%W`), or clears it.
+    fn set_weekly_modifier(&self, week: &str, modifier: Option<WeeklyModifier>) -> Result<()>;
+    fn create_season(&self, name: &str, starts_on: &str, ends_on: &str) -> Result<i64>;
+    /// Snapshots the all-time board into `season_scores` for every season
+    /// that has started and whose end date has arrived, then resets it.
+    /// Returns how many were archived.
+    fn archive_due_seasons(&self) -> Result<usize>;
+    /// Seasons not archived yet, by start date.
+    fn get_open_seasons(&self) -> Result<Vec<Season>>;
+    fn get_past_seasons(&self) -> Result<Vec<Season>>;
+    fn get_season_scores(&self, season_id: i64, limit: u32, offset: u32)
+    -> Result<Vec<ScoreEntry>>;
+
+    fn get_motd(&self) -> Result<Option<String>>;
+    /// Replaces the message of the day; `None` or a blank message clears it.
+    fn set_motd(&self, message: Option<&str>) -> Result<()>;
+
+    fn get_banned_keys(&self) -> Result<Vec<String>>;
+    fn set_key_banned(&self, fingerprint: &str, banned: bool) -> Result<()>;
+
+    /// Remembers an uploaded replay by its hash; false if it was seen before.
+    fn record_upload(&self, hash: &[u8; 32]) -> Result<bool>;
+
+    /// Stores a finished duel; `winner` is `None` for a draw.
+    fn record_duel(&self, players: [i64; 2], scores: [u32; 2], winner: Option<i64>) -> Result<()>;
+    fn get_head_to_head(&self, user_id: i64, opponent_id: i64) -> Result<HeadToHead>;
+
+    /// Takes the named lease for `ttl` unless it is still held, by anyone;
+    /// nodes sharing the store use it to run a job once per `ttl` between them.
+    fn try_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool>;
+    fn publish_event(&self, origin: &str, event: &ClusterEvent) -> Result<()>;
+    /// Events with an id above `after`, oldest first.
+    fn events_after(&self, after: i64) -> Result<Vec<ClusterRecord>>;
+    fn prune_events(&self, max_age: Duration) -> Result<usize>;
 }
 
 pub struct Repository {
-    conn: Connection,
+    store: Box<dyn ScoreStore + Send>,
     max_users: i64,
+    ranked_users: RefCell<VecDeque<i64>>,
+    percentiles: RefCell<HashMap<i64, ScorePercentile>>,
+    last_season_check: Cell<Option<Instant>>,
+    /// UTC date the cached daily and weekly boards were built for.
+    board_date: Cell<Option<chrono::NaiveDate>>,
+    name_filter: NameFilter,
+    /// Re-applied to the replay of every run that would reach the top ranks.
+    anticheat: AntiCheatConfig,
+    /// Holds improbable ranked runs back for review.
+    outliers: OutlierPolicy,
+    /// Population stats per mode, with when they were aggregated.
+    population_stats: RefCell<HashMap<GameMode, (Instant, GameStats)>>,
+    trust: TrustPolicy,
+    /// When set, runs without a matching event signature are refused.
+    signing_key: Option<SigningKey>,
+    /// Whether refused hits are stored next to the audit log.
+    check_telemetry: bool,
+    retention: RetentionPolicy,
+    last_prune: Cell<Option<Instant>>,
+    pruned_users: Cell<u64>,
+    /// Set when other processes share the store, see `with_cluster`.
+    cluster: Option<Cluster>,
+}
+
+/// Which idle accounts the worker deletes, and how often it looks.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    /// Users with a zero high score and no games in this many days are pruned.
+    pub inactive_days: u32,
+    pub interval: Duration,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            inactive_days: 90,
+            interval: Duration::from_secs(60 * 60),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum DbRequest {
     SaveGame {
         user_id: i64,
-        score: u32,
-        hits: u32,
-        misses: u32,
+        result: GameResult,
+        mode: GameMode,
+        replay: Arc<Replay>,
+        client: ClientKind,
+        /// HMAC of the run's game events, from frontends given the key.
+        signature: Option<Signature>,
+        /// Hits the anticheat refused during the run.
+        checks: Vec<CheckEvent>,
+        /// Link the run was played over, for judging its replay.
+        latency: LatencyClass,
+        /// Measured round trip of that link; the live checks excused this much.
+        round_trip: Duration,
     },
     UpdateUsername {
         user_id: i64,
@@ -60,67 +510,1023 @@ pub enum DbRequest {
     },
     GetOrCreateUser {
         fingerprint: String,
+        /// Region for a new account, e.g. from GeoIP; existing ones keep theirs.
+        region: Option<Region>,
         reply_tx: tokio::sync::oneshot::Sender<UserContext>,
     },
+    /// Looks the key's account up without creating one, e.g. for `ssh <host> stats`.
+    FindUser {
+        fingerprint: String,
+        reply_tx: tokio::sync::oneshot::Sender<Result<Option<UserContext>>>,
+    },
     DeleteUser {
         user_id: i64,
         reply_tx: tokio::sync::oneshot::Sender<Result<(), anyhow::Error>>,
     },
+    SavePreferences {
+        user_id: i64,
+        preferences: Preferences,
+    },
+    /// Outcome of the challenge a session with repeated cheat warnings had to pass.
+    Verification { user_id: i64, passed: bool },
+    GetScoresPage {
+        period: RankingPeriod,
+        offset: u32,
+        limit: u32,
+        reply_tx: tokio::sync::oneshot::Sender<ScoresPage>,
+    },
+    GetSeasonScores {
+        season_id: i64,
+        offset: u32,
+        limit: u32,
+        reply_tx: tokio::sync::oneshot::Sender<ScoresPage>,
+    },
+    /// The player's profile, stats and history as JSON, see `Repository::export_user_data`.
+    ExportUserData {
+        user_id: i64,
+        fingerprint: String,
+        reply_tx: tokio::sync::oneshot::Sender<Result<String>>,
+    },
+    /// Issues a short-lived code another device can redeem to join this account.
+    CreateLinkCode {
+        user_id: i64,
+        reply_tx: tokio::sync::oneshot::Sender<Result<String>>,
+    },
+    /// Attaches `fingerprint` to the account that issued `code`.
+    RedeemLinkCode {
+        fingerprint: String,
+        /// Where the player connected from, when the frontend knows.
+        peer_ip: Option<IpAddr>,
+        code: String,
+        reply_tx: tokio::sync::oneshot::Sender<Result<UserContext>>,
+    },
+    /// Issues a new one-time recovery code, invalidating the previous one.
+    CreateRecoveryCode {
+        user_id: i64,
+        reply_tx: tokio::sync::oneshot::Sender<Result<String>>,
+    },
+    /// Moves the account behind a recovery code onto `fingerprint`.
+    RedeemRecoveryCode {
+        fingerprint: String,
+        code: String,
+        reply_tx: tokio::sync::oneshot::Sender<Result<UserContext>>,
+    },
+    /// Picks up changes made directly in the database, e.g. a new MOTD.
+    RefreshCache,
+    /// Replaces the message of the day from the admin console; `None` clears it.
+    SetMotd {
+        message: Option<String>,
+        reply_tx: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    /// Schedules a weekly modifier (`None` clears it) for a `%Y-%W` week.
+    SetWeeklyModifier {
+        week: String,
+        modifier: Option<WeeklyModifier>,
+        reply_tx: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    /// Bans or unbans a key fingerprint from the admin console.
+    SetKeyBan {
+        fingerprint: String,
+        banned: bool,
+        reply_tx: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    /// Result of a finished duel, for the head-to-head records.
+    RecordDuel {
+        players: [i64; 2],
+        scores: [u32; 2],
+        winner: Option<i64>,
+    },
+    GetHeadToHead {
+        user_id: i64,
+        opponent_id: i64,
+        reply_tx: tokio::sync::oneshot::Sender<HeadToHead>,
+    },
+    /// The player's own rank on each board they have a score on.
+    GetUserRanks {
+        user_id: i64,
+        reply_tx: tokio::sync::oneshot::Sender<HashMap<RankingPeriod, UserRank>>,
+    },
+    /// Whether `UpdateUsername` would take the name: allowed and not in use.
+    CheckUsername {
+        name: String,
+        reply_tx: tokio::sync::oneshot::Sender<bool>,
+    },
+    /// Answers once the store responds to a trivial query, for health checks.
+    Ping {
+        reply_tx: tokio::sync::oneshot::Sender<Result<()>>,
+    },
+    /// Tells the other nodes sharing the store; ignored outside a cluster.
+    Broadcast(ClusterEvent),
+    /// A standard run played offline, recounted and verified from its replay alone.
+    UploadGame {
+        user_id: i64,
+        replay: Arc<Replay>,
+        reply_tx: tokio::sync::oneshot::Sender<Submission>,
+    },
+}
+
+impl DbRequest {
+    /// Requests a player is actively waiting on, e.g. a login with a timeout.
+    pub fn is_priority(&self) -> bool {
+        matches!(
+            self,
+            Self::GetOrCreateUser { .. }
+                | Self::FindUser { .. }
+                | Self::UpdateUsername { .. }
+                | Self::DeleteUser { .. }
+                | Self::GetScoresPage { .. }
+                | Self::GetSeasonScores { .. }
+                | Self::CreateLinkCode { .. }
+                | Self::RedeemLinkCode { .. }
+                | Self::CreateRecoveryCode { .. }
+                | Self::RedeemRecoveryCode { .. }
+                | Self::SetMotd { .. }
+                | Self::SetWeeklyModifier { .. }
+                | Self::SetKeyBan { .. }
+                | Self::GetHeadToHead { .. }
+                | Self::CheckUsername { .. }
+                | Self::Ping { .. }
+        )
+    }
+}
+
+/// Pending requests: priority ones first, FIFO within each class.
+#[derive(Default)]
+pub struct DbQueue {
+    priority: VecDeque<DbRequest>,
+    bulk: VecDeque<DbRequest>,
+}
+
+impl DbQueue {
+    pub fn push(&mut self, req: DbRequest) {
+        if req.is_priority() {
+            self.priority.push_back(req);
+        } else {
+            self.bulk.push_back(req);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<DbRequest> {
+        self.priority.pop_front().or_else(|| self.bulk.pop_front())
+    }
+
+    pub fn len(&self) -> usize {
+        self.priority.len() + self.bulk.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl Repository {
+    /// SQLite-backed repository, the default for single-node setups.
     pub fn new(conn: Connection, max_users: i64) -> Result<Self> {
-        self::setup_schema(&conn)?;
-        Ok(Self { conn, max_users })
+        Ok(Self::with_store(SqliteStore::new(conn)?, max_users))
+    }
+
+    /// Nothing survives a restart; for demos, tests and read-only filesystems.
+    pub fn in_memory(max_users: i64) -> Self {
+        Self::with_store(MemoryStore::new(), max_users)
+    }
+
+    pub fn with_store(store: impl ScoreStore + Send + 'static, max_users: i64) -> Self {
+        Self {
+            store: Box::new(store),
+            max_users,
+            ranked_users: RefCell::new(VecDeque::new()),
+            percentiles: RefCell::new(HashMap::new()),
+            last_season_check: Cell::new(None),
+            board_date: Cell::new(None),
+            name_filter: NameFilter::default(),
+            anticheat: AntiCheatConfig::default(),
+            outliers: OutlierPolicy::default(),
+            population_stats: RefCell::new(HashMap::new()),
+            trust: TrustPolicy::default(),
+            signing_key: None,
+            check_telemetry: false,
+            retention: RetentionPolicy::default(),
+            last_prune: Cell::new(None),
+            pruned_users: Cell::new(0),
+            cluster: None,
+        }
+    }
+
+    /// For running several processes against one store: scheduled jobs run
+    /// on one node at a time, cache changes reach the others within
+    /// `CLUSTER_SYNC_INTERVAL`, and their session events arrive on `events_tx`.
+    /// `node_id` must differ between the processes.
+    pub fn with_cluster(
+        mut self,
+        node_id: String,
+        events_tx: tokio::sync::mpsc::UnboundedSender<ClusterEvent>,
+    ) -> Self {
+        self.cluster = Some(Cluster::new(node_id, events_tx));
+        self
+    }
+
+    /// How long the worker may wait for a request before `run_idle` is due.
+    pub fn idle_interval(&self) -> Duration {
+        if self.cluster.is_some() {
+            CLUSTER_SYNC_INTERVAL
+        } else {
+            IDLE_TICK_INTERVAL
+        }
+    }
+
+    pub fn with_anticheat(mut self, anticheat: AntiCheatConfig) -> Self {
+        self.anticheat = anticheat;
+        self
+    }
+
+    pub fn with_outlier_policy(mut self, outliers: OutlierPolicy) -> Self {
+        self.outliers = outliers;
+        self
+    }
+
+    pub fn with_trust_policy(mut self, trust: TrustPolicy) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    pub fn with_signing_key(mut self, key: SigningKey) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    pub fn with_check_telemetry(mut self, enabled: bool) -> Self {
+        self.check_telemetry = enabled;
+        self
+    }
+
+    pub fn with_retention(mut self, retention: RetentionPolicy) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Users removed by the retention policy since startup.
+    pub fn pruned_users(&self) -> u64 {
+        self.pruned_users.get()
+    }
+
+    pub fn with_name_filter(mut self, name_filter: NameFilter) -> Self {
+        self.name_filter = name_filter;
+        self
+    }
+
+    pub fn store(&self) -> &dyn ScoreStore {
+        self.store.as_ref()
     }
 
     pub fn get_current_cache(&self) -> DbCache {
+        self.board_date.set(Some(chrono::Utc::now().date_naive()));
         DbCache {
             daily_scores: self
-                .get_top_scores(RankingPeriod::Daily, 10)
+                .store
+                .get_top_scores(RankingPeriod::Daily, CACHED_PAGE_SIZE, 0)
                 .unwrap_or_default(),
             weekly_scores: self
-                .get_top_scores(RankingPeriod::Weekly, 10)
+                .store
+                .get_top_scores(RankingPeriod::Weekly, CACHED_PAGE_SIZE, 0)
                 .unwrap_or_default(),
             all_time_scores: self
-                .get_top_scores(RankingPeriod::AllTime, 10)
+                .store
+                .get_top_scores(RankingPeriod::AllTime, CACHED_PAGE_SIZE, 0)
                 .unwrap_or_default(),
+            daily_challenge_scores: self
+                .store
+                .get_top_scores(RankingPeriod::DailyChallenge, CACHED_PAGE_SIZE, 0)
+                .unwrap_or_default(),
+            weekly_modifier: self.store.get_weekly_modifier().unwrap_or_default(),
+            motd: self.store.get_motd().unwrap_or_default(),
+            score_percentiles: self.percentiles.borrow().clone(),
+            past_seasons: self.store.get_past_seasons().unwrap_or_default(),
+            banned_keys: self
+                .store
+                .get_banned_keys()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    /// Periodic jobs run from the worker between requests. Returns whether
+    /// the boards changed, i.e. the cache needs rebuilding.
+    fn run_scheduled(&self) -> bool {
+        let prune_due = self
+            .last_prune
+            .get()
+            .is_none_or(|t| t.elapsed() >= self.retention.interval);
+        if prune_due {
+            self.last_prune.set(Some(Instant::now()));
+            if self.take_turn("prune", self.retention.interval) {
+                let _ = self.prune_inactive_users();
+                let _ = self.store.prune_events(cluster::EVENT_RETENTION);
+            }
+        }
+
+        // Weeks start on a Monday, so a new week is also a new day.
+        let today = chrono::Utc::now().date_naive();
+        let day_rolled_over = self.board_date.get().is_some_and(|d| d != today);
+
+        let due = self
+            .last_season_check
+            .get()
+            .is_none_or(|t| t.elapsed() >= SEASON_CHECK_INTERVAL);
+        if !due {
+            return day_rolled_over;
+        }
+        self.last_season_check.set(Some(Instant::now()));
+        let archived = self.take_turn("seasons", SEASON_CHECK_INTERVAL)
+            && matches!(self.store.archive_due_seasons(), Ok(n) if n > 0);
+        archived || day_rolled_over
+    }
+
+    /// Runs the anticheat over the replay of a run that would make the top
+    /// ranks of any board it enters; returns the failed check. Lower runs are
+    /// trusted to keep the worker cheap, unless the player is watched that closely.
+    fn verify_top_run(
+        &self,
+        mode: GameMode,
+        result: &GameResult,
+        replay: &Replay,
+        link: Link,
+        strictness: Strictness,
+    ) -> Option<&'static str> {
+        if strictness >= Strictness::Replay {
+            return verify_replay(&self.anticheat, replay, result.score, link).err();
+        }
+        // a fresh day or week is easy to top, so those boards count as well
+        let periods: &[RankingPeriod] = match mode {
+            GameMode::Standard => &[
+                RankingPeriod::Daily,
+                RankingPeriod::Weekly,
+                RankingPeriod::AllTime,
+            ],
+            GameMode::DailyChallenge => &[RankingPeriod::DailyChallenge],
+            GameMode::Practice => &[],
+        };
+        let places = periods.iter().any(|&period| {
+            let board = self
+                .store
+                .get_top_scores(period, REPLAY_RANKS, 0)
+                .unwrap_or_default();
+            board.len() < REPLAY_RANKS as usize
+                || board.last().is_none_or(|e| result.score >= e.score)
+        });
+        if !places {
+            return None;
+        }
+        verify_replay(&self.anticheat, replay, result.score, link).err()
+    }
+
+    /// Bound over every round length and weekly modifier a run in `mode`
+    /// could have been played with, on the slowest link the anticheat excuses.
+    fn max_score(&self, mode: GameMode) -> u32 {
+        let fastest = self
+            .anticheat
+            .min_reaction_time
+            .saturating_sub(self.anticheat.max_latency_slack);
+        let lengths: Vec<Duration> = match mode {
+            GameMode::Practice => GameDuration::ALL.iter().map(|d| d.as_duration()).collect(),
+            GameMode::Standard | GameMode::DailyChallenge => {
+                vec![GameDuration::default().as_duration()]
+            }
+        };
+        let modifiers = std::iter::once(None).chain(WeeklyModifier::ALL.into_iter().map(Some));
+        modifiers
+            .flat_map(|modifier| {
+                lengths.iter().map(move |&playing_time| {
+                    GameConfig {
+                        playing_time,
+                        ..GameConfig::default()
+                    }
+                    .with_modifier(modifier)
+                    .max_score(fastest)
+                })
+            })
+            .max()
+            .unwrap_or(u32::MAX)
+    }
+
+    /// Keeps the replay of a run that places on the all-time board and drops
+    /// those pushed off it.
+    fn keep_top_replay(&self, game_id: i64, score: u32, replay: &Replay) -> Result<()> {
+        let board = self
+            .store
+            .get_top_scores(RankingPeriod::AllTime, REPLAY_RANKS, 0)?;
+        let cutoff = if board.len() >= REPLAY_RANKS as usize {
+            board.last().map(|e| e.score)
+        } else {
+            None
+        };
+        if cutoff.is_none_or(|cutoff| score >= cutoff) {
+            let data = replay.compress()?;
+            if data.len() <= MAX_REPLAY_BYTES {
+                self.store.save_replay(game_id, &data)?;
+            }
+        }
+        if let Some(cutoff) = cutoff {
+            self.store.prune_replays(cutoff)?;
+        }
+        Ok(())
+    }
+
+    /// Full replay of a top-ranked run, if it was kept.
+    pub fn get_replay(&self, game_id: i64) -> Result<Option<Replay>> {
+        self.store
+            .get_replay(game_id)?
+            .map(|data| Replay::decompress(&data))
+            .transpose()
+    }
+
+    /// Pruned users never scored, so the boards are unaffected.
+    fn prune_inactive_users(&self) -> Result<usize> {
+        let pruned = self
+            .store
+            .prune_inactive_users(self.retention.inactive_days)?;
+        self.pruned_users
+            .set(self.pruned_users.get() + pruned as u64);
+        Ok(pruned)
+    }
+
+    pub fn get_scores_page(
+        &self,
+        period: RankingPeriod,
+        offset: u32,
+        limit: u32,
+    ) -> Result<ScoresPage> {
+        let entries = self.store.get_top_scores(period, limit, offset)?;
+        let (_, total) = self.store.count_scores(period, 0)?;
+        Ok(ScoresPage {
+            entries,
+            total: Some(total),
+        })
+    }
+
+    /// Top-% of `score` on the current `period` board, rounded up so the
+    /// best run is "top 1%". `None` while the board is too small to mean much.
+    pub fn get_score_percentile(&self, score: u32, period: RankingPeriod) -> Result<Option<u32>> {
+        let (better, total) = self.store.count_scores(period, score)?;
+        Ok(top_percent(better, total))
+    }
+
+    /// Replaces any earlier code for the same user. Kept in the store, so
+    /// any node sharing it can redeem the code.
+    pub fn create_link_code(&self, user_id: i64) -> Result<String> {
+        loop {
+            let code = random_code(LINK_CODE_LEN);
+            if self
+                .store
+                .put_link_code(user_id, &hash_code(&code), LINK_CODE_TTL)?
+            {
+                return Ok(code);
+            }
+        }
+    }
+
+    /// Links `fingerprint` to the account behind `code`, see `claim_key`.
+    pub fn redeem_link_code(
+        &self,
+        fingerprint: &str,
+        peer_ip: Option<IpAddr>,
+        code: &str,
+    ) -> Result<UserContext> {
+        let keys = link_failure_keys(fingerprint, peer_ip);
+        for key in &keys {
+            if self.store.link_failures(key, LINK_CODE_TTL)? >= MAX_LINK_FAILURES {
+                anyhow::bail!("Too many wrong codes; try again in a few minutes");
+            }
+        }
+        let code = code.trim().to_ascii_uppercase();
+        let Some(target) = self.store.take_link_code(&hash_code(&code))? else {
+            for key in &keys {
+                self.store.add_link_failure(key, LINK_CODE_TTL)?;
+            }
+            anyhow::bail!("Invalid or expired code");
+        };
+
+        if self.claim_key(fingerprint, target)? {
+            anyhow::bail!("This key is already linked");
+        }
+        self.store.add_user_key(target, fingerprint)?;
+        self.find_user(fingerprint)?
+            .ok_or_else(|| anyhow::anyhow!("Linked account disappeared"))
+    }
+
+    /// Only the hash is stored, so the code can be shown exactly once.
+    pub fn create_recovery_code(&self, user_id: i64) -> Result<String> {
+        let code = random_code(RECOVERY_CODE_LEN);
+        self.store
+            .set_recovery_code(user_id, Some(&hash_code(&code)))?;
+        Ok(code)
+    }
+
+    /// Makes `fingerprint` the account's only key and uses up the code. Any
+    /// other keys are dropped, since the point is that they were lost.
+    pub fn redeem_recovery_code(&self, fingerprint: &str, code: &str) -> Result<UserContext> {
+        let code: String = code
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_uppercase();
+        let Some(target) = self.store.find_recovery_code(&hash_code(&code))? else {
+            anyhow::bail!("Invalid recovery code");
+        };
+        self.claim_key(fingerprint, target)?;
+        self.store.replace_user_keys(target, fingerprint)?;
+        self.store.set_recovery_code(target, None)?;
+        self.find_user(fingerprint)?
+            .ok_or_else(|| anyhow::anyhow!("Recovered account disappeared"))
+    }
+
+    /// Frees `fingerprint` for `target`. Returns `true` if it already
+    /// belongs to `target`. A new device's key is given an empty account
+    /// on first connection; that one is dropped, anything else is refused.
+    fn claim_key(&self, fingerprint: &str, target: i64) -> Result<bool> {
+        let Some(current) = self.store.find_user(fingerprint)? else {
+            return Ok(false);
+        };
+        if current.id == target {
+            return Ok(true);
+        }
+        if current.name.is_some() || current.high_score > 0 {
+            anyhow::bail!("This key already has an account");
+        }
+        self.store.delete_user(current.id)?;
+        self.forget_user(current.id);
+        Ok(false)
+    }
+
+    fn track_user(&self, user_id: i64) {
+        let mut users = self.ranked_users.borrow_mut();
+        users.retain(|&id| id != user_id);
+        users.push_back(user_id);
+        let evicted = if users.len() > RANKED_USERS_LIMIT {
+            users.pop_front()
+        } else {
+            None
+        };
+        if let Some(evicted) = evicted {
+            self.percentiles.borrow_mut().remove(&evicted);
+        }
+    }
+
+    fn forget_user(&self, user_id: i64) {
+        self.ranked_users.borrow_mut().retain(|&id| id != user_id);
+        self.percentiles.borrow_mut().remove(&user_id);
+    }
+
+    /// A store error counts as taken, so nothing is offered that may be refused.
+    pub fn is_username_available(&self, name: &str) -> bool {
+        self.name_filter.check(name).is_ok()
+            && self.store.is_username_taken(name).is_ok_and(|taken| !taken)
+    }
+
+    /// Looked up per player on request: a rank is a count over the whole
+    /// board, too costly to keep current for everyone in the shared cache.
+    pub fn get_user_ranks(&self, user_id: i64) -> HashMap<RankingPeriod, UserRank> {
+        RankingPeriod::ALL
+            .into_iter()
+            .filter_map(|period| {
+                let rank = self.store.get_user_rank(user_id, period).ok()??;
+                Some((period, rank))
+            })
+            .collect()
+    }
+
+    fn find_user(&self, fingerprint: &str) -> Result<Option<UserContext>> {
+        let mut user = self.store.find_user(fingerprint)?;
+        if let Some(user) = &mut user {
+            user.strictness = self.strictness(user.id);
+        }
+        Ok(user)
+    }
+
+    pub fn get_or_create_user_context(&self, fingerprint: &str) -> Result<UserContext> {
+        self.get_or_create_user_in(fingerprint, None)
+    }
+
+    /// Like `get_or_create_user_context`, giving a new account `region`.
+    pub fn get_or_create_user_in(
+        &self,
+        fingerprint: &str,
+        region: Option<Region>,
+    ) -> Result<UserContext> {
+        if let Some(user) = self.find_user(fingerprint)? {
+            return Ok(user);
         }
+
+        if self.store.count_users()? >= self.max_users {
+            self.prune_inactive_users()?;
+            if self.store.count_users()? >= self.max_users {
+                return Err(anyhow::anyhow!("User limit reached"));
+            }
+        }
+        let id = self.store.create_user(fingerprint)?;
+        let preferences = Preferences {
+            region,
+            ..Preferences::default()
+        };
+        if region.is_some() {
+            self.store.save_preferences(id, &preferences)?;
+        }
+        Ok(UserContext {
+            id,
+            fingerprint: fingerprint.to_string(),
+            name: None,
+            high_score: 0,
+            total_hits: 0,
+            total_misses: 0,
+            sessions: 0,
+            best_combo: 0,
+            user_activity: Vec::new(),
+            best_replay: None,
+            preferences,
+            reaction_total_ms: 0,
+            reaction_samples: 0,
+            score_history: Vec::new(),
+            best_accuracy_bp: 0,
+            best_reaction_ms: None,
+            strictness: Strictness::default(),
+        })
     }
 
     pub fn handle_request(&self, req: DbRequest) -> Option<DbCache> {
+        let rolled_over = self.run_scheduled();
+        // these only rebuild this node's view of what is already stored
+        let local = matches!(
+            req,
+            DbRequest::RefreshCache | DbRequest::GetOrCreateUser { .. }
+        );
+        let cache = self.handle(req);
+        self.mark_changed(rolled_over || (cache.is_some() && !local));
+        let stale = self.sync_cluster();
+        if rolled_over || stale {
+            cache.or_else(|| Some(self.get_current_cache()))
+        } else {
+            cache
+        }
+    }
+
+    /// Runs the periodic jobs while no requests arrive; returns a new cache
+    /// if the boards changed.
+    pub fn run_idle(&self) -> Option<DbCache> {
+        let rolled_over = self.run_scheduled();
+        self.mark_changed(rolled_over);
+        let stale = self.sync_cluster();
+        (rolled_over || stale).then(|| self.get_current_cache())
+    }
+
+    /// Audits a run refused before it was recorded; it doesn't count as played.
+    fn refuse_run(
+        &self,
+        user_id: i64,
+        mode: GameMode,
+        result: &GameResult,
+        client: ClientKind,
+        reason: &str,
+    ) -> Submission {
+        let submission = Submission {
+            game_id: None,
+            mode,
+            score: result.score,
+            flagged_clicks: result.flagged_clicks,
+            client,
+            verdict: Verdict::Rejected,
+            reason: Some(reason.to_string()),
+        };
+        let _ = self.store.append_audit(user_id, &submission);
+        let _ = self.update_trust(user_id, Verdict::Rejected);
+        submission
+    }
+
+    /// Records a run that wasn't refused outright and audits what became of
+    /// it; the cache is returned if the run was stored.
+    fn save_run(
+        &self,
+        user_id: i64,
+        mode: GameMode,
+        result: &GameResult,
+        replay: &Replay,
+        client: ClientKind,
+        link: Link,
+    ) -> (Submission, Option<DbCache>) {
+        // a failed or held run still counts as played, but ranks nowhere
+        let strictness = self.strictness(user_id);
+        let failed_check = self.verify_top_run(mode, result, replay, link, strictness);
+        let outlier = failed_check
+            .is_none()
+            .then(|| self.find_outlier(user_id, mode, result))
+            .flatten();
+        let ranked_mode = if failed_check.is_some() || outlier.is_some() {
+            GameMode::Practice
+        } else {
+            mode
+        };
+        let saved = match ranked_mode {
+            GameMode::Standard => self
+                .store
+                .save_game(user_id, result)
+                .and_then(|_| self.store.save_best_replay(user_id, result.score, replay)),
+            GameMode::DailyChallenge => self.store.save_daily_challenge(user_id, result),
+            GameMode::Practice => self.store.save_practice(user_id, result),
+        }
+        .and_then(|_| self.store.record_game(user_id, mode, result));
+        if let (Ok(game_id), GameMode::Standard) = (&saved, ranked_mode) {
+            let _ = self.keep_top_replay(*game_id, result.score, replay);
+        }
+        // a held run's replay is kept so the reviewer can watch it
+        let held_replay = outlier
+            .and_then(|_| replay.compress().ok())
+            .filter(|data| data.len() <= MAX_REPLAY_BYTES);
+        if let (Ok(game_id), Some(data)) = (&saved, held_replay) {
+            let _ = self.store.save_replay(*game_id, &data);
+        }
+        let verdict = match &saved {
+            Err(_) => Verdict::Rejected,
+            Ok(_) if failed_check.is_some() => Verdict::Rejected,
+            Ok(_) if outlier.is_some() => Verdict::Held,
+            Ok(_) if result.flagged_clicks > 0 => Verdict::Flagged,
+            Ok(_) => Verdict::Accepted,
+        };
+        let submission = Submission {
+            game_id: saved.as_ref().ok().copied(),
+            mode,
+            score: result.score,
+            flagged_clicks: result.flagged_clicks,
+            client,
+            verdict,
+            reason: failed_check.or(outlier).map(str::to_string),
+        };
+        let _ = self.store.append_audit(user_id, &submission);
+        let _ = self.update_trust(user_id, verdict);
+        self.track_user(user_id);
+
+        let period = match ranked_mode {
+            GameMode::Standard => Some(RankingPeriod::Daily),
+            GameMode::DailyChallenge => Some(RankingPeriod::DailyChallenge),
+            GameMode::Practice => None,
+        };
+        let percentile = period.and_then(|period| {
+            let top = self.get_score_percentile(result.score, period).ok()??;
+            Some((period, top))
+        });
+        if let Some((period, top_percent)) = percentile {
+            self.percentiles.borrow_mut().insert(
+                user_id,
+                ScorePercentile {
+                    score: result.score,
+                    period,
+                    top_percent,
+                },
+            );
+        }
+        let cache = saved.is_ok().then(|| self.get_current_cache());
+        (submission, cache)
+    }
+
+    fn handle(&self, req: DbRequest) -> Option<DbCache> {
         match req {
             DbRequest::GetOrCreateUser {
                 fingerprint,
+                region,
                 reply_tx,
-            } => {
-                match self.get_or_create_user_context(&fingerprint) {
-                    Ok(user_context) => {
-                        let _ = reply_tx.send(user_context);
-                    }
-                    Err(_) => {}
+            } => match self.get_or_create_user_in(&fingerprint, region) {
+                Ok(user_context) => {
+                    self.track_user(user_context.id);
+                    let _ = reply_tx.send(user_context);
+                    Some(self.get_current_cache())
                 }
-                None
-            }
+                Err(_) => None,
+            },
             DbRequest::SaveGame {
                 user_id,
-                score,
-                hits,
-                misses,
+                result,
+                mode,
+                replay,
+                client,
+                signature,
+                checks,
+                latency,
+                round_trip,
             } => {
-                if self.save_game(user_id, score, hits, misses).is_ok() {
-                    Some(self.get_current_cache())
+                // forged or impossible submissions aren't even counted as played
+                let forged = self.signing_key.as_ref().is_some_and(|key| {
+                    signature.is_none_or(|signature| !signing::verify(key, &replay, &signature))
+                });
+                let refusal = if forged {
+                    Some("bad_signature")
+                } else if result.score > self.max_score(mode) {
+                    Some("impossible_score")
                 } else {
                     None
+                };
+                if let Some(reason) = refusal {
+                    self.refuse_run(user_id, mode, &result, client, reason);
+                    return None;
+                }
+                let link = Link {
+                    latency,
+                    round_trip,
+                };
+                let (submission, cache) =
+                    self.save_run(user_id, mode, &result, &replay, client, link);
+                if self.check_telemetry && !checks.is_empty() {
+                    let _ = self
+                        .store
+                        .append_check_events(user_id, submission.game_id, &checks);
+                }
+                cache
+            }
+            DbRequest::UploadGame {
+                user_id,
+                replay,
+                reply_tx,
+            } => {
+                let (result, refusal) = self.check_upload(&replay);
+                let mode = GameMode::Standard;
+                if let Some(reason) = refusal {
+                    let submission =
+                        self.refuse_run(user_id, mode, &result, ClientKind::Upload, reason);
+                    let _ = reply_tx.send(submission);
+                    return None;
                 }
+                let (submission, cache) = self.save_run(
+                    user_id,
+                    mode,
+                    &result,
+                    &replay,
+                    ClientKind::Upload,
+                    Link::LOCAL,
+                );
+                let _ = reply_tx.send(submission);
+                cache
+            }
+            DbRequest::SavePreferences {
+                user_id,
+                preferences,
+            } => {
+                let _ = self.store.save_preferences(user_id, &preferences);
+                None
+            }
+            DbRequest::Verification { user_id, passed } => {
+                // passing counts as a clean run, failing as a rejected one
+                let verdict = if passed {
+                    Verdict::Accepted
+                } else {
+                    Verdict::Rejected
+                };
+                let _ = self.update_trust(user_id, verdict);
+                None
+            }
+            DbRequest::GetScoresPage {
+                period,
+                offset,
+                limit,
+                reply_tx,
+            } => {
+                let page = self
+                    .get_scores_page(period, offset, limit)
+                    .unwrap_or_default();
+                let _ = reply_tx.send(page);
+                None
+            }
+            DbRequest::GetSeasonScores {
+                season_id,
+                offset,
+                limit,
+                reply_tx,
+            } => {
+                let entries = self
+                    .store
+                    .get_season_scores(season_id, limit, offset)
+                    .unwrap_or_default();
+                let _ = reply_tx.send(ScoresPage {
+                    entries,
+                    total: None,
+                });
+                None
+            }
+            DbRequest::ExportUserData {
+                user_id,
+                fingerprint,
+                reply_tx,
+            } => {
+                let _ = reply_tx.send(self.export_user_data(user_id, &fingerprint));
+                None
+            }
+            DbRequest::CreateLinkCode { user_id, reply_tx } => {
+                let _ = reply_tx.send(self.create_link_code(user_id));
+                None
+            }
+            DbRequest::RedeemLinkCode {
+                fingerprint,
+                peer_ip,
+                code,
+                reply_tx,
+            } => {
+                let result = self.redeem_link_code(&fingerprint, peer_ip, &code);
+                if let Ok(user) = &result {
+                    self.track_user(user.id);
+                }
+                let _ = reply_tx.send(result);
+                None
+            }
+            DbRequest::CreateRecoveryCode { user_id, reply_tx } => {
+                let _ = reply_tx.send(self.create_recovery_code(user_id));
+                None
+            }
+            DbRequest::RedeemRecoveryCode {
+                fingerprint,
+                code,
+                reply_tx,
+            } => {
+                let result = self.redeem_recovery_code(&fingerprint, &code);
+                if let Ok(user) = &result {
+                    self.track_user(user.id);
+                }
+                let _ = reply_tx.send(result);
+                None
+            }
+            DbRequest::RefreshCache => Some(self.get_current_cache()),
+            DbRequest::SetMotd { message, reply_tx } => {
+                let result = self.store.set_motd(message.as_deref());
+                let changed = result.is_ok();
+                let _ = reply_tx.send(result);
+                changed.then(|| self.get_current_cache())
+            }
+            DbRequest::SetWeeklyModifier {
+                week,
+                modifier,
+                reply_tx,
+            } => {
+                let result = self.store.set_weekly_modifier(&week, modifier);
+                let changed = result.is_ok();
+                let _ = reply_tx.send(result);
+                changed.then(|| self.get_current_cache())
+            }
+            DbRequest::SetKeyBan {
+                fingerprint,
+                banned,
+                reply_tx,
+            } => {
+                let result = self.store.set_key_banned(&fingerprint, banned);
+                let changed = result.is_ok();
+                let _ = reply_tx.send(result);
+                changed.then(|| self.get_current_cache())
+            }
+            DbRequest::RecordDuel {
+                players,
+                scores,
+                winner,
+            } => {
+                let _ = self.store.record_duel(players, scores, winner);
+                None
+            }
+            DbRequest::GetHeadToHead {
+                user_id,
+                opponent_id,
+                reply_tx,
+            } => {
+                let record = self
+                    .store
+                    .get_head_to_head(user_id, opponent_id)
+                    .unwrap_or_default();
+                let _ = reply_tx.send(record);
+                None
+            }
+            DbRequest::GetUserRanks { user_id, reply_tx } => {
+                let _ = reply_tx.send(self.get_user_ranks(user_id));
+                None
+            }
+            DbRequest::CheckUsername { name, reply_tx } => {
+                let _ = reply_tx.send(self.is_username_available(&name));
+                None
+            }
+            DbRequest::Ping { reply_tx } => {
+                let _ = reply_tx.send(self.store.count_users().map(|_| ()));
+                None
+            }
+            DbRequest::Broadcast(event) => {
+                self.publish(event);
+                None
+            }
+            DbRequest::FindUser {
+                fingerprint,
+                reply_tx,
+            } => {
+                let _ = reply_tx.send(self.find_user(&fingerprint));
+                None
             }
-            DbRequest::DeleteUser { user_id, reply_tx } => match self.delete_user(user_id) {
+            DbRequest::DeleteUser { user_id, reply_tx } => match self.store.delete_user(user_id) {
                 Ok(_) => {
+                    self.forget_user(user_id);
                     let _ = reply_tx.send(Ok(()));
                     Some(self.get_current_cache())
                 }
                 Err(e) => {
-                    let _ = reply_tx.send(Err(e.into()));
+                    let _ = reply_tx.send(Err(e));
                     None
                 }
             },
@@ -128,14 +1534,21 @@ impl Repository {
                 user_id,
                 new_name,
                 reply_tx,
-            } => match self.update_username(user_id, &new_name) {
+            } => match self
+                .name_filter
+                .check(&new_name)
+                .map_err(anyhow::Error::from)
+                .and_then(|_| self.store.update_username(user_id, &new_name))
+            {
                 Ok(_) => {
                     let _ = reply_tx.send(Ok(()));
                     Some(self.get_current_cache())
                 }
                 Err(e) => {
-                    let msg = if e.to_string().contains("UNIQUE") {
+                    let msg = if e.is::<UsernameTaken>() {
                         anyhow::anyhow!("Username already taken")
+                    } else if let Some(rejected) = e.downcast_ref::<NameRejected>() {
+                        anyhow::anyhow!("{rejected}")
                     } else {
                         anyhow::anyhow!("Failed to update username")
                     };
@@ -145,285 +1558,578 @@ impl Repository {
             },
         }
     }
+}
 
-    pub fn delete_user(&self, user_id: i64) -> Result<()> {
-        self.conn
-            .execute("DELETE FROM users WHERE id = ?1", params![user_id])?;
-        Ok(())
+fn random_code(len: usize) -> String {
+    let mut rng = rand::rng();
+    (0..len)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect()
+}
+
+fn hash_code(code: &str) -> String {
+    format!("{:x}", Sha256::digest(code.as_bytes()))
+}
+
+/// Wrong link codes count against the key, which a fresh `ssh-keygen`
+/// replaces for free, and against the network it came from. IPv6 clients
+/// usually get a whole /64, so that is what they are counted as.
+fn link_failure_keys(fingerprint: &str, peer_ip: Option<IpAddr>) -> Vec<String> {
+    let network = peer_ip.map(|ip| match ip {
+        IpAddr::V4(ip) => format!("ip:{ip}"),
+        IpAddr::V6(ip) => {
+            let [a, b, c, d, ..] = ip.segments();
+            format!("ip:{a:x}:{b:x}:{c:x}:{d:x}::/64")
+        }
+    });
+    std::iter::once(fingerprint.to_string())
+        .chain(network)
+        .collect()
+}
+
+/// The run itself counts as an entry even if it isn't on the board, e.g.
+/// because the player already has a better score today.
+fn top_percent(better: u32, total: u32) -> Option<u32> {
+    let total = total.max(better + 1);
+    (total >= PERCENTILE_MIN_ENTRIES).then(|| ((better + 1) * 100).div_ceil(total).clamp(1, 100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::ReplayEvent;
+    use rusqlite::Connection;
+
+    /// Both local backends, so every test covers the SQL as well.
+    fn repos(max_users: i64) -> [Repository; 2] {
+        let conn = Connection::open_in_memory().unwrap();
+        [
+            Repository::in_memory(max_users),
+            Repository::with_store(SqliteStore::new(conn).unwrap(), max_users),
+        ]
     }
 
-    pub fn save_game(&self, user_id: i64, score: u32, hits: u32, misses: u32) -> Result<()> {
-        self.conn.execute(
-            "INSERT INTO user_stats (
-                user_id, 
-                high_score, 
-                high_score_at,
-                daily_high_score,
-                daily_high_score_at,
-                weekly_high_score,
-                weekly_high_score_at,
-                total_hits, 
-                total_misses, 
-                sessions
-            )
-            VALUES (?1, ?2, DATETIME('now'), ?2, DATE('now'), ?2, strftime('%Y-%W', 'now'), ?3, ?4, 1)
-            ON CONFLICT(user_id) DO UPDATE SET
-                -- all time
-                high_score_at = CASE 
-                    WHEN ?2 > high_score THEN DATETIME('now') 
-                    ELSE high_score_at 
-                END,
-                high_score = MAX(high_score, ?2),
-
-                -- daily.
-                daily_high_score = CASE 
-                    WHEN daily_high_score_at != DATE('now') THEN ?2
-                    ELSE MAX(daily_high_score, ?2)
-                END,
-                daily_high_score_at = DATE('now'),
-
-                -- weekly
-                weekly_high_score = CASE 
-                    WHEN weekly_high_score_at != strftime('%Y-%W', 'now') THEN ?2
-                    ELSE MAX(weekly_high_score, ?2)
-                END,
-                weekly_high_score_at = strftime('%Y-%W', 'now'),
-
-                total_hits = total_hits + ?3,
-                total_misses = total_misses + ?4,
-                sessions = sessions + 1",
-            params![user_id, score, hits, misses],
-        )?;
-
-        self.conn.execute(
-            "INSERT INTO daily_activity (user_id, date, count)
-            VALUES (?1, DATE('now'), 1)
-            ON CONFLICT(user_id, date) DO UPDATE SET
-                count = count + 1",
-            params![user_id],
-        )?;
+    /// A `SaveGame` as a test submits it, the rest left at the defaults.
+    #[derive(Default)]
+    struct Run {
+        user_id: i64,
+        result: GameResult,
+        mode: GameMode,
+        replay: Replay,
+        client: ClientKind,
+        signature: Option<Signature>,
+        checks: Vec<CheckEvent>,
+    }
 
-        Ok(())
+    impl Run {
+        fn scoring(user_id: i64, score: u32) -> Self {
+            Self {
+                user_id,
+                result: GameResult {
+                    score,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        fn request(self) -> DbRequest {
+            DbRequest::SaveGame {
+                user_id: self.user_id,
+                result: self.result,
+                mode: self.mode,
+                replay: Arc::new(self.replay),
+                client: self.client,
+                signature: self.signature,
+                checks: self.checks,
+                latency: LatencyClass::default(),
+                round_trip: Duration::ZERO,
+            }
+        }
     }
 
-    pub fn get_top_scores(&self, period: RankingPeriod, limit: u32) -> Result<Vec<ScoreEntry>> {
-        let (score_col, date_col, date_val, date_format) = match period {
-            RankingPeriod::Daily => (
-                "daily_high_score",
-                "daily_high_score_at",
-                "date('now')",
-                "%m-%d %H:%M",
-            ),
-            RankingPeriod::Weekly => (
-                "weekly_high_score",
-                "weekly_high_score_at",
-                "strftime('%Y-%W', 'now')",
-                "%m-%d %H:%M",
-            ),
-            RankingPeriod::AllTime => ("high_score", "high_score_at", "NULL", "%Y-%m-%d"),
-        };
+    /// A replay that backs up a run of one hit worth 10 points.
+    fn one_hit_replay() -> Replay {
+        let mut replay = Replay::default();
+        let target = crate::domain::Point { x: 3, y: 3 };
+        replay.record(Duration::ZERO, ReplayEvent::TargetSpawn(target));
+        replay.record(Duration::from_millis(300), ReplayEvent::MouseClick(target));
+        replay.record(Duration::from_millis(300), ReplayEvent::Score(10));
+        replay
+    }
 
-        let where_clause = if let RankingPeriod::AllTime = period {
-            format!("WHERE {} > 0", score_col)
-        } else {
-            format!("WHERE {} > 0 AND {} = {}", score_col, date_col, date_val)
-        };
+    #[test]
+    fn test_top_percent() {
+        assert_eq!(top_percent(0, 3), None);
+        assert_eq!(top_percent(0, 200), Some(1));
+        assert_eq!(top_percent(13, 200), Some(7));
+        assert_eq!(top_percent(99, 100), Some(100));
+        // a run below the player's own best isn't on the board yet
+        assert_eq!(top_percent(10, 10), Some(100));
+    }
 
-        let query = format!(
-            "SELECT 
-            u.username, 
-            s.{}, 
-            strftime('{}', s.high_score_at)
-            FROM users u
-            JOIN user_stats s ON u.id = s.user_id
-            {}
-            ORDER BY s.{} DESC
-            LIMIT ?1",
-            score_col, date_format, where_clause, score_col
-        );
+    #[test]
+    fn test_accuracy_needs_enough_shots() {
+        let mut result = GameResult {
+            hits: 3,
+            misses: 0,
+            ..Default::default()
+        };
+        assert_eq!(result.accuracy_bp(), None);
+        result.hits = 9;
+        result.misses = 3;
+        assert_eq!(result.accuracy_bp(), Some(7_500));
+    }
 
-        let mut stmt = self.conn.prepare_cached(&query)?;
+    #[test]
+    fn test_queue_serves_logins_before_saves() {
+        let mut queue = DbQueue::default();
+        queue.push(Run::scoring(1, 1).request());
+        queue.push(DbRequest::RefreshCache);
+        let (reply_tx, _rx) = tokio::sync::oneshot::channel();
+        queue.push(DbRequest::GetOrCreateUser {
+            fingerprint: "fp".to_string(),
+            region: None,
+            reply_tx,
+        });
+        queue.push(Run::scoring(1, 2).request());
 
-        let entries = stmt
-            .query_map(params![limit], |row| {
-                Ok(ScoreEntry {
-                    name: row.get(0)?,
-                    score: row.get(1)?,
-                    created_at: row.get(2)?,
-                })
-            })?
-            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
-
-        Ok(entries)
-    }
-
-    pub fn get_user_activity(&self, user_id: i64, days_limit: u32) -> Vec<ActivityDay> {
-        let mut stmt = self
-            .conn
-            .prepare_cached(
-                "SELECT date, count FROM daily_activity 
-                WHERE user_id = ?1 AND date > DATE('now', '-' || ?2 || ' days')
-                ORDER BY date ASC",
-            )
-            .expect("Failed to prepare activity query");
-
-        stmt.query_map(params![user_id, days_limit], |row| {
-            Ok(ActivityDay {
-                date: row.get(0)?,
-                count: row.get(1)?,
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop())
+            .map(|req| match req {
+                DbRequest::GetOrCreateUser { .. } => "login".to_string(),
+                DbRequest::SaveGame { result, .. } => format!("save {}", result.score),
+                DbRequest::RefreshCache => "refresh".to_string(),
+                _ => unreachable!(),
             })
-        })
-        .expect("Query failed")
-        .filter_map(|r| r.ok())
-        .collect()
+            .collect();
+        assert_eq!(order, ["login", "save 1", "refresh", "save 2"]);
+        assert!(queue.is_empty());
     }
 
-    pub fn get_user_by_fingerprint(&self, fingerprint: &str) -> Result<Option<(i64, String)>> {
-        let mut stmt = self
-            .conn
-            .prepare_cached("SELECT id, username FROM users WHERE fingerprint = ?1")?;
+    #[test]
+    fn test_region_only_fills_new_accounts() {
+        for repo in repos(10) {
+            let jp = Region::parse("JP");
+            let user = repo.get_or_create_user_in("fp", jp).unwrap();
+            assert_eq!(user.preferences.region, jp);
+            let again = repo
+                .get_or_create_user_in("fp", Region::parse("US"))
+                .unwrap();
+            assert_eq!(again.preferences.region, jp);
+        }
+    }
 
-        let mut rows = stmt.query(params![fingerprint])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some((row.get(0)?, row.get(1)?)))
-        } else {
-            Ok(None)
+    #[test]
+    fn test_only_free_allowed_names_are_available() {
+        for repo in repos(10) {
+            let repo = repo.with_name_filter(NameFilter::empty().with_reserved(["admin"]));
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            assert!(repo.is_username_available("alice"));
+            repo.store.update_username(user.id, "alice").unwrap();
+            assert!(!repo.is_username_available("alice"));
+            assert!(!repo.is_username_available("admin"));
         }
     }
 
-    pub fn create_user(&self, fingerprint: &str) -> Result<i64> {
-        self.conn.execute(
-            "INSERT INTO users (fingerprint) VALUES (?1)",
-            params![fingerprint],
-        )?;
-        Ok(self.conn.last_insert_rowid())
+    #[test]
+    fn test_top_run_keeps_compressed_replay() {
+        for repo in repos(10) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            repo.store.update_username(user.id, "alice").unwrap();
+            let replay = one_hit_replay();
+            repo.handle_request(
+                Run {
+                    replay: replay.clone(),
+                    ..Run::scoring(user.id, 10)
+                }
+                .request(),
+            );
+
+            let game = &repo.store.get_games(user.id, 1).unwrap()[0];
+            assert_eq!(repo.get_replay(game.id).unwrap(), Some(replay));
+        }
     }
 
-    pub fn update_username(&self, user_id: i64, name: &str) -> Result<()> {
-        self.conn.execute(
-            "UPDATE users SET username = ?1 WHERE id = ?2",
-            params![name, user_id],
-        )?;
-        Ok(())
+    #[test]
+    fn test_submissions_are_audited() {
+        for repo in repos(10) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            repo.handle_request(
+                Run {
+                    user_id: user.id,
+                    result: GameResult {
+                        flagged_clicks: 2,
+                        ..Default::default()
+                    },
+                    client: ClientKind::Local,
+                    ..Default::default()
+                }
+                .request(),
+            );
+            // unknown user, so the store refuses the score
+            repo.handle_request(
+                Run {
+                    user_id: user.id + 1,
+                    ..Default::default()
+                }
+                .request(),
+            );
+
+            let log = repo.audit_log(None, 10).unwrap();
+            let verdicts: Vec<_> = log.iter().map(|e| e.submission.verdict).collect();
+            assert_eq!(verdicts, [Verdict::Rejected, Verdict::Flagged]);
+            assert_eq!(log[1].submission.client, ClientKind::Local);
+            assert_eq!(repo.audit_log(Some(user.id), 10).unwrap().len(), 1);
+        }
     }
 
-    pub fn get_or_create_user_context(&self, fingerprint: &str) -> Result<UserContext> {
-        let mut stmt = self.conn.prepare_cached(
-            "SELECT
-            u.id,
-            u.username,
-            IFNULL(s.high_score, 0),
-            IFNULL(s.total_hits, 0),
-            IFNULL(s.total_misses, 0),
-            IFNULL(s.sessions, 0)
-        FROM users u 
-        LEFT JOIN user_stats s ON u.id = s.user_id 
-        WHERE u.fingerprint = ?1",
-        )?;
-
-        let res = stmt.query_row(params![fingerprint], |row| {
-            let id: i64 = row.get(0)?;
-            let user_activity = self.get_user_activity(id, 30);
-
-            Ok(UserContext {
-                id,
-                fingerprint: fingerprint.to_string(),
-                name: row.get(1)?,
-                high_score: row.get(2)?,
-                total_hits: row.get(3)?,
-                total_misses: row.get(4)?,
-                sessions: row.get(5)?,
-                user_activity,
+    #[test]
+    fn test_unverified_top_run_is_not_ranked() {
+        for repo in repos(10) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            repo.store.update_username(user.id, "mallory").unwrap();
+            // a patched client reporting a score its replay doesn't back up
+            repo.handle_request(Run::scoring(user.id, 9000).request());
+
+            let board = repo.store.get_top_scores(RankingPeriod::AllTime, 10, 0);
+            assert!(board.unwrap().is_empty());
+            let log = repo.audit_log(Some(user.id), 1).unwrap();
+            assert_eq!(log[0].submission.verdict, Verdict::Rejected);
+        }
+    }
+
+    #[test]
+    fn test_forged_signature_is_refused() {
+        let key = SigningKey::random();
+        for repo in repos(10).map(|r| r.with_signing_key(key.clone())) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            let mut replay = Replay::default();
+            replay.record(Duration::ZERO, ReplayEvent::Score(10));
+            let mut signer = crate::signing::EventSigner::new(&key);
+            signer.update(&replay.frames[0]);
+            let signature = signer.finish();
+            let submit = |score, signature| {
+                repo.handle_request(
+                    Run {
+                        mode: GameMode::Practice,
+                        replay: replay.clone(),
+                        signature,
+                        ..Run::scoring(user.id, score)
+                    }
+                    .request(),
+                )
+            };
+
+            assert!(submit(10, None).is_none());
+            assert!(submit(10, Some([0; 32])).is_none());
+            let log = repo.audit_log(Some(user.id), 10).unwrap();
+            assert_eq!(log[0].submission.reason.as_deref(), Some("bad_signature"));
+            assert!(submit(10, Some(signature)).is_some());
+        }
+    }
+
+    #[test]
+    fn test_impossible_score_is_refused() {
+        for repo in repos(10) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            let submit = |score| {
+                repo.handle_request(
+                    Run {
+                        mode: GameMode::Practice,
+                        ..Run::scoring(user.id, score)
+                    }
+                    .request(),
+                )
+            };
+
+            assert!(submit(1_000_000).is_none());
+            let log = repo.audit_log(Some(user.id), 10).unwrap();
+            assert_eq!(
+                log[0].submission.reason.as_deref(),
+                Some("impossible_score")
+            );
+            assert!(submit(500).is_some());
+        }
+    }
+
+    #[test]
+    fn test_refused_hits_are_kept_when_telemetry_is_on() {
+        for repo in repos(10).map(|r| r.with_check_telemetry(true)) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            let refused = CheckEvent {
+                check: "reaction_time",
+                measurement: Some(crate::anticheat::Measurement {
+                    value: 40.0,
+                    limit: 100.0,
+                }),
+            };
+            repo.handle_request(
+                Run {
+                    user_id: user.id,
+                    result: GameResult {
+                        flagged_clicks: 1,
+                        ..Default::default()
+                    },
+                    mode: GameMode::Practice,
+                    checks: vec![refused],
+                    ..Default::default()
+                }
+                .request(),
+            );
+
+            let log = repo.check_log(Some("reaction_time"), 10).unwrap();
+            assert_eq!(log.len(), 1);
+            assert_eq!((log[0].value, log[0].limit), (Some(40.0), Some(100.0)));
+            assert!(repo.check_log(Some("path_shape"), 10).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_shadowed_player_only_sees_own_rank() {
+        for repo in repos(10) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            repo.store.update_username(user.id, "alice").unwrap();
+            let shadowed = Trust {
+                score: 10,
+                shadowed: true,
+                ..Trust::default()
+            };
+            repo.store.set_trust(user.id, shadowed).unwrap();
+            repo.handle_request(
+                Run {
+                    replay: one_hit_replay(),
+                    ..Run::scoring(user.id, 10)
+                }
+                .request(),
+            );
+
+            assert!(repo.get_current_cache().all_time_scores.is_empty());
+            let rank = repo.store.get_user_rank(user.id, RankingPeriod::AllTime);
+            assert_eq!(rank.unwrap().map(|r| r.rank), Some(1));
+            assert_eq!(repo.shadowed_users().unwrap()[0].user_id, user.id);
+
+            repo.clear_shadow(user.id).unwrap();
+            assert_eq!(repo.get_current_cache().all_time_scores.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_review_retracts_rejected_and_publishes_approved_runs() {
+        // keep the rejection from shadowing the player
+        for repo in repos(10).map(|r| {
+            r.with_trust_policy(TrustPolicy {
+                shadow_below: 0,
+                ..Default::default()
             })
-        });
+        }) {
+            let user = repo.get_or_create_user_context("fp").unwrap();
+            repo.store.update_username(user.id, "alice").unwrap();
+            repo.handle_request(
+                Run {
+                    user_id: user.id,
+                    result: GameResult {
+                        score: 10,
+                        flagged_clicks: REVIEW_MIN_FLAGGED_CLICKS,
+                        ..Default::default()
+                    },
+                    replay: one_hit_replay(),
+                    ..Default::default()
+                }
+                .request(),
+            );
+            let board = |repo: &Repository| repo.store.get_top_scores(RankingPeriod::Daily, 10, 0);
+            assert_eq!(board(&repo).unwrap().len(), 1);
 
-        match res {
-            Ok(ctx) => Ok(ctx),
-            Err(rusqlite::Error::QueryReturnedNoRows) => {
-                self.enforce_user_limit()?;
-                let id = self.create_user(fingerprint)?;
-                Ok(UserContext {
-                    id,
-                    fingerprint: fingerprint.to_string(),
-                    name: None,
-                    high_score: 0,
-                    total_hits: 0,
-                    total_misses: 0,
-                    sessions: 0,
-                    user_activity: Vec::new(),
-                })
-            }
-            Err(e) => Err(e.into()),
+            // a run with a single refused hit is flagged, but not worth a review
+            let stray = repo
+                .store
+                .record_game(user.id, GameMode::Standard, &GameResult::default())
+                .unwrap();
+            let submission = Submission {
+                game_id: Some(stray),
+                mode: GameMode::Standard,
+                score: 0,
+                flagged_clicks: 1,
+                client: ClientKind::Ssh,
+                verdict: Verdict::Flagged,
+                reason: None,
+            };
+            repo.store.append_audit(user.id, &submission).unwrap();
+
+            let queue = repo.review_queue(10).unwrap();
+            assert_eq!(queue.len(), 1);
+            assert_eq!(queue[0].replay.map(|r| r.hits), Some(1));
+            let flagged = queue[0].entry.submission.game_id.unwrap();
+            repo.review(flagged, false).unwrap();
+            assert!(board(&repo).unwrap().is_empty());
+            assert!(repo.review(flagged, true).is_err());
+
+            let result = GameResult {
+                score: 20,
+                ..Default::default()
+            };
+            let held = repo
+                .store
+                .record_game(user.id, GameMode::Standard, &result)
+                .unwrap();
+            let submission = Submission {
+                game_id: Some(held),
+                mode: GameMode::Standard,
+                score: 20,
+                flagged_clicks: 0,
+                client: ClientKind::Ssh,
+                verdict: Verdict::Held,
+                reason: Some("score_outlier".to_string()),
+            };
+            repo.store.append_audit(user.id, &submission).unwrap();
+            assert!(repo.store.retract_game(held).is_err());
+            repo.review(held, true).unwrap();
+            assert_eq!(board(&repo).unwrap()[0].score, 20);
+            assert!(repo.review_queue(10).unwrap().is_empty());
         }
     }
 
-    fn enforce_user_limit(&self) -> Result<()> {
-        let count: i64 = self
-            .conn
-            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+    #[test]
+    fn test_idle_worker_rebuilds_boards_after_midnight() {
+        for repo in repos(10) {
+            repo.get_current_cache();
+            assert!(repo.run_idle().is_none());
 
-        if count >= self.max_users {
-            let deleted = self.conn.execute(
-                "DELETE FROM users 
-                WHERE id IN (
-                    SELECT u.id FROM users u
-                    LEFT JOIN user_stats s ON u.id = s.user_id
-                    WHERE IFNULL(s.high_score, 0) = 0
-                    ORDER BY u.created_at ASC
-                    LIMIT 1
-                )",
-                [],
-            )?;
+            let yesterday = chrono::Utc::now().date_naive() - chrono::Days::new(1);
+            repo.board_date.set(Some(yesterday));
+            assert!(repo.run_idle().is_some());
+            assert!(repo.run_idle().is_none());
+        }
+    }
 
-            if deleted == 0 {
-                return Err(anyhow::anyhow!("User limit reached"));
+    #[test]
+    fn test_link_code_moves_new_key_onto_account() {
+        for repo in repos(10) {
+            let laptop = repo.get_or_create_user_context("laptop").unwrap();
+            repo.store.update_username(laptop.id, "alice").unwrap();
+            let desktop = repo.get_or_create_user_context("desktop").unwrap();
+
+            let code = repo.create_link_code(laptop.id).unwrap();
+            assert!(repo.redeem_link_code("desktop", None, "nope").is_err());
+            let linked = repo
+                .redeem_link_code("desktop", None, &code.to_lowercase())
+                .unwrap();
+            assert_eq!(linked.id, laptop.id);
+            assert_eq!(linked.name.as_deref(), Some("alice"));
+            assert!(repo.store.find_user("desktop").unwrap().unwrap().id != desktop.id);
+            assert_eq!(repo.store.count_users().unwrap(), 1);
+            // codes are single use
+            assert!(repo.redeem_link_code("other", None, &code).is_err());
+
+            // a key that keeps guessing is turned away, even with the right code
+            let code = repo.create_link_code(laptop.id).unwrap();
+            for _ in 1..MAX_LINK_FAILURES {
+                assert!(repo.redeem_link_code("other", None, "WRONG1").is_err());
+            }
+            let err = repo.redeem_link_code("other", None, &code).unwrap_err();
+            assert!(err.to_string().starts_with("Too many"));
+            assert!(repo.redeem_link_code("tablet", None, &code).is_ok());
+
+            // and so is a network that keeps generating fresh keys
+            let code = repo.create_link_code(laptop.id).unwrap();
+            let home = |host| Some(IpAddr::from([0x2001, 0xdb8, 0, 1, 0, 0, 0, host]));
+            for host in 0..MAX_LINK_FAILURES as u16 {
+                let key = format!("fresh{host}");
+                assert!(repo.redeem_link_code(&key, home(host), "WRONG1").is_err());
             }
+            let err = repo.redeem_link_code("fresh", home(99), &code);
+            assert!(err.unwrap_err().to_string().starts_with("Too many"));
+            let elsewhere = Some(IpAddr::from([192, 0, 2, 1]));
+            assert!(repo.redeem_link_code("phone", elsewhere, &code).is_ok());
         }
-        Ok(())
     }
-}
-
-fn setup_schema(conn: &Connection) -> Result<()> {
-    // conn.pragma_update(None, "journal_mode", &"WAL")?;
 
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS users (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            fingerprint TEXT UNIQUE NOT NULL,
-            username TEXT UNIQUE,
-            created_at DATETIME DEFAULT (DATETIME('now'))
-        );
+    #[test]
+    fn test_recovery_code_replaces_lost_keys() {
+        for repo in repos(10) {
+            let old = repo.get_or_create_user_context("old").unwrap();
+            repo.store.add_user_key(old.id, "old-laptop").unwrap();
+            let code = repo.create_recovery_code(old.id).unwrap();
+            assert_eq!(code.len(), RECOVERY_CODE_LEN);
 
-        CREATE TABLE IF NOT EXISTS user_stats (
-            user_id INTEGER PRIMARY KEY,
+            let typed = format!("{}-{}", code[..8].to_lowercase(), &code[8..]);
+            let recovered = repo.redeem_recovery_code("new", &typed);
+            assert_eq!(recovered.unwrap().id, old.id);
+            assert!(repo.store.find_user("old").unwrap().is_none());
+            assert!(repo.store.find_user("old-laptop").unwrap().is_none());
+            assert!(repo.redeem_recovery_code("newer", &code).is_err());
+        }
+    }
 
-            high_score INTEGER DEFAULT 0,
-            high_score_at DATETIME DEFAULT (DATETIME('now')),
+    #[test]
+    fn test_banned_keys_reach_the_cache() {
+        for repo in repos(10) {
+            let (reply_tx, _rx) = tokio::sync::oneshot::channel();
+            let cache = repo.handle_request(DbRequest::SetKeyBan {
+                fingerprint: "SHA256:abc".to_string(),
+                banned: true,
+                reply_tx,
+            });
+            assert!(cache.unwrap().banned_keys.contains("SHA256:abc"));
 
-            daily_high_score INTEGER DEFAULT 0,
-            daily_high_score_at DATE DEFAULT (DATE('now')),
+            let (reply_tx, _rx) = tokio::sync::oneshot::channel();
+            let cache = repo.handle_request(DbRequest::SetKeyBan {
+                fingerprint: "SHA256:abc".to_string(),
+                banned: false,
+                reply_tx,
+            });
+            assert!(cache.unwrap().banned_keys.is_empty());
+        }
+    }
 
-            weekly_high_score INTEGER DEFAULT 0,
-            weekly_high_score_at TEXT DEFAULT (strftime('%Y-%W', 'now')),
+    #[test]
+    fn test_head_to_head_counts_both_orders() {
+        for repo in repos(10) {
+            let alice = repo.get_or_create_user_context("alice").unwrap().id;
+            let bob = repo.get_or_create_user_context("bob").unwrap().id;
+            repo.handle_request(DbRequest::RecordDuel {
+                players: [alice, bob],
+                scores: [30, 20],
+                winner: Some(alice),
+            });
+            repo.handle_request(DbRequest::RecordDuel {
+                players: [bob, alice],
+                scores: [25, 25],
+                winner: None,
+            });
 
-            total_hits INTEGER DEFAULT 0,
-            total_misses INTEGER DEFAULT 0,
-            sessions INTEGER DEFAULT 0,
+            let record = repo.store.get_head_to_head(alice, bob).unwrap();
+            let expected = HeadToHead {
+                wins: 1,
+                losses: 0,
+                draws: 1,
+            };
+            assert_eq!(record, expected);
+            assert_eq!(
+                repo.store.get_head_to_head(bob, alice).unwrap(),
+                expected.flipped()
+            );
+        }
+    }
 
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
+    #[test]
+    fn test_seasons_are_validated_and_do_not_overlap() {
+        let day = |offset: i64| {
+            (chrono::Utc::now().date_naive() + chrono::Duration::days(offset)).to_string()
+        };
+        for repo in repos(10) {
+            assert!(repo.create_season("S1", "2026-13-01", &day(30)).is_err());
+            assert!(repo.create_season("S1", &day(30), &day(10)).is_err());
+            assert!(repo.create_season("S1", &day(-30), &day(-1)).is_err());
 
-        CREATE TABLE IF NOT EXISTS daily_activity (
-            user_id INTEGER,
-            date DATE DEFAULT (DATE('now')),
-            count INTEGER DEFAULT 0,
-            PRIMARY KEY (user_id, date),
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        );
+            let id = repo.create_season(" S1 ", &day(-10), &day(20)).unwrap();
+            assert!(repo.create_season("S2", &day(19), &day(40)).is_err());
+            repo.create_season("S2", &day(20), &day(40)).unwrap();
 
-        CREATE INDEX IF NOT EXISTS idx_stats_daily ON user_stats (daily_high_score_at, daily_high_score DESC);
-        CREATE INDEX IF NOT EXISTS idx_stats_weekly ON user_stats (weekly_high_score_at, weekly_high_score DESC);
-        CREATE INDEX IF NOT EXISTS idx_stats_high_score ON user_stats (high_score DESC);",
-    )?;
-    Ok(())
+            let open = repo.store.get_open_seasons().unwrap();
+            assert_eq!(open.len(), 2);
+            assert_eq!((open[0].id, open[0].name.as_str()), (id, "S1"));
+            assert_eq!(repo.store.archive_due_seasons().unwrap(), 0);
+        }
+    }
 }
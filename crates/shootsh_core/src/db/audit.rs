@@ -0,0 +1,112 @@
+use super::Repository;
+use crate::domain::GameMode;
+use anyhow::Result;
+
+/// Which frontend a score was submitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientKind {
+    #[default]
+    Ssh,
+    /// The local `shootsh_cli` binary.
+    Local,
+    /// A browser on `shootsh_web`.
+    Web,
+    /// Played offline in `shootsh_cli` and uploaded with `sync`.
+    Upload,
+}
+
+impl ClientKind {
+    pub const ALL: [Self; 4] = [Self::Ssh, Self::Local, Self::Web, Self::Upload];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Ssh => "ssh",
+            Self::Local => "local",
+            Self::Web => "web",
+            Self::Upload => "upload",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.key() == key)
+    }
+}
+
+/// What became of a score submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accepted,
+    /// Stored, but the anticheat discarded some of the run's hits.
+    Flagged,
+    /// Counts as played but kept off the boards.
+    Rejected,
+    /// Kept off the boards until an admin reviews it.
+    Held,
+}
+
+impl Verdict {
+    pub const ALL: [Self; 4] = [Self::Accepted, Self::Flagged, Self::Rejected, Self::Held];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Accepted => "accepted",
+            Self::Flagged => "flagged",
+            Self::Rejected => "rejected",
+            Self::Held => "held",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|v| v.key() == key)
+    }
+}
+
+/// One score submission as written to the append-only `audit_log` table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submission {
+    /// `games.id` of the run, if it was recorded.
+    pub game_id: Option<i64>,
+    pub mode: GameMode,
+    pub score: u32,
+    pub flagged_clicks: u32,
+    pub client: ClientKind,
+    pub verdict: Verdict,
+    /// Check that refused or held the run.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub user_id: i64,
+    /// Current name; `None` once the account is gone.
+    pub name: Option<String>,
+    pub submission: Submission,
+    pub created_at: String,
+}
+
+/// A hit refused by an anticheat check, as written to the `check_events` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckRecord {
+    pub id: i64,
+    pub user_id: i64,
+    /// `games.id` of the run, if it was recorded.
+    pub game_id: Option<i64>,
+    pub check: String,
+    /// What the check measured and its threshold, in the check's own unit.
+    pub value: Option<f64>,
+    pub limit: Option<f64>,
+    pub created_at: String,
+}
+
+impl Repository {
+    /// Newest first, optionally for one user only.
+    pub fn audit_log(&self, user_id: Option<i64>, limit: u32) -> Result<Vec<AuditEntry>> {
+        self.store.get_audit_log(user_id, limit)
+    }
+
+    /// Refused hits, newest first, optionally for one check only.
+    pub fn check_log(&self, check: Option<&str>, limit: u32) -> Result<Vec<CheckRecord>> {
+        self.store.get_check_events(check, limit)
+    }
+}
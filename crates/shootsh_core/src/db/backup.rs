@@ -0,0 +1,120 @@
+use anyhow::{Context, Result, bail};
+use rusqlite::Connection;
+use rusqlite::backup::Backup;
+use std::path::Path;
+use std::time::Duration;
+
+/// How long to wait for the running server's write lock before giving up.
+const BACKUP_BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Pages copied per step; the pause between steps lets the server's writes through.
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// Copies the live database at `db_path` to `dest` with SQLite's online backup
+/// API, which is safe while the server keeps writing, then checks the copy.
+pub fn backup_database(db_path: &Path, dest: &Path) -> Result<()> {
+    ensure_exists(db_path)?;
+    if dest.exists() {
+        bail!("{} already exists", dest.display());
+    }
+    let src = Connection::open(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    src.busy_timeout(BACKUP_BUSY_TIMEOUT)?;
+    let mut dst =
+        Connection::open(dest).with_context(|| format!("Failed to create {}", dest.display()))?;
+    copy(&src, &mut dst).context("Backup failed")?;
+    drop(dst);
+    verify_integrity(dest)
+}
+
+/// Replaces the database at `db_path` with the snapshot at `src` after
+/// checking the snapshot. Stop the server first; it caches boards and users.
+pub fn restore_database(db_path: &Path, src: &Path) -> Result<()> {
+    ensure_exists(src)?;
+    verify_integrity(src)?;
+    let mut dest = Connection::open(db_path)
+        .with_context(|| format!("Failed to open {}", db_path.display()))?;
+    dest.busy_timeout(BACKUP_BUSY_TIMEOUT)?;
+    let snapshot =
+        Connection::open(src).with_context(|| format!("Failed to open {}", src.display()))?;
+    copy(&snapshot, &mut dest).context("Restore failed")?;
+    drop(dest);
+    verify_integrity(db_path)
+}
+
+/// `Connection::open` would quietly create an empty database instead.
+fn ensure_exists(path: &Path) -> Result<()> {
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+    Ok(())
+}
+
+fn copy(from: &Connection, to: &mut Connection) -> Result<()> {
+    Backup::new(from, to)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+    Ok(())
+}
+
+/// Runs `PRAGMA integrity_check` and fails with SQLite's findings unless it reports `ok`.
+pub fn verify_integrity(path: &Path) -> Result<()> {
+    let conn =
+        Connection::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let problems = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+    if problems != ["ok"] {
+        bail!(
+            "{} failed integrity check: {}",
+            path.display(),
+            problems.join("; ")
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{GameResult, RankingPeriod, ScoreStore, SqliteStore};
+
+    fn store(path: &Path) -> SqliteStore {
+        SqliteStore::new(Connection::open(path).unwrap()).unwrap()
+    }
+
+    fn add_player(store: &SqliteStore, name: &str, score: u32) {
+        let id = store.create_user(&format!("fp-{name}")).unwrap();
+        store.update_username(id, name).unwrap();
+        let result = GameResult {
+            score,
+            ..Default::default()
+        };
+        store.save_game(id, &result).unwrap();
+    }
+
+    #[test]
+    fn test_backup_and_restore_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("shootsh-backup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (live, snapshot) = (dir.join("live.db"), dir.join("snapshot.db"));
+
+        add_player(&store(&live), "alice", 120);
+        backup_database(&live, &snapshot).unwrap();
+        assert!(backup_database(&live, &snapshot).is_err());
+
+        // written after the snapshot, so gone once it is restored
+        add_player(&store(&live), "bob", 300);
+        restore_database(&live, &snapshot).unwrap();
+
+        let restored = store(&live);
+        assert_eq!(restored.count_users().unwrap(), 1);
+        assert!(restored.find_user("fp-alice").unwrap().is_some());
+        let top = restored
+            .get_top_scores(RankingPeriod::AllTime, 10, 0)
+            .unwrap();
+        let top: Vec<_> = top.iter().map(|e| (e.name.as_str(), e.score)).collect();
+        assert_eq!(top, [("alice", 120)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
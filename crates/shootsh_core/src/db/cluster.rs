@@ -0,0 +1,303 @@
+use super::Repository;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// How often a clustered node publishes its changes and reads the others'.
+pub const CLUSTER_SYNC_INTERVAL: Duration = Duration::from_secs(1);
+/// Events older than this are deleted; a node stalled longer misses them.
+pub(super) const EVENT_RETENTION: Duration = Duration::from_secs(10 * 60);
+/// How long a skipped event id is watched for. Concurrent writers can commit
+/// ids out of order, so a later id may show up before an earlier one;
+/// an id still missing after this was rolled back.
+const GAP_WAIT: Duration = Duration::from_secs(30);
+/// Skipped ids watched at once; a sequence jumping further is not followed.
+const MAX_GAPS: usize = 1000;
+
+/// What one node tells the others sharing its database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClusterEvent {
+    /// Boards, bans or the MOTD changed; the cache has to be rebuilt.
+    CacheChanged,
+    /// This key started a session on the sending node.
+    SessionStarted(String),
+    /// An operator disconnected this key.
+    Kicked(String),
+}
+
+impl ClusterEvent {
+    /// Stored as a kind and an optional fingerprint.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::CacheChanged => "cache",
+            Self::SessionStarted(_) => "session",
+            Self::Kicked(_) => "kick",
+        }
+    }
+
+    pub fn fingerprint(&self) -> Option<&str> {
+        match self {
+            Self::CacheChanged => None,
+            Self::SessionStarted(fp) | Self::Kicked(fp) => Some(fp),
+        }
+    }
+
+    pub fn from_parts(kind: &str, fingerprint: Option<String>) -> Option<Self> {
+        match (kind, fingerprint) {
+            ("cache", _) => Some(Self::CacheChanged),
+            ("session", Some(fp)) => Some(Self::SessionStarted(fp)),
+            ("kick", Some(fp)) => Some(Self::Kicked(fp)),
+            _ => None,
+        }
+    }
+}
+
+/// One event as read back from the store.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterRecord {
+    pub id: i64,
+    /// Node id of the sender.
+    pub origin: String,
+    pub event: ClusterEvent,
+}
+
+/// This node's place among several processes sharing one database.
+pub(super) struct Cluster {
+    node_id: String,
+    /// Events sessions act on, e.g. a kick, go out here.
+    events_tx: mpsc::UnboundedSender<ClusterEvent>,
+    /// Last event read; `None` until the first sync.
+    last_event: Cell<Option<i64>>,
+    /// Ids below `last_event` not seen yet, with when they were first missed.
+    gaps: RefCell<BTreeMap<i64, Instant>>,
+    last_sync: Cell<Option<Instant>>,
+    /// The cache changed here since the other nodes were last told.
+    dirty: Cell<bool>,
+}
+
+impl Cluster {
+    pub(super) fn new(node_id: String, events_tx: mpsc::UnboundedSender<ClusterEvent>) -> Self {
+        Self {
+            node_id,
+            events_tx,
+            last_event: Cell::new(None),
+            gaps: RefCell::new(BTreeMap::new()),
+            last_sync: Cell::new(None),
+            dirty: Cell::new(false),
+        }
+    }
+}
+
+impl Repository {
+    /// Runs the job on at most one node per `interval`; always true outside a cluster.
+    pub(super) fn take_turn(&self, job: &str, interval: Duration) -> bool {
+        let Some(cluster) = &self.cluster else {
+            return true;
+        };
+        self.store
+            .try_lease(job, &cluster.node_id, interval)
+            .unwrap_or_else(|e| {
+                tracing::warn!(job, error = %e, "Cannot take the job lease");
+                false
+            })
+    }
+
+    pub(super) fn mark_changed(&self, changed: bool) {
+        if let Some(cluster) = self.cluster.as_ref().filter(|_| changed) {
+            cluster.dirty.set(true);
+        }
+    }
+
+    pub(super) fn publish(&self, event: ClusterEvent) {
+        let Some(cluster) = &self.cluster else {
+            return;
+        };
+        if let Err(e) = self.store.publish_event(&cluster.node_id, &event) {
+            tracing::warn!(error = %e, ?event, "Cannot publish cluster event");
+        }
+    }
+
+    /// Tells the other nodes about local changes and picks up theirs, at
+    /// most once per `CLUSTER_SYNC_INTERVAL`. True if the cache is stale.
+    pub(super) fn sync_cluster(&self) -> bool {
+        let Some(cluster) = &self.cluster else {
+            return false;
+        };
+        if cluster
+            .last_sync
+            .get()
+            .is_some_and(|t| t.elapsed() < CLUSTER_SYNC_INTERVAL)
+        {
+            return false;
+        }
+        cluster.last_sync.set(Some(Instant::now()));
+        if cluster.dirty.take() {
+            self.publish(ClusterEvent::CacheChanged);
+        }
+
+        let after = cluster.last_event.get();
+        let mut gaps = cluster.gaps.borrow_mut();
+        gaps.retain(|_, missed_at| missed_at.elapsed() < GAP_WAIT);
+        // re-read from the oldest gap, in case its event has committed since
+        let from = gaps.keys().next().map_or(after.unwrap_or(0), |id| id - 1);
+        let records = match self.store.events_after(from) {
+            Ok(records) => records,
+            Err(e) => {
+                tracing::warn!(error = %e, "Cannot read cluster events");
+                return false;
+            }
+        };
+        // a node starting up only follows what happens from now on
+        let Some(mut last) = after else {
+            let last = records.last().map_or(0, |r| r.id);
+            cluster.last_event.set(Some(last));
+            return false;
+        };
+
+        let mut fresh = Vec::new();
+        for record in records {
+            if record.id > last {
+                let now = Instant::now();
+                for id in (last + 1..record.id).take(MAX_GAPS.saturating_sub(gaps.len())) {
+                    gaps.insert(id, now);
+                }
+                last = record.id;
+            } else if gaps.remove(&record.id).is_none() {
+                continue;
+            }
+            fresh.push(record);
+        }
+        cluster.last_event.set(Some(last));
+
+        let mut stale = false;
+        for record in fresh.into_iter().filter(|r| r.origin != cluster.node_id) {
+            match record.event {
+                ClusterEvent::CacheChanged => stale = true,
+                event => {
+                    let _ = cluster.events_tx.send(event);
+                }
+            }
+        }
+        stale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{DbRequest, SqliteStore};
+    use rusqlite::Connection;
+
+    #[test]
+    fn test_nodes_share_changes_and_sessions() {
+        // declared first, so the file goes after every connection to it
+        let db = TempDb::new();
+        let path = &db.0;
+        let node = |id: &str| {
+            let store = SqliteStore::new(Connection::open(path).unwrap()).unwrap();
+            let (tx, rx) = mpsc::unbounded_channel();
+            (
+                Repository::with_store(store, 100).with_cluster(id.to_string(), tx),
+                rx,
+            )
+        };
+        let (a, mut a_events) = node("a");
+        let (b, mut b_events) = node("b");
+        assert!(a.run_idle().is_none());
+        assert!(b.run_idle().is_none());
+
+        a.handle_request(DbRequest::SetMotd {
+            message: Some("hello".to_string()),
+            reply_tx: tokio::sync::oneshot::channel().0,
+        });
+        a.handle_request(DbRequest::Broadcast(ClusterEvent::SessionStarted(
+            "SHA256:one".to_string(),
+        )));
+        a.cluster.as_ref().unwrap().last_sync.set(None);
+        assert!(a.run_idle().is_none());
+
+        b.cluster.as_ref().unwrap().last_sync.set(None);
+        let cache = b.run_idle().expect("b rebuilds its cache");
+        assert_eq!(cache.motd.as_deref(), Some("hello"));
+        assert_eq!(
+            b_events.try_recv().unwrap(),
+            ClusterEvent::SessionStarted("SHA256:one".to_string())
+        );
+        // nobody hears their own events
+        assert!(a_events.try_recv().is_err());
+        assert!(b_events.try_recv().is_err());
+
+        // a link code issued on one node can be redeemed on another
+        let laptop = a.get_or_create_user_context("laptop").unwrap();
+        let code = a.create_link_code(laptop.id).unwrap();
+        assert!(b.redeem_link_code("desktop", None, "WRONG1").is_err());
+        assert_eq!(
+            b.redeem_link_code("desktop", None, &code).unwrap().id,
+            laptop.id
+        );
+
+        // only one node runs a job per interval
+        assert!(a.take_turn("test", Duration::from_secs(60)));
+        assert!(!b.take_turn("test", Duration::from_secs(60)));
+        assert!(!a.take_turn("test", Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_events_committed_out_of_order_are_not_skipped() {
+        let db = TempDb::new();
+        let path = &db.0;
+        let store = SqliteStore::new(Connection::open(path).unwrap()).unwrap();
+        let (tx, mut events) = mpsc::unbounded_channel();
+        let node = Repository::with_store(store, 100).with_cluster("b".to_string(), tx);
+        let sync = || {
+            node.cluster.as_ref().unwrap().last_sync.set(None);
+            node.sync_cluster()
+        };
+        sync();
+
+        // a writer that took id 1 commits after the one that took id 2
+        let writer = Connection::open(path).unwrap();
+        let commit = |id: i64, fp: &str| {
+            writer
+                .execute(
+                    "INSERT INTO cluster_events (id, origin, kind, fingerprint, created_at)
+                    VALUES (?1, 'a', 'kick', ?2, 0)",
+                    rusqlite::params![id, fp],
+                )
+                .unwrap();
+        };
+        commit(2, "SHA256:two");
+        sync();
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ClusterEvent::Kicked("SHA256:two".into())
+        );
+        commit(1, "SHA256:one");
+        sync();
+        assert_eq!(
+            events.try_recv().unwrap(),
+            ClusterEvent::Kicked("SHA256:one".into())
+        );
+        sync();
+        assert!(events.try_recv().is_err());
+    }
+
+    /// A database file in the temp dir, removed even when the test fails.
+    struct TempDb(std::path::PathBuf);
+
+    impl TempDb {
+        fn new() -> Self {
+            let name = format!("shootsh-cluster-{}.db", rand::random::<u64>());
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{suffix}", self.0.display()));
+            }
+        }
+    }
+}
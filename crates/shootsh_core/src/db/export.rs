@@ -0,0 +1,203 @@
+use super::{RankingPeriod, Repository, ScoreEntry};
+use anyhow::{Result, bail};
+use std::io::Write;
+
+/// Rows fetched per query while exporting, so large boards never sit in memory at once.
+const EXPORT_BATCH_SIZE: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            _ => bail!("Unknown export format '{s}' (expected csv or json)"),
+        }
+    }
+}
+
+impl RankingPeriod {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::AllTime => "all_time",
+            Self::DailyChallenge => "daily_challenge",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.key() == key)
+    }
+}
+
+impl Repository {
+    /// Writes the whole `period` board, best first, and returns the row count.
+    pub fn export_scores(
+        &self,
+        mut writer: impl Write,
+        format: ExportFormat,
+        period: RankingPeriod,
+    ) -> Result<usize> {
+        match format {
+            ExportFormat::Csv => writeln!(writer, "rank,name,score,created_at")?,
+            ExportFormat::Json => write!(writer, "[")?,
+        }
+
+        let mut written = 0;
+        loop {
+            let batch = self
+                .store
+                .get_top_scores(period, EXPORT_BATCH_SIZE, written as u32)?;
+            for entry in &batch {
+                written += 1;
+                match format {
+                    ExportFormat::Csv => write_csv_row(&mut writer, written, entry)?,
+                    ExportFormat::Json => write_json_row(&mut writer, written, entry)?,
+                }
+            }
+            if batch.len() < EXPORT_BATCH_SIZE as usize {
+                break;
+            }
+        }
+
+        if let ExportFormat::Json = format {
+            writeln!(writer, "{}]", if written > 0 { "\n" } else { "" })?;
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+}
+
+fn write_csv_row(writer: &mut impl Write, rank: usize, entry: &ScoreEntry) -> Result<()> {
+    writeln!(
+        writer,
+        "{rank},{},{},{}",
+        csv_field(&entry.name),
+        entry.score,
+        csv_field(&entry.created_at)
+    )?;
+    Ok(())
+}
+
+fn write_json_row(writer: &mut impl Write, rank: usize, entry: &ScoreEntry) -> Result<()> {
+    write!(
+        writer,
+        "{}\n  {{\"rank\":{rank},\"name\":{},\"score\":{},\"created_at\":{}}}",
+        if rank > 1 { "," } else { "" },
+        json_string(&entry.name),
+        entry.score,
+        json_string(&entry.created_at)
+    )?;
+    Ok(())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub(super) fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::GameResult;
+
+    fn seeded() -> Repository {
+        let repo = Repository::in_memory(10);
+        for (fp, name, score) in [("a", "alice", 120), ("b", "bob, jr", 300)] {
+            let id = repo.store.create_user(fp).unwrap();
+            repo.store.update_username(id, name).unwrap();
+            let result = GameResult {
+                score,
+                ..Default::default()
+            };
+            repo.store.save_game(id, &result).unwrap();
+        }
+        repo
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+    }
+
+    #[test]
+    fn test_export_empty_board() {
+        let repo = Repository::in_memory(10);
+        let mut out = Vec::new();
+        let rows = repo
+            .export_scores(&mut out, ExportFormat::Json, RankingPeriod::AllTime)
+            .unwrap();
+        assert_eq!(rows, 0);
+        assert_eq!(String::from_utf8(out).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_export_seeded_board_as_csv() {
+        let mut out = Vec::new();
+        let rows = seeded()
+            .export_scores(&mut out, ExportFormat::Csv, RankingPeriod::AllTime)
+            .unwrap();
+        assert_eq!(rows, 2);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "rank,name,score,created_at");
+        assert!(lines[1].starts_with("1,\"bob, jr\",300,"), "{}", lines[1]);
+        assert!(lines[2].starts_with("2,alice,120,"), "{}", lines[2]);
+    }
+
+    #[test]
+    fn test_export_seeded_board_as_json() {
+        let mut out = Vec::new();
+        let rows = seeded()
+            .export_scores(&mut out, ExportFormat::Json, RankingPeriod::AllTime)
+            .unwrap();
+        assert_eq!(rows, 2);
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!((lines[0], lines[3]), ("[", "]"));
+        assert!(
+            lines[1].starts_with("  {\"rank\":1,\"name\":\"bob, jr\",\"score\":300,"),
+            "{}",
+            lines[1]
+        );
+        assert!(lines[1].ends_with("},"), "{}", lines[1]);
+        assert!(
+            lines[2].starts_with("  {\"rank\":2,\"name\":\"alice\",\"score\":120,"),
+            "{}",
+            lines[2]
+        );
+    }
+}
@@ -0,0 +1,1132 @@
+use super::{
+    ACTIVITY_HISTORY_DAYS, ActivityDay, AuditEntry, BEST_ACCURACY_MIN_SHOTS, CheckRecord,
+    ClusterEvent, ClusterRecord, Distribution, GameRecord, GameResult, GameStats, HeadToHead,
+    RankingPeriod, SCORE_HISTORY_LEN, ScoreEntry, ScoreStore, Season, ShadowedUser, Submission,
+    Trust, UserContext, UserRank, UsernameTaken, Verdict,
+};
+use crate::anticheat::{CheckEvent, Strictness};
+use crate::domain::{GameMode, WeeklyModifier};
+use crate::preferences::Preferences;
+use crate::replay::Replay;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Keeps everything in process memory and forgets it on exit. Meant for
+/// demo deployments, tests and read-only filesystems.
+#[derive(Default)]
+pub struct MemoryStore {
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    next_id: i64,
+    users: BTreeMap<i64, User>,
+    /// Keyed by (user id, `%Y-%m-%d`).
+    daily_challenge: HashMap<(i64, String), Board>,
+    weekly_modifiers: HashMap<String, WeeklyModifier>,
+    motd: Option<String>,
+    banned_keys: HashSet<String>,
+    /// Hashes of uploaded replays.
+    uploads: HashSet<[u8; 32]>,
+    /// (players, winner) of finished duels.
+    duels: Vec<([i64; 2], Option<i64>)>,
+    /// With the frozen board once archived.
+    seasons: Vec<(Season, Option<Vec<ScoreEntry>>)>,
+    /// Compressed replays keyed by game id.
+    replays: HashMap<i64, Vec<u8>>,
+    /// Oldest first; names are resolved when read.
+    audit_log: Vec<AuditEntry>,
+    /// Oldest first.
+    check_events: Vec<CheckRecord>,
+    /// Lease name -> expiry.
+    leases: HashMap<String, DateTime<Utc>>,
+    /// Oldest first, with when they were published.
+    cluster_events: Vec<(ClusterRecord, DateTime<Utc>)>,
+    /// Code hash -> (user id, expiry).
+    link_codes: HashMap<String, (i64, DateTime<Utc>)>,
+    /// (fingerprint, when) of wrong link codes, oldest first.
+    link_failures: Vec<(String, DateTime<Utc>)>,
+}
+
+struct User {
+    fingerprint: String,
+    /// Fingerprints added through device linking.
+    linked_keys: Vec<String>,
+    recovery_code_hash: Option<String>,
+    created_at: DateTime<Utc>,
+    name: Option<String>,
+    stats: Stats,
+    activity: BTreeMap<String, u32>,
+    score_history: VecDeque<u32>,
+    best_replay: Option<(u32, Arc<Replay>)>,
+    preferences: Option<Preferences>,
+    /// Oldest first.
+    games: Vec<GameRecord>,
+    trust: Trust,
+}
+
+/// Best score on one board and when it was set.
+#[derive(Clone)]
+struct Board {
+    score: u32,
+    at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Stats {
+    all_time: Option<Board>,
+    /// Keyed by `%Y-%m-%d`, reset when the day changes.
+    daily: Option<(String, Board)>,
+    /// Keyed by `%Y-%W`, reset when the week changes.
+    weekly: Option<(String, Board)>,
+    total_hits: u32,
+    total_misses: u32,
+    sessions: u32,
+    best_combo: u32,
+    total_reaction_ms: u64,
+    reaction_samples: u32,
+    best_accuracy_bp: u32,
+    best_reaction_ms: Option<u64>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+fn this_week() -> String {
+    Utc::now().format("%Y-%W").to_string()
+}
+
+fn raise(board: &mut Option<Board>, score: u32, now: DateTime<Utc>) {
+    match board {
+        Some(b) if b.score >= score => {}
+        _ => *board = Some(Board { score, at: now }),
+    }
+}
+
+fn raise_keyed(board: &mut Option<(String, Board)>, key: String, score: u32, now: DateTime<Utc>) {
+    match board {
+        Some((k, b)) if *k == key => {
+            if score > b.score {
+                *b = Board { score, at: now };
+            }
+        }
+        _ => *board = Some((key, Board { score, at: now })),
+    }
+}
+
+impl User {
+    fn has_key(&self, fingerprint: &str) -> bool {
+        self.fingerprint == fingerprint || self.linked_keys.iter().any(|k| k == fingerprint)
+    }
+
+    fn record_practice(&mut self, result: &GameResult) {
+        let stats = &mut self.stats;
+        stats.total_hits += result.hits;
+        stats.total_misses += result.misses;
+        stats.sessions += 1;
+        stats.best_combo = stats.best_combo.max(result.best_combo);
+        stats.total_reaction_ms += result.reaction_total_ms;
+        stats.reaction_samples += result.reaction_samples;
+        stats.best_accuracy_bp = stats
+            .best_accuracy_bp
+            .max(result.accuracy_bp().unwrap_or(0));
+        stats.best_reaction_ms = match (stats.best_reaction_ms, result.average_reaction_ms()) {
+            (Some(best), Some(ms)) => Some(best.min(ms)),
+            (best, ms) => best.or(ms),
+        };
+        *self.activity.entry(today()).or_default() += 1;
+    }
+
+    fn push_score_history(&mut self, score: u32) {
+        self.score_history.push_back(score);
+        while self.score_history.len() > SCORE_HISTORY_LEN {
+            self.score_history.pop_front();
+        }
+    }
+
+    /// This user's entry on `period`, if it counts for the current day/week.
+    fn board(&self, period: RankingPeriod) -> Option<&Board> {
+        let board = match period {
+            RankingPeriod::Daily => self.stats.daily.as_ref().filter(|(k, _)| *k == today()),
+            RankingPeriod::Weekly => self
+                .stats
+                .weekly
+                .as_ref()
+                .filter(|(k, _)| *k == this_week()),
+            RankingPeriod::AllTime | RankingPeriod::DailyChallenge => {
+                return self.stats.all_time.as_ref().filter(|b| b.score > 0);
+            }
+        };
+        board.map(|(_, b)| b).filter(|b| b.score > 0)
+    }
+}
+
+/// When a recorded game was played, to the minute.
+fn played_at(game: &GameRecord) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(&game.played_at, "%Y-%m-%d %H:%M")
+        .ok()
+        .map(|at| at.and_utc())
+}
+
+impl State {
+    /// (user id, game) for `game_id`.
+    fn game(&self, game_id: i64) -> Option<(i64, GameRecord)> {
+        self.users.iter().find_map(|(id, u)| {
+            let game = u.games.iter().find(|g| g.id == game_id)?;
+            Some((*id, game.clone()))
+        })
+    }
+
+    /// Whether the latest audit verdict on `game_id` put it on the boards.
+    fn is_ranked(&self, game_id: i64) -> bool {
+        self.audit_log
+            .iter()
+            .rev()
+            .find(|e| e.submission.game_id == Some(game_id))
+            .is_some_and(|e| matches!(e.submission.verdict, Verdict::Accepted | Verdict::Flagged))
+    }
+
+    fn user_mut(&mut self, user_id: i64) -> Result<&mut User> {
+        self.users
+            .get_mut(&user_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown user {user_id}"))
+    }
+
+    /// (user id, board) pairs for `period`, best first. Shadowed users are
+    /// left out, except `viewer`.
+    fn leaderboard(&self, period: RankingPeriod, viewer: Option<i64>) -> Vec<(i64, Board)> {
+        let mut rows: Vec<(i64, Board)> = if let RankingPeriod::DailyChallenge = period {
+            let date = today();
+            self.daily_challenge
+                .iter()
+                .filter(|((_, d), b)| *d == date && b.score > 0)
+                .map(|((id, _), b)| (*id, b.clone()))
+                .collect()
+        } else {
+            self.users
+                .iter()
+                .filter_map(|(id, u)| u.board(period).map(|b| (*id, b.clone())))
+                .collect()
+        };
+        rows.retain(|(id, _)| {
+            viewer == Some(*id) || self.users.get(id).is_some_and(|u| !u.trust.shadowed)
+        });
+        rows.sort_by_key(|(_, board)| std::cmp::Reverse(board.score));
+        rows
+    }
+}
+
+impl ScoreStore for MemoryStore {
+    fn find_user(&self, fingerprint: &str) -> Result<Option<UserContext>> {
+        let state = self.state.borrow();
+        let Some((&id, user)) = state.users.iter().find(|(_, u)| u.has_key(fingerprint)) else {
+            return Ok(None);
+        };
+
+        let since = (Utc::now().date_naive() - chrono::Days::new(ACTIVITY_HISTORY_DAYS as u64))
+            .format("%Y-%m-%d")
+            .to_string();
+        let stats = &user.stats;
+        Ok(Some(UserContext {
+            id,
+            fingerprint: fingerprint.to_string(),
+            name: user.name.clone(),
+            high_score: stats.all_time.as_ref().map_or(0, |b| b.score),
+            total_hits: stats.total_hits,
+            total_misses: stats.total_misses,
+            sessions: stats.sessions,
+            best_combo: stats.best_combo,
+            user_activity: user
+                .activity
+                .iter()
+                .filter(|(date, _)| **date > since)
+                .map(|(date, &count)| ActivityDay {
+                    date: date.clone(),
+                    count,
+                })
+                .collect(),
+            best_replay: user.best_replay.as_ref().map(|(_, r)| Arc::clone(r)),
+            preferences: user.preferences.unwrap_or_default(),
+            reaction_total_ms: stats.total_reaction_ms,
+            reaction_samples: stats.reaction_samples,
+            score_history: user.score_history.iter().copied().collect(),
+            best_accuracy_bp: stats.best_accuracy_bp,
+            best_reaction_ms: stats.best_reaction_ms,
+            strictness: Strictness::default(),
+        }))
+    }
+
+    fn count_users(&self) -> Result<i64> {
+        Ok(self.state.borrow().users.len() as i64)
+    }
+
+    fn prune_inactive_users(&self, inactive_days: u32) -> Result<usize> {
+        let mut state = self.state.borrow_mut();
+        let cutoff = Utc::now() - chrono::Duration::days(inactive_days.into());
+        let cutoff_day = cutoff.format("%Y-%m-%d").to_string();
+        let victims: Vec<i64> = state
+            .users
+            .iter()
+            .filter(|(_, u)| {
+                u.stats.all_time.as_ref().is_none_or(|b| b.score == 0)
+                    && u.created_at < cutoff
+                    && u.activity.range(cutoff_day.clone()..).next().is_none()
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for id in &victims {
+            state.users.remove(id);
+        }
+        state
+            .daily_challenge
+            .retain(|(uid, _), _| !victims.contains(uid));
+        Ok(victims.len())
+    }
+
+    fn create_user(&self, fingerprint: &str) -> Result<i64> {
+        let mut state = self.state.borrow_mut();
+        if state.users.values().any(|u| u.has_key(fingerprint)) {
+            anyhow::bail!("Fingerprint already registered");
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        state.users.insert(
+            id,
+            User {
+                fingerprint: fingerprint.to_string(),
+                linked_keys: Vec::new(),
+                recovery_code_hash: None,
+                created_at: Utc::now(),
+                name: None,
+                stats: Stats::default(),
+                activity: BTreeMap::new(),
+                score_history: VecDeque::new(),
+                best_replay: None,
+                preferences: None,
+                games: Vec::new(),
+                trust: Trust::default(),
+            },
+        );
+        Ok(id)
+    }
+
+    fn update_username(&self, user_id: i64, name: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let taken = state
+            .users
+            .iter()
+            .any(|(&id, u)| id != user_id && u.name.as_deref() == Some(name));
+        if taken {
+            return Err(UsernameTaken.into());
+        }
+        state.user_mut(user_id)?.name = Some(name.to_string());
+        Ok(())
+    }
+
+    fn is_username_taken(&self, name: &str) -> Result<bool> {
+        let state = self.state.borrow();
+        Ok(state
+            .users
+            .values()
+            .any(|u| u.name.as_deref() == Some(name)))
+    }
+
+    fn add_user_key(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        if state.users.values().any(|u| u.has_key(fingerprint)) {
+            anyhow::bail!("Fingerprint already registered");
+        }
+        state
+            .user_mut(user_id)?
+            .linked_keys
+            .push(fingerprint.to_string());
+        Ok(())
+    }
+
+    fn replace_user_keys(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let user = state.user_mut(user_id)?;
+        user.fingerprint = fingerprint.to_string();
+        user.linked_keys.clear();
+        Ok(())
+    }
+
+    fn set_recovery_code(&self, user_id: i64, code_hash: Option<&str>) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.user_mut(user_id)?.recovery_code_hash = code_hash.map(str::to_string);
+        Ok(())
+    }
+
+    fn find_recovery_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        Ok(self
+            .state
+            .borrow()
+            .users
+            .iter()
+            .find(|(_, u)| u.recovery_code_hash.as_deref() == Some(code_hash))
+            .map(|(&id, _)| id))
+    }
+
+    fn put_link_code(&self, user_id: i64, code_hash: &str, ttl: Duration) -> Result<bool> {
+        let now = Utc::now();
+        let codes = &mut self.state.borrow_mut().link_codes;
+        codes.retain(|_, (id, expires)| *id != user_id && *expires > now);
+        if codes.contains_key(code_hash) {
+            return Ok(false);
+        }
+        let expires = now + chrono::Duration::seconds(ttl.as_secs() as i64);
+        codes.insert(code_hash.to_string(), (user_id, expires));
+        Ok(true)
+    }
+
+    fn take_link_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        let code = self.state.borrow_mut().link_codes.remove(code_hash);
+        Ok(code
+            .filter(|(_, expires)| *expires > Utc::now())
+            .map(|(id, _)| id))
+    }
+
+    fn link_failures(&self, fingerprint: &str, window: Duration) -> Result<u32> {
+        let since = Utc::now() - chrono::Duration::seconds(window.as_secs() as i64);
+        Ok(self
+            .state
+            .borrow()
+            .link_failures
+            .iter()
+            .filter(|(fp, at)| fp == fingerprint && *at > since)
+            .count() as u32)
+    }
+
+    fn add_link_failure(&self, fingerprint: &str, window: Duration) -> Result<()> {
+        let now = Utc::now();
+        let since = now - chrono::Duration::seconds(window.as_secs() as i64);
+        let failures = &mut self.state.borrow_mut().link_failures;
+        failures.retain(|(_, at)| *at > since);
+        failures.push((fingerprint.to_string(), now));
+        Ok(())
+    }
+
+    fn delete_user(&self, user_id: i64) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        state.users.remove(&user_id);
+        state.daily_challenge.retain(|(id, _), _| *id != user_id);
+        state
+            .duels
+            .retain(|(players, _)| !players.contains(&user_id));
+        Ok(())
+    }
+
+    fn save_game(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let user = state.user_mut(user_id)?;
+        let now = Utc::now();
+        raise(&mut user.stats.all_time, result.score, now);
+        raise_keyed(&mut user.stats.daily, today(), result.score, now);
+        raise_keyed(&mut user.stats.weekly, this_week(), result.score, now);
+        user.record_practice(result);
+        user.push_score_history(result.score);
+        Ok(())
+    }
+
+    fn save_daily_challenge(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let user = state.user_mut(user_id)?;
+        user.record_practice(result);
+        user.push_score_history(result.score);
+
+        let mut board = state.daily_challenge.remove(&(user_id, today()));
+        raise(&mut board, result.score, Utc::now());
+        if let Some(board) = board {
+            state.daily_challenge.insert((user_id, today()), board);
+        }
+        Ok(())
+    }
+
+    fn save_practice(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .user_mut(user_id)?
+            .record_practice(result);
+        Ok(())
+    }
+
+    fn save_best_replay(&self, user_id: i64, score: u32, replay: &Replay) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let user = state.user_mut(user_id)?;
+        if user
+            .best_replay
+            .as_ref()
+            .is_none_or(|(best, _)| score > *best)
+        {
+            user.best_replay = Some((score, Arc::new(replay.clone())));
+        }
+        Ok(())
+    }
+
+    fn save_preferences(&self, user_id: i64, prefs: &Preferences) -> Result<()> {
+        self.state.borrow_mut().user_mut(user_id)?.preferences = Some(*prefs);
+        Ok(())
+    }
+
+    fn record_game(&self, user_id: i64, mode: GameMode, result: &GameResult) -> Result<i64> {
+        let mut state = self.state.borrow_mut();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.user_mut(user_id)?.games.push(GameRecord {
+            id,
+            mode,
+            score: result.score,
+            hits: result.hits,
+            misses: result.misses,
+            duration_ms: result.duration_ms,
+            reaction_ms: result.average_reaction_ms(),
+            played_at: Utc::now().format("%Y-%m-%d %H:%M").to_string(),
+        });
+        Ok(id)
+    }
+
+    fn get_games(&self, user_id: i64, limit: u32) -> Result<Vec<GameRecord>> {
+        let state = self.state.borrow();
+        let Some(user) = state.users.get(&user_id) else {
+            return Ok(Vec::new());
+        };
+        Ok(user
+            .games
+            .iter()
+            .rev()
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn game_stats(&self, mode: GameMode, user_id: Option<i64>) -> Result<GameStats> {
+        let state = self.state.borrow();
+        let games: Vec<&GameRecord> = state
+            .users
+            .iter()
+            .filter(|(id, _)| user_id.is_none_or(|user_id| **id == user_id))
+            .flat_map(|(_, u)| &u.games)
+            .filter(|g| g.mode == mode)
+            .collect();
+        Ok(GameStats {
+            score: Distribution::from_values(games.iter().map(|g| g.score as f64)),
+            accuracy_bp: Distribution::from_values(games.iter().filter_map(|g| {
+                let shots = g.hits + g.misses;
+                (shots >= BEST_ACCURACY_MIN_SHOTS).then(|| g.hits as f64 * 10_000.0 / shots as f64)
+            })),
+            reaction_ms: Distribution::from_values(
+                games
+                    .iter()
+                    .filter_map(|g| g.reaction_ms.map(|ms| ms as f64)),
+            ),
+        })
+    }
+
+    fn save_replay(&self, game_id: i64, data: &[u8]) -> Result<()> {
+        self.state
+            .borrow_mut()
+            .replays
+            .insert(game_id, data.to_vec());
+        Ok(())
+    }
+
+    fn get_replay(&self, game_id: i64) -> Result<Option<Vec<u8>>> {
+        Ok(self.state.borrow().replays.get(&game_id).cloned())
+    }
+
+    fn prune_replays(&self, min_score: u32) -> Result<usize> {
+        let mut state = self.state.borrow_mut();
+        let kept: HashSet<i64> = state
+            .users
+            .values()
+            .flat_map(|u| &u.games)
+            .filter(|g| g.score >= min_score)
+            .map(|g| g.id)
+            .collect();
+        let before = state.replays.len();
+        state.replays.retain(|id, _| kept.contains(id));
+        Ok(before - state.replays.len())
+    }
+
+    fn append_audit(&self, user_id: i64, submission: &Submission) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let id = state.audit_log.len() as i64 + 1;
+        state.audit_log.push(AuditEntry {
+            id,
+            user_id,
+            name: None,
+            submission: submission.clone(),
+            created_at: Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+        Ok(())
+    }
+
+    fn get_audit_log(&self, user_id: Option<i64>, limit: u32) -> Result<Vec<AuditEntry>> {
+        let state = self.state.borrow();
+        Ok(state
+            .audit_log
+            .iter()
+            .rev()
+            .filter(|e| user_id.is_none_or(|id| e.user_id == id))
+            .take(limit as usize)
+            .map(|e| AuditEntry {
+                name: state.users.get(&e.user_id).and_then(|u| u.name.clone()),
+                ..e.clone()
+            })
+            .collect())
+    }
+
+    fn append_check_events(
+        &self,
+        user_id: i64,
+        game_id: Option<i64>,
+        events: &[CheckEvent],
+    ) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let created_at = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        for event in events {
+            let id = state.check_events.len() as i64 + 1;
+            state.check_events.push(CheckRecord {
+                id,
+                user_id,
+                game_id,
+                check: event.check.to_string(),
+                value: event.measurement.map(|m| m.value),
+                limit: event.measurement.map(|m| m.limit),
+                created_at: created_at.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    fn get_check_events(&self, check: Option<&str>, limit: u32) -> Result<Vec<CheckRecord>> {
+        let state = self.state.borrow();
+        Ok(state
+            .check_events
+            .iter()
+            .rev()
+            .filter(|r| check.is_none_or(|c| r.check == c))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn get_review_queue(&self, limit: u32, min_flagged_clicks: u32) -> Result<Vec<AuditEntry>> {
+        let state = self.state.borrow();
+        let log = &state.audit_log;
+        Ok(log
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| {
+                let s = &e.submission;
+                (s.verdict == Verdict::Held
+                    || (s.verdict == Verdict::Flagged && s.flagged_clicks >= min_flagged_clicks))
+                    && e.submission.game_id.is_some_and(|game_id| {
+                        !log[i + 1..]
+                            .iter()
+                            .any(|r| r.submission.game_id == Some(game_id))
+                    })
+            })
+            .take(limit as usize)
+            .map(|(_, e)| AuditEntry {
+                name: state.users.get(&e.user_id).and_then(|u| u.name.clone()),
+                ..e.clone()
+            })
+            .collect())
+    }
+
+    fn get_game(&self, game_id: i64) -> Result<Option<GameRecord>> {
+        Ok(self.state.borrow().game(game_id).map(|(_, game)| game))
+    }
+
+    fn publish_game(&self, game_id: i64) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let (user_id, game) = state
+            .game(game_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown game {game_id}"))?;
+        let at = played_at(&game).unwrap_or_else(Utc::now);
+        let day = at.format("%Y-%m-%d").to_string();
+        match game.mode {
+            // daily and weekly entries only if the run still belongs to them
+            GameMode::Standard => {
+                let stats = &mut state.user_mut(user_id)?.stats;
+                raise(&mut stats.all_time, game.score, at);
+                if day == today() {
+                    raise_keyed(&mut stats.daily, day, game.score, at);
+                }
+                let week = at.format("%Y-%W").to_string();
+                if week == this_week() {
+                    raise_keyed(&mut stats.weekly, week, game.score, at);
+                }
+            }
+            GameMode::DailyChallenge => {
+                let mut board = state.daily_challenge.remove(&(user_id, day.clone()));
+                raise(&mut board, game.score, at);
+                if let Some(board) = board {
+                    state.daily_challenge.insert((user_id, day), board);
+                }
+            }
+            GameMode::Practice => {}
+        }
+        Ok(())
+    }
+
+    fn retract_game(&self, game_id: i64) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let flagged = state.audit_log.iter().any(|e| {
+            e.submission.game_id == Some(game_id) && e.submission.verdict == Verdict::Flagged
+        });
+        anyhow::ensure!(flagged, "Game {game_id} was never flagged");
+        let Some((user_id, game)) = state.game(game_id) else {
+            return Ok(());
+        };
+        let others: Vec<Board> = state.users[&user_id]
+            .games
+            .iter()
+            .filter(|g| g.mode == game.mode && g.id != game_id && state.is_ranked(g.id))
+            .filter_map(|g| {
+                Some(Board {
+                    score: g.score,
+                    at: played_at(g)?,
+                })
+            })
+            .collect();
+        let best = |key: &str, format: &str| {
+            others
+                .iter()
+                .filter(|b| b.at.format(format).to_string() == key)
+                .max_by_key(|b| b.score)
+                .cloned()
+        };
+        let set_by_game = |board: Option<&Board>| board.is_some_and(|b| b.score == game.score);
+
+        match game.mode {
+            GameMode::Standard => {
+                let (today, week) = (today(), this_week());
+                let all_time = others.iter().max_by_key(|b| b.score).cloned();
+                let daily = best(&today, "%Y-%m-%d");
+                let weekly = best(&week, "%Y-%W");
+                let stats = &mut state.user_mut(user_id)?.stats;
+                if set_by_game(stats.all_time.as_ref()) {
+                    stats.all_time = all_time;
+                }
+                if set_by_game(
+                    stats
+                        .daily
+                        .as_ref()
+                        .filter(|(k, _)| *k == today)
+                        .map(|(_, b)| b),
+                ) {
+                    stats.daily = daily.map(|b| (today, b));
+                }
+                if set_by_game(
+                    stats
+                        .weekly
+                        .as_ref()
+                        .filter(|(k, _)| *k == week)
+                        .map(|(_, b)| b),
+                ) {
+                    stats.weekly = weekly.map(|b| (week, b));
+                }
+            }
+            GameMode::DailyChallenge => {
+                let day = played_at(&game)
+                    .unwrap_or_else(Utc::now)
+                    .format("%Y-%m-%d")
+                    .to_string();
+                let key = (user_id, day.clone());
+                if set_by_game(state.daily_challenge.get(&key)) {
+                    match best(&day, "%Y-%m-%d") {
+                        Some(board) => state.daily_challenge.insert(key, board),
+                        None => state.daily_challenge.remove(&key),
+                    };
+                }
+            }
+            GameMode::Practice => {}
+        }
+        Ok(())
+    }
+
+    fn get_trust(&self, user_id: i64) -> Result<Option<Trust>> {
+        Ok(self.state.borrow().users.get(&user_id).map(|u| u.trust))
+    }
+
+    fn set_trust(&self, user_id: i64, trust: Trust) -> Result<()> {
+        self.state.borrow_mut().user_mut(user_id)?.trust = trust;
+        Ok(())
+    }
+
+    fn get_shadowed_users(&self) -> Result<Vec<ShadowedUser>> {
+        let state = self.state.borrow();
+        let mut users: Vec<ShadowedUser> = state
+            .users
+            .iter()
+            .filter(|(_, u)| u.trust.shadowed)
+            .map(|(id, u)| ShadowedUser {
+                user_id: *id,
+                name: u.name.clone(),
+                trust: u.trust.score,
+            })
+            .collect();
+        users.sort_by_key(|u| u.trust);
+        Ok(users)
+    }
+
+    fn get_top_scores(
+        &self,
+        period: RankingPeriod,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        let state = self.state.borrow();
+        let date_format = match period {
+            RankingPeriod::AllTime => "%Y-%m-%d",
+            _ => "%m-%d %H:%M",
+        };
+        Ok(state
+            .leaderboard(period, None)
+            .into_iter()
+            .filter_map(|(id, board)| {
+                let user = state.users.get(&id)?;
+                Some(ScoreEntry {
+                    name: user.name.clone()?,
+                    score: board.score,
+                    created_at: board.at.format(date_format).to_string(),
+                    region: user.preferences.and_then(|p| p.region),
+                })
+            })
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    fn get_user_rank(&self, user_id: i64, period: RankingPeriod) -> Result<Option<UserRank>> {
+        let board = self.state.borrow().leaderboard(period, Some(user_id));
+        let Some((_, own)) = board.iter().find(|(id, _)| *id == user_id) else {
+            return Ok(None);
+        };
+        let ahead = board.iter().filter(|(_, b)| b.score > own.score).count();
+        Ok(Some(UserRank {
+            rank: ahead as u32 + 1,
+            score: own.score,
+        }))
+    }
+
+    fn count_scores(&self, period: RankingPeriod, score: u32) -> Result<(u32, u32)> {
+        let board = self.state.borrow().leaderboard(period, None);
+        let better = board.iter().filter(|(_, b)| b.score > score).count();
+        Ok((better as u32, board.len() as u32))
+    }
+
+    fn get_weekly_modifier(&self) -> Result<Option<WeeklyModifier>> {
+        Ok(self
+            .state
+            .borrow()
+            .weekly_modifiers
+            .get(&this_week())
+            .copied())
+    }
+
+    fn set_weekly_modifier(&self, week: &str, modifier: Option<WeeklyModifier>) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        match modifier {
+            Some(m) => state.weekly_modifiers.insert(week.to_string(), m),
+            None => state.weekly_modifiers.remove(week),
+        };
+        Ok(())
+    }
+
+    fn create_season(&self, name: &str, starts_on: &str, ends_on: &str) -> Result<i64> {
+        let mut state = self.state.borrow_mut();
+        let id = state.seasons.len() as i64 + 1;
+        state.seasons.push((
+            Season {
+                id,
+                name: name.to_string(),
+                starts_on: starts_on.to_string(),
+                ends_on: ends_on.to_string(),
+            },
+            None,
+        ));
+        Ok(id)
+    }
+
+    fn archive_due_seasons(&self) -> Result<usize> {
+        let today = today();
+        // (end date, index), so the earliest season gets the board
+        let mut due: Vec<(String, usize)> = self
+            .state
+            .borrow()
+            .seasons
+            .iter()
+            .enumerate()
+            .filter(|(_, (season, board))| {
+                board.is_none() && season.starts_on <= today && season.ends_on <= today
+            })
+            .map(|(i, (season, _))| (season.ends_on.clone(), i))
+            .collect();
+        due.sort();
+
+        for &(_, i) in &due {
+            let board = self.get_top_scores(RankingPeriod::AllTime, u32::MAX, 0)?;
+            let mut state = self.state.borrow_mut();
+            state.seasons[i].1 = Some(board);
+            for user in state.users.values_mut() {
+                user.stats.all_time = None;
+            }
+        }
+        Ok(due.len())
+    }
+
+    fn get_open_seasons(&self) -> Result<Vec<Season>> {
+        let state = self.state.borrow();
+        let mut seasons: Vec<Season> = state
+            .seasons
+            .iter()
+            .filter(|(_, board)| board.is_none())
+            .map(|(season, _)| season.clone())
+            .collect();
+        seasons.sort_by(|a, b| a.starts_on.cmp(&b.starts_on));
+        Ok(seasons)
+    }
+
+    fn get_past_seasons(&self) -> Result<Vec<Season>> {
+        let state = self.state.borrow();
+        let mut seasons: Vec<Season> = state
+            .seasons
+            .iter()
+            .filter(|(_, board)| board.is_some())
+            .map(|(season, _)| season.clone())
+            .collect();
+        seasons.sort_by(|a, b| b.ends_on.cmp(&a.ends_on));
+        Ok(seasons)
+    }
+
+    fn get_season_scores(
+        &self,
+        season_id: i64,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        let state = self.state.borrow();
+        let board = state
+            .seasons
+            .iter()
+            .find(|(season, _)| season.id == season_id)
+            .and_then(|(_, board)| board.as_ref());
+        Ok(board
+            .map(|entries| {
+                entries
+                    .iter()
+                    .skip(offset as usize)
+                    .take(limit as usize)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn get_motd(&self) -> Result<Option<String>> {
+        Ok(self.state.borrow().motd.clone())
+    }
+
+    fn set_motd(&self, message: Option<&str>) -> Result<()> {
+        self.state.borrow_mut().motd = message
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(str::to_string);
+        Ok(())
+    }
+
+    fn get_banned_keys(&self) -> Result<Vec<String>> {
+        Ok(self.state.borrow().banned_keys.iter().cloned().collect())
+    }
+
+    fn set_key_banned(&self, fingerprint: &str, banned: bool) -> Result<()> {
+        let keys = &mut self.state.borrow_mut().banned_keys;
+        if banned {
+            keys.insert(fingerprint.to_string());
+        } else {
+            keys.remove(fingerprint);
+        }
+        Ok(())
+    }
+
+    fn record_upload(&self, hash: &[u8; 32]) -> Result<bool> {
+        Ok(self.state.borrow_mut().uploads.insert(*hash))
+    }
+
+    fn record_duel(&self, players: [i64; 2], _scores: [u32; 2], winner: Option<i64>) -> Result<()> {
+        self.state.borrow_mut().duels.push((players, winner));
+        Ok(())
+    }
+
+    fn get_head_to_head(&self, user_id: i64, opponent_id: i64) -> Result<HeadToHead> {
+        let mut record = HeadToHead::default();
+        let state = self.state.borrow();
+        let duels = state.duels.iter().filter(|(players, _)| {
+            *players == [user_id, opponent_id] || *players == [opponent_id, user_id]
+        });
+        for (_, winner) in duels {
+            match winner {
+                Some(id) if *id == user_id => record.wins += 1,
+                Some(_) => record.losses += 1,
+                None => record.draws += 1,
+            }
+        }
+        Ok(record)
+    }
+
+    fn try_lease(&self, name: &str, _holder: &str, ttl: Duration) -> Result<bool> {
+        let now = Utc::now();
+        let leases = &mut self.state.borrow_mut().leases;
+        if leases.get(name).is_some_and(|expires| *expires > now) {
+            return Ok(false);
+        }
+        leases.insert(
+            name.to_string(),
+            now + chrono::Duration::seconds(ttl.as_secs() as i64),
+        );
+        Ok(true)
+    }
+
+    fn publish_event(&self, origin: &str, event: &ClusterEvent) -> Result<()> {
+        let events = &mut self.state.borrow_mut().cluster_events;
+        let id = events.last().map_or(1, |(record, _)| record.id + 1);
+        let record = ClusterRecord {
+            id,
+            origin: origin.to_string(),
+            event: event.clone(),
+        };
+        events.push((record, Utc::now()));
+        Ok(())
+    }
+
+    fn events_after(&self, after: i64) -> Result<Vec<ClusterRecord>> {
+        let state = self.state.borrow();
+        Ok(state
+            .cluster_events
+            .iter()
+            .filter(|(record, _)| record.id > after)
+            .map(|(record, _)| record.clone())
+            .collect())
+    }
+
+    fn prune_events(&self, max_age: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - chrono::Duration::seconds(max_age.as_secs() as i64);
+        let events = &mut self.state.borrow_mut().cluster_events;
+        let before = events.len();
+        events.retain(|(_, at)| *at >= cutoff);
+        Ok(before - events.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(score: u32) -> GameResult {
+        GameResult {
+            score,
+            hits: 3,
+            misses: 1,
+            ..Default::default()
+        }
+    }
+
+    fn named_user(store: &MemoryStore, name: &str) -> i64 {
+        let id = store.create_user(name).unwrap();
+        store.update_username(id, name).unwrap();
+        id
+    }
+
+    #[test]
+    fn test_leaderboards_and_ranks() {
+        let store = MemoryStore::new();
+        let alice = named_user(&store, "alice");
+        let bob = named_user(&store, "bob");
+        store.save_game(alice, &result(500)).unwrap();
+        store.save_game(alice, &result(200)).unwrap();
+        store.save_game(bob, &result(800)).unwrap();
+        store.save_practice(bob, &result(9000)).unwrap();
+
+        let top = store.get_top_scores(RankingPeriod::AllTime, 10, 0).unwrap();
+        let names: Vec<_> = top.iter().map(|e| (e.name.as_str(), e.score)).collect();
+        assert_eq!(names, vec![("bob", 800), ("alice", 500)]);
+
+        let rank = store.get_user_rank(alice, RankingPeriod::Daily).unwrap();
+        assert_eq!(
+            rank,
+            Some(UserRank {
+                rank: 2,
+                score: 500
+            })
+        );
+        assert_eq!(
+            store
+                .get_user_rank(alice, RankingPeriod::DailyChallenge)
+                .unwrap(),
+            None
+        );
+
+        let ctx = store.find_user("bob").unwrap().unwrap();
+        assert_eq!(ctx.high_score, 800);
+        assert_eq!(ctx.sessions, 2);
+        assert_eq!(ctx.score_history, vec![800]);
+    }
+
+    #[test]
+    fn test_season_rollover_archives_and_resets() {
+        let store = MemoryStore::new();
+        let alice = named_user(&store, "alice");
+        store.save_game(alice, &result(700)).unwrap();
+        let season = store
+            .create_season("Season 1", "2000-01-01", &today())
+            .unwrap();
+
+        assert_eq!(store.archive_due_seasons().unwrap(), 1);
+        assert_eq!(store.archive_due_seasons().unwrap(), 0);
+
+        assert!(
+            store
+                .get_top_scores(RankingPeriod::AllTime, 10, 0)
+                .unwrap()
+                .is_empty()
+        );
+        let archived = store.get_season_scores(season, 10, 0).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(
+            (archived[0].name.as_str(), archived[0].score),
+            ("alice", 700)
+        );
+        assert_eq!(store.get_past_seasons().unwrap()[0].id, season);
+    }
+
+    #[test]
+    fn test_username_uniqueness_and_eviction() {
+        let store = MemoryStore::new();
+        let alice = named_user(&store, "alice");
+        let idle = store.create_user("idle").unwrap();
+        store.save_game(alice, &result(100)).unwrap();
+
+        let err = store.update_username(idle, "alice").unwrap_err();
+        assert!(err.is::<UsernameTaken>());
+
+        assert_eq!(store.prune_inactive_users(30).unwrap(), 0);
+        store.state.borrow_mut().user_mut(idle).unwrap().created_at -= chrono::Duration::days(31);
+        assert_eq!(store.prune_inactive_users(30).unwrap(), 1);
+        assert_eq!(store.count_users().unwrap(), 1);
+    }
+}
@@ -0,0 +1,704 @@
+//! Numbered schema migrations for the SQLite backend.
+//!
+//! Each migration runs once, inside a transaction, and is recorded in
+//! `schema_migrations`. Append new ones to `MIGRATIONS`; never edit or
+//! reorder those already released.
+
+use super::{BEST_ACCURACY_MIN_SHOTS, MAX_TRUST};
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, params};
+
+struct Migration {
+    version: u32,
+    name: &'static str,
+    up: fn(&Connection) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial schema",
+        up: initial_schema,
+    },
+    Migration {
+        version: 2,
+        name: "import legacy leaderboard",
+        up: import_legacy_leaderboard,
+    },
+    Migration {
+        version: 3,
+        name: "games table",
+        up: games_table,
+    },
+    Migration {
+        version: 4,
+        name: "accuracy and reaction aggregates",
+        up: accuracy_aggregates,
+    },
+    Migration {
+        version: 5,
+        name: "seasons",
+        up: seasons,
+    },
+    Migration {
+        version: 6,
+        name: "linked keys",
+        up: user_keys,
+    },
+    Migration {
+        version: 7,
+        name: "recovery codes",
+        up: recovery_codes,
+    },
+    Migration {
+        version: 8,
+        name: "user regions",
+        up: user_regions,
+    },
+    Migration {
+        version: 9,
+        name: "top replays",
+        up: replays,
+    },
+    Migration {
+        version: 10,
+        name: "audit log",
+        up: audit_log,
+    },
+    Migration {
+        version: 11,
+        name: "outlier review",
+        up: outlier_review,
+    },
+    Migration {
+        version: 12,
+        name: "trust",
+        up: trust,
+    },
+    Migration {
+        version: 13,
+        name: "check events",
+        up: check_events,
+    },
+    Migration {
+        version: 14,
+        name: "strictness",
+        up: strictness,
+    },
+    Migration {
+        version: 15,
+        name: "banned keys",
+        up: banned_keys,
+    },
+    Migration {
+        version: 16,
+        name: "duels",
+        up: duels,
+    },
+    Migration {
+        version: 17,
+        name: "cluster",
+        up: cluster,
+    },
+    Migration {
+        version: 18,
+        name: "uploads",
+        up: uploads,
+    },
+    Migration {
+        version: 19,
+        name: "upload queue",
+        up: upload_queue,
+    },
+];
+
+/// Brings `conn` up to the latest schema version.
+pub fn run(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at DATETIME DEFAULT (DATETIME('now'))
+        );",
+    )?;
+
+    let current = current_version(conn)?;
+    let latest = MIGRATIONS.last().map_or(0, |m| m.version);
+    if current > latest {
+        bail!("Database schema v{current} is newer than this build supports (v{latest})");
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.unchecked_transaction()?;
+        (migration.up)(&tx).with_context(|| {
+            format!(
+                "Migration {} ({}) failed",
+                migration.version, migration.name
+            )
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            params![migration.version, migration.name],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn current_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.query_row(
+        "SELECT COALESCE(MAX(version), 0) FROM schema_migrations",
+        [],
+        |row| row.get(0),
+    )?)
+}
+
+/// Idempotent, so databases created before versioning was introduced are
+/// adopted as-is.
+fn initial_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS users (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            fingerprint TEXT UNIQUE NOT NULL,
+            username TEXT UNIQUE,
+            created_at DATETIME DEFAULT (DATETIME('now'))
+        );
+
+        CREATE TABLE IF NOT EXISTS user_stats (
+            user_id INTEGER PRIMARY KEY,
+
+            high_score INTEGER DEFAULT 0,
+            high_score_at DATETIME DEFAULT (DATETIME('now')),
+
+            daily_high_score INTEGER DEFAULT 0,
+            daily_high_score_at DATE DEFAULT (DATE('now')),
+
+            weekly_high_score INTEGER DEFAULT 0,
+            weekly_high_score_at TEXT DEFAULT (strftime('%Y-%W', 'now')),
+
+            total_hits INTEGER DEFAULT 0,
+            total_misses INTEGER DEFAULT 0,
+            sessions INTEGER DEFAULT 0,
+            best_combo INTEGER DEFAULT 0,
+            total_reaction_ms INTEGER DEFAULT 0,
+            reaction_samples INTEGER DEFAULT 0,
+
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS daily_activity (
+            user_id INTEGER,
+            date DATE DEFAULT (DATE('now')),
+            count INTEGER DEFAULT 0,
+            PRIMARY KEY (user_id, date),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS daily_challenge (
+            user_id INTEGER,
+            date DATE DEFAULT (DATE('now')),
+            score INTEGER DEFAULT 0,
+            score_at DATETIME DEFAULT (DATETIME('now')),
+            PRIMARY KEY (user_id, date),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS best_replays (
+            user_id INTEGER PRIMARY KEY,
+            score INTEGER DEFAULT 0,
+            data BLOB NOT NULL,
+            created_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS user_preferences (
+            user_id INTEGER PRIMARY KEY,
+            theme TEXT,
+            cursor_style TEXT,
+            effects INTEGER DEFAULT 1,
+            game_duration TEXT,
+            language TEXT,
+            reduced_motion INTEGER DEFAULT 0,
+            hit_feedback TEXT,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS score_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            score INTEGER NOT NULL,
+            played_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS weekly_modifiers (
+            week TEXT PRIMARY KEY,
+            modifier TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS motd (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            message TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_score_history ON score_history (user_id, id DESC);
+        CREATE INDEX IF NOT EXISTS idx_daily_challenge ON daily_challenge (date, score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_daily ON user_stats (daily_high_score_at, daily_high_score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_weekly ON user_stats (weekly_high_score_at, weekly_high_score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_high_score ON user_stats (high_score DESC);",
+    )?;
+
+    // files created before migrations existed may predate these columns
+    ensure_column(conn, "user_stats", "best_combo", "INTEGER DEFAULT 0")?;
+    ensure_column(conn, "user_stats", "total_reaction_ms", "INTEGER DEFAULT 0")?;
+    ensure_column(conn, "user_stats", "reaction_samples", "INTEGER DEFAULT 0")?;
+    ensure_column(conn, "user_preferences", "language", "TEXT")?;
+    ensure_column(
+        conn,
+        "user_preferences",
+        "reduced_motion",
+        "INTEGER DEFAULT 0",
+    )?;
+    ensure_column(conn, "user_preferences", "hit_feedback", "TEXT")?;
+    Ok(())
+}
+
+/// The original release kept one row per run in `leaderboard`. Each distinct
+/// name becomes a user (fingerprint `legacy:<name>`) with their best score
+/// and run count; the old table is kept as `legacy_leaderboard`. A name
+/// already taken gets an `(old)` suffix, numbered until it is free.
+fn import_legacy_leaderboard(conn: &Connection) -> Result<()> {
+    if !column_exists(conn, "leaderboard", "name")? || !column_exists(conn, "leaderboard", "score")?
+    {
+        return Ok(());
+    }
+    let played_at = if column_exists(conn, "leaderboard", "created_at")? {
+        "l.created_at"
+    } else {
+        "DATETIME('now')"
+    };
+
+    let names = conn
+        .prepare(&format!(
+            "SELECT l.name, MIN({played_at})
+            FROM leaderboard l
+            WHERE l.name IS NOT NULL AND l.name <> ''
+            GROUP BY l.name
+            ORDER BY l.name"
+        ))?
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+        })?
+        .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+    let taken = |username: &str| -> Result<bool> {
+        Ok(conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM users WHERE username = ?1)",
+            params![username],
+            |row| row.get(0),
+        )?)
+    };
+    for (name, first_played) in names {
+        let mut username = name.clone();
+        let mut n = 1;
+        while taken(&username)? {
+            username = match n {
+                1 => format!("{name} (old)"),
+                n => format!("{name} (old {n})"),
+            };
+            n += 1;
+        }
+        conn.execute(
+            "INSERT INTO users (fingerprint, username, created_at) VALUES (?1, ?2, ?3)",
+            params![format!("legacy:{name}"), username, first_played],
+        )
+        .with_context(|| format!("Failed to import legacy player '{name}'"))?;
+    }
+
+    conn.execute_batch(&format!(
+        "-- SQLite takes the bare column from the row holding MAX(score)
+        INSERT OR IGNORE INTO user_stats (user_id, high_score, high_score_at, sessions)
+        SELECT u.id, MAX(l.score), {played_at}, COUNT(*)
+        FROM leaderboard l
+        JOIN users u ON u.fingerprint = 'legacy:' || l.name
+        GROUP BY u.id;
+
+        ALTER TABLE leaderboard RENAME TO legacy_leaderboard;"
+    ))?;
+    Ok(())
+}
+
+fn games_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE games (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            mode TEXT NOT NULL,
+            score INTEGER NOT NULL,
+            hits INTEGER NOT NULL,
+            misses INTEGER NOT NULL,
+            duration_ms INTEGER NOT NULL,
+            played_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+
+        CREATE INDEX idx_games_user ON games (user_id, id DESC);",
+    )?;
+    Ok(())
+}
+
+/// Best accuracy can be recovered from `games`; reaction times were never
+/// stored per run, so `best_reaction_ms` starts empty.
+fn accuracy_aggregates(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "ALTER TABLE user_stats ADD COLUMN best_accuracy_bp INTEGER DEFAULT 0;
+        ALTER TABLE user_stats ADD COLUMN best_reaction_ms INTEGER;
+
+        UPDATE user_stats SET best_accuracy_bp = IFNULL((
+            SELECT MAX(g.hits * 10000 / (g.hits + g.misses))
+            FROM games g
+            WHERE g.user_id = user_stats.user_id
+                AND g.hits + g.misses >= {BEST_ACCURACY_MIN_SHOTS}
+        ), 0);"
+    ))?;
+    Ok(())
+}
+
+fn seasons(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE seasons (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            starts_on DATE NOT NULL,
+            ends_on DATE NOT NULL,
+            archived_at DATETIME
+        );
+
+        -- names are copied so the archive survives renames and deleted accounts
+        CREATE TABLE season_scores (
+            season_id INTEGER NOT NULL,
+            rank INTEGER NOT NULL,
+            user_id INTEGER,
+            username TEXT NOT NULL,
+            score INTEGER NOT NULL,
+            scored_at DATETIME,
+            PRIMARY KEY (season_id, rank),
+            FOREIGN KEY (season_id) REFERENCES seasons(id) ON DELETE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// Extra key fingerprints for a user, on top of `users.fingerprint`, and the
+/// pending codes that add them. Codes live here rather than in memory so any
+/// node sharing the database can redeem them.
+fn user_keys(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE user_keys (
+            fingerprint TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            added_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+        CREATE TABLE link_codes (
+            code_hash TEXT PRIMARY KEY,
+            user_id INTEGER NOT NULL UNIQUE,
+            expires_at INTEGER NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );
+        CREATE TABLE link_failures (
+            fingerprint TEXT NOT NULL,
+            failed_at INTEGER NOT NULL
+        );
+
+        CREATE INDEX idx_user_keys_user ON user_keys (user_id);
+        CREATE INDEX idx_link_failures ON link_failures (fingerprint, failed_at);",
+    )?;
+    Ok(())
+}
+
+/// Only a SHA-256 of each code is kept.
+fn recovery_codes(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE recovery_codes (
+            user_id INTEGER PRIMARY KEY,
+            code_hash TEXT NOT NULL UNIQUE,
+            created_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+fn user_regions(conn: &Connection) -> Result<()> {
+    conn.execute_batch("ALTER TABLE users ADD COLUMN region TEXT;")?;
+    Ok(())
+}
+
+/// Compressed replays of top-ranked runs, keyed by `games.id`.
+fn replays(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE replays (
+            game_id INTEGER PRIMARY KEY,
+            data BLOB NOT NULL,
+            created_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (game_id) REFERENCES games(id) ON DELETE CASCADE
+        );",
+    )?;
+    Ok(())
+}
+
+/// No foreign key: entries must survive the user being deleted.
+fn audit_log(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            mode TEXT NOT NULL,
+            score INTEGER NOT NULL,
+            flagged_clicks INTEGER NOT NULL,
+            client TEXT NOT NULL,
+            verdict TEXT NOT NULL,
+            created_at DATETIME DEFAULT (DATETIME('now'))
+        );
+        CREATE INDEX idx_audit_log_user ON audit_log (user_id, id DESC);",
+    )?;
+    Ok(())
+}
+
+/// Per-run reaction times feed the outlier detector; audit entries point
+/// at the run they judged so held runs can be reviewed.
+fn outlier_review(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE games ADD COLUMN reaction_ms INTEGER;
+        ALTER TABLE audit_log ADD COLUMN game_id INTEGER;
+        ALTER TABLE audit_log ADD COLUMN reason TEXT;",
+    )?;
+    Ok(())
+}
+
+fn trust(conn: &Connection) -> Result<()> {
+    conn.execute_batch(&format!(
+        "ALTER TABLE users ADD COLUMN trust INTEGER NOT NULL DEFAULT {MAX_TRUST};
+        ALTER TABLE users ADD COLUMN shadowed INTEGER NOT NULL DEFAULT 0;"
+    ))?;
+    Ok(())
+}
+
+/// Like the audit log, entries must survive the user being deleted.
+fn check_events(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE check_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL,
+            game_id INTEGER,
+            check_name TEXT NOT NULL,
+            value REAL,
+            threshold REAL,
+            created_at DATETIME DEFAULT (DATETIME('now'))
+        );
+        CREATE INDEX idx_check_events_check ON check_events (check_name, id DESC);",
+    )?;
+    Ok(())
+}
+
+fn strictness(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "ALTER TABLE users ADD COLUMN warnings INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE users ADD COLUMN clean_streak INTEGER NOT NULL DEFAULT 0;",
+    )?;
+    Ok(())
+}
+
+fn banned_keys(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE banned_keys (
+            fingerprint TEXT PRIMARY KEY,
+            created_at DATETIME DEFAULT (DATETIME('now'))
+        );",
+    )?;
+    Ok(())
+}
+
+fn duels(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE duels (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            player_a INTEGER NOT NULL,
+            player_b INTEGER NOT NULL,
+            score_a INTEGER NOT NULL,
+            score_b INTEGER NOT NULL,
+            winner INTEGER,
+            played_at DATETIME DEFAULT (DATETIME('now')),
+            FOREIGN KEY (player_a) REFERENCES users(id) ON DELETE CASCADE,
+            FOREIGN KEY (player_b) REFERENCES users(id) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_duels_players ON duels (player_a, player_b);",
+    )?;
+    Ok(())
+}
+
+fn cluster(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE leases (
+            name TEXT PRIMARY KEY,
+            holder TEXT NOT NULL,
+            expires_at INTEGER NOT NULL
+        );
+        CREATE TABLE cluster_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            origin TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            fingerprint TEXT,
+            created_at INTEGER NOT NULL
+        );",
+    )?;
+    Ok(())
+}
+
+/// Hashes of uploaded replays, so a run is ranked once however often it's sent.
+fn uploads(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE uploads (
+            hash BLOB PRIMARY KEY,
+            created_at DATETIME DEFAULT (DATETIME('now'))
+        );",
+    )?;
+    Ok(())
+}
+
+/// Standard runs a local client hasn't uploaded yet. Clients made the table
+/// themselves before it was a migration, hence `IF NOT EXISTS`.
+fn upload_queue(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS upload_queue (
+            id INTEGER PRIMARY KEY,
+            replay BLOB NOT NULL,
+            played_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+    Ok(())
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    Ok(conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        params![column],
+        |row| row.get(0),
+    )?)
+}
+
+fn ensure_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    if !column_exists(conn, table, column)? {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {};",
+            table, column, decl
+        ))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_is_idempotent() {
+        let conn = Connection::open_in_memory().unwrap();
+        run(&conn).unwrap();
+        run(&conn).unwrap();
+        let latest = MIGRATIONS.last().unwrap().version;
+        assert_eq!(current_version(&conn).unwrap(), latest);
+    }
+
+    #[test]
+    fn test_imports_legacy_leaderboard() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE leaderboard (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                score INTEGER NOT NULL,
+                created_at DATETIME
+            );
+            INSERT INTO leaderboard (name, score, created_at) VALUES
+                ('alice', 300, '2024-01-01 10:00:00'),
+                ('alice', 900, '2024-02-01 10:00:00'),
+                ('bob', 500, '2024-03-01 10:00:00');",
+        )
+        .unwrap();
+
+        run(&conn).unwrap();
+
+        let (score, at, sessions): (u32, String, u32) = conn
+            .query_row(
+                "SELECT s.high_score, s.high_score_at, s.sessions
+                FROM users u JOIN user_stats s ON u.id = s.user_id
+                WHERE u.username = 'alice'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (score, at.as_str(), sessions),
+            (900, "2024-02-01 10:00:00", 2)
+        );
+
+        let kept: u32 = conn
+            .query_row("SELECT COUNT(*) FROM legacy_leaderboard", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(kept, 3);
+    }
+
+    #[test]
+    fn test_legacy_names_that_collide_are_suffixed() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                fingerprint TEXT UNIQUE NOT NULL,
+                username TEXT UNIQUE,
+                created_at DATETIME DEFAULT (DATETIME('now'))
+            );
+            INSERT INTO users (fingerprint, username) VALUES
+                ('SHA256:a', 'alice'),
+                ('SHA256:b', 'alice (old)');
+            CREATE TABLE leaderboard (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                score INTEGER NOT NULL
+            );
+            INSERT INTO leaderboard (name, score) VALUES
+                ('alice', 300),
+                ('alice (old)', 500);",
+        )
+        .unwrap();
+
+        run(&conn).unwrap();
+
+        let imported: Vec<(String, String)> = conn
+            .prepare(
+                "SELECT fingerprint, username FROM users
+                WHERE fingerprint LIKE 'legacy:%' ORDER BY fingerprint",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            imported,
+            [
+                ("legacy:alice".to_string(), "alice (old 2)".to_string()),
+                (
+                    "legacy:alice (old)".to_string(),
+                    "alice (old) (old)".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use super::{GameResult, POPULATION_STATS_TTL, Repository};
+use crate::domain::GameMode;
+use anyhow::Result;
+use std::time::Instant;
+
+/// Mean and spread of one metric over a set of finished games.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Distribution {
+    pub count: u32,
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Distribution {
+    /// From the count, mean and mean of squares, which SQL can aggregate cheaply.
+    pub fn from_moments(count: u32, mean: f64, mean_sq: f64) -> Self {
+        Self {
+            count,
+            mean,
+            std_dev: (mean_sq - mean * mean).max(0.0).sqrt(),
+        }
+    }
+
+    pub fn from_values(values: impl IntoIterator<Item = f64>) -> Self {
+        let (mut count, mut sum, mut sum_sq) = (0, 0.0, 0.0);
+        for v in values {
+            count += 1;
+            sum += v;
+            sum_sq += v * v;
+        }
+        if count == 0 {
+            return Self::default();
+        }
+        Self::from_moments(count, sum / count as f64, sum_sq / count as f64)
+    }
+
+    /// `None` until there are `min_samples` values with some spread.
+    fn z_score(&self, value: f64, min_samples: u32) -> Option<f64> {
+        (self.count >= min_samples && self.std_dev > 0.0)
+            .then(|| (value - self.mean) / self.std_dev)
+    }
+}
+
+/// Score, accuracy (basis points) and average reaction time of past games.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GameStats {
+    pub score: Distribution,
+    pub accuracy_bp: Distribution,
+    pub reaction_ms: Distribution,
+}
+
+/// How far from normal a ranked run may land before it is held for review.
+#[derive(Debug, Clone, Copy)]
+pub struct OutlierPolicy {
+    /// Against everyone's games in the mode.
+    pub max_population_z: f64,
+    /// Against the player's own games; ignored until they have `min_samples`.
+    pub max_user_z: f64,
+    pub min_samples: u32,
+}
+
+impl Default for OutlierPolicy {
+    fn default() -> Self {
+        Self {
+            max_population_z: 3.5,
+            max_user_z: 3.0,
+            min_samples: 10,
+        }
+    }
+}
+
+impl OutlierPolicy {
+    /// A run is held if it is improbable for the population and, once the
+    /// player has `min_samples` games, for them too: a newcomer's first run far
+    /// above everyone is held, a regular who always plays that well isn't.
+    /// Reaction times count when unusually low.
+    pub fn find_outlier(
+        &self,
+        user: &GameStats,
+        population: &GameStats,
+        result: &GameResult,
+    ) -> Option<&'static str> {
+        type Pick = fn(&GameStats) -> Distribution;
+        let metrics: [(&'static str, Option<f64>, f64, Pick); 3] = [
+            ("score_outlier", Some(result.score as f64), 1.0, |s| s.score),
+            (
+                "accuracy_outlier",
+                result.accuracy_bp().map(f64::from),
+                1.0,
+                |s| s.accuracy_bp,
+            ),
+            (
+                "reaction_outlier",
+                result.average_reaction_ms().map(|ms| ms as f64),
+                -1.0,
+                |s| s.reaction_ms,
+            ),
+        ];
+
+        metrics.into_iter().find_map(|(name, value, sign, pick)| {
+            let value = value?;
+            let population_z = pick(population).z_score(value, self.min_samples)? * sign;
+            let user_z = pick(user)
+                .z_score(value, self.min_samples)
+                .map(|z| z * sign);
+            let improbable =
+                population_z > self.max_population_z && user_z.is_none_or(|z| z > self.max_user_z);
+            improbable.then_some(name)
+        })
+    }
+}
+
+impl Repository {
+    /// Name of the metric that makes a ranked run too improbable to publish unreviewed.
+    pub(super) fn find_outlier(
+        &self,
+        user_id: i64,
+        mode: GameMode,
+        result: &GameResult,
+    ) -> Option<&'static str> {
+        if mode == GameMode::Practice {
+            return None;
+        }
+        let population = self.population_stats(mode).ok()?;
+        let user = self.store.game_stats(mode, Some(user_id)).ok()?;
+        self.outliers.find_outlier(&user, &population, result)
+    }
+
+    /// Everyone's game stats in `mode`, aggregated at most every `POPULATION_STATS_TTL`.
+    fn population_stats(&self, mode: GameMode) -> Result<GameStats> {
+        if let Some((at, stats)) = self.population_stats.borrow().get(&mode)
+            && at.elapsed() < POPULATION_STATS_TTL
+        {
+            return Ok(*stats);
+        }
+        let stats = self.store.game_stats(mode, None)?;
+        self.population_stats
+            .borrow_mut()
+            .insert(mode, (Instant::now(), stats));
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(scores: &[f64]) -> GameStats {
+        GameStats {
+            score: Distribution::from_values(scores.iter().copied()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_jump_far_above_player_and_population_is_held() {
+        let policy = OutlierPolicy::default();
+        let population = stats(&[
+            100.0, 120.0, 80.0, 110.0, 90.0, 100.0, 130.0, 70.0, 100.0, 100.0,
+        ]);
+        let run = |score| GameResult {
+            score,
+            ..Default::default()
+        };
+        // a newcomer has no games of their own to be judged against
+        assert_eq!(
+            policy.find_outlier(&GameStats::default(), &population, &run(1000)),
+            Some("score_outlier")
+        );
+        assert_eq!(
+            policy.find_outlier(&GameStats::default(), &population, &run(150)),
+            None
+        );
+        // a player who always scores this high isn't suspicious
+        let regular = stats(&[
+            950.0, 1000.0, 1050.0, 990.0, 1010.0, 980.0, 1020.0, 1000.0, 970.0, 1030.0,
+        ]);
+        assert_eq!(policy.find_outlier(&regular, &population, &run(1000)), None);
+    }
+}
@@ -0,0 +1,1658 @@
+use super::{
+    ACTIVITY_HISTORY_DAYS, ActivityDay, AuditEntry, BEST_ACCURACY_MIN_SHOTS, CheckRecord,
+    ClientKind, ClusterEvent, ClusterRecord, Distribution, GameRecord, GameResult, GameStats,
+    HeadToHead, RankingPeriod, SCORE_HISTORY_LEN, ScoreEntry, ScoreStore, Season, ShadowedUser,
+    Submission, Trust, UserContext, UserRank, UsernameTaken, Verdict,
+};
+use crate::anticheat::{CheckEvent, Strictness};
+use crate::domain::{GameMode, WeeklyModifier};
+use crate::preferences::{
+    CursorStyle, GameDuration, HitFeedback, Language, PreferenceOption, Preferences, ThemeKind,
+};
+use crate::region::Region;
+use crate::replay::Replay;
+use anyhow::Result;
+use postgres::error::SqlState;
+use postgres::types::ToSql;
+use postgres::{Client, NoTls, Row};
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Shared database for deployments running several SSH nodes.
+///
+/// Day and week keys are computed in UTC on the client with the same
+/// `%Y-%m-%d` / `%Y-%W` formats the SQLite backend uses, so operators can
+/// schedule weekly modifiers the same way on either backend.
+pub struct PostgresStore {
+    client: RefCell<Client>,
+}
+
+impl PostgresStore {
+    pub fn connect(url: &str) -> Result<Self> {
+        let mut client = Client::connect(url, NoTls)?;
+        setup_schema(&mut client)?;
+        Ok(Self {
+            client: RefCell::new(client),
+        })
+    }
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+fn this_week() -> String {
+    chrono::Utc::now().format("%Y-%W").to_string()
+}
+
+/// (score column, date key column) in `user_stats`, and the current key.
+fn period_columns(period: RankingPeriod) -> (&'static str, Option<(&'static str, String)>) {
+    match period {
+        RankingPeriod::Daily => ("daily_high_score", Some(("daily_high_score_at", today()))),
+        RankingPeriod::Weekly => (
+            "weekly_high_score",
+            Some(("weekly_high_score_at", this_week())),
+        ),
+        RankingPeriod::AllTime | RankingPeriod::DailyChallenge => ("high_score", None),
+    }
+}
+
+const GAME_COLUMNS: &str = "id, mode, score, hits, misses, duration_ms, reaction_ms,
+    to_char(played_at, 'YYYY-MM-DD HH24:MI')";
+
+fn game_record(row: &Row) -> Result<GameRecord> {
+    Ok(GameRecord {
+        id: row.try_get(0)?,
+        mode: GameMode::from_key(row.try_get(1)?).unwrap_or_default(),
+        score: row.try_get::<_, i64>(2)? as u32,
+        hits: row.try_get::<_, i64>(3)? as u32,
+        misses: row.try_get::<_, i64>(4)? as u32,
+        duration_ms: row.try_get::<_, i64>(5)? as u64,
+        reaction_ms: row.try_get::<_, Option<i64>>(6)?.map(|ms| ms as u64),
+        played_at: row.try_get(7)?,
+    })
+}
+
+const AUDIT_COLUMNS: &str = "a.id, a.user_id, u.username, a.mode, a.score, a.flagged_clicks,
+    a.client, a.verdict, to_char(a.created_at, 'YYYY-MM-DD HH24:MI:SS'), a.game_id, a.reason";
+
+fn audit_entry(row: &Row) -> Result<AuditEntry> {
+    Ok(AuditEntry {
+        id: row.try_get(0)?,
+        user_id: row.try_get(1)?,
+        name: row.try_get(2)?,
+        submission: Submission {
+            game_id: row.try_get(9)?,
+            mode: GameMode::from_key(row.try_get(3)?).unwrap_or_default(),
+            score: row.try_get::<_, i64>(4)? as u32,
+            flagged_clicks: row.try_get::<_, i64>(5)? as u32,
+            client: ClientKind::from_key(row.try_get(6)?).unwrap_or_default(),
+            verdict: Verdict::from_key(row.try_get(7)?).unwrap_or(Verdict::Rejected),
+            reason: row.try_get(10)?,
+        },
+        created_at: row.try_get(8)?,
+    })
+}
+
+/// Games `g` whose latest audit verdict put them on the boards.
+const RANKED_GAME: &str = "(SELECT a.verdict FROM audit_log a WHERE a.game_id = g.id
+    ORDER BY a.id DESC LIMIT 1) IN ('accepted', 'flagged')";
+
+/// Rows of `alias` whose `user_id` isn't shadowed.
+fn visible(alias: &str) -> String {
+    format!("{alias}.user_id NOT IN (SELECT id FROM users WHERE shadowed)")
+}
+
+fn score_entry(row: &Row) -> Result<ScoreEntry> {
+    Ok(ScoreEntry {
+        name: row.try_get(0)?,
+        score: row.try_get::<_, i64>(1)? as u32,
+        created_at: row.try_get(2)?,
+        region: row.try_get::<_, Option<&str>>(3)?.and_then(Region::parse),
+    })
+}
+
+fn get_user_activity(
+    client: &mut Client,
+    user_id: i64,
+    days_limit: u32,
+) -> Result<Vec<ActivityDay>> {
+    let since = (chrono::Utc::now().date_naive() - chrono::Days::new(days_limit as u64))
+        .format("%Y-%m-%d")
+        .to_string();
+    client
+        .query(
+            "SELECT date, count FROM daily_activity
+            WHERE user_id = $1 AND date > $2
+            ORDER BY date ASC",
+            &[&user_id, &since],
+        )?
+        .iter()
+        .map(|row| {
+            Ok(ActivityDay {
+                date: row.try_get(0)?,
+                count: row.try_get::<_, i64>(1)? as u32,
+            })
+        })
+        .collect()
+}
+
+fn get_best_replay(client: &mut Client, user_id: i64) -> Result<Option<Replay>> {
+    let row = client.query_opt(
+        "SELECT data FROM best_replays WHERE user_id = $1",
+        &[&user_id],
+    )?;
+    row.map(|r| Replay::from_bytes(&r.try_get::<_, Vec<u8>>(0)?))
+        .transpose()
+}
+
+/// Unknown keys (e.g. from a newer build) fall back to defaults.
+fn get_preferences(client: &mut Client, user_id: i64) -> Result<Preferences> {
+    let row = client.query_opt(
+        "SELECT COALESCE(theme, ''), COALESCE(cursor_style, ''), effects,
+            COALESCE(game_duration, ''), COALESCE(language, ''), reduced_motion,
+            COALESCE(hit_feedback, '')
+        FROM user_preferences WHERE user_id = $1",
+        &[&user_id],
+    )?;
+
+    let region = client
+        .query_opt("SELECT region FROM users WHERE id = $1", &[&user_id])?
+        .map(|r| r.try_get::<_, Option<String>>(0))
+        .transpose()?
+        .flatten()
+        .and_then(|code| Region::parse(&code));
+
+    let defaults = Preferences::default();
+    let Some(row) = row else {
+        return Ok(Preferences { region, ..defaults });
+    };
+    Ok(Preferences {
+        theme: ThemeKind::from_key(row.try_get(0)?).unwrap_or(defaults.theme),
+        cursor_style: CursorStyle::from_key(row.try_get(1)?).unwrap_or(defaults.cursor_style),
+        effects: row.try_get(2)?,
+        game_duration: GameDuration::from_key(row.try_get(3)?).unwrap_or(defaults.game_duration),
+        language: Language::from_key(row.try_get(4)?).unwrap_or(defaults.language),
+        reduced_motion: row.try_get(5)?,
+        hit_feedback: HitFeedback::from_key(row.try_get(6)?).unwrap_or(defaults.hit_feedback),
+        region,
+    })
+}
+
+/// Most recent scores, oldest first.
+fn get_score_history(client: &mut Client, user_id: i64, limit: usize) -> Result<Vec<u32>> {
+    let mut scores = client
+        .query(
+            "SELECT score FROM score_history
+            WHERE user_id = $1
+            ORDER BY id DESC
+            LIMIT $2",
+            &[&user_id, &(limit as i64)],
+        )?
+        .iter()
+        .map(|row| Ok(row.try_get::<_, i64>(0)? as u32))
+        .collect::<Result<Vec<_>>>()?;
+    scores.reverse();
+    Ok(scores)
+}
+
+fn push_score_history(client: &mut Client, user_id: i64, score: u32) -> Result<()> {
+    client.execute(
+        "INSERT INTO score_history (user_id, score) VALUES ($1, $2)",
+        &[&user_id, &(score as i64)],
+    )?;
+    client.execute(
+        "DELETE FROM score_history
+        WHERE user_id = $1 AND id NOT IN (
+            SELECT id FROM score_history WHERE user_id = $1 ORDER BY id DESC LIMIT $2
+        )",
+        &[&user_id, &(SCORE_HISTORY_LEN as i64)],
+    )?;
+    Ok(())
+}
+
+fn bump_activity(client: &mut Client, user_id: i64) -> Result<()> {
+    client.execute(
+        "INSERT INTO daily_activity (user_id, date, count)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (user_id, date) DO UPDATE SET
+            count = daily_activity.count + 1",
+        &[&user_id, &today()],
+    )?;
+    Ok(())
+}
+
+impl ScoreStore for PostgresStore {
+    fn find_user(&self, fingerprint: &str) -> Result<Option<UserContext>> {
+        let mut client = self.client.borrow_mut();
+        let Some(row) = client.query_opt(
+            "SELECT
+                u.id,
+                u.username,
+                COALESCE(s.high_score, 0),
+                COALESCE(s.total_hits, 0),
+                COALESCE(s.total_misses, 0),
+                COALESCE(s.sessions, 0),
+                COALESCE(s.best_combo, 0),
+                COALESCE(s.total_reaction_ms, 0),
+                COALESCE(s.reaction_samples, 0),
+                COALESCE(s.best_accuracy_bp, 0),
+                s.best_reaction_ms
+            FROM users u
+            LEFT JOIN user_stats s ON u.id = s.user_id
+            WHERE u.fingerprint = $1
+                OR u.id = (SELECT user_id FROM user_keys WHERE fingerprint = $1)",
+            &[&fingerprint],
+        )?
+        else {
+            return Ok(None);
+        };
+
+        let id: i64 = row.try_get(0)?;
+        let count = |i: usize| -> Result<i64> { Ok(row.try_get::<_, i64>(i)?) };
+        Ok(Some(UserContext {
+            id,
+            fingerprint: fingerprint.to_string(),
+            name: row.try_get(1)?,
+            high_score: count(2)? as u32,
+            total_hits: count(3)? as u32,
+            total_misses: count(4)? as u32,
+            sessions: count(5)? as u32,
+            best_combo: count(6)? as u32,
+            user_activity: get_user_activity(&mut client, id, ACTIVITY_HISTORY_DAYS)
+                .unwrap_or_default(),
+            best_replay: get_best_replay(&mut client, id)
+                .ok()
+                .flatten()
+                .map(Arc::new),
+            preferences: get_preferences(&mut client, id).unwrap_or_default(),
+            reaction_total_ms: count(7)? as u64,
+            reaction_samples: count(8)? as u32,
+            best_accuracy_bp: count(9)? as u32,
+            best_reaction_ms: row.try_get::<_, Option<i64>>(10)?.map(|ms| ms as u64),
+            score_history: get_score_history(&mut client, id, SCORE_HISTORY_LEN)
+                .unwrap_or_default(),
+            strictness: Strictness::default(),
+        }))
+    }
+
+    fn count_users(&self) -> Result<i64> {
+        let row = self
+            .client
+            .borrow_mut()
+            .query_one("SELECT COUNT(*) FROM users", &[])?;
+        Ok(row.try_get(0)?)
+    }
+
+    fn prune_inactive_users(&self, inactive_days: u32) -> Result<usize> {
+        let days = inactive_days as i32;
+        let deleted = self.client.borrow_mut().execute(
+            "DELETE FROM users
+            WHERE id IN (
+                SELECT u.id FROM users u
+                LEFT JOIN user_stats s ON u.id = s.user_id
+                WHERE COALESCE(s.high_score, 0) = 0
+                AND u.created_at < now() - $1::int * INTERVAL '1 day'
+                AND NOT EXISTS (
+                    SELECT 1 FROM daily_activity a
+                    WHERE a.user_id = u.id
+                    AND a.date >= to_char(CURRENT_DATE - $1::int, 'YYYY-MM-DD')
+                )
+            )",
+            &[&days],
+        )?;
+        Ok(deleted as usize)
+    }
+
+    fn create_user(&self, fingerprint: &str) -> Result<i64> {
+        let row = self.client.borrow_mut().query_one(
+            "INSERT INTO users (fingerprint) VALUES ($1) RETURNING id",
+            &[&fingerprint],
+        )?;
+        Ok(row.try_get(0)?)
+    }
+
+    fn update_username(&self, user_id: i64, name: &str) -> Result<()> {
+        match self.client.borrow_mut().execute(
+            "UPDATE users SET username = $1 WHERE id = $2",
+            &[&name, &user_id],
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) if e.code() == Some(&SqlState::UNIQUE_VIOLATION) => Err(UsernameTaken.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn is_username_taken(&self, name: &str) -> Result<bool> {
+        let row = self.client.borrow_mut().query_one(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE username = $1)",
+            &[&name],
+        )?;
+        Ok(row.get(0))
+    }
+
+    fn delete_user(&self, user_id: i64) -> Result<()> {
+        self.client
+            .borrow_mut()
+            .execute("DELETE FROM users WHERE id = $1", &[&user_id])?;
+        Ok(())
+    }
+
+    fn add_user_key(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO user_keys (fingerprint, user_id) VALUES ($1, $2)",
+            &[&fingerprint, &user_id],
+        )?;
+        Ok(())
+    }
+
+    fn replace_user_keys(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        let mut tx = client.transaction()?;
+        tx.execute("DELETE FROM user_keys WHERE user_id = $1", &[&user_id])?;
+        tx.execute(
+            "UPDATE users SET fingerprint = $2 WHERE id = $1",
+            &[&user_id, &fingerprint],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_recovery_code(&self, user_id: i64, code_hash: Option<&str>) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        match code_hash {
+            Some(hash) => client.execute(
+                "INSERT INTO recovery_codes (user_id, code_hash) VALUES ($1, $2)
+                ON CONFLICT (user_id) DO UPDATE SET
+                    code_hash = EXCLUDED.code_hash,
+                    created_at = now()",
+                &[&user_id, &hash],
+            )?,
+            None => client.execute("DELETE FROM recovery_codes WHERE user_id = $1", &[&user_id])?,
+        };
+        Ok(())
+    }
+
+    fn find_recovery_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        let row = self.client.borrow_mut().query_opt(
+            "SELECT user_id FROM recovery_codes WHERE code_hash = $1",
+            &[&code_hash],
+        )?;
+        Ok(row.map(|r| r.try_get(0)).transpose()?)
+    }
+
+    fn put_link_code(&self, user_id: i64, code_hash: &str, ttl: Duration) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "DELETE FROM link_codes WHERE user_id = $1 OR expires_at <= $2",
+            &[&user_id, &now],
+        )?;
+        let stored = client.execute(
+            "INSERT INTO link_codes (code_hash, user_id, expires_at) VALUES ($1, $2, $3)
+            ON CONFLICT (code_hash) DO NOTHING",
+            &[&code_hash, &user_id, &(now + ttl.as_secs() as i64)],
+        )?;
+        Ok(stored > 0)
+    }
+
+    fn take_link_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        let row = self.client.borrow_mut().query_opt(
+            "DELETE FROM link_codes WHERE code_hash = $1 RETURNING user_id, expires_at",
+            &[&code_hash],
+        )?;
+        let now = chrono::Utc::now().timestamp();
+        Ok(match row {
+            Some(row) if row.try_get::<_, i64>(1)? > now => Some(row.try_get(0)?),
+            _ => None,
+        })
+    }
+
+    fn link_failures(&self, fingerprint: &str, window: Duration) -> Result<u32> {
+        let since = chrono::Utc::now().timestamp() - window.as_secs() as i64;
+        let row = self.client.borrow_mut().query_one(
+            "SELECT COUNT(*) FROM link_failures WHERE fingerprint = $1 AND failed_at > $2",
+            &[&fingerprint, &since],
+        )?;
+        Ok(row.try_get::<_, i64>(0)? as u32)
+    }
+
+    fn add_link_failure(&self, fingerprint: &str, window: Duration) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "DELETE FROM link_failures WHERE failed_at <= $1",
+            &[&(now - window.as_secs() as i64)],
+        )?;
+        client.execute(
+            "INSERT INTO link_failures (fingerprint, failed_at) VALUES ($1, $2)",
+            &[&fingerprint, &now],
+        )?;
+        Ok(())
+    }
+
+    fn save_game(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "INSERT INTO user_stats (
+                user_id,
+                high_score,
+                high_score_at,
+                daily_high_score,
+                daily_high_score_at,
+                weekly_high_score,
+                weekly_high_score_at,
+                total_hits,
+                total_misses,
+                sessions,
+                best_combo,
+                total_reaction_ms,
+                reaction_samples,
+                best_accuracy_bp,
+                best_reaction_ms
+            )
+            VALUES ($1, $2, now(), $2, $3, $2, $4, $5, $6, 1, $7, $8, $9, $10, $11)
+            ON CONFLICT (user_id) DO UPDATE SET
+                -- all time
+                high_score_at = CASE
+                    WHEN $2 > user_stats.high_score THEN now()
+                    ELSE user_stats.high_score_at
+                END,
+                high_score = GREATEST(user_stats.high_score, $2),
+
+                -- daily
+                daily_high_score = CASE
+                    WHEN user_stats.daily_high_score_at <> $3 THEN $2
+                    ELSE GREATEST(user_stats.daily_high_score, $2)
+                END,
+                daily_high_score_at = $3,
+
+                -- weekly
+                weekly_high_score = CASE
+                    WHEN user_stats.weekly_high_score_at <> $4 THEN $2
+                    ELSE GREATEST(user_stats.weekly_high_score, $2)
+                END,
+                weekly_high_score_at = $4,
+
+                total_hits = user_stats.total_hits + $5,
+                total_misses = user_stats.total_misses + $6,
+                sessions = user_stats.sessions + 1,
+                best_combo = GREATEST(user_stats.best_combo, $7),
+                total_reaction_ms = user_stats.total_reaction_ms + $8,
+                reaction_samples = user_stats.reaction_samples + $9,
+                best_accuracy_bp = GREATEST(user_stats.best_accuracy_bp, $10),
+                best_reaction_ms = LEAST(user_stats.best_reaction_ms, $11)",
+            &[
+                &user_id,
+                &(result.score as i64),
+                &today(),
+                &this_week(),
+                &(result.hits as i64),
+                &(result.misses as i64),
+                &(result.best_combo as i64),
+                &(result.reaction_total_ms as i64),
+                &(result.reaction_samples as i64),
+                &(result.accuracy_bp().unwrap_or(0) as i64),
+                &result.average_reaction_ms().map(|ms| ms as i64),
+            ],
+        )?;
+
+        bump_activity(&mut client, user_id)?;
+        push_score_history(&mut client, user_id, result.score)
+    }
+
+    fn save_daily_challenge(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        self.save_practice(user_id, result)?;
+
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "INSERT INTO daily_challenge (user_id, date, score, score_at)
+            VALUES ($1, $2, $3, now())
+            ON CONFLICT (user_id, date) DO UPDATE SET
+                score_at = CASE
+                    WHEN $3 > daily_challenge.score THEN now()
+                    ELSE daily_challenge.score_at
+                END,
+                score = GREATEST(daily_challenge.score, $3)",
+            &[&user_id, &today(), &(result.score as i64)],
+        )?;
+
+        push_score_history(&mut client, user_id, result.score)
+    }
+
+    fn save_practice(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "INSERT INTO user_stats (
+                user_id, total_hits, total_misses, sessions, best_combo,
+                total_reaction_ms, reaction_samples, best_accuracy_bp, best_reaction_ms
+            )
+            VALUES ($1, $2, $3, 1, $4, $5, $6, $7, $8)
+            ON CONFLICT (user_id) DO UPDATE SET
+                total_hits = user_stats.total_hits + $2,
+                total_misses = user_stats.total_misses + $3,
+                sessions = user_stats.sessions + 1,
+                best_combo = GREATEST(user_stats.best_combo, $4),
+                total_reaction_ms = user_stats.total_reaction_ms + $5,
+                reaction_samples = user_stats.reaction_samples + $6,
+                best_accuracy_bp = GREATEST(user_stats.best_accuracy_bp, $7),
+                best_reaction_ms = LEAST(user_stats.best_reaction_ms, $8)",
+            &[
+                &user_id,
+                &(result.hits as i64),
+                &(result.misses as i64),
+                &(result.best_combo as i64),
+                &(result.reaction_total_ms as i64),
+                &(result.reaction_samples as i64),
+                &(result.accuracy_bp().unwrap_or(0) as i64),
+                &result.average_reaction_ms().map(|ms| ms as i64),
+            ],
+        )?;
+
+        bump_activity(&mut client, user_id)
+    }
+
+    fn save_best_replay(&self, user_id: i64, score: u32, replay: &Replay) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO best_replays (user_id, score, data)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id) DO UPDATE SET
+                score = excluded.score,
+                data = excluded.data,
+                created_at = now()
+            WHERE excluded.score > best_replays.score",
+            &[&user_id, &(score as i64), &replay.to_bytes()],
+        )?;
+        Ok(())
+    }
+
+    fn save_preferences(&self, user_id: i64, prefs: &Preferences) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        client.execute(
+            "INSERT INTO user_preferences
+                (user_id, theme, cursor_style, effects, game_duration, language, reduced_motion,
+                hit_feedback)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (user_id) DO UPDATE SET
+                theme = $2,
+                cursor_style = $3,
+                effects = $4,
+                game_duration = $5,
+                language = $6,
+                reduced_motion = $7,
+                hit_feedback = $8",
+            &[
+                &user_id,
+                &prefs.theme.key(),
+                &prefs.cursor_style.key(),
+                &prefs.effects,
+                &prefs.game_duration.key(),
+                &prefs.language.key(),
+                &prefs.reduced_motion,
+                &prefs.hit_feedback.key(),
+            ],
+        )?;
+        client.execute(
+            "UPDATE users SET region = $2 WHERE id = $1",
+            &[&user_id, &prefs.region.map(|r| r.code())],
+        )?;
+        Ok(())
+    }
+
+    fn record_game(&self, user_id: i64, mode: GameMode, result: &GameResult) -> Result<i64> {
+        let row = self.client.borrow_mut().query_one(
+            "INSERT INTO games (user_id, mode, score, hits, misses, duration_ms, reaction_ms)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id",
+            &[
+                &user_id,
+                &mode.key(),
+                &(result.score as i64),
+                &(result.hits as i64),
+                &(result.misses as i64),
+                &(result.duration_ms as i64),
+                &result.average_reaction_ms().map(|ms| ms as i64),
+            ],
+        )?;
+        Ok(row.try_get(0)?)
+    }
+
+    fn get_games(&self, user_id: i64, limit: u32) -> Result<Vec<GameRecord>> {
+        self.client
+            .borrow_mut()
+            .query(
+                &format!(
+                    "SELECT {GAME_COLUMNS}
+                    FROM games
+                    WHERE user_id = $1
+                    ORDER BY id DESC
+                    LIMIT $2"
+                ),
+                &[&user_id, &(limit as i64)],
+            )?
+            .iter()
+            .map(game_record)
+            .collect()
+    }
+
+    fn get_game(&self, game_id: i64) -> Result<Option<GameRecord>> {
+        self.client
+            .borrow_mut()
+            .query_opt(
+                &format!("SELECT {GAME_COLUMNS} FROM games WHERE id = $1"),
+                &[&game_id],
+            )?
+            .map(|row| game_record(&row))
+            .transpose()
+    }
+
+    fn game_stats(&self, mode: GameMode, user_id: Option<i64>) -> Result<GameStats> {
+        // a plain `user_id = $2` lets one player's games come off idx_games_user
+        let player = if user_id.is_some() {
+            "AND user_id = $2"
+        } else {
+            "AND $2::int8 IS NULL"
+        };
+        let sql = format!(
+            "WITH g AS (
+                SELECT score::float8 AS score, reaction_ms::float8 AS reaction_ms,
+                    CASE WHEN hits + misses >= {BEST_ACCURACY_MIN_SHOTS}
+                        THEN hits * 10000.0::float8 / (hits + misses) END AS accuracy_bp
+                FROM games
+                WHERE mode = $1 {player}
+            )
+            SELECT COUNT(score), AVG(score), AVG(score * score),
+                COUNT(accuracy_bp), AVG(accuracy_bp), AVG(accuracy_bp * accuracy_bp),
+                COUNT(reaction_ms), AVG(reaction_ms), AVG(reaction_ms * reaction_ms)
+            FROM g"
+        );
+        let row = self
+            .client
+            .borrow_mut()
+            .query_one(&sql, &[&mode.key(), &user_id])?;
+        let dist = |i: usize| -> Result<Distribution> {
+            Ok(Distribution::from_moments(
+                row.try_get::<_, i64>(i)? as u32,
+                row.try_get::<_, Option<f64>>(i + 1)?.unwrap_or(0.0),
+                row.try_get::<_, Option<f64>>(i + 2)?.unwrap_or(0.0),
+            ))
+        };
+        Ok(GameStats {
+            score: dist(0)?,
+            accuracy_bp: dist(3)?,
+            reaction_ms: dist(6)?,
+        })
+    }
+
+    fn save_replay(&self, game_id: i64, data: &[u8]) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO replays (game_id, data) VALUES ($1, $2)
+            ON CONFLICT (game_id) DO UPDATE SET data = EXCLUDED.data",
+            &[&game_id, &data],
+        )?;
+        Ok(())
+    }
+
+    fn get_replay(&self, game_id: i64) -> Result<Option<Vec<u8>>> {
+        let row = self
+            .client
+            .borrow_mut()
+            .query_opt("SELECT data FROM replays WHERE game_id = $1", &[&game_id])?;
+        Ok(row.map(|r| r.try_get(0)).transpose()?)
+    }
+
+    fn append_audit(&self, user_id: i64, submission: &Submission) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO audit_log
+                (user_id, mode, score, flagged_clicks, client, verdict, game_id, reason)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &user_id,
+                &submission.mode.key(),
+                &(submission.score as i64),
+                &(submission.flagged_clicks as i64),
+                &submission.client.key(),
+                &submission.verdict.key(),
+                &submission.game_id,
+                &submission.reason,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_audit_log(&self, user_id: Option<i64>, limit: u32) -> Result<Vec<AuditEntry>> {
+        self.client
+            .borrow_mut()
+            .query(
+                &format!(
+                    "SELECT {AUDIT_COLUMNS}
+                    FROM audit_log a
+                    LEFT JOIN users u ON u.id = a.user_id
+                    WHERE $1::BIGINT IS NULL OR a.user_id = $1
+                    ORDER BY a.id DESC
+                    LIMIT $2"
+                ),
+                &[&user_id, &(limit as i64)],
+            )?
+            .iter()
+            .map(audit_entry)
+            .collect()
+    }
+
+    fn append_check_events(
+        &self,
+        user_id: i64,
+        game_id: Option<i64>,
+        events: &[CheckEvent],
+    ) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        let mut tx = client.transaction()?;
+        let stmt = tx.prepare(
+            "INSERT INTO check_events (user_id, game_id, check_name, value, threshold)
+            VALUES ($1, $2, $3, $4, $5)",
+        )?;
+        for event in events {
+            tx.execute(
+                &stmt,
+                &[
+                    &user_id,
+                    &game_id,
+                    &event.check,
+                    &event.measurement.map(|m| m.value),
+                    &event.measurement.map(|m| m.limit),
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_check_events(&self, check: Option<&str>, limit: u32) -> Result<Vec<CheckRecord>> {
+        self.client
+            .borrow_mut()
+            .query(
+                "SELECT id, user_id, game_id, check_name, value, threshold,
+                    to_char(created_at, 'YYYY-MM-DD HH24:MI:SS')
+                FROM check_events
+                WHERE $1::TEXT IS NULL OR check_name = $1
+                ORDER BY id DESC
+                LIMIT $2",
+                &[&check, &(limit as i64)],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(CheckRecord {
+                    id: row.try_get(0)?,
+                    user_id: row.try_get(1)?,
+                    game_id: row.try_get(2)?,
+                    check: row.try_get(3)?,
+                    value: row.try_get(4)?,
+                    limit: row.try_get(5)?,
+                    created_at: row.try_get(6)?,
+                })
+            })
+            .collect()
+    }
+
+    fn get_review_queue(&self, limit: u32, min_flagged_clicks: u32) -> Result<Vec<AuditEntry>> {
+        self.client
+            .borrow_mut()
+            .query(
+                &format!(
+                    "SELECT {AUDIT_COLUMNS}
+                    FROM audit_log a
+                    LEFT JOIN users u ON u.id = a.user_id
+                    WHERE (a.verdict = 'held'
+                            OR (a.verdict = 'flagged' AND a.flagged_clicks >= $2))
+                        AND a.game_id IS NOT NULL
+                        AND NOT EXISTS (
+                            SELECT 1 FROM audit_log r WHERE r.game_id = a.game_id AND r.id > a.id
+                        )
+                    ORDER BY a.id ASC
+                    LIMIT $1"
+                ),
+                &[&(limit as i64), &(min_flagged_clicks as i64)],
+            )?
+            .iter()
+            .map(audit_entry)
+            .collect()
+    }
+
+    fn publish_game(&self, game_id: i64) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        let row = client.query_one(
+            "SELECT user_id, mode, score, to_char(played_at, 'YYYY-MM-DD') FROM games WHERE id = $1",
+            &[&game_id],
+        )?;
+        let user_id: i64 = row.try_get(0)?;
+        let score: i64 = row.try_get(2)?;
+        let day: String = row.try_get(3)?;
+        match GameMode::from_key(row.try_get(1)?) {
+            // daily and weekly entries only if the run still belongs to them
+            Some(GameMode::Standard) => {
+                let week = chrono::NaiveDate::parse_from_str(&day, "%Y-%m-%d")?
+                    .format("%Y-%W")
+                    .to_string();
+                client.execute(
+                    "UPDATE user_stats SET
+                        high_score_at = CASE
+                            WHEN $2 > high_score THEN (SELECT played_at FROM games WHERE id = $3)
+                            ELSE high_score_at
+                        END,
+                        high_score = GREATEST(high_score, $2),
+
+                        daily_high_score = CASE
+                            WHEN NOT $4 THEN daily_high_score
+                            WHEN daily_high_score_at <> $6 THEN $2
+                            ELSE GREATEST(daily_high_score, $2)
+                        END,
+                        daily_high_score_at = CASE WHEN $4 THEN $6 ELSE daily_high_score_at END,
+
+                        weekly_high_score = CASE
+                            WHEN NOT $5 THEN weekly_high_score
+                            WHEN weekly_high_score_at <> $7 THEN $2
+                            ELSE GREATEST(weekly_high_score, $2)
+                        END,
+                        weekly_high_score_at = CASE WHEN $5 THEN $7 ELSE weekly_high_score_at END
+                    WHERE user_id = $1",
+                    &[
+                        &user_id,
+                        &score,
+                        &game_id,
+                        &(day == today()),
+                        &(week == this_week()),
+                        &today(),
+                        &this_week(),
+                    ],
+                )?;
+            }
+            Some(GameMode::DailyChallenge) => {
+                client.execute(
+                    "INSERT INTO daily_challenge (user_id, date, score, score_at)
+                    VALUES ($1, $3, $2, (SELECT played_at FROM games WHERE id = $4))
+                    ON CONFLICT (user_id, date) DO UPDATE SET
+                        score_at = CASE
+                            WHEN $2 > daily_challenge.score THEN EXCLUDED.score_at
+                            ELSE daily_challenge.score_at
+                        END,
+                        score = GREATEST(daily_challenge.score, $2)",
+                    &[&user_id, &score, &day, &game_id],
+                )?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn retract_game(&self, game_id: i64) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        let flagged: bool = client
+            .query_one(
+                "SELECT EXISTS (
+                    SELECT 1 FROM audit_log WHERE game_id = $1 AND verdict = 'flagged'
+                )",
+                &[&game_id],
+            )?
+            .try_get(0)?;
+        anyhow::ensure!(flagged, "Game {game_id} was never flagged");
+        let row = client.query_one(
+            "SELECT user_id, mode, score FROM games WHERE id = $1",
+            &[&game_id],
+        )?;
+        let user_id: i64 = row.try_get(0)?;
+        let mode: String = row.try_get(1)?;
+        let score: i64 = row.try_get(2)?;
+        let best = |period: &str| {
+            format!(
+                "(SELECT COALESCE(MAX(g.score), 0) FROM games g
+                WHERE g.user_id = $1 AND g.mode = $2 AND g.id <> $3 AND {RANKED_GAME}
+                    AND {period})"
+            )
+        };
+        match GameMode::from_key(&mode) {
+            Some(GameMode::Standard) => {
+                let query = format!(
+                    "UPDATE user_stats SET
+                        high_score = CASE WHEN high_score = $4 THEN {} ELSE high_score END,
+                        daily_high_score = CASE
+                            WHEN daily_high_score = $4 AND daily_high_score_at = $5 THEN {}
+                            ELSE daily_high_score
+                        END,
+                        weekly_high_score = CASE
+                            WHEN weekly_high_score = $4 AND weekly_high_score_at = $6 THEN {}
+                            ELSE weekly_high_score
+                        END
+                    WHERE user_id = $1",
+                    // the all-time board restarts with every season
+                    best(
+                        "g.played_at >= COALESCE((SELECT MAX(archived_at) FROM seasons), '-infinity')"
+                    ),
+                    best("to_char(g.played_at, 'YYYY-MM-DD') = $5"),
+                    best("g.played_at >= date_trunc('week', now())"),
+                );
+                client.execute(
+                    &query,
+                    &[&user_id, &mode, &game_id, &score, &today(), &this_week()],
+                )?;
+            }
+            Some(GameMode::DailyChallenge) => {
+                let query = format!(
+                    "UPDATE daily_challenge SET score = {}
+                    WHERE user_id = $1 AND score = $4
+                        AND date = (SELECT to_char(played_at, 'YYYY-MM-DD') FROM games WHERE id = $3)",
+                    best("to_char(g.played_at, 'YYYY-MM-DD') = daily_challenge.date"),
+                );
+                client.execute(&query, &[&user_id, &mode, &game_id, &score])?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn prune_replays(&self, min_score: u32) -> Result<usize> {
+        let pruned = self.client.borrow_mut().execute(
+            "DELETE FROM replays r USING games g
+            WHERE r.game_id = g.id AND g.score < $1",
+            &[&(min_score as i64)],
+        )?;
+        Ok(pruned as usize)
+    }
+
+    fn get_trust(&self, user_id: i64) -> Result<Option<Trust>> {
+        self.client
+            .borrow_mut()
+            .query_opt(
+                "SELECT trust, shadowed, warnings, clean_streak FROM users WHERE id = $1",
+                &[&user_id],
+            )?
+            .map(|row| {
+                Ok(Trust {
+                    score: row.try_get::<_, i64>(0)? as u32,
+                    shadowed: row.try_get(1)?,
+                    warnings: row.try_get::<_, i64>(2)? as u32,
+                    clean_streak: row.try_get::<_, i64>(3)? as u32,
+                })
+            })
+            .transpose()
+    }
+
+    fn set_trust(&self, user_id: i64, trust: Trust) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "UPDATE users SET trust = $2, shadowed = $3, warnings = $4, clean_streak = $5
+             WHERE id = $1",
+            &[
+                &user_id,
+                &(trust.score as i64),
+                &trust.shadowed,
+                &(trust.warnings as i64),
+                &(trust.clean_streak as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_shadowed_users(&self) -> Result<Vec<ShadowedUser>> {
+        self.client
+            .borrow_mut()
+            .query(
+                "SELECT id, username, trust FROM users WHERE shadowed ORDER BY trust ASC, id ASC",
+                &[],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(ShadowedUser {
+                    user_id: row.try_get(0)?,
+                    name: row.try_get(1)?,
+                    trust: row.try_get::<_, i64>(2)? as u32,
+                })
+            })
+            .collect()
+    }
+
+    fn get_top_scores(
+        &self,
+        period: RankingPeriod,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        let (limit, offset) = (limit as i64, offset as i64);
+        let mut client = self.client.borrow_mut();
+
+        if let RankingPeriod::DailyChallenge = period {
+            return client
+                .query(
+                    "SELECT u.username, c.score, to_char(c.score_at, 'MM-DD HH24:MI'), u.region
+                    FROM users u
+                    JOIN daily_challenge c ON u.id = c.user_id
+                    WHERE c.score > 0 AND c.date = $3 AND NOT u.shadowed
+                    ORDER BY c.score DESC
+                    LIMIT $1 OFFSET $2",
+                    &[&limit, &offset, &today()],
+                )?
+                .iter()
+                .map(score_entry)
+                .collect();
+        }
+
+        let (score_col, date) = period_columns(period);
+        let (date_format, date_filter) = match &date {
+            Some((date_col, _)) => ("MM-DD HH24:MI", format!("AND s.{date_col} = $3")),
+            None => ("YYYY-MM-DD", String::new()),
+        };
+        let query = format!(
+            "SELECT u.username, s.{score_col}, to_char(s.high_score_at, '{date_format}'),
+                u.region
+            FROM users u
+            JOIN user_stats s ON u.id = s.user_id
+            WHERE s.{score_col} > 0 {date_filter} AND NOT u.shadowed
+            ORDER BY s.{score_col} DESC
+            LIMIT $1 OFFSET $2"
+        );
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&limit, &offset];
+        if let Some((_, key)) = &date {
+            params.push(key);
+        }
+        client
+            .query(&query, &params)?
+            .iter()
+            .map(score_entry)
+            .collect()
+    }
+
+    fn get_user_rank(&self, user_id: i64, period: RankingPeriod) -> Result<Option<UserRank>> {
+        let mut client = self.client.borrow_mut();
+
+        let row = if let RankingPeriod::DailyChallenge = period {
+            let query = format!(
+                "SELECT c.score, 1 + (
+                    SELECT COUNT(*) FROM daily_challenge o
+                    WHERE o.date = c.date AND o.score > c.score AND {}
+                )
+                FROM daily_challenge c
+                WHERE c.user_id = $1 AND c.score > 0 AND c.date = $2",
+                visible("o"),
+            );
+            client.query_opt(&query, &[&user_id, &today()])?
+        } else {
+            let (score_col, date) = period_columns(period);
+            let filter = |alias: &str| match &date {
+                Some((date_col, _)) => {
+                    format!("{alias}.{score_col} > 0 AND {alias}.{date_col} = $2")
+                }
+                None => format!("{alias}.{score_col} > 0"),
+            };
+            let query = format!(
+                "SELECT s.{score_col}, 1 + (
+                    SELECT COUNT(*) FROM user_stats o
+                    WHERE {} AND o.{score_col} > s.{score_col} AND {}
+                )
+                FROM user_stats s
+                WHERE s.user_id = $1 AND {}",
+                filter("o"),
+                visible("o"),
+                filter("s"),
+            );
+
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![&user_id];
+            if let Some((_, key)) = &date {
+                params.push(key);
+            }
+            client.query_opt(&query, &params)?
+        };
+
+        row.map(|row| {
+            Ok(UserRank {
+                score: row.try_get::<_, i64>(0)? as u32,
+                rank: row.try_get::<_, i64>(1)? as u32,
+            })
+        })
+        .transpose()
+    }
+
+    fn count_scores(&self, period: RankingPeriod, score: u32) -> Result<(u32, u32)> {
+        let score = score as i64;
+        let mut client = self.client.borrow_mut();
+
+        let row = if let RankingPeriod::DailyChallenge = period {
+            let query = format!(
+                "SELECT COUNT(*) FILTER (WHERE c.score > $1), COUNT(*)
+                FROM daily_challenge c
+                WHERE c.score > 0 AND c.date = $2 AND {}",
+                visible("c"),
+            );
+            client.query_one(&query, &[&score, &today()])?
+        } else {
+            let (score_col, date) = period_columns(period);
+            let date_filter = match &date {
+                Some((date_col, _)) => format!("AND s.{date_col} = $2"),
+                None => String::new(),
+            };
+            let query = format!(
+                "SELECT COUNT(*) FILTER (WHERE s.{score_col} > $1), COUNT(*)
+                FROM user_stats s
+                WHERE s.{score_col} > 0 {date_filter} AND {}",
+                visible("s"),
+            );
+
+            let mut params: Vec<&(dyn ToSql + Sync)> = vec![&score];
+            if let Some((_, key)) = &date {
+                params.push(key);
+            }
+            client.query_one(&query, &params)?
+        };
+
+        Ok((
+            row.try_get::<_, i64>(0)? as u32,
+            row.try_get::<_, i64>(1)? as u32,
+        ))
+    }
+
+    fn get_weekly_modifier(&self) -> Result<Option<WeeklyModifier>> {
+        let row = self.client.borrow_mut().query_opt(
+            "SELECT modifier FROM weekly_modifiers WHERE week = $1",
+            &[&this_week()],
+        )?;
+        Ok(match row {
+            Some(row) => WeeklyModifier::from_key(row.try_get(0)?),
+            None => None,
+        })
+    }
+
+    fn set_weekly_modifier(&self, week: &str, modifier: Option<WeeklyModifier>) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        match modifier {
+            Some(m) => client.execute(
+                "INSERT INTO weekly_modifiers (week, modifier) VALUES ($1, $2)
+                ON CONFLICT (week) DO UPDATE SET modifier = $2",
+                &[&week, &m.key()],
+            )?,
+            None => client.execute("DELETE FROM weekly_modifiers WHERE week = $1", &[&week])?,
+        };
+        Ok(())
+    }
+
+    fn create_season(&self, name: &str, starts_on: &str, ends_on: &str) -> Result<i64> {
+        let row = self.client.borrow_mut().query_one(
+            "INSERT INTO seasons (name, starts_on, ends_on)
+            VALUES ($1, $2::text::date, $3::text::date)
+            RETURNING id",
+            &[&name, &starts_on, &ends_on],
+        )?;
+        Ok(row.try_get(0)?)
+    }
+
+    fn archive_due_seasons(&self) -> Result<usize> {
+        let mut client = self.client.borrow_mut();
+        let due: Vec<i64> = client
+            .query(
+                "SELECT id FROM seasons
+                WHERE archived_at IS NULL AND starts_on <= CURRENT_DATE AND ends_on <= CURRENT_DATE
+                ORDER BY ends_on ASC",
+                &[],
+            )?
+            .iter()
+            .map(|row| row.try_get(0))
+            .collect::<Result<_, _>>()?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+
+        // if several ended at once, the board belongs to the earliest and
+        // the rest are archived empty
+        let mut tx = client.transaction()?;
+        for season_id in &due {
+            tx.execute(
+                "INSERT INTO season_scores (season_id, rank, user_id, username, score, scored_at)
+                SELECT
+                    $1,
+                    ROW_NUMBER() OVER (ORDER BY s.high_score DESC, s.high_score_at ASC),
+                    u.id,
+                    u.username,
+                    s.high_score,
+                    s.high_score_at
+                FROM users u
+                JOIN user_stats s ON u.id = s.user_id
+                WHERE s.high_score > 0 AND u.username IS NOT NULL AND NOT u.shadowed",
+                &[season_id],
+            )?;
+            tx.execute(
+                "UPDATE seasons SET archived_at = now() WHERE id = $1",
+                &[season_id],
+            )?;
+            tx.execute(
+                "UPDATE user_stats SET high_score = 0, high_score_at = now()",
+                &[],
+            )?;
+        }
+        tx.commit()?;
+        Ok(due.len())
+    }
+
+    fn get_open_seasons(&self) -> Result<Vec<Season>> {
+        self.client
+            .borrow_mut()
+            .query(
+                "SELECT id, name, to_char(starts_on, 'YYYY-MM-DD'), to_char(ends_on, 'YYYY-MM-DD')
+                FROM seasons
+                WHERE archived_at IS NULL
+                ORDER BY starts_on ASC",
+                &[],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(Season {
+                    id: row.try_get(0)?,
+                    name: row.try_get(1)?,
+                    starts_on: row.try_get(2)?,
+                    ends_on: row.try_get(3)?,
+                })
+            })
+            .collect()
+    }
+
+    fn get_past_seasons(&self) -> Result<Vec<Season>> {
+        self.client
+            .borrow_mut()
+            .query(
+                "SELECT id, name, to_char(starts_on, 'YYYY-MM-DD'), to_char(ends_on, 'YYYY-MM-DD')
+                FROM seasons
+                WHERE archived_at IS NOT NULL
+                ORDER BY ends_on DESC",
+                &[],
+            )?
+            .iter()
+            .map(|row| {
+                Ok(Season {
+                    id: row.try_get(0)?,
+                    name: row.try_get(1)?,
+                    starts_on: row.try_get(2)?,
+                    ends_on: row.try_get(3)?,
+                })
+            })
+            .collect()
+    }
+
+    fn get_season_scores(
+        &self,
+        season_id: i64,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        self.client
+            .borrow_mut()
+            .query(
+                "SELECT username, score, to_char(scored_at, 'YYYY-MM-DD'), NULL::TEXT
+                FROM season_scores
+                WHERE season_id = $1
+                ORDER BY rank ASC
+                LIMIT $2 OFFSET $3",
+                &[&season_id, &(limit as i64), &(offset as i64)],
+            )?
+            .iter()
+            .map(score_entry)
+            .collect()
+    }
+
+    fn get_motd(&self) -> Result<Option<String>> {
+        let row = self
+            .client
+            .borrow_mut()
+            .query_opt("SELECT message FROM motd WHERE id = 1", &[])?;
+        let motd = row.map(|r| r.try_get::<_, String>(0)).transpose()?;
+        Ok(motd.filter(|m| !m.trim().is_empty()))
+    }
+
+    fn set_motd(&self, message: Option<&str>) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        match message.map(str::trim).filter(|m| !m.is_empty()) {
+            Some(m) => client.execute(
+                "INSERT INTO motd (id, message) VALUES (1, $1)
+                ON CONFLICT (id) DO UPDATE SET message = $1",
+                &[&m],
+            )?,
+            None => client.execute("DELETE FROM motd", &[])?,
+        };
+        Ok(())
+    }
+
+    fn get_banned_keys(&self) -> Result<Vec<String>> {
+        self.client
+            .borrow_mut()
+            .query("SELECT fingerprint FROM banned_keys", &[])?
+            .iter()
+            .map(|r| Ok(r.try_get(0)?))
+            .collect()
+    }
+
+    fn set_key_banned(&self, fingerprint: &str, banned: bool) -> Result<()> {
+        let mut client = self.client.borrow_mut();
+        if banned {
+            client.execute(
+                "INSERT INTO banned_keys (fingerprint) VALUES ($1) ON CONFLICT DO NOTHING",
+                &[&fingerprint],
+            )?;
+        } else {
+            client.execute(
+                "DELETE FROM banned_keys WHERE fingerprint = $1",
+                &[&fingerprint],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn record_upload(&self, hash: &[u8; 32]) -> Result<bool> {
+        let inserted = self.client.borrow_mut().execute(
+            "INSERT INTO uploads (hash) VALUES ($1) ON CONFLICT DO NOTHING",
+            &[&&hash[..]],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    fn record_duel(&self, players: [i64; 2], scores: [u32; 2], winner: Option<i64>) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO duels (player_a, player_b, score_a, score_b, winner)
+            VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &players[0],
+                &players[1],
+                &(scores[0] as i64),
+                &(scores[1] as i64),
+                &winner,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_head_to_head(&self, user_id: i64, opponent_id: i64) -> Result<HeadToHead> {
+        let row = self.client.borrow_mut().query_one(
+            "SELECT COUNT(*) FILTER (WHERE winner = $1), COUNT(*) FILTER (WHERE winner = $2),
+                COUNT(*) FILTER (WHERE winner IS NULL)
+            FROM duels
+            WHERE (player_a = $1 AND player_b = $2) OR (player_a = $2 AND player_b = $1)",
+            &[&user_id, &opponent_id],
+        )?;
+        Ok(HeadToHead {
+            wins: row.try_get::<_, i64>(0)? as u32,
+            losses: row.try_get::<_, i64>(1)? as u32,
+            draws: row.try_get::<_, i64>(2)? as u32,
+        })
+    }
+
+    fn try_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        let taken = self.client.borrow_mut().execute(
+            "INSERT INTO leases (name, holder, expires_at) VALUES ($1, $2, $3)
+            ON CONFLICT (name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+            WHERE leases.expires_at <= $4",
+            &[&name, &holder, &(now + ttl.as_secs() as i64), &now],
+        )?;
+        Ok(taken > 0)
+    }
+
+    fn publish_event(&self, origin: &str, event: &ClusterEvent) -> Result<()> {
+        self.client.borrow_mut().execute(
+            "INSERT INTO cluster_events (origin, kind, fingerprint, created_at)
+            VALUES ($1, $2, $3, $4)",
+            &[
+                &origin,
+                &event.kind(),
+                &event.fingerprint(),
+                &chrono::Utc::now().timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn events_after(&self, after: i64) -> Result<Vec<ClusterRecord>> {
+        let rows = self.client.borrow_mut().query(
+            "SELECT id, origin, kind, fingerprint FROM cluster_events WHERE id > $1 ORDER BY id",
+            &[&after],
+        )?;
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            let kind: String = row.try_get(2)?;
+            // kinds from a newer build are skipped
+            if let Some(event) = ClusterEvent::from_parts(&kind, row.try_get(3)?) {
+                records.push(ClusterRecord {
+                    id: row.try_get(0)?,
+                    origin: row.try_get(1)?,
+                    event,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    fn prune_events(&self, max_age: Duration) -> Result<usize> {
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+        let deleted = self.client.borrow_mut().execute(
+            "DELETE FROM cluster_events WHERE created_at < $1",
+            &[&cutoff],
+        )?;
+        Ok(deleted as usize)
+    }
+}
+
+fn setup_schema(client: &mut Client) -> Result<()> {
+    client.batch_execute(
+        "SET TIME ZONE 'UTC';
+
+        CREATE TABLE IF NOT EXISTS users (
+            id BIGSERIAL PRIMARY KEY,
+            fingerprint TEXT UNIQUE NOT NULL,
+            username TEXT UNIQUE,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS region TEXT;
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS trust BIGINT NOT NULL DEFAULT 100;
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS shadowed BOOLEAN NOT NULL DEFAULT FALSE;
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS warnings BIGINT NOT NULL DEFAULT 0;
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS clean_streak BIGINT NOT NULL DEFAULT 0;
+
+        CREATE TABLE IF NOT EXISTS user_stats (
+            user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+
+            high_score BIGINT NOT NULL DEFAULT 0,
+            high_score_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+
+            daily_high_score BIGINT NOT NULL DEFAULT 0,
+            daily_high_score_at TEXT NOT NULL DEFAULT '',
+
+            weekly_high_score BIGINT NOT NULL DEFAULT 0,
+            weekly_high_score_at TEXT NOT NULL DEFAULT '',
+
+            total_hits BIGINT NOT NULL DEFAULT 0,
+            total_misses BIGINT NOT NULL DEFAULT 0,
+            sessions BIGINT NOT NULL DEFAULT 0,
+            best_combo BIGINT NOT NULL DEFAULT 0,
+            total_reaction_ms BIGINT NOT NULL DEFAULT 0,
+            reaction_samples BIGINT NOT NULL DEFAULT 0,
+            best_accuracy_bp BIGINT NOT NULL DEFAULT 0,
+            best_reaction_ms BIGINT
+        );
+
+        -- columns added after the first Postgres release
+        ALTER TABLE user_stats ADD COLUMN IF NOT EXISTS best_accuracy_bp BIGINT NOT NULL DEFAULT 0;
+        ALTER TABLE user_stats ADD COLUMN IF NOT EXISTS best_reaction_ms BIGINT;
+
+        CREATE TABLE IF NOT EXISTS daily_activity (
+            user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            count BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (user_id, date)
+        );
+
+        CREATE TABLE IF NOT EXISTS daily_challenge (
+            user_id BIGINT REFERENCES users(id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            score BIGINT NOT NULL DEFAULT 0,
+            score_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (user_id, date)
+        );
+
+        CREATE TABLE IF NOT EXISTS best_replays (
+            user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+            score BIGINT NOT NULL DEFAULT 0,
+            data BYTEA NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS user_preferences (
+            user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+            theme TEXT,
+            cursor_style TEXT,
+            effects BOOLEAN NOT NULL DEFAULT TRUE,
+            game_duration TEXT,
+            language TEXT,
+            reduced_motion BOOLEAN NOT NULL DEFAULT FALSE,
+            hit_feedback TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS score_history (
+            id BIGSERIAL PRIMARY KEY,
+            user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            score BIGINT NOT NULL,
+            played_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS games (
+            id BIGSERIAL PRIMARY KEY,
+            user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            mode TEXT NOT NULL,
+            score BIGINT NOT NULL,
+            hits BIGINT NOT NULL,
+            misses BIGINT NOT NULL,
+            duration_ms BIGINT NOT NULL,
+            played_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE games ADD COLUMN IF NOT EXISTS reaction_ms BIGINT;
+
+        CREATE TABLE IF NOT EXISTS replays (
+            game_id BIGINT PRIMARY KEY REFERENCES games(id) ON DELETE CASCADE,
+            data BYTEA NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id BIGSERIAL PRIMARY KEY,
+            user_id BIGINT NOT NULL,
+            mode TEXT NOT NULL,
+            score BIGINT NOT NULL,
+            flagged_clicks BIGINT NOT NULL,
+            client TEXT NOT NULL,
+            verdict TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+        ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS game_id BIGINT;
+        ALTER TABLE audit_log ADD COLUMN IF NOT EXISTS reason TEXT;
+
+        CREATE TABLE IF NOT EXISTS check_events (
+            id BIGSERIAL PRIMARY KEY,
+            user_id BIGINT NOT NULL,
+            game_id BIGINT,
+            check_name TEXT NOT NULL,
+            value DOUBLE PRECISION,
+            threshold DOUBLE PRECISION,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS seasons (
+            id BIGSERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            starts_on DATE NOT NULL,
+            ends_on DATE NOT NULL,
+            archived_at TIMESTAMPTZ
+        );
+
+        CREATE TABLE IF NOT EXISTS season_scores (
+            season_id BIGINT NOT NULL REFERENCES seasons(id) ON DELETE CASCADE,
+            rank BIGINT NOT NULL,
+            user_id BIGINT,
+            username TEXT NOT NULL,
+            score BIGINT NOT NULL,
+            scored_at TIMESTAMPTZ,
+            PRIMARY KEY (season_id, rank)
+        );
+
+        CREATE TABLE IF NOT EXISTS user_keys (
+            fingerprint TEXT PRIMARY KEY,
+            user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            added_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS recovery_codes (
+            user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+            code_hash TEXT NOT NULL UNIQUE,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS link_codes (
+            code_hash TEXT PRIMARY KEY,
+            user_id BIGINT NOT NULL UNIQUE REFERENCES users(id) ON DELETE CASCADE,
+            expires_at BIGINT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS link_failures (
+            fingerprint TEXT NOT NULL,
+            failed_at BIGINT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS weekly_modifiers (
+            week TEXT PRIMARY KEY,
+            modifier TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS motd (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            message TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS banned_keys (
+            fingerprint TEXT PRIMARY KEY,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS uploads (
+            hash BYTEA PRIMARY KEY,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS duels (
+            id BIGSERIAL PRIMARY KEY,
+            player_a BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            player_b BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            score_a BIGINT NOT NULL,
+            score_b BIGINT NOT NULL,
+            winner BIGINT,
+            played_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        );
+
+        CREATE TABLE IF NOT EXISTS leases (
+            name TEXT PRIMARY KEY,
+            holder TEXT NOT NULL,
+            expires_at BIGINT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS cluster_events (
+            id BIGSERIAL PRIMARY KEY,
+            origin TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            fingerprint TEXT,
+            created_at BIGINT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_duels_players ON duels (player_a, player_b);
+        CREATE INDEX IF NOT EXISTS idx_link_failures ON link_failures (fingerprint, failed_at);
+        CREATE INDEX IF NOT EXISTS idx_score_history ON score_history (user_id, id DESC);
+        CREATE INDEX IF NOT EXISTS idx_games_user ON games (user_id, id DESC);
+        CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log (user_id, id DESC);
+        CREATE INDEX IF NOT EXISTS idx_check_events_check ON check_events (check_name, id DESC);
+        CREATE INDEX IF NOT EXISTS idx_daily_challenge ON daily_challenge (date, score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_daily ON user_stats (daily_high_score_at, daily_high_score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_weekly ON user_stats (weekly_high_score_at, weekly_high_score DESC);
+        CREATE INDEX IF NOT EXISTS idx_stats_high_score ON user_stats (high_score DESC);",
+    )?;
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use super::{AuditEntry, GameRecord, Repository, Submission, Verdict};
+use crate::replay::{Replay, ReplayEvent};
+use anyhow::Result;
+use std::time::Duration;
+
+/// Flagged runs with fewer refused hits aren't queued; a stray refusal or two
+/// is routine, and those runs stay on the boards.
+pub const REVIEW_MIN_FLAGGED_CLICKS: u32 = 3;
+
+/// What a reviewer needs to judge one held or flagged run.
+#[derive(Debug, Clone)]
+pub struct ReviewItem {
+    pub entry: AuditEntry,
+    pub game: Option<GameRecord>,
+    /// Only top-ranked and held runs keep their replay.
+    pub replay: Option<ReplaySummary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplaySummary {
+    pub duration: Duration,
+    pub targets: u32,
+    pub moves: u32,
+    pub clicks: u32,
+    pub hits: u32,
+}
+
+impl ReplaySummary {
+    pub fn of(replay: &Replay) -> Self {
+        let mut summary = Self {
+            duration: replay.duration(),
+            targets: 0,
+            moves: 0,
+            clicks: 0,
+            hits: 0,
+        };
+        for frame in &replay.frames {
+            match frame.event {
+                ReplayEvent::TargetSpawn(_) => summary.targets += 1,
+                ReplayEvent::MouseMove(_) => summary.moves += 1,
+                ReplayEvent::MouseClick(_) => summary.clicks += 1,
+                ReplayEvent::Score(_) => summary.hits += 1,
+                ReplayEvent::Resize(_) => {}
+            }
+        }
+        summary
+    }
+}
+
+impl Repository {
+    /// Held runs, and runs flagged `REVIEW_MIN_FLAGGED_CLICKS` times, that
+    /// nobody has decided on yet, oldest first.
+    pub fn review_queue(&self, limit: u32) -> Result<Vec<ReviewItem>> {
+        self.store
+            .get_review_queue(limit, REVIEW_MIN_FLAGGED_CLICKS)?
+            .into_iter()
+            .map(|entry| {
+                let game_id = entry.submission.game_id.unwrap_or_default();
+                Ok(ReviewItem {
+                    game: self.store.get_game(game_id)?,
+                    replay: self.get_replay(game_id)?.as_ref().map(ReplaySummary::of),
+                    entry,
+                })
+            })
+            .collect()
+    }
+
+    /// Approving a held run puts it on the boards; rejecting a flagged one
+    /// takes it off and costs the player trust. The decision is appended to
+    /// the audit log, which takes the run out of the queue.
+    pub fn review(&self, game_id: i64, approve: bool) -> Result<()> {
+        let entry = self
+            .store
+            .get_review_queue(u32::MAX, REVIEW_MIN_FLAGGED_CLICKS)?
+            .into_iter()
+            .find(|e| e.submission.game_id == Some(game_id))
+            .ok_or_else(|| anyhow::anyhow!("Game {game_id} is not awaiting review"))?;
+        let on_boards = entry.submission.verdict == Verdict::Flagged;
+
+        match (approve, on_boards) {
+            (true, false) => self.store.publish_game(game_id)?,
+            (false, true) => self.store.retract_game(game_id)?,
+            _ => {}
+        }
+        let verdict = if approve {
+            Verdict::Accepted
+        } else {
+            Verdict::Rejected
+        };
+        self.store.append_audit(
+            entry.user_id,
+            &Submission {
+                verdict,
+                reason: Some("review".to_string()),
+                ..entry.submission
+            },
+        )?;
+        if !approve {
+            self.update_trust(entry.user_id, Verdict::Rejected)?;
+        }
+        Ok(())
+    }
+}
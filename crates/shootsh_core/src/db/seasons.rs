@@ -0,0 +1,36 @@
+use super::Repository;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+
+impl Repository {
+    /// Schedules a season over `[starts_on, ends_on)`, both `%Y-%m-%d`. It
+    /// must end in the future and not overlap a season that is still open,
+    /// so the all-time board belongs to one season at a time.
+    pub fn create_season(&self, name: &str, starts_on: &str, ends_on: &str) -> Result<i64> {
+        let name = name.trim();
+        anyhow::ensure!(!name.is_empty(), "The season needs a name");
+        let date = |s: &str| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .with_context(|| format!("Expected a date as in 2026-07-01, got '{s}'"))
+        };
+        let (start, end) = (date(starts_on)?, date(ends_on)?);
+        anyhow::ensure!(start < end, "The season must end after it starts");
+        anyhow::ensure!(
+            end > chrono::Utc::now().date_naive(),
+            "The season must end after today"
+        );
+
+        for open in self.store.get_open_seasons()? {
+            if date(&open.starts_on)? < end && start < date(&open.ends_on)? {
+                anyhow::bail!(
+                    "Overlaps season '{}' ({} - {})",
+                    open.name,
+                    open.starts_on,
+                    open.ends_on
+                );
+            }
+        }
+        self.store
+            .create_season(name, &start.to_string(), &end.to_string())
+    }
+}
@@ -0,0 +1,1484 @@
+use super::{
+    ACTIVITY_HISTORY_DAYS, ActivityDay, AuditEntry, BEST_ACCURACY_MIN_SHOTS, CheckRecord,
+    ClientKind, ClusterEvent, ClusterRecord, Distribution, GameRecord, GameResult, GameStats,
+    HeadToHead, RankingPeriod, SCORE_HISTORY_LEN, ScoreEntry, ScoreStore, Season, ShadowedUser,
+    Submission, Trust, UserContext, UserRank, UsernameTaken, Verdict,
+};
+use crate::anticheat::{CheckEvent, Strictness};
+use crate::domain::{GameMode, WeeklyModifier};
+use crate::preferences::{
+    CursorStyle, GameDuration, HitFeedback, Language, PreferenceOption, Preferences, ThemeKind,
+};
+use crate::region::Region;
+use crate::replay::Replay;
+use anyhow::Result;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a write waits on a lock held by another process (e.g. admin tooling).
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Leaderboard queries are built per period, so keep room for all variants.
+const STATEMENT_CACHE_CAPACITY: usize = 64;
+
+/// Single-file database, the default backend.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    pub fn new(conn: Connection) -> Result<Self> {
+        // WAL lets readers proceed while the worker writes; NORMAL is durable
+        // enough under WAL and avoids an fsync per game.
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE_CAPACITY);
+
+        super::migrations::run(&conn)?;
+        Ok(Self { conn })
+    }
+
+    /// Active journal mode, e.g. `wal`. In-memory databases report `memory`.
+    pub fn journal_mode(&self) -> Result<String> {
+        Ok(self
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))?)
+    }
+
+    /// Unknown keys (e.g. from a newer build) fall back to defaults.
+    pub fn get_preferences(&self, user_id: i64) -> Result<Preferences> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT theme, cursor_style, effects, game_duration, IFNULL(language, ''),
+                    IFNULL(reduced_motion, 0), IFNULL(hit_feedback, '')
+                FROM user_preferences WHERE user_id = ?1",
+                params![user_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, bool>(2)?,
+                        row.get::<_, String>(3)?,
+                        row.get::<_, String>(4)?,
+                        row.get::<_, bool>(5)?,
+                        row.get::<_, String>(6)?,
+                    ))
+                },
+            )
+            .optional()?;
+
+        let defaults = Preferences::default();
+        let region: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT region FROM users WHERE id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        let region = region.as_deref().and_then(Region::parse);
+        Ok(match row {
+            Some((
+                theme,
+                cursor_style,
+                effects,
+                game_duration,
+                language,
+                reduced_motion,
+                hit_feedback,
+            )) => Preferences {
+                theme: ThemeKind::from_key(&theme).unwrap_or(defaults.theme),
+                cursor_style: CursorStyle::from_key(&cursor_style).unwrap_or(defaults.cursor_style),
+                effects,
+                game_duration: GameDuration::from_key(&game_duration)
+                    .unwrap_or(defaults.game_duration),
+                language: Language::from_key(&language).unwrap_or(defaults.language),
+                reduced_motion,
+                hit_feedback: HitFeedback::from_key(&hit_feedback).unwrap_or(defaults.hit_feedback),
+                region,
+            },
+            None => Preferences { region, ..defaults },
+        })
+    }
+
+    fn get_daily_challenge_scores(&self, limit: u32, offset: u32) -> Result<Vec<ScoreEntry>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT
+            u.username,
+            c.score,
+            strftime('%m-%d %H:%M', c.score_at),
+            u.region
+            FROM users u
+            JOIN daily_challenge c ON u.id = c.user_id
+            WHERE c.score > 0 AND c.date = DATE('now') AND NOT u.shadowed
+            ORDER BY c.score DESC
+            LIMIT ?1 OFFSET ?2",
+        )?;
+
+        let entries = stmt
+            .query_map(params![limit, offset], |row| {
+                Ok(ScoreEntry {
+                    name: row.get(0)?,
+                    score: row.get(1)?,
+                    created_at: row.get(2)?,
+                    region: region_column(row, 3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn get_best_replay(&self, user_id: i64) -> Result<Option<Replay>> {
+        let data: Option<Vec<u8>> = self
+            .conn
+            .query_row(
+                "SELECT data FROM best_replays WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        data.map(|d| Replay::from_bytes(&d)).transpose()
+    }
+
+    pub fn get_user_activity(&self, user_id: i64, days_limit: u32) -> Vec<ActivityDay> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "SELECT date, count FROM daily_activity 
+                WHERE user_id = ?1 AND date > DATE('now', '-' || ?2 || ' days')
+                ORDER BY date ASC",
+            )
+            .expect("Failed to prepare activity query");
+
+        stmt.query_map(params![user_id, days_limit], |row| {
+            Ok(ActivityDay {
+                date: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .expect("Query failed")
+        .filter_map(|r| r.ok())
+        .collect()
+    }
+
+    pub fn get_user_by_fingerprint(&self, fingerprint: &str) -> Result<Option<(i64, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT id, username FROM users WHERE fingerprint = ?1")?;
+
+        let mut rows = stmt.query(params![fingerprint])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some((row.get(0)?, row.get(1)?)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn push_score_history(&self, user_id: i64, score: u32) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO score_history (user_id, score) VALUES (?1, ?2)",
+            params![user_id, score],
+        )?;
+        self.conn.execute(
+            "DELETE FROM score_history
+            WHERE user_id = ?1 AND id NOT IN (
+                SELECT id FROM score_history WHERE user_id = ?1 ORDER BY id DESC LIMIT ?2
+            )",
+            params![user_id, SCORE_HISTORY_LEN as i64],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent scores, oldest first.
+    pub fn get_score_history(&self, user_id: i64, limit: usize) -> Result<Vec<u32>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT score FROM score_history
+            WHERE user_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2",
+        )?;
+        let mut scores = stmt
+            .query_map(params![user_id, limit as i64], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()?;
+        scores.reverse();
+        Ok(scores)
+    }
+}
+
+impl RankingPeriod {
+    /// (score column, date column, current date value, display format) in `user_stats`.
+    fn columns(self) -> (&'static str, &'static str, &'static str, &'static str) {
+        match self {
+            Self::Daily => (
+                "daily_high_score",
+                "daily_high_score_at",
+                "date('now')",
+                "%m-%d %H:%M",
+            ),
+            Self::Weekly => (
+                "weekly_high_score",
+                "weekly_high_score_at",
+                "strftime('%Y-%W', 'now')",
+                "%m-%d %H:%M",
+            ),
+            Self::AllTime | Self::DailyChallenge => {
+                ("high_score", "high_score_at", "NULL", "%Y-%m-%d")
+            }
+        }
+    }
+
+    /// Rows of `user_stats` (aliased as `alias`) that belong on this leaderboard.
+    fn filter(self, alias: &str) -> String {
+        let (score_col, date_col, date_val, _) = self.columns();
+        if let Self::AllTime = self {
+            format!("{alias}.{score_col} > 0")
+        } else {
+            format!("{alias}.{score_col} > 0 AND {alias}.{date_col} = {date_val}")
+        }
+    }
+}
+
+const GAME_COLUMNS: &str = "id, mode, score, hits, misses, duration_ms, reaction_ms,
+    strftime('%Y-%m-%d %H:%M', played_at)";
+
+fn game_record(row: &rusqlite::Row) -> rusqlite::Result<GameRecord> {
+    let mode: String = row.get(1)?;
+    Ok(GameRecord {
+        id: row.get(0)?,
+        mode: GameMode::from_key(&mode).unwrap_or_default(),
+        score: row.get(2)?,
+        hits: row.get(3)?,
+        misses: row.get(4)?,
+        duration_ms: ms_column(row, 5)?,
+        reaction_ms: optional_ms_column(row, 6)?,
+        played_at: row.get(7)?,
+    })
+}
+
+/// SQLite only stores signed integers; no millisecond count comes near the
+/// limit, but a corrupt row shouldn't wrap around either.
+fn sql_ms(ms: u64) -> i64 {
+    i64::try_from(ms).unwrap_or(i64::MAX)
+}
+
+fn ms_column(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<u64> {
+    let ms: i64 = row.get(idx)?;
+    u64::try_from(ms).map_err(|_| rusqlite::Error::IntegralValueOutOfRange(idx, ms))
+}
+
+fn optional_ms_column(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<Option<u64>> {
+    match row.get::<_, Option<i64>>(idx)? {
+        Some(_) => ms_column(row, idx).map(Some),
+        None => Ok(None),
+    }
+}
+
+const AUDIT_COLUMNS: &str = "a.id, a.user_id, u.username, a.mode, a.score, a.flagged_clicks,
+    a.client, a.verdict, a.created_at, a.game_id, a.reason";
+
+fn audit_entry(row: &rusqlite::Row) -> rusqlite::Result<AuditEntry> {
+    let mode: String = row.get(3)?;
+    let client: String = row.get(6)?;
+    let verdict: String = row.get(7)?;
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        user_id: row.get(1)?,
+        name: row.get(2)?,
+        submission: Submission {
+            game_id: row.get(9)?,
+            mode: GameMode::from_key(&mode).unwrap_or_default(),
+            score: row.get(4)?,
+            flagged_clicks: row.get(5)?,
+            client: ClientKind::from_key(&client).unwrap_or_default(),
+            verdict: Verdict::from_key(&verdict).unwrap_or(Verdict::Rejected),
+            reason: row.get(10)?,
+        },
+        created_at: row.get(8)?,
+    })
+}
+
+/// Games `g` whose latest audit verdict put them on the boards.
+const RANKED_GAME: &str = "(SELECT a.verdict FROM audit_log a WHERE a.game_id = g.id
+    ORDER BY a.id DESC LIMIT 1) IN ('accepted', 'flagged')";
+
+/// Rows of `alias` whose `user_id` isn't shadowed.
+fn visible(alias: &str) -> String {
+    format!("{alias}.user_id NOT IN (SELECT id FROM users WHERE shadowed)")
+}
+
+/// Unknown codes (e.g. removed from ISO 3166) are dropped rather than failing the query.
+fn region_column(row: &rusqlite::Row, idx: usize) -> rusqlite::Result<Option<Region>> {
+    let code: Option<String> = row.get(idx)?;
+    Ok(code.as_deref().and_then(Region::parse))
+}
+
+impl ScoreStore for SqliteStore {
+    fn find_user(&self, fingerprint: &str) -> Result<Option<UserContext>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT
+            u.id,
+            u.username,
+            IFNULL(s.high_score, 0),
+            IFNULL(s.total_hits, 0),
+            IFNULL(s.total_misses, 0),
+            IFNULL(s.sessions, 0),
+            IFNULL(s.best_combo, 0),
+            IFNULL(s.total_reaction_ms, 0),
+            IFNULL(s.reaction_samples, 0),
+            IFNULL(s.best_accuracy_bp, 0),
+            s.best_reaction_ms
+        FROM users u 
+        LEFT JOIN user_stats s ON u.id = s.user_id 
+        WHERE u.fingerprint = ?1
+            OR u.id = (SELECT user_id FROM user_keys WHERE fingerprint = ?1)",
+        )?;
+
+        let user = stmt
+            .query_row(params![fingerprint], |row| {
+                let id: i64 = row.get(0)?;
+                let user_activity = self.get_user_activity(id, ACTIVITY_HISTORY_DAYS);
+                let best_replay = self.get_best_replay(id).ok().flatten().map(Arc::new);
+                let preferences = self.get_preferences(id).unwrap_or_default();
+                let score_history = self
+                    .get_score_history(id, SCORE_HISTORY_LEN)
+                    .unwrap_or_default();
+
+                Ok(UserContext {
+                    id,
+                    fingerprint: fingerprint.to_string(),
+                    name: row.get(1)?,
+                    high_score: row.get(2)?,
+                    total_hits: row.get(3)?,
+                    total_misses: row.get(4)?,
+                    sessions: row.get(5)?,
+                    best_combo: row.get(6)?,
+                    user_activity,
+                    best_replay,
+                    preferences,
+                    reaction_total_ms: ms_column(row, 7)?,
+                    reaction_samples: row.get(8)?,
+                    score_history,
+                    best_accuracy_bp: row.get(9)?,
+                    best_reaction_ms: optional_ms_column(row, 10)?,
+                    strictness: Strictness::default(),
+                })
+            })
+            .optional()?;
+        Ok(user)
+    }
+
+    fn count_users(&self) -> Result<i64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?)
+    }
+
+    fn prune_inactive_users(&self, inactive_days: u32) -> Result<usize> {
+        let cutoff = format!("-{inactive_days} days");
+        let deleted = self.conn.execute(
+            "DELETE FROM users
+            WHERE id IN (
+                SELECT u.id FROM users u
+                LEFT JOIN user_stats s ON u.id = s.user_id
+                WHERE IFNULL(s.high_score, 0) = 0
+                AND u.created_at < DATETIME('now', ?1)
+                AND NOT EXISTS (
+                    SELECT 1 FROM daily_activity a
+                    WHERE a.user_id = u.id AND a.date >= DATE('now', ?1)
+                )
+            )",
+            params![cutoff],
+        )?;
+        Ok(deleted)
+    }
+
+    fn create_user(&self, fingerprint: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO users (fingerprint) VALUES (?1)",
+            params![fingerprint],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn update_username(&self, user_id: i64, name: &str) -> Result<()> {
+        match self.conn.execute(
+            "UPDATE users SET username = ?1 WHERE id = ?2",
+            params![name, user_id],
+        ) {
+            Ok(_) => Ok(()),
+            Err(e) if e.to_string().contains("UNIQUE") => Err(UsernameTaken.into()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn is_username_taken(&self, name: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM users WHERE username = ?1)",
+            params![name],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn delete_user(&self, user_id: i64) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM users WHERE id = ?1", params![user_id])?;
+        // a stale key row would shadow the fingerprint if it connects again
+        self.conn
+            .execute("DELETE FROM user_keys WHERE user_id = ?1", params![user_id])?;
+        self.set_recovery_code(user_id, None)?;
+        Ok(())
+    }
+
+    fn add_user_key(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO user_keys (fingerprint, user_id) VALUES (?1, ?2)",
+            params![fingerprint, user_id],
+        )?;
+        Ok(())
+    }
+
+    fn replace_user_keys(&self, user_id: i64, fingerprint: &str) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM user_keys WHERE user_id = ?1", params![user_id])?;
+        tx.execute(
+            "UPDATE users SET fingerprint = ?2 WHERE id = ?1",
+            params![user_id, fingerprint],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn set_recovery_code(&self, user_id: i64, code_hash: Option<&str>) -> Result<()> {
+        match code_hash {
+            Some(hash) => self.conn.execute(
+                "INSERT INTO recovery_codes (user_id, code_hash) VALUES (?1, ?2)
+                ON CONFLICT(user_id) DO UPDATE SET
+                    code_hash = ?2,
+                    created_at = DATETIME('now')",
+                params![user_id, hash],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM recovery_codes WHERE user_id = ?1",
+                params![user_id],
+            )?,
+        };
+        Ok(())
+    }
+
+    fn find_recovery_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT user_id FROM recovery_codes WHERE code_hash = ?1",
+                params![code_hash],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn put_link_code(&self, user_id: i64, code_hash: &str, ttl: Duration) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "DELETE FROM link_codes WHERE user_id = ?1 OR expires_at <= ?2",
+            params![user_id, now],
+        )?;
+        let stored = self.conn.execute(
+            "INSERT INTO link_codes (code_hash, user_id, expires_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT (code_hash) DO NOTHING",
+            params![code_hash, user_id, now + ttl.as_secs() as i64],
+        )?;
+        Ok(stored > 0)
+    }
+
+    fn take_link_code(&self, code_hash: &str) -> Result<Option<i64>> {
+        let code = self
+            .conn
+            .query_row(
+                "DELETE FROM link_codes WHERE code_hash = ?1 RETURNING user_id, expires_at",
+                params![code_hash],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+            )
+            .optional()?;
+        let now = chrono::Utc::now().timestamp();
+        Ok(code
+            .filter(|&(_, expires_at)| expires_at > now)
+            .map(|(id, _)| id))
+    }
+
+    fn link_failures(&self, fingerprint: &str, window: Duration) -> Result<u32> {
+        let since = chrono::Utc::now().timestamp() - window.as_secs() as i64;
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM link_failures WHERE fingerprint = ?1 AND failed_at > ?2",
+            params![fingerprint, since],
+            |row| row.get(0),
+        )?)
+    }
+
+    fn add_link_failure(&self, fingerprint: &str, window: Duration) -> Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        self.conn.execute(
+            "DELETE FROM link_failures WHERE failed_at <= ?1",
+            params![now - window.as_secs() as i64],
+        )?;
+        self.conn.execute(
+            "INSERT INTO link_failures (fingerprint, failed_at) VALUES (?1, ?2)",
+            params![fingerprint, now],
+        )?;
+        Ok(())
+    }
+
+    fn save_game(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO user_stats (
+                user_id, 
+                high_score, 
+                high_score_at,
+                daily_high_score,
+                daily_high_score_at,
+                weekly_high_score,
+                weekly_high_score_at,
+                total_hits, 
+                total_misses, 
+                sessions,
+                best_combo,
+                total_reaction_ms,
+                reaction_samples,
+                best_accuracy_bp,
+                best_reaction_ms
+            )
+            VALUES (?1, ?2, DATETIME('now'), ?2, DATE('now'), ?2, strftime('%Y-%W', 'now'), ?3, ?4, 1, ?5, ?6, ?7, ?8, ?9)
+            ON CONFLICT(user_id) DO UPDATE SET
+                -- all time
+                high_score_at = CASE 
+                    WHEN ?2 > high_score THEN DATETIME('now') 
+                    ELSE high_score_at 
+                END,
+                high_score = MAX(high_score, ?2),
+
+                -- daily.
+                daily_high_score = CASE 
+                    WHEN daily_high_score_at != DATE('now') THEN ?2
+                    ELSE MAX(daily_high_score, ?2)
+                END,
+                daily_high_score_at = DATE('now'),
+
+                -- weekly
+                weekly_high_score = CASE 
+                    WHEN weekly_high_score_at != strftime('%Y-%W', 'now') THEN ?2
+                    ELSE MAX(weekly_high_score, ?2)
+                END,
+                weekly_high_score_at = strftime('%Y-%W', 'now'),
+
+                total_hits = total_hits + ?3,
+                total_misses = total_misses + ?4,
+                sessions = sessions + 1,
+                best_combo = MAX(best_combo, ?5),
+                total_reaction_ms = total_reaction_ms + ?6,
+                reaction_samples = reaction_samples + ?7,
+                best_accuracy_bp = MAX(best_accuracy_bp, ?8),
+                -- SQLite's MIN() is NULL if either side is
+                best_reaction_ms = COALESCE(MIN(best_reaction_ms, ?9), best_reaction_ms, ?9)",
+            params![
+                user_id,
+                result.score,
+                result.hits,
+                result.misses,
+                result.best_combo,
+                sql_ms(result.reaction_total_ms),
+                result.reaction_samples,
+                result.accuracy_bp().unwrap_or(0),
+                result.average_reaction_ms().map(sql_ms)
+            ],
+        )?;
+
+        self.conn.execute(
+            "INSERT INTO daily_activity (user_id, date, count)
+            VALUES (?1, DATE('now'), 1)
+            ON CONFLICT(user_id, date) DO UPDATE SET
+                count = count + 1",
+            params![user_id],
+        )?;
+
+        self.push_score_history(user_id, result.score)
+    }
+
+    /// Daily challenge runs count towards lifetime stats and activity,
+    /// but rank only on the challenge board, never on the regular ones.
+    fn save_daily_challenge(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        self.save_practice(user_id, result)?;
+
+        self.conn.execute(
+            "INSERT INTO daily_challenge (user_id, date, score, score_at)
+            VALUES (?1, DATE('now'), ?2, DATETIME('now'))
+            ON CONFLICT(user_id, date) DO UPDATE SET
+                score_at = CASE
+                    WHEN ?2 > score THEN DATETIME('now')
+                    ELSE score_at
+                END,
+                score = MAX(score, ?2)",
+            params![user_id, result.score],
+        )?;
+
+        self.push_score_history(user_id, result.score)
+    }
+
+    /// Lifetime stats and activity only, no leaderboard entry.
+    fn save_practice(&self, user_id: i64, result: &GameResult) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO user_stats (
+                user_id, total_hits, total_misses, sessions, best_combo,
+                total_reaction_ms, reaction_samples, best_accuracy_bp, best_reaction_ms
+            )
+            VALUES (?1, ?2, ?3, 1, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(user_id) DO UPDATE SET
+                total_hits = total_hits + ?2,
+                total_misses = total_misses + ?3,
+                sessions = sessions + 1,
+                best_combo = MAX(best_combo, ?4),
+                total_reaction_ms = total_reaction_ms + ?5,
+                reaction_samples = reaction_samples + ?6,
+                best_accuracy_bp = MAX(best_accuracy_bp, ?7),
+                best_reaction_ms = COALESCE(MIN(best_reaction_ms, ?8), best_reaction_ms, ?8)",
+            params![
+                user_id,
+                result.hits,
+                result.misses,
+                result.best_combo,
+                sql_ms(result.reaction_total_ms),
+                result.reaction_samples,
+                result.accuracy_bp().unwrap_or(0),
+                result.average_reaction_ms().map(sql_ms)
+            ],
+        )?;
+
+        self.conn.execute(
+            "INSERT INTO daily_activity (user_id, date, count)
+            VALUES (?1, DATE('now'), 1)
+            ON CONFLICT(user_id, date) DO UPDATE SET
+                count = count + 1",
+            params![user_id],
+        )?;
+
+        Ok(())
+    }
+
+    /// Keeps only the highest-scoring run per user.
+    fn save_best_replay(&self, user_id: i64, score: u32, replay: &Replay) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO best_replays (user_id, score, data)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(user_id) DO UPDATE SET
+                score = excluded.score,
+                data = excluded.data,
+                created_at = DATETIME('now')
+            WHERE excluded.score > best_replays.score",
+            params![user_id, score, replay.to_bytes()],
+        )?;
+        Ok(())
+    }
+
+    fn save_preferences(&self, user_id: i64, prefs: &Preferences) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO user_preferences
+                (user_id, theme, cursor_style, effects, game_duration, language, reduced_motion,
+                hit_feedback)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            ON CONFLICT(user_id) DO UPDATE SET
+                theme = ?2,
+                cursor_style = ?3,
+                effects = ?4,
+                game_duration = ?5,
+                language = ?6,
+                reduced_motion = ?7,
+                hit_feedback = ?8",
+            params![
+                user_id,
+                prefs.theme.key(),
+                prefs.cursor_style.key(),
+                prefs.effects,
+                prefs.game_duration.key(),
+                prefs.language.key(),
+                prefs.reduced_motion,
+                prefs.hit_feedback.key()
+            ],
+        )?;
+        self.conn.execute(
+            "UPDATE users SET region = ?2 WHERE id = ?1",
+            params![user_id, prefs.region.map(|r| r.code())],
+        )?;
+        Ok(())
+    }
+
+    fn record_game(&self, user_id: i64, mode: GameMode, result: &GameResult) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO games (user_id, mode, score, hits, misses, duration_ms, reaction_ms)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                user_id,
+                mode.key(),
+                result.score,
+                result.hits,
+                result.misses,
+                sql_ms(result.duration_ms),
+                result.average_reaction_ms().map(sql_ms)
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn get_games(&self, user_id: i64, limit: u32) -> Result<Vec<GameRecord>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {GAME_COLUMNS}
+            FROM games
+            WHERE user_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2"
+        ))?;
+
+        let games = stmt
+            .query_map(params![user_id, limit], game_record)?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(games)
+    }
+
+    fn get_game(&self, game_id: i64) -> Result<Option<GameRecord>> {
+        let game = self
+            .conn
+            .query_row(
+                &format!("SELECT {GAME_COLUMNS} FROM games WHERE id = ?1"),
+                params![game_id],
+                game_record,
+            )
+            .optional()?;
+        Ok(game)
+    }
+
+    fn game_stats(&self, mode: GameMode, user_id: Option<i64>) -> Result<GameStats> {
+        // a plain `user_id = ?2` lets one player's games come off idx_games_user
+        let player = if user_id.is_some() {
+            "AND user_id = ?2"
+        } else {
+            "AND ?2 IS NULL"
+        };
+        let sql = format!(
+            "WITH g AS (
+                SELECT CAST(score AS REAL) AS score, CAST(reaction_ms AS REAL) AS reaction_ms,
+                    CASE WHEN hits + misses >= {BEST_ACCURACY_MIN_SHOTS}
+                        THEN hits * 10000.0 / (hits + misses) END AS accuracy_bp
+                FROM games
+                WHERE mode = ?1 {player}
+            )
+            SELECT COUNT(score), AVG(score), AVG(score * score),
+                COUNT(accuracy_bp), AVG(accuracy_bp), AVG(accuracy_bp * accuracy_bp),
+                COUNT(reaction_ms), AVG(reaction_ms), AVG(reaction_ms * reaction_ms)
+            FROM g"
+        );
+        let stats = self
+            .conn
+            .query_row(&sql, params![mode.key(), user_id], |row| {
+                let dist = |i: usize| -> rusqlite::Result<Distribution> {
+                    Ok(Distribution::from_moments(
+                        row.get(i)?,
+                        row.get::<_, Option<f64>>(i + 1)?.unwrap_or(0.0),
+                        row.get::<_, Option<f64>>(i + 2)?.unwrap_or(0.0),
+                    ))
+                };
+                Ok(GameStats {
+                    score: dist(0)?,
+                    accuracy_bp: dist(3)?,
+                    reaction_ms: dist(6)?,
+                })
+            })?;
+        Ok(stats)
+    }
+
+    fn save_replay(&self, game_id: i64, data: &[u8]) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO replays (game_id, data) VALUES (?1, ?2)",
+            params![game_id, data],
+        )?;
+        Ok(())
+    }
+
+    fn get_replay(&self, game_id: i64) -> Result<Option<Vec<u8>>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT data FROM replays WHERE game_id = ?1",
+                params![game_id],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    fn prune_replays(&self, min_score: u32) -> Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM replays WHERE game_id NOT IN (
+                SELECT id FROM games WHERE score >= ?1
+            )",
+            params![min_score],
+        )?)
+    }
+
+    fn append_audit(&self, user_id: i64, submission: &Submission) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO audit_log
+                (user_id, game_id, mode, score, flagged_clicks, client, verdict, reason)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                user_id,
+                submission.game_id,
+                submission.mode.key(),
+                submission.score,
+                submission.flagged_clicks,
+                submission.client.key(),
+                submission.verdict.key(),
+                submission.reason
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_audit_log(&self, user_id: Option<i64>, limit: u32) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {AUDIT_COLUMNS}
+            FROM audit_log a
+            LEFT JOIN users u ON u.id = a.user_id
+            WHERE ?1 IS NULL OR a.user_id = ?1
+            ORDER BY a.id DESC
+            LIMIT ?2"
+        ))?;
+
+        let entries = stmt
+            .query_map(params![user_id, limit], audit_entry)?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(entries)
+    }
+
+    fn append_check_events(
+        &self,
+        user_id: i64,
+        game_id: Option<i64>,
+        events: &[CheckEvent],
+    ) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO check_events (user_id, game_id, check_name, value, threshold)
+                VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for event in events {
+                stmt.execute(params![
+                    user_id,
+                    game_id,
+                    event.check,
+                    event.measurement.map(|m| m.value),
+                    event.measurement.map(|m| m.limit)
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_check_events(&self, check: Option<&str>, limit: u32) -> Result<Vec<CheckRecord>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, user_id, game_id, check_name, value, threshold, created_at
+            FROM check_events
+            WHERE ?1 IS NULL OR check_name = ?1
+            ORDER BY id DESC
+            LIMIT ?2",
+        )?;
+
+        let records = stmt
+            .query_map(params![check, limit], |row| {
+                Ok(CheckRecord {
+                    id: row.get(0)?,
+                    user_id: row.get(1)?,
+                    game_id: row.get(2)?,
+                    check: row.get(3)?,
+                    value: row.get(4)?,
+                    limit: row.get(5)?,
+                    created_at: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(records)
+    }
+
+    fn get_review_queue(&self, limit: u32, min_flagged_clicks: u32) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {AUDIT_COLUMNS}
+            FROM audit_log a
+            LEFT JOIN users u ON u.id = a.user_id
+            WHERE (a.verdict = 'held' OR (a.verdict = 'flagged' AND a.flagged_clicks >= ?2))
+                AND a.game_id IS NOT NULL
+                AND NOT EXISTS (
+                    SELECT 1 FROM audit_log r WHERE r.game_id = a.game_id AND r.id > a.id
+                )
+            ORDER BY a.id ASC
+            LIMIT ?1"
+        ))?;
+
+        let entries = stmt
+            .query_map(params![limit, min_flagged_clicks], audit_entry)?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(entries)
+    }
+
+    fn publish_game(&self, game_id: i64) -> Result<()> {
+        let (user_id, mode, score, played_at): (i64, String, u32, String) = self.conn.query_row(
+            "SELECT user_id, mode, score, played_at FROM games WHERE id = ?1",
+            params![game_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        let query = match GameMode::from_key(&mode) {
+            // daily and weekly entries only if the run still belongs to them
+            Some(GameMode::Standard) => {
+                "UPDATE user_stats SET
+                    high_score_at = CASE WHEN ?2 > high_score THEN ?3 ELSE high_score_at END,
+                    high_score = MAX(high_score, ?2),
+
+                    daily_high_score = CASE
+                        WHEN DATE(?3) != DATE('now') THEN daily_high_score
+                        WHEN daily_high_score_at != DATE('now') THEN ?2
+                        ELSE MAX(daily_high_score, ?2)
+                    END,
+                    daily_high_score_at = CASE
+                        WHEN DATE(?3) = DATE('now') THEN DATE('now')
+                        ELSE daily_high_score_at
+                    END,
+
+                    weekly_high_score = CASE
+                        WHEN strftime('%Y-%W', ?3) != strftime('%Y-%W', 'now') THEN weekly_high_score
+                        WHEN weekly_high_score_at != strftime('%Y-%W', 'now') THEN ?2
+                        ELSE MAX(weekly_high_score, ?2)
+                    END,
+                    weekly_high_score_at = CASE
+                        WHEN strftime('%Y-%W', ?3) = strftime('%Y-%W', 'now')
+                            THEN strftime('%Y-%W', 'now')
+                        ELSE weekly_high_score_at
+                    END
+                WHERE user_id = ?1"
+            }
+            Some(GameMode::DailyChallenge) => {
+                "INSERT INTO daily_challenge (user_id, date, score, score_at)
+                VALUES (?1, DATE(?3), ?2, ?3)
+                ON CONFLICT(user_id, date) DO UPDATE SET
+                    score_at = CASE WHEN ?2 > score THEN ?3 ELSE score_at END,
+                    score = MAX(score, ?2)"
+            }
+            _ => return Ok(()),
+        };
+        self.conn
+            .execute(query, params![user_id, score, played_at])?;
+        Ok(())
+    }
+
+    fn retract_game(&self, game_id: i64) -> Result<()> {
+        let flagged: bool = self.conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM audit_log WHERE game_id = ?1 AND verdict = 'flagged'
+            )",
+            params![game_id],
+            |row| row.get(0),
+        )?;
+        anyhow::ensure!(flagged, "Game {game_id} was never flagged");
+        let (user_id, mode, score): (i64, String, u32) = self.conn.query_row(
+            "SELECT user_id, mode, score FROM games WHERE id = ?1",
+            params![game_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let best = |period: &str| {
+            format!(
+                "(SELECT IFNULL(MAX(g.score), 0) FROM games g
+                WHERE g.user_id = ?1 AND g.mode = ?2 AND g.id != ?3 AND {RANKED_GAME}
+                    AND {period})"
+            )
+        };
+        let query = match GameMode::from_key(&mode) {
+            Some(GameMode::Standard) => format!(
+                "UPDATE user_stats SET
+                    high_score = CASE WHEN high_score = ?4 THEN {} ELSE high_score END,
+                    daily_high_score = CASE
+                        WHEN daily_high_score = ?4 AND daily_high_score_at = DATE('now') THEN {}
+                        ELSE daily_high_score
+                    END,
+                    weekly_high_score = CASE
+                        WHEN weekly_high_score = ?4
+                            AND weekly_high_score_at = strftime('%Y-%W', 'now') THEN {}
+                        ELSE weekly_high_score
+                    END
+                WHERE user_id = ?1",
+                // the all-time board restarts with every season
+                best("g.played_at >= (SELECT IFNULL(MAX(archived_at), '') FROM seasons)"),
+                best("DATE(g.played_at) = DATE('now')"),
+                best("strftime('%Y-%W', g.played_at) = strftime('%Y-%W', 'now')"),
+            ),
+            Some(GameMode::DailyChallenge) => format!(
+                "UPDATE daily_challenge SET score = {}
+                WHERE user_id = ?1 AND score = ?4
+                    AND date = (SELECT DATE(played_at) FROM games WHERE id = ?3)",
+                best("DATE(g.played_at) = daily_challenge.date"),
+            ),
+            _ => return Ok(()),
+        };
+        self.conn
+            .execute(&query, params![user_id, mode, game_id, score])?;
+        Ok(())
+    }
+
+    fn get_trust(&self, user_id: i64) -> Result<Option<Trust>> {
+        let trust = self
+            .conn
+            .query_row(
+                "SELECT trust, shadowed, warnings, clean_streak FROM users WHERE id = ?1",
+                params![user_id],
+                |row| {
+                    Ok(Trust {
+                        score: row.get(0)?,
+                        shadowed: row.get(1)?,
+                        warnings: row.get(2)?,
+                        clean_streak: row.get(3)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(trust)
+    }
+
+    fn set_trust(&self, user_id: i64, trust: Trust) -> Result<()> {
+        self.conn.execute(
+            "UPDATE users SET trust = ?2, shadowed = ?3, warnings = ?4, clean_streak = ?5
+             WHERE id = ?1",
+            params![
+                user_id,
+                trust.score,
+                trust.shadowed,
+                trust.warnings,
+                trust.clean_streak
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_shadowed_users(&self) -> Result<Vec<ShadowedUser>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, username, trust FROM users WHERE shadowed ORDER BY trust ASC, id ASC",
+        )?;
+        let users = stmt
+            .query_map([], |row| {
+                Ok(ShadowedUser {
+                    user_id: row.get(0)?,
+                    name: row.get(1)?,
+                    trust: row.get(2)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+        Ok(users)
+    }
+
+    fn get_top_scores(
+        &self,
+        period: RankingPeriod,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        if let RankingPeriod::DailyChallenge = period {
+            return self.get_daily_challenge_scores(limit, offset);
+        }
+
+        let (score_col, _, _, date_format) = period.columns();
+        let where_clause = format!("WHERE {} AND NOT u.shadowed", period.filter("s"));
+
+        let query = format!(
+            "SELECT 
+            u.username, 
+            s.{}, 
+            strftime('{}', s.high_score_at),
+            u.region
+            FROM users u
+            JOIN user_stats s ON u.id = s.user_id
+            {}
+            ORDER BY s.{} DESC
+            LIMIT ?1 OFFSET ?2",
+            score_col, date_format, where_clause, score_col
+        );
+
+        let mut stmt = self.conn.prepare_cached(&query)?;
+
+        let entries = stmt
+            .query_map(params![limit, offset], |row| {
+                Ok(ScoreEntry {
+                    name: row.get(0)?,
+                    score: row.get(1)?,
+                    created_at: row.get(2)?,
+                    region: region_column(row, 3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(entries)
+    }
+
+    /// `None` when the player has no score on that leaderboard yet.
+    fn get_user_rank(&self, user_id: i64, period: RankingPeriod) -> Result<Option<UserRank>> {
+        let query = if let RankingPeriod::DailyChallenge = period {
+            format!(
+                "SELECT c.score, 1 + (
+                    SELECT COUNT(*) FROM daily_challenge o
+                    WHERE o.date = c.date AND o.score > c.score AND {}
+                )
+                FROM daily_challenge c
+                WHERE c.user_id = ?1 AND c.score > 0 AND c.date = DATE('now')",
+                visible("o"),
+            )
+        } else {
+            let (score_col, _, _, _) = period.columns();
+            format!(
+                "SELECT s.{score_col}, 1 + (
+                    SELECT COUNT(*) FROM user_stats o
+                    WHERE {} AND o.{score_col} > s.{score_col} AND {}
+                )
+                FROM user_stats s
+                WHERE s.user_id = ?1 AND {}",
+                period.filter("o"),
+                visible("o"),
+                period.filter("s"),
+            )
+        };
+
+        let mut stmt = self.conn.prepare_cached(&query)?;
+        let rank = stmt
+            .query_row(params![user_id], |row| {
+                Ok(UserRank {
+                    score: row.get(0)?,
+                    rank: row.get(1)?,
+                })
+            })
+            .optional()?;
+        Ok(rank)
+    }
+
+    fn count_scores(&self, period: RankingPeriod, score: u32) -> Result<(u32, u32)> {
+        let query = if let RankingPeriod::DailyChallenge = period {
+            format!(
+                "SELECT IFNULL(SUM(c.score > ?1), 0), COUNT(*)
+                FROM daily_challenge c
+                WHERE c.score > 0 AND c.date = DATE('now') AND {}",
+                visible("c"),
+            )
+        } else {
+            let (score_col, _, _, _) = period.columns();
+            format!(
+                "SELECT IFNULL(SUM(s.{score_col} > ?1), 0), COUNT(*)
+                FROM user_stats s
+                WHERE {} AND {}",
+                period.filter("s"),
+                visible("s"),
+            )
+        };
+
+        let mut stmt = self.conn.prepare_cached(&query)?;
+        Ok(stmt.query_row(params![score], |row| Ok((row.get(0)?, row.get(1)?)))?)
+    }
+
+    /// Modifier scheduled for the current `%Y-%W` week, if the operator set one.
+    fn get_weekly_modifier(&self) -> Result<Option<WeeklyModifier>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT modifier FROM weekly_modifiers WHERE week = strftime('%Y-%W', 'now')",
+        )?;
+
+        let mut rows = stmt.query([])?;
+        if let Some(row) = rows.next()? {
+            let key: String = row.get(0)?;
+            Ok(WeeklyModifier::from_key(&key))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Schedules `modifier` for `week` (formatted as `%Y-%W`), or clears it.
+    fn set_weekly_modifier(&self, week: &str, modifier: Option<WeeklyModifier>) -> Result<()> {
+        match modifier {
+            Some(m) => self.conn.execute(
+                "INSERT INTO weekly_modifiers (week, modifier) VALUES (?1, ?2)
+                ON CONFLICT(week) DO UPDATE SET modifier = ?2",
+                params![week, m.key()],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM weekly_modifiers WHERE week = ?1",
+                params![week],
+            )?,
+        };
+        Ok(())
+    }
+
+    fn create_season(&self, name: &str, starts_on: &str, ends_on: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO seasons (name, starts_on, ends_on) VALUES (?1, ?2, ?3)",
+            params![name, starts_on, ends_on],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    fn archive_due_seasons(&self) -> Result<usize> {
+        let due = self
+            .conn
+            .prepare_cached(
+                "SELECT id FROM seasons
+                WHERE archived_at IS NULL AND starts_on <= DATE('now') AND ends_on <= DATE('now')
+                ORDER BY ends_on ASC",
+            )?
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+
+        // if several ended at once, the board belongs to the earliest and
+        // the rest are archived empty
+        let tx = self.conn.unchecked_transaction()?;
+        for season_id in &due {
+            tx.execute(
+                "INSERT INTO season_scores (season_id, rank, user_id, username, score, scored_at)
+                SELECT
+                    ?1,
+                    ROW_NUMBER() OVER (ORDER BY s.high_score DESC, s.high_score_at ASC),
+                    u.id,
+                    u.username,
+                    s.high_score,
+                    s.high_score_at
+                FROM users u
+                JOIN user_stats s ON u.id = s.user_id
+                WHERE s.high_score > 0 AND u.username IS NOT NULL AND NOT u.shadowed",
+                params![season_id],
+            )?;
+            tx.execute(
+                "UPDATE seasons SET archived_at = DATETIME('now') WHERE id = ?1",
+                params![season_id],
+            )?;
+            tx.execute(
+                "UPDATE user_stats SET high_score = 0, high_score_at = DATETIME('now')",
+                [],
+            )?;
+        }
+        tx.commit()?;
+        Ok(due.len())
+    }
+
+    fn get_open_seasons(&self) -> Result<Vec<Season>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, name, starts_on, ends_on FROM seasons
+            WHERE archived_at IS NULL
+            ORDER BY starts_on ASC",
+        )?;
+
+        let seasons = stmt
+            .query_map([], |row| {
+                Ok(Season {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    starts_on: row.get(2)?,
+                    ends_on: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(seasons)
+    }
+
+    fn get_past_seasons(&self) -> Result<Vec<Season>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, name, starts_on, ends_on FROM seasons
+            WHERE archived_at IS NOT NULL
+            ORDER BY ends_on DESC",
+        )?;
+
+        let seasons = stmt
+            .query_map([], |row| {
+                Ok(Season {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    starts_on: row.get(2)?,
+                    ends_on: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(seasons)
+    }
+
+    fn get_season_scores(
+        &self,
+        season_id: i64,
+        limit: u32,
+        offset: u32,
+    ) -> Result<Vec<ScoreEntry>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT username, score, strftime('%Y-%m-%d', scored_at), NULL
+            FROM season_scores
+            WHERE season_id = ?1
+            ORDER BY rank ASC
+            LIMIT ?2 OFFSET ?3",
+        )?;
+
+        let entries = stmt
+            .query_map(params![season_id, limit, offset], |row| {
+                Ok(ScoreEntry {
+                    name: row.get(0)?,
+                    score: row.get(1)?,
+                    created_at: row.get(2)?,
+                    region: region_column(row, 3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+
+        Ok(entries)
+    }
+
+    fn get_motd(&self) -> Result<Option<String>> {
+        let motd = self
+            .conn
+            .query_row("SELECT message FROM motd WHERE id = 1", [], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()?;
+        Ok(motd.filter(|m| !m.trim().is_empty()))
+    }
+
+    /// Replaces the message of the day; `None` or a blank message clears it.
+    fn set_motd(&self, message: Option<&str>) -> Result<()> {
+        match message.map(str::trim).filter(|m| !m.is_empty()) {
+            Some(m) => self.conn.execute(
+                "INSERT INTO motd (id, message) VALUES (1, ?1)
+                ON CONFLICT(id) DO UPDATE SET message = ?1",
+                params![m],
+            )?,
+            None => self.conn.execute("DELETE FROM motd", [])?,
+        };
+        Ok(())
+    }
+
+    fn get_banned_keys(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT fingerprint FROM banned_keys")?;
+        let keys = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+        Ok(keys)
+    }
+
+    fn set_key_banned(&self, fingerprint: &str, banned: bool) -> Result<()> {
+        if banned {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO banned_keys (fingerprint) VALUES (?1)",
+                params![fingerprint],
+            )?;
+        } else {
+            self.conn.execute(
+                "DELETE FROM banned_keys WHERE fingerprint = ?1",
+                params![fingerprint],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn record_upload(&self, hash: &[u8; 32]) -> Result<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO uploads (hash) VALUES (?1)",
+            params![&hash[..]],
+        )?;
+        Ok(inserted == 1)
+    }
+
+    fn record_duel(&self, players: [i64; 2], scores: [u32; 2], winner: Option<i64>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO duels (player_a, player_b, score_a, score_b, winner)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![players[0], players[1], scores[0], scores[1], winner],
+        )?;
+        Ok(())
+    }
+
+    fn get_head_to_head(&self, user_id: i64, opponent_id: i64) -> Result<HeadToHead> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT IFNULL(SUM(winner = ?1), 0), IFNULL(SUM(winner = ?2), 0),
+                IFNULL(SUM(winner IS NULL), 0)
+            FROM duels
+            WHERE (player_a = ?1 AND player_b = ?2) OR (player_a = ?2 AND player_b = ?1)",
+        )?;
+        Ok(stmt.query_row(params![user_id, opponent_id], |row| {
+            Ok(HeadToHead {
+                wins: row.get(0)?,
+                losses: row.get(1)?,
+                draws: row.get(2)?,
+            })
+        })?)
+    }
+
+    fn try_lease(&self, name: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = chrono::Utc::now().timestamp();
+        let taken = self.conn.execute(
+            "INSERT INTO leases (name, holder, expires_at) VALUES (?1, ?2, ?3)
+            ON CONFLICT (name) DO UPDATE SET holder = excluded.holder, expires_at = excluded.expires_at
+            WHERE leases.expires_at <= ?4",
+            params![name, holder, now + ttl.as_secs() as i64, now],
+        )?;
+        Ok(taken > 0)
+    }
+
+    fn publish_event(&self, origin: &str, event: &ClusterEvent) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO cluster_events (origin, kind, fingerprint, created_at)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![
+                origin,
+                event.kind(),
+                event.fingerprint(),
+                chrono::Utc::now().timestamp()
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn events_after(&self, after: i64) -> Result<Vec<ClusterRecord>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, origin, kind, fingerprint FROM cluster_events WHERE id > ?1 ORDER BY id",
+        )?;
+        let rows = stmt
+            .query_map(params![after], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, rusqlite::Error>>()?;
+        // kinds from a newer build are skipped
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, origin, kind, fp)| {
+                let event = ClusterEvent::from_parts(&kind, fp)?;
+                Some(ClusterRecord { id, origin, event })
+            })
+            .collect())
+    }
+
+    fn prune_events(&self, max_age: Duration) -> Result<usize> {
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+        Ok(self.conn.execute(
+            "DELETE FROM cluster_events WHERE created_at < ?1",
+            params![cutoff],
+        )?)
+    }
+}
@@ -0,0 +1,122 @@
+use super::Repository;
+use super::export::json_string;
+use crate::preferences::PreferenceOption;
+use anyhow::{Result, bail};
+use std::fmt::Write;
+
+impl Repository {
+    /// Everything stored about one player as a JSON document, for
+    /// data-portability requests. The fingerprint must match `user_id`.
+    pub fn export_user_data(&self, user_id: i64, fingerprint: &str) -> Result<String> {
+        let Some(user) = self
+            .store
+            .find_user(fingerprint)?
+            .filter(|u| u.id == user_id)
+        else {
+            bail!("User not found");
+        };
+        let games = self.store.get_games(user_id, u32::MAX)?;
+        let prefs = &user.preferences;
+
+        let mut out = String::new();
+        writeln!(out, "{{")?;
+        writeln!(
+            out,
+            "  \"exported_at\": {},",
+            json_string(&chrono::Utc::now().to_rfc3339())
+        )?;
+        writeln!(
+            out,
+            "  \"profile\": {{\"id\": {}, \"name\": {}, \"fingerprint\": {}}},",
+            user.id,
+            json_opt_string(user.name.as_deref()),
+            json_string(&user.fingerprint)
+        )?;
+        writeln!(
+            out,
+            "  \"stats\": {{\"high_score\": {}, \"total_hits\": {}, \"total_misses\": {}, \
+             \"sessions\": {}, \"best_combo\": {}, \"average_reaction_ms\": {}, \
+             \"best_accuracy_bp\": {}, \"best_reaction_ms\": {}}},",
+            user.high_score,
+            user.total_hits,
+            user.total_misses,
+            user.sessions,
+            user.best_combo,
+            json_opt(user.average_reaction_ms()),
+            user.best_accuracy_bp,
+            json_opt(user.best_reaction_ms)
+        )?;
+        writeln!(
+            out,
+            "  \"preferences\": {{\"theme\": {}, \"cursor_style\": {}, \"effects\": {}, \
+             \"game_duration\": {}, \"language\": {}, \"reduced_motion\": {}, \
+             \"hit_feedback\": {}, \"region\": {}}},",
+            json_string(prefs.theme.key()),
+            json_string(prefs.cursor_style.key()),
+            prefs.effects,
+            json_string(prefs.game_duration.key()),
+            json_string(prefs.language.key()),
+            prefs.reduced_motion,
+            json_string(prefs.hit_feedback.key()),
+            json_opt_string(prefs.region.map(|r| r.code()))
+        )?;
+
+        let history: Vec<String> = user.score_history.iter().map(u32::to_string).collect();
+        writeln!(out, "  \"score_history\": [{}],", history.join(", "))?;
+
+        let activity: Vec<String> = user
+            .user_activity
+            .iter()
+            .map(|day| {
+                format!(
+                    "\n    {{\"date\": {}, \"count\": {}}}",
+                    json_string(&day.date),
+                    day.count
+                )
+            })
+            .collect();
+        writeln!(out, "  \"activity\": [{}\n  ],", activity.join(","))?;
+
+        let games: Vec<String> = games
+            .iter()
+            .map(|game| {
+                format!(
+                    "\n    {{\"mode\": {}, \"score\": {}, \"hits\": {}, \"misses\": {}, \
+                     \"duration_ms\": {}, \"played_at\": {}}}",
+                    json_string(game.mode.key()),
+                    game.score,
+                    game.hits,
+                    game.misses,
+                    game.duration_ms,
+                    json_string(&game.played_at)
+                )
+            })
+            .collect();
+        writeln!(out, "  \"games\": [{}\n  ]", games.join(","))?;
+        writeln!(out, "}}")?;
+        Ok(out)
+    }
+}
+
+fn json_opt(value: Option<u64>) -> String {
+    value.map_or_else(|| "null".to_string(), |v| v.to_string())
+}
+
+fn json_opt_string(value: Option<&str>) -> String {
+    value.map_or_else(|| "null".to_string(), json_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_user_data_checks_owner() {
+        let repo = Repository::in_memory(10);
+        let user = repo.get_or_create_user_context("fp").unwrap();
+        let json = repo.export_user_data(user.id, "fp").unwrap();
+        assert!(json.contains("\"fingerprint\": \"fp\""));
+        assert!(json.contains("\"games\": [\n  ]"));
+        assert!(repo.export_user_data(user.id + 1, "fp").is_err());
+    }
+}
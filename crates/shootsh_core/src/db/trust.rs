@@ -0,0 +1,181 @@
+use super::{Repository, Verdict};
+use crate::anticheat::Strictness;
+use anyhow::Result;
+
+pub const MAX_TRUST: u32 = 100;
+
+/// How much the anticheat currently believes a player.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trust {
+    /// 0..=`MAX_TRUST`.
+    pub score: u32,
+    /// Kept off the public boards until an admin clears it; the player
+    /// still sees their own rank as if they were listed.
+    pub shadowed: bool,
+    /// Flagged or rejected runs since the last clean streak.
+    pub warnings: u32,
+    /// Clean runs in a row.
+    pub clean_streak: u32,
+}
+
+impl Default for Trust {
+    fn default() -> Self {
+        Self {
+            score: MAX_TRUST,
+            shadowed: false,
+            warnings: 0,
+            clean_streak: 0,
+        }
+    }
+}
+
+/// A shadowed player as listed for review.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowedUser {
+    pub user_id: i64,
+    pub name: Option<String>,
+    pub trust: u32,
+}
+
+/// How submissions move a player's trust.
+#[derive(Debug, Clone, Copy)]
+pub struct TrustPolicy {
+    /// Lost for a run with refused hits.
+    pub flag_penalty: u32,
+    /// Lost for a run that failed replay verification.
+    pub reject_penalty: u32,
+    /// Regained for every clean run.
+    pub clean_recovery: u32,
+    /// Falling below this shadows the player.
+    pub shadow_below: u32,
+    /// Warnings that turn on the full analysis for the player.
+    pub full_checks_after: u32,
+    /// Warnings after which every run's replay is verified.
+    pub replay_after: u32,
+    /// Clean runs in a row that clear the warnings.
+    pub clean_streak_reset: u32,
+}
+
+impl Default for TrustPolicy {
+    fn default() -> Self {
+        Self {
+            flag_penalty: 15,
+            reject_penalty: 40,
+            clean_recovery: 5,
+            shadow_below: 50,
+            full_checks_after: 1,
+            replay_after: 3,
+            clean_streak_reset: 5,
+        }
+    }
+}
+
+impl TrustPolicy {
+    /// Trust after a submission judged `verdict`. Recovering trust does not
+    /// lift a shadow; only a review does.
+    pub fn apply(&self, trust: Trust, verdict: Verdict) -> Trust {
+        let score = match verdict {
+            Verdict::Accepted => (trust.score + self.clean_recovery).min(MAX_TRUST),
+            Verdict::Flagged => trust.score.saturating_sub(self.flag_penalty),
+            Verdict::Rejected => trust.score.saturating_sub(self.reject_penalty),
+            // statistical holds are reviewed on their own
+            Verdict::Held => trust.score,
+        };
+        let (warnings, clean_streak) = match verdict {
+            Verdict::Accepted if trust.clean_streak + 1 >= self.clean_streak_reset => (0, 0),
+            Verdict::Accepted => (trust.warnings, trust.clean_streak + 1),
+            Verdict::Flagged | Verdict::Rejected => (trust.warnings + 1, 0),
+            Verdict::Held => (trust.warnings, trust.clean_streak),
+        };
+        Trust {
+            score,
+            shadowed: trust.shadowed || score < self.shadow_below,
+            warnings,
+            clean_streak,
+        }
+    }
+
+    pub fn strictness(&self, trust: &Trust) -> Strictness {
+        if trust.warnings >= self.replay_after {
+            Strictness::Replay
+        } else if trust.warnings >= self.full_checks_after {
+            Strictness::Full
+        } else {
+            Strictness::Baseline
+        }
+    }
+}
+
+impl Repository {
+    pub(super) fn update_trust(&self, user_id: i64, verdict: Verdict) -> Result<()> {
+        let Some(trust) = self.store.get_trust(user_id)? else {
+            return Ok(());
+        };
+        let updated = self.trust.apply(trust, verdict);
+        if updated != trust {
+            self.store.set_trust(user_id, updated)?;
+        }
+        Ok(())
+    }
+
+    /// How closely the player's next runs are watched.
+    pub fn strictness(&self, user_id: i64) -> Strictness {
+        self.store
+            .get_trust(user_id)
+            .ok()
+            .flatten()
+            .map_or(Strictness::default(), |trust| self.trust.strictness(&trust))
+    }
+
+    pub fn shadowed_users(&self) -> Result<Vec<ShadowedUser>> {
+        self.store.get_shadowed_users()
+    }
+
+    /// Puts a reviewed player back on the public boards with full trust.
+    pub fn clear_shadow(&self, user_id: i64) -> Result<()> {
+        anyhow::ensure!(
+            self.store.get_trust(user_id)?.is_some(),
+            "Unknown user {user_id}"
+        );
+        self.store.set_trust(user_id, Trust::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_shadow_until_reviewed() {
+        let policy = TrustPolicy::default();
+        let mut trust = Trust::default();
+        for _ in 0..4 {
+            trust = policy.apply(trust, Verdict::Flagged);
+        }
+        assert!(trust.shadowed);
+        for _ in 0..20 {
+            trust = policy.apply(trust, Verdict::Accepted);
+        }
+        assert_eq!(trust.score, MAX_TRUST);
+        assert!(trust.shadowed);
+    }
+
+    #[test]
+    fn test_warnings_escalate_until_a_clean_streak() {
+        let policy = TrustPolicy::default();
+        let mut trust = Trust::default();
+        assert_eq!(policy.strictness(&trust), Strictness::Baseline);
+        trust = policy.apply(trust, Verdict::Flagged);
+        assert_eq!(policy.strictness(&trust), Strictness::Full);
+        for _ in 0..2 {
+            trust = policy.apply(trust, Verdict::Flagged);
+        }
+        assert_eq!(policy.strictness(&trust), Strictness::Replay);
+        for _ in 0..4 {
+            trust = policy.apply(trust, Verdict::Accepted);
+        }
+        assert_eq!(policy.strictness(&trust), Strictness::Replay);
+        trust = policy.apply(trust, Verdict::Accepted);
+        assert_eq!(policy.strictness(&trust), Strictness::Baseline);
+    }
+}
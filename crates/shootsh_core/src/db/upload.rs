@@ -0,0 +1,194 @@
+use super::{GameResult, Repository};
+use crate::anticheat::{Link, verify_replay};
+use crate::domain::{CombatStats, GameConfig, Target};
+use crate::preferences::GameDuration;
+use crate::replay::{Replay, ReplayEvent};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::time::Duration;
+
+/// Frames logged just after the clock ran out still belong to the run.
+const LENGTH_GRACE: Duration = Duration::from_secs(1);
+
+impl GameResult {
+    /// Recounts a run from its replay alone, for uploads whose own numbers
+    /// aren't taken on trust. Targets are placed again from the replay's
+    /// seed, and each recorded spawn must be where the game put it. Each
+    /// `Score` frame only stands for a hit on the click before it, which must
+    /// land on the target last spawned; the scores the client wrote are left
+    /// for `verify_replay` to compare.
+    pub fn from_replay(replay: &Replay, config: &GameConfig) -> Result<Self, &'static str> {
+        let mut rng = StdRng::seed_from_u64(replay.seed.ok_or("no_seed")?);
+        let mut screen = None;
+        let mut stats = CombatStats::new();
+        let mut target: Option<(Target, u32)> = None;
+        // a click on the target that no score has followed yet
+        let mut on_target: Option<u32> = None;
+        for frame in &replay.frames {
+            match frame.event {
+                ReplayEvent::Resize(size) => screen = Some(size),
+                ReplayEvent::TargetSpawn(pos) => {
+                    let spawned =
+                        Target::new_random(screen.ok_or("spawn_mismatch")?, config, &mut rng);
+                    if spawned.pos != pos {
+                        return Err("spawn_mismatch");
+                    }
+                    if on_target.take().is_some() {
+                        stats.register_flagged();
+                    }
+                    // the last one ran out before anyone hit it
+                    if target.is_some() {
+                        stats.register_miss();
+                    }
+                    target = Some((spawned, frame.at_ms));
+                }
+                ReplayEvent::MouseMove(_) => {}
+                ReplayEvent::MouseClick(pos) => {
+                    // the anticheat refused the hit before this one
+                    if on_target.take().is_some() {
+                        stats.register_flagged();
+                    }
+                    match &target {
+                        Some((t, _)) if t.is_hit(pos.x, pos.y) => on_target = Some(frame.at_ms),
+                        _ => stats.register_miss(),
+                    }
+                }
+                ReplayEvent::Score(_) => {
+                    let clicked_at = on_target.take().ok_or("hit_off_target")?;
+                    let (_, spawned_at) = target.take().ok_or("hit_off_target")?;
+                    let reaction = clicked_at.saturating_sub(spawned_at);
+                    stats.record_reaction(Duration::from_millis(reaction.into()));
+                    stats.register_hit();
+                }
+            }
+        }
+        if on_target.is_some() {
+            stats.register_flagged();
+        }
+        Ok(Self {
+            duration_ms: replay.duration().as_millis() as u64,
+            ..Self::from_stats(&stats)
+        })
+    }
+}
+
+impl Repository {
+    /// The run as the server counts it, and why it can't be ranked, if it
+    /// can't. Unlike live runs, every upload goes through the anticheat,
+    /// however it places, and a run is only taken once, from anyone.
+    pub(super) fn check_upload(&self, replay: &Replay) -> (GameResult, Option<&'static str>) {
+        let fresh = self.store.record_upload(&replay.hash()).unwrap_or(true);
+        // offline runs are played without weekly modifiers
+        let config = GameConfig {
+            playing_time: GameDuration::default().as_duration(),
+            ..GameConfig::default()
+        };
+        let result = match GameResult::from_replay(replay, &config) {
+            Ok(result) if !fresh => return (result, Some("duplicate")),
+            Ok(result) => result,
+            Err(reason) => {
+                let result = GameResult {
+                    duration_ms: replay.duration().as_millis() as u64,
+                    ..GameResult::default()
+                };
+                return (result, Some(reason));
+            }
+        };
+        let reason = if replay.duration() > config.playing_time + LENGTH_GRACE {
+            Some("too_long")
+        } else if result.score > config.max_score(self.anticheat.min_reaction_time) {
+            Some("impossible_score")
+        } else {
+            // played offline, on the machine that recorded it
+            verify_replay(&self.anticheat, replay, result.score, Link::LOCAL).err()
+        };
+        (result, reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ClientKind, DbRequest, Verdict};
+    use crate::domain::{Point, Size};
+    use std::sync::Arc;
+
+    /// A replay of `seed` on an 80x24 screen up to its first spawn, and
+    /// where that target went.
+    fn started(seed: u64) -> (Replay, Point) {
+        let screen = Size {
+            width: 80,
+            height: 24,
+        };
+        let mut rng = StdRng::seed_from_u64(seed);
+        let pos = Target::new_random(screen, &GameConfig::default(), &mut rng).pos;
+        let mut replay = Replay {
+            seed: Some(seed),
+            ..Replay::default()
+        };
+        replay.record(Duration::ZERO, ReplayEvent::Resize(screen));
+        replay.record(Duration::ZERO, ReplayEvent::TargetSpawn(pos));
+        (replay, pos)
+    }
+
+    #[test]
+    fn test_upload_is_recounted_from_the_replay() {
+        let at = Duration::from_millis;
+        let (mut replay, pos) = started(7);
+        replay.record(at(100), ReplayEvent::MouseClick(Point { x: 0, y: 0 }));
+        replay.record(at(400), ReplayEvent::MouseClick(pos));
+        replay.record(at(400), ReplayEvent::Score(120));
+        let result = GameResult::from_replay(&replay, &GameConfig::default()).unwrap();
+        assert_eq!((result.score, result.hits, result.misses), (120, 1, 1));
+        assert_eq!(result.average_reaction_ms(), Some(400));
+
+        let repo = Repository::in_memory(10);
+        let user = repo.get_or_create_user_in("fp", None).unwrap();
+        let upload = |replay: Replay| {
+            let (reply_tx, mut reply_rx) = tokio::sync::oneshot::channel();
+            repo.handle_request(DbRequest::UploadGame {
+                user_id: user.id,
+                replay: Arc::new(replay),
+                reply_tx,
+            });
+            reply_rx.try_recv().unwrap()
+        };
+        let submission = upload(replay.clone());
+        assert_eq!(submission.client, ClientKind::Upload);
+        assert_eq!(submission.verdict, Verdict::Accepted);
+        let submission = upload(replay.clone());
+        assert_eq!(submission.reason.as_deref(), Some("duplicate"));
+
+        // the score the client wrote doesn't count, the hits do
+        let (mut forged, pos) = started(8);
+        forged.record(at(300), ReplayEvent::MouseClick(pos));
+        forged.record(at(300), ReplayEvent::Score(50_000));
+        let submission = upload(forged.clone());
+        assert_eq!(submission.reason.as_deref(), Some("score_mismatch"));
+        assert_eq!(submission.score, 120);
+
+        forged.record(at(600), ReplayEvent::MouseClick(Point { x: 0, y: 0 }));
+        forged.record(at(600), ReplayEvent::Score(50_100));
+        let submission = upload(forged);
+        assert_eq!(submission.reason.as_deref(), Some("hit_off_target"));
+
+        // targets go where the seed puts them, not where the client says
+        let (mut moved, pos) = started(9);
+        moved.frames.pop();
+        let beside = Point {
+            x: pos.x + 1,
+            ..pos
+        };
+        moved.record(at(0), ReplayEvent::TargetSpawn(beside));
+        let submission = upload(moved.clone());
+        assert_eq!(submission.reason.as_deref(), Some("spawn_mismatch"));
+        moved.seed = None;
+        let submission = upload(moved);
+        assert_eq!(submission.reason.as_deref(), Some("no_seed"));
+
+        replay.record(at(10 * 60 * 1000), ReplayEvent::MouseClick(pos));
+        let submission = upload(replay);
+        assert_eq!(submission.verdict, Verdict::Rejected);
+        assert_eq!(submission.reason.as_deref(), Some("too_long"));
+    }
+}
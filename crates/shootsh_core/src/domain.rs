@@ -1,3 +1,4 @@
+use rand::Rng;
 use std::time::{Duration, Instant};
 
 pub const MAX_PLAYER_NAME_LEN: usize = 15;
@@ -8,6 +9,187 @@ const INITIAL_MULTIPLIER: f64 = 1.0;
 const MAX_MULTIPLIER: f64 = 3.0;
 const DECAY_RATE: f64 = 0.95;
 const MAX_TARGET_LIFETIME_MS: u64 = 1800;
+/// (combo, flat bonus) awarded once per streak when the combo reaches it.
+pub const COMBO_MILESTONES: [(u32, u32); 3] = [(10, 500), (25, 1500), (50, 5000)];
+pub const DEFAULT_TARGET_WIDTH: u16 = 4;
+pub const DEFAULT_TARGET_HEIGHT: u16 = 2;
+pub const DEFAULT_HIT_MARGIN_X: u16 = 2;
+pub const DEFAULT_HIT_MARGIN_Y: u16 = 1;
+/// Largest target side, in cells; bigger ones wouldn't leave room to move.
+pub const MAX_TARGET_SIZE: u16 = 40;
+pub const MAX_HIT_MARGIN: u16 = 10;
+
+/// Tunables for a single run, built from defaults plus the active weekly modifier.
+#[derive(PartialEq, Clone, Debug)]
+pub struct GameConfig {
+    pub playing_time: Duration,
+    pub max_target_lifetime: Duration,
+    pub decay_rate: f64,
+    pub target_width: u16,
+    pub target_height: u16,
+    pub hit_margin_x: u16,
+    pub hit_margin_y: u16,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            playing_time: Duration::from_secs(PLAYING_TIME_SEC.into()),
+            max_target_lifetime: Duration::from_millis(MAX_TARGET_LIFETIME_MS),
+            decay_rate: DECAY_RATE,
+            target_width: DEFAULT_TARGET_WIDTH,
+            target_height: DEFAULT_TARGET_HEIGHT,
+            hit_margin_x: DEFAULT_HIT_MARGIN_X,
+            hit_margin_y: DEFAULT_HIT_MARGIN_Y,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Deployment-level target geometry; sizes are clamped to
+    /// 1..=`MAX_TARGET_SIZE` cells and margins to `MAX_HIT_MARGIN`.
+    pub fn with_target_geometry(
+        mut self,
+        width: u16,
+        height: u16,
+        margin_x: u16,
+        margin_y: u16,
+    ) -> Self {
+        self.target_width = width.clamp(1, MAX_TARGET_SIZE);
+        self.target_height = height.clamp(1, MAX_TARGET_SIZE);
+        self.hit_margin_x = margin_x.min(MAX_HIT_MARGIN);
+        self.hit_margin_y = margin_y.min(MAX_HIT_MARGIN);
+        self
+    }
+
+    pub fn with_modifier(mut self, modifier: Option<WeeklyModifier>) -> Self {
+        if let Some(m) = modifier {
+            m.apply(&mut self);
+        }
+        self
+    }
+
+    /// Highest score a run can reach: every target hit exactly `min_reaction`
+    /// after it spawns, without a miss, until time runs out or target
+    /// lifetimes decay below `min_reaction`.
+    pub fn max_score(&self, min_reaction: Duration) -> u32 {
+        let min_reaction = min_reaction.max(Duration::from_millis(1));
+        let mut stats = CombatStats::new();
+        let mut elapsed = min_reaction;
+        while elapsed <= self.playing_time && stats.get_target_lifetime(self) > min_reaction {
+            stats.register_hit();
+            elapsed += min_reaction;
+        }
+        stats.current_score()
+    }
+}
+
+/// Server-scheduled twist applied to every run during a given week. None
+/// changes the round length, so the week's runs rank with everyone else's.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WeeklyModifier {
+    TinyTargets,
+    DoubleDecay,
+    ShortFuse,
+}
+
+impl WeeklyModifier {
+    pub const ALL: [Self; 3] = [Self::TinyTargets, Self::DoubleDecay, Self::ShortFuse];
+
+    /// Stable identifier stored in the `weekly_modifiers` table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::TinyTargets => "tiny_targets",
+            Self::DoubleDecay => "double_decay",
+            Self::ShortFuse => "short_fuse",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.key() == key)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::TinyTargets => "TINY TARGETS WEEK",
+            Self::DoubleDecay => "DOUBLE DECAY WEEK",
+            Self::ShortFuse => "SHORT FUSE WEEK",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::TinyTargets => "Targets are half size",
+            Self::DoubleDecay => "Targets vanish twice as fast per hit",
+            Self::ShortFuse => "Targets start with a shorter lifetime",
+        }
+    }
+
+    pub fn apply(&self, config: &mut GameConfig) {
+        match self {
+            Self::TinyTargets => {
+                config.target_width = (config.target_width / 2).max(1);
+                config.target_height = (config.target_height / 2).max(1);
+                config.hit_margin_x = halve_margin(config.hit_margin_x);
+                config.hit_margin_y = halve_margin(config.hit_margin_y);
+            }
+            Self::DoubleDecay => config.decay_rate = config.decay_rate.powi(2),
+            Self::ShortFuse => config.max_target_lifetime = config.max_target_lifetime * 2 / 3,
+        }
+    }
+}
+
+/// Halves a hit margin on either axis alike: a one-cell margin survives, one
+/// the operator turned off stays off.
+fn halve_margin(margin: u16) -> u16 {
+    (margin / 2).max(margin.min(1))
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Default)]
+pub enum GameMode {
+    #[default]
+    Standard,
+    /// Seeded from the current UTC date, so everyone gets the same targets.
+    DailyChallenge,
+    /// Non-default round length; counts towards stats but never ranks.
+    Practice,
+}
+
+impl GameMode {
+    pub const ALL: [Self; 3] = [Self::Standard, Self::DailyChallenge, Self::Practice];
+
+    /// Stable identifier stored in the `games` table.
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::DailyChallenge => "daily_challenge",
+            Self::Practice => "practice",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|m| m.key() == key)
+    }
+
+    /// Seed of a round's target positions; the same for everyone on a given
+    /// day's challenge.
+    pub fn new_seed(&self) -> u64 {
+        match self {
+            Self::Standard | Self::Practice => rand::random(),
+            Self::DailyChallenge => daily_challenge_seed(chrono::Utc::now().date_naive()),
+        }
+    }
+}
+
+/// FNV-1a over the ISO date, stable across builds and platforms.
+pub fn daily_challenge_seed(date: chrono::NaiveDate) -> u64 {
+    date.format("%Y-%m-%d")
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+        })
+}
 
 #[derive(PartialEq, Clone, Copy, Debug, Default)]
 pub struct Size {
@@ -21,6 +203,55 @@ pub struct Point {
     pub y: u16,
 }
 
+/// Click positions of a single run, kept for the post-game heatmap.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Heatmap {
+    pub screen: Size,
+    pub hits: Vec<Point>,
+    pub misses: Vec<Point>,
+}
+
+impl Heatmap {
+    pub fn new(screen: Size) -> Self {
+        Self {
+            screen,
+            ..Default::default()
+        }
+    }
+
+    pub fn record(&mut self, pos: Point, is_hit: bool) {
+        if is_hit {
+            self.hits.push(pos);
+        } else {
+            self.misses.push(pos);
+        }
+    }
+
+    /// Buckets clicks into a `cols` x `rows` grid of (hits, misses), row-major.
+    pub fn grid(&self, cols: u16, rows: u16) -> Vec<Vec<(u32, u32)>> {
+        let mut grid = vec![vec![(0, 0); cols as usize]; rows as usize];
+        if cols == 0 || rows == 0 || self.screen.width == 0 || self.screen.height == 0 {
+            return grid;
+        }
+
+        let bucket = |pos: &Point| {
+            let gx = (pos.x as u32 * cols as u32 / self.screen.width as u32).min(cols as u32 - 1);
+            let gy = (pos.y as u32 * rows as u32 / self.screen.height as u32).min(rows as u32 - 1);
+            (gx as usize, gy as usize)
+        };
+
+        for pos in &self.hits {
+            let (gx, gy) = bucket(pos);
+            grid[gy][gx].0 += 1;
+        }
+        for pos in &self.misses {
+            let (gx, gy) = bucket(pos);
+            grid[gy][gx].1 += 1;
+        }
+        grid
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct MouseTrace {
     pub pos: Point,
@@ -36,33 +267,46 @@ impl MouseTrace {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct CombatStats {
     score: f64,
     combo: u32,
+    best_combo: u32,
     pub hit_count: u32,
     pub miss_count: u32,
+    pub reaction_total_ms: u64,
+    pub reaction_samples: u32,
+    /// Hits the anticheat refused; already counted as misses.
+    pub flagged_count: u32,
 }
 
 impl CombatStats {
     pub fn new() -> Self {
-        Self {
-            score: 0.0,
-            combo: 0,
-            hit_count: 0,
-            miss_count: 0,
-        }
+        Self::default()
+    }
+
+    /// Time from target spawn to the accepted hit.
+    pub fn record_reaction(&mut self, reaction: Duration) {
+        self.reaction_total_ms += reaction.as_millis() as u64;
+        self.reaction_samples += 1;
     }
 
-    /// FinalScore = SUM(HitValue * ComboMultiplier)
-    pub fn register_hit(&mut self) {
+    /// FinalScore = SUM(HitValue * ComboMultiplier) + SUM(MilestoneBonus)
+    ///
+    /// Returns the milestone combo if this hit reached one.
+    pub fn register_hit(&mut self) -> Option<u32> {
         self.hit_count += 1;
         self.combo += 1;
+        self.best_combo = self.best_combo.max(self.combo);
 
-        let raw_multiplier = INITIAL_MULTIPLIER + (self.combo as f64 * COMBO_MULTIPLIER_STEP);
-        let multiplier = raw_multiplier.min(MAX_MULTIPLIER);
+        self.score += BASE_HIT_VALUE * self.current_multiplier();
 
-        self.score += BASE_HIT_VALUE * multiplier;
+        let bonus = Self::milestone_bonus(self.combo);
+        if bonus == 0 {
+            return None;
+        }
+        self.score += bonus as f64;
+        Some(self.combo)
     }
 
     /// Reset combo
@@ -71,18 +315,44 @@ impl CombatStats {
         self.miss_count += 1;
     }
 
+    /// A hit the anticheat refused counts as a miss.
+    pub fn register_flagged(&mut self) {
+        self.register_miss();
+        self.flagged_count += 1;
+    }
+
     pub fn current_score(&self) -> u32 {
         self.score as u32
     }
 
     pub fn current_combo(&self) -> u32 {
-        self.combo as u32
+        self.combo
+    }
+
+    pub fn best_combo(&self) -> u32 {
+        self.best_combo
+    }
+
+    pub fn current_multiplier(&self) -> f64 {
+        (INITIAL_MULTIPLIER + self.combo as f64 * COMBO_MULTIPLIER_STEP).min(MAX_MULTIPLIER)
+    }
+
+    /// How far the multiplier is from its base (0.0) to the cap (1.0).
+    pub fn multiplier_progress(&self) -> f64 {
+        (self.current_multiplier() - INITIAL_MULTIPLIER) / (MAX_MULTIPLIER - INITIAL_MULTIPLIER)
+    }
+
+    pub fn milestone_bonus(combo: u32) -> u32 {
+        COMBO_MILESTONES
+            .into_iter()
+            .find(|(c, _)| *c == combo)
+            .map_or(0, |(_, bonus)| bonus)
     }
 
     /// T_lifetime = T_max_life * (DecayRate)^Hits
-    pub fn get_target_lifetime(&self) -> Duration {
-        let decay = DECAY_RATE.powi(self.hit_count as i32);
-        let millis = MAX_TARGET_LIFETIME_MS as f64 * decay;
+    pub fn get_target_lifetime(&self, config: &GameConfig) -> Duration {
+        let decay = config.decay_rate.powi(self.hit_count as i32);
+        let millis = config.max_target_lifetime.as_millis() as f64 * decay;
         Duration::from_millis(millis as u64)
     }
 }
@@ -97,44 +367,52 @@ pub struct Target {
 }
 
 impl Target {
-    const DEFAULT_VISUAL_WIDTH: u16 = 4;
-    const DEFAULT_VISUAL_HEIGHT: u16 = 2;
-    const DEFAULT_HIT_MARGIN_X: u16 = 2;
-    const DEFAULT_HIT_MARGIN_Y: u16 = 1;
     const MIN_PADDING: u16 = 2;
 
-    pub fn new_random(screen: Size) -> Self {
-        use rand::Rng;
-        let mut rng = rand::rng();
+    /// Positions are drawn as fractions of the free area, so a seeded
+    /// sequence lands on the same relative spots regardless of screen size.
+    pub fn new_random<R: Rng + ?Sized>(screen: Size, config: &GameConfig, rng: &mut R) -> Self {
+        let frac_x: f64 = rng.random();
+        let frac_y: f64 = rng.random();
+        Self::at_fraction(screen, config, frac_x, frac_y)
+    }
 
-        let total_w = Self::DEFAULT_VISUAL_WIDTH;
-        let total_h = Self::DEFAULT_VISUAL_HEIGHT;
+    /// Places the target at a fixed relative spot of the free area.
+    pub fn at_fraction(screen: Size, config: &GameConfig, frac_x: f64, frac_y: f64) -> Self {
+        let needed_w = config.target_width.saturating_add(Self::MIN_PADDING * 2);
+        let needed_h = config.target_height.saturating_add(Self::MIN_PADDING * 2);
 
-        if screen.width <= total_w + Self::MIN_PADDING * 2
-            || screen.height <= total_h + Self::MIN_PADDING * 2
-        {
-            return Self::fallback();
+        if screen.width <= needed_w || screen.height <= needed_h {
+            return Self::fallback(config);
         }
 
+        let span_x = screen.width - needed_w;
+        let span_y = screen.height - needed_h;
+
         Self {
             pos: Point {
-                x: rng.random_range(Self::MIN_PADDING..screen.width - total_w - Self::MIN_PADDING),
-                y: rng.random_range(Self::MIN_PADDING..screen.height - total_h - Self::MIN_PADDING),
+                x: Self::MIN_PADDING + (frac_x * span_x as f64) as u16,
+                y: Self::MIN_PADDING + (frac_y * span_y as f64) as u16,
             },
-            visual_width: Self::DEFAULT_VISUAL_WIDTH,
-            visual_height: Self::DEFAULT_VISUAL_HEIGHT,
-            hit_margin_x: Self::DEFAULT_HIT_MARGIN_X,
-            hit_margin_y: Self::DEFAULT_HIT_MARGIN_Y,
+            visual_width: config.target_width,
+            visual_height: config.target_height,
+            hit_margin_x: config.hit_margin_x,
+            hit_margin_y: config.hit_margin_y,
         }
     }
 
-    fn fallback() -> Self {
+    fn fallback(config: &GameConfig) -> Self {
+        Self::at(Point { x: 0, y: 0 }, config)
+    }
+
+    /// A target of `config`'s size at a known spot, e.g. one from a replay.
+    pub fn at(pos: Point, config: &GameConfig) -> Self {
         Self {
-            pos: Point { x: 0, y: 0 },
-            visual_width: Self::DEFAULT_VISUAL_WIDTH,
-            visual_height: Self::DEFAULT_VISUAL_HEIGHT,
-            hit_margin_x: Self::DEFAULT_HIT_MARGIN_X,
-            hit_margin_y: Self::DEFAULT_HIT_MARGIN_Y,
+            pos,
+            visual_width: config.target_width,
+            visual_height: config.target_height,
+            hit_margin_x: config.hit_margin_x,
+            hit_margin_y: config.hit_margin_y,
         }
     }
 
@@ -162,7 +440,161 @@ impl Target {
         x >= left_edge && x < right_edge
     }
 
-    pub fn is_expired(&self, elapsed: Duration, stats: &CombatStats) -> bool {
-        elapsed >= stats.get_target_lifetime()
+    pub fn is_expired(&self, elapsed: Duration, stats: &CombatStats, config: &GameConfig) -> bool {
+        elapsed >= stats.get_target_lifetime(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_max_score_stops_when_targets_outpace_reactions() {
+        // 1800ms * 0.95^n drops to 100ms after 57 hits, well within 15s
+        let mut stats = CombatStats::new();
+        for _ in 0..57 {
+            stats.register_hit();
+        }
+        let config = GameConfig::default();
+        assert_eq!(
+            config.max_score(Duration::from_millis(100)),
+            stats.current_score()
+        );
+        // a slower floor runs out of time first
+        assert!(config.max_score(Duration::from_secs(5)) < 1000);
+    }
+
+    #[test]
+    fn test_daily_challenge_sequence_is_shared() {
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let screen = Size {
+            width: 80,
+            height: 24,
+        };
+        let config = GameConfig::default();
+        let mut a = StdRng::seed_from_u64(daily_challenge_seed(date));
+        let mut b = StdRng::seed_from_u64(daily_challenge_seed(date));
+
+        for _ in 0..10 {
+            assert_eq!(
+                Target::new_random(screen, &config, &mut a),
+                Target::new_random(screen, &config, &mut b)
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversized_geometry_is_clamped() {
+        let config = GameConfig::default().with_target_geometry(u16::MAX, 0, u16::MAX, 3);
+        assert_eq!(
+            (config.target_width, config.target_height),
+            (MAX_TARGET_SIZE, 1)
+        );
+        assert_eq!(
+            (config.hit_margin_x, config.hit_margin_y),
+            (MAX_HIT_MARGIN, 3)
+        );
+
+        // even unclamped, placement doesn't overflow
+        let huge = GameConfig {
+            target_width: u16::MAX,
+            ..GameConfig::default()
+        };
+        let screen = Size {
+            width: u16::MAX,
+            height: 24,
+        };
+        assert_eq!(
+            Target::at_fraction(screen, &huge, 1.0, 1.0).pos,
+            Point { x: 0, y: 0 }
+        );
+    }
+
+    #[test]
+    fn test_daily_challenge_seed_changes_daily() {
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let tomorrow = today.succ_opt().unwrap();
+        assert_ne!(daily_challenge_seed(today), daily_challenge_seed(tomorrow));
+    }
+
+    #[test]
+    fn test_weekly_modifier_key_roundtrip() {
+        for m in WeeklyModifier::ALL {
+            assert_eq!(WeeklyModifier::from_key(m.key()), Some(m));
+        }
+        assert_eq!(WeeklyModifier::from_key("unknown"), None);
+    }
+
+    #[test]
+    fn test_tiny_targets_keep_both_margins() {
+        let mut config = GameConfig::default().with_target_geometry(4, 2, 2, 1);
+        WeeklyModifier::TinyTargets.apply(&mut config);
+        assert_eq!((config.hit_margin_x, config.hit_margin_y), (1, 1));
+
+        let mut config = GameConfig::default().with_target_geometry(4, 2, 0, 0);
+        WeeklyModifier::TinyTargets.apply(&mut config);
+        assert_eq!((config.hit_margin_x, config.hit_margin_y), (0, 0));
+    }
+
+    #[test]
+    fn test_game_mode_key_roundtrip() {
+        for m in GameMode::ALL {
+            assert_eq!(GameMode::from_key(m.key()), Some(m));
+        }
+        assert_eq!(GameMode::from_key("unknown"), None);
+    }
+
+    #[test]
+    fn test_combo_milestone_bonus() {
+        let mut stats = CombatStats::new();
+        for _ in 0..9 {
+            assert_eq!(stats.register_hit(), None);
+        }
+        let before = stats.score;
+        assert_eq!(stats.register_hit(), Some(10));
+        // 10th hit is at the 3.0x cap: 300 + 500 bonus
+        assert!((stats.score - before - 800.0).abs() < 1e-6);
+
+        stats.register_miss();
+        assert_eq!(stats.best_combo(), 10);
+        assert_eq!(stats.current_combo(), 0);
+    }
+
+    #[test]
+    fn test_heatmap_grid_buckets() {
+        let mut heatmap = Heatmap::new(Size {
+            width: 80,
+            height: 24,
+        });
+        heatmap.record(Point { x: 0, y: 0 }, true);
+        heatmap.record(Point { x: 79, y: 23 }, false);
+        heatmap.record(Point { x: 78, y: 22 }, false);
+
+        let grid = heatmap.grid(4, 2);
+        assert_eq!(grid[0][0], (1, 0));
+        assert_eq!(grid[1][3], (0, 2));
+    }
+
+    #[test]
+    fn test_multiplier_progress_caps() {
+        let mut stats = CombatStats::new();
+        assert_eq!(stats.multiplier_progress(), 0.0);
+
+        for _ in 0..5 {
+            stats.register_hit();
+        }
+        assert!((stats.current_multiplier() - 2.0).abs() < 1e-9);
+        assert!((stats.multiplier_progress() - 0.5).abs() < 1e-9);
+
+        for _ in 0..20 {
+            stats.register_hit();
+        }
+        assert_eq!(stats.multiplier_progress(), 1.0);
+
+        stats.register_miss();
+        assert_eq!(stats.current_multiplier(), 1.0);
     }
 }
@@ -0,0 +1,132 @@
+use crate::db::HeadToHead;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuelOutcome {
+    Won,
+    Lost,
+    Draw,
+}
+
+/// Live scores of both sides of a duel, shared by the two sessions and the
+/// lobby that paired them. Sides are 0 and 1.
+#[derive(Debug, Default)]
+pub struct DuelBoard {
+    scores: [AtomicU32; 2],
+    finished: [AtomicBool; 2],
+    forfeited: [AtomicBool; 2],
+}
+
+impl DuelBoard {
+    pub fn set_score(&self, side: usize, score: u32) {
+        self.scores[side].store(score, Ordering::Relaxed);
+    }
+
+    pub fn score(&self, side: usize) -> u32 {
+        self.scores[side].load(Ordering::Relaxed)
+    }
+
+    /// Marks the side's round as played out; its score is final.
+    pub fn finish(&self, side: usize) {
+        self.finished[side].store(true, Ordering::Release);
+    }
+
+    /// The side left before the round ended and loses whatever the scores.
+    pub fn forfeit(&self, side: usize) {
+        self.forfeited[side].store(true, Ordering::Relaxed);
+        self.finish(side);
+    }
+
+    pub fn is_finished(&self, side: usize) -> bool {
+        self.finished[side].load(Ordering::Acquire)
+    }
+
+    /// `None` until both sides are done.
+    pub fn outcome(&self, side: usize) -> Option<DuelOutcome> {
+        let other = 1 - side;
+        if !self.is_finished(side) || !self.is_finished(other) {
+            return None;
+        }
+        let forfeited = |s: usize| self.forfeited[s].load(Ordering::Relaxed);
+        let outcome = match (forfeited(side), forfeited(other)) {
+            (true, false) => DuelOutcome::Lost,
+            (false, true) => DuelOutcome::Won,
+            (true, true) => DuelOutcome::Draw,
+            (false, false) => match self.score(side).cmp(&self.score(other)) {
+                std::cmp::Ordering::Greater => DuelOutcome::Won,
+                std::cmp::Ordering::Less => DuelOutcome::Lost,
+                std::cmp::Ordering::Equal => DuelOutcome::Draw,
+            },
+        };
+        Some(outcome)
+    }
+}
+
+/// One player's side of a matched duel. Both players get the same seed, so
+/// they face the same sequence of targets.
+#[derive(Debug, Clone)]
+pub struct DuelLink {
+    pub opponent: String,
+    pub seed: u64,
+    pub side: usize,
+    pub board: Arc<DuelBoard>,
+    /// Past duels against this opponent.
+    pub record: HeadToHead,
+    /// Both rounds start together after a short countdown.
+    pub starts_at: Instant,
+}
+
+impl DuelLink {
+    pub fn opponent_score(&self) -> u32 {
+        self.board.score(1 - self.side)
+    }
+
+    pub fn outcome(&self) -> Option<DuelOutcome> {
+        self.board.outcome(self.side)
+    }
+
+    pub fn starts_in(&self) -> Duration {
+        self.starts_at.saturating_duration_since(Instant::now())
+    }
+}
+
+impl PartialEq for DuelLink {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.board, &other.board) && self.side == other.side
+    }
+}
+
+/// A player waiting for an opponent; the lobby answers on `reply_tx`, and
+/// skips tickets whose player has given up waiting.
+#[derive(Debug)]
+pub struct DuelTicket {
+    pub user_id: i64,
+    pub name: String,
+    pub reply_tx: oneshot::Sender<DuelLink>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_waits_for_both_and_forfeit_loses() {
+        let board = DuelBoard::default();
+        board.set_score(0, 10);
+        board.set_score(1, 20);
+        board.finish(1);
+        assert_eq!(board.outcome(0), None);
+        board.finish(0);
+        assert_eq!(board.outcome(0), Some(DuelOutcome::Lost));
+        assert_eq!(board.outcome(1), Some(DuelOutcome::Won));
+
+        let board = DuelBoard::default();
+        board.set_score(1, 20);
+        board.finish(0);
+        board.forfeit(1);
+        assert_eq!(board.outcome(0), Some(DuelOutcome::Won));
+    }
+}
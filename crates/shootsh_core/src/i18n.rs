@@ -0,0 +1,626 @@
+use crate::preferences::Language;
+use std::fmt::Display;
+
+/// Every user-facing string rendered by `ui`. Templates use `{}` placeholders
+/// filled in order by [`fill`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Messages {
+    pub terminal_too_small: &'static str,
+    pub cheat_warning: &'static str,
+    pub cheat_discarded: &'static str,
+    pub idle_warning: &'static str,
+    /// Seconds left before an idle session is closed.
+    pub idle_countdown: &'static str,
+    pub announcement_title: &'static str,
+    pub announcement_close: &'static str,
+
+    pub help_title: &'static str,
+    pub help_close: &'static str,
+    /// One entry per row of the help popup, in display order.
+    pub help: [&'static str; 15],
+
+    pub menu: &'static str,
+    pub quit: &'static str,
+    pub restart: &'static str,
+    pub retry: &'static str,
+    pub submit: &'static str,
+    pub select: &'static str,
+    pub change: &'static str,
+    pub save_and_back: &'static str,
+    pub tab: &'static str,
+    pub page: &'static str,
+    pub seasons: &'static str,
+    pub season_title: &'static str,
+    pub cancel: &'static str,
+    pub skip_tutorial: &'static str,
+    pub confirm_reset: &'static str,
+    pub daily: &'static str,
+    pub duel: &'static str,
+    pub forfeit: &'static str,
+    pub replay: &'static str,
+    pub profile: &'static str,
+    pub prefs: &'static str,
+    pub ranking: &'static str,
+    pub help_short: &'static str,
+    pub export_data: &'static str,
+    pub export_failed: &'static str,
+
+    pub stats_title: &'static str,
+    pub profile_title: &'static str,
+    pub sessions: &'static str,
+    pub high_score: &'static str,
+    pub hits: &'static str,
+    pub misses: &'static str,
+    pub accuracy: &'static str,
+    pub best_combo: &'static str,
+    pub avg_reaction: &'static str,
+    pub best_accuracy: &'static str,
+    pub best_reaction: &'static str,
+    pub last_games: &'static str,
+
+    pub welcome: &'static str,
+    pub enter_name: &'static str,
+    pub enter_link_code: &'static str,
+    pub link_account: &'static str,
+    pub link_device: &'static str,
+    pub link_code_line: &'static str,
+    pub recovery_code: &'static str,
+    pub recovery_code_line: &'static str,
+    pub recovery_code_hint: &'static str,
+    pub saving: &'static str,
+    pub please_wait: &'static str,
+    pub press_enter: &'static str,
+
+    pub click_to_start: &'static str,
+    pub space_to_start: &'static str,
+    pub keyboard_aim_hint: &'static str,
+    pub high_score_line: &'static str,
+    pub motd_title: &'static str,
+
+    pub hud: &'static str,
+    pub hud_daily: &'static str,
+    pub hud_practice: &'static str,
+    pub hud_ghost: &'static str,
+    pub hud_watching: &'static str,
+    pub hud_duel: &'static str,
+    pub hud_opponent: &'static str,
+    pub duel_searching: &'static str,
+    pub duel_found: &'static str,
+    pub duel_starts_in: &'static str,
+    pub duel_record: &'static str,
+    pub duel_rules: &'static str,
+    pub duel_waiting: &'static str,
+    pub duel_won: &'static str,
+    pub duel_lost: &'static str,
+    pub duel_draw: &'static str,
+    pub chat_hint: &'static str,
+    pub chat_prompt: &'static str,
+    pub chat_too_fast: &'static str,
+    pub chat_offensive: &'static str,
+    pub demo_header: &'static str,
+    pub demo_hint: &'static str,
+    pub any_key: &'static str,
+    pub milestone: &'static str,
+
+    pub tutorial_aim_title: &'static str,
+    pub tutorial_aim: [&'static str; 2],
+    pub tutorial_shoot_title: &'static str,
+    pub tutorial_shoot: [&'static str; 2],
+    pub tutorial_combo_title: &'static str,
+    pub tutorial_combo: [&'static str; 4],
+
+    pub calibration_title: &'static str,
+    pub calibration_prompt: &'static str,
+    pub calibration_offset: &'static str,
+    pub verify_title: &'static str,
+    pub verify_prompt: &'static str,
+    pub verify_progress: &'static str,
+
+    pub settings_title: &'static str,
+    /// Labels for `SettingsField::ALL`, in the same order.
+    pub settings_fields: [&'static str; 8],
+    pub on: &'static str,
+    pub off: &'static str,
+    pub motion_full: &'static str,
+    pub motion_reduced: &'static str,
+    pub bell: &'static str,
+    pub flash: &'static str,
+    pub region_none: &'static str,
+
+    pub replay_header: &'static str,
+
+    pub final_score: &'static str,
+    pub new_high_score: &'static str,
+    pub challenge_complete: &'static str,
+    pub try_again: &'static str,
+    pub click_to_return: &'static str,
+    pub podium_place: &'static str,
+    pub top_percent_today: &'static str,
+
+    pub aim_map: &'static str,
+    pub aim_hit: &'static str,
+    pub aim_miss: &'static str,
+    pub aim_both: &'static str,
+
+    pub danger_title: &'static str,
+    pub reset_question: &'static str,
+    pub reset_target: &'static str,
+    pub reset_confirm: &'static str,
+    pub reset_cancel: &'static str,
+
+    pub tab_daily: &'static str,
+    pub tab_weekly: &'static str,
+    pub tab_all_time: &'static str,
+    pub tab_challenge: &'static str,
+    pub loading: &'static str,
+    pub no_entries: &'static str,
+    pub you: &'static str,
+    pub table_header: [&'static str; 4],
+
+    pub activity_title: &'static str,
+    pub activity_scrolled: &'static str,
+    pub less: &'static str,
+    pub more: &'static str,
+}
+
+impl Messages {
+    pub const ENGLISH: Self = Self {
+        terminal_too_small: "TERMINAL TOO SMALL\n\nRequired: {}x{}\nCurrent: {}x{}\n\nPlease resize!",
+        cheat_warning: "!! ABNORMAL BEHAVIOR DETECTED !!",
+        cheat_discarded: "The interaction was discarded.",
+        idle_warning: "Still there?",
+        idle_countdown: "Disconnecting in {}s. Press any key to stay.",
+        announcement_title: " ANNOUNCEMENT ",
+        announcement_close: " [ENTER/ESC] Dismiss ",
+
+        help_title: " CONTROLS ",
+        help_close: " [?/ESC] Close ",
+        help: [
+            "Shoot / start game",
+            "Restart current game",
+            "Back to menu",
+            "Daily challenge",
+            "Watch best replay",
+            "Profile",
+            "Settings",
+            "Full ranking",
+            "Calibrate mouse",
+            "Switch tab / scroll activity",
+            "Move selection / focus / page",
+            "Delete account",
+            "Toggle this help",
+            "Performance overlay",
+            "Quit",
+        ],
+
+        menu: "Menu",
+        quit: "Quit",
+        restart: "Restart",
+        retry: "Retry",
+        submit: "Submit",
+        select: "Select",
+        change: "Change",
+        save_and_back: "Save & Back",
+        tab: "Tab",
+        seasons: "Seasons",
+        season_title: " {} ({} - {}) ",
+        page: "Page",
+        cancel: "Cancel",
+        skip_tutorial: "Skip tutorial",
+        confirm_reset: "Confirm RESET",
+        daily: "Daily",
+        duel: "Duel",
+        forfeit: "Forfeit",
+        replay: "Replay",
+        profile: "Profile",
+        prefs: "Prefs",
+        ranking: "Ranking",
+        help_short: "Help",
+        export_data: "Export my data",
+        export_failed: " Export failed, try again later ",
+
+        stats_title: " [ {}'S STATS ] ",
+        profile_title: " {}'S PROFILE ",
+        sessions: "Sessions",
+        high_score: "High Score",
+        hits: "Hits",
+        misses: "Misses",
+        accuracy: "Accuracy",
+        best_combo: "Best Combo",
+        avg_reaction: "Avg. Reaction",
+        best_accuracy: "Best Accuracy",
+        best_reaction: "Best Reaction",
+        last_games: " Last {} games (max {})",
+
+        welcome: "WELCOME TO SHOOT.SH",
+        enter_name: " ENTER YOUR NAME ",
+        enter_link_code: " ENTER LINK CODE ",
+        link_account: "Use existing account",
+        link_device: "Link device",
+        link_code_line: " Link code: {} (valid for {} min) ",
+        recovery_code: "Recovery code",
+        recovery_code_line: "Recovery code: {}",
+        recovery_code_hint: "Write it down. Shown once; enter it on a new key.",
+        saving: "{} (Saving...)",
+        please_wait: "Please wait...",
+        press_enter: "Press ENTER to start",
+
+        click_to_start: "!!! CLICK TO START !!!",
+        space_to_start: "!!! PRESS SPACE TO START !!!",
+        keyboard_aim_hint: "No mouse detected: aim with the arrow keys or hjkl, shoot with space",
+        high_score_line: "HIGH SCORE: {}",
+        motd_title: " NOTICE ",
+
+        hud: "{} SCORE: {} | COMBO {}{} ",
+        hud_daily: " DAILY CHALLENGE |",
+        hud_practice: " PRACTICE |",
+        hud_ghost: " | GHOST: {}",
+        hud_watching: " | 👁 {} watching",
+        hud_duel: " DUEL |",
+        hud_opponent: " | VS {}: {}",
+        duel_searching: "Looking for an opponent... {}s",
+        duel_found: "VS {}",
+        duel_starts_in: "Starting in {}",
+        duel_record: "Head-to-head: {}W {}L {}D",
+        duel_rules: "Same targets, same time. Highest score wins.",
+        duel_waiting: "Waiting for {} to finish ({} so far)",
+        duel_won: "You beat {} ({})",
+        duel_lost: "{} wins with {}",
+        duel_draw: "Draw with {} ({})",
+        chat_hint: "Say something to everyone online",
+        chat_prompt: "Say: ",
+        chat_too_fast: "Slow down a little",
+        chat_offensive: "That word isn't allowed",
+        demo_header: " DEMO | SCORE: {} | COMBO {} ",
+        demo_hint: "PRESS ANY KEY TO PLAY",
+        any_key: "Any key",
+        milestone: "*** {} COMBO! +{} ***",
+
+        tutorial_aim_title: " 1/3 AIM ",
+        tutorial_aim: [
+            "Move your mouse around the terminal.",
+            "The crosshair follows it.",
+        ],
+        tutorial_shoot_title: " 2/3 SHOOT ",
+        tutorial_shoot: [
+            "Click the targets to shoot them.",
+            "{} / 3 hit. Real targets vanish if you are too slow.",
+        ],
+        tutorial_combo_title: " 3/3 COMBO & DECAY ",
+        tutorial_combo: [
+            "Consecutive hits build a combo that multiplies your score.",
+            "A miss or an expired target resets it.",
+            "Targets live shorter as your combo grows.",
+            "Click or press ENTER to start your first game!",
+        ],
+
+        calibration_title: " MOUSE CALIBRATION ",
+        calibration_prompt: "Click the center of the highlighted marker ({}/{}).",
+        calibration_offset: "Current offset: x {:+}, y {:+}",
+        verify_title: " QUICK CHECK ",
+        verify_prompt: "Some of your hits looked automated. Click the targets as they appear.",
+        verify_progress: "Target {} of {}",
+
+        settings_title: " SETTINGS ",
+        settings_fields: [
+            "Theme", "Cursor", "Effects", "Duration", "Language", "Motion", "Feedback", "Region",
+        ],
+        on: "On",
+        off: "Off",
+        motion_full: "Full",
+        motion_reduced: "Reduced",
+        bell: "Bell",
+        flash: "Flash",
+        region_none: "None",
+
+        replay_header: " REPLAY | SCORE: {} | {}s / {}s ",
+
+        final_score: "FINAL SCORE: {}",
+        new_high_score: "!!! NEW HIGH SCORE !!!",
+        challenge_complete: "DAILY CHALLENGE COMPLETE",
+        try_again: "TRY AGAIN!",
+        click_to_return: "Click to return Menu",
+        podium_place: "*** #{} ON THE BOARD! ***",
+        top_percent_today: "Top {}% today",
+
+        aim_map: " AIM MAP ",
+        aim_hit: " hit ",
+        aim_miss: " miss ",
+        aim_both: " both",
+
+        danger_title: " DANGER ACTION ",
+        reset_question: "Are you sure you want to",
+        reset_target: "DELETE ALL YOUR DATA?",
+        reset_confirm: " Confirm: ",
+        reset_cancel: "  |  Cancel: ",
+
+        tab_daily: " DAILY ",
+        tab_weekly: " WEEKLY ",
+        tab_all_time: " ALL-TIME ",
+        tab_challenge: " CHALLENGE ",
+        loading: " loading... ",
+        no_entries: " no entries ",
+        you: "you",
+        table_header: ["RANK", "NAME", "SCORE", "DATE"],
+
+        activity_title: " ACTIVITY ({}weeks) ",
+        activity_scrolled: " ACTIVITY ({}weeks ago) ",
+        less: "Less ",
+        more: " More",
+    };
+
+    pub const JAPANESE: Self = Self {
+        terminal_too_small: "画面が小さすぎます\n\n必要: {}x{}\n現在: {}x{}\n\nサイズを変更してください",
+        cheat_warning: "!! 不自然な操作を検出しました !!",
+        cheat_discarded: "この操作は無効になりました。",
+        idle_warning: "まだプレイしていますか？",
+        idle_countdown: "{}秒後に切断します。キーを押すと続行します。",
+        announcement_title: " お知らせ ",
+        announcement_close: " [ENTER/ESC] 閉じる ",
+
+        help_title: " 操作方法 ",
+        help_close: " [?/ESC] 閉じる ",
+        help: [
+            "射撃 / ゲーム開始",
+            "ゲームをやり直す",
+            "メニューに戻る",
+            "デイリーチャレンジ",
+            "ベストリプレイを見る",
+            "プロフィール",
+            "設定",
+            "ランキング一覧",
+            "マウス補正",
+            "タブ切替 / 履歴スクロール",
+            "選択 / フォーカス / ページ",
+            "アカウント削除",
+            "このヘルプを開閉",
+            "パフォーマンス表示",
+            "終了",
+        ],
+
+        menu: "メニュー",
+        quit: "終了",
+        restart: "やり直す",
+        retry: "リトライ",
+        submit: "決定",
+        select: "選択",
+        change: "変更",
+        save_and_back: "保存して戻る",
+        tab: "タブ",
+        seasons: "シーズン",
+        season_title: " {} ({}〜{}) ",
+        page: "ページ",
+        cancel: "キャンセル",
+        skip_tutorial: "スキップ",
+        confirm_reset: "リセット実行",
+        daily: "デイリー",
+        duel: "対戦",
+        forfeit: "棄権",
+        replay: "リプレイ",
+        profile: "成績",
+        prefs: "設定",
+        ranking: "順位",
+        help_short: "ヘルプ",
+        export_data: "データを書き出す",
+        export_failed: " 書き出しに失敗しました ",
+
+        stats_title: " [ {} の成績 ] ",
+        profile_title: " {} のプロフィール ",
+        sessions: "プレイ回数",
+        high_score: "最高スコア",
+        hits: "命中",
+        misses: "ミス",
+        accuracy: "命中率",
+        best_combo: "最大コンボ",
+        avg_reaction: "平均反応",
+        best_accuracy: "最高命中率",
+        best_reaction: "最速反応",
+        last_games: " 直近 {} ゲーム (最高 {})",
+
+        welcome: "SHOOT.SH へようこそ",
+        enter_name: " 名前を入力 ",
+        enter_link_code: " 連携コードを入力 ",
+        link_account: "既存のアカウントを使う",
+        link_device: "端末を連携",
+        link_code_line: " 連携コード: {} (有効期限 {} 分) ",
+        recovery_code: "復旧コード",
+        recovery_code_line: "復旧コード: {}",
+        recovery_code_hint: "控えてください。表示は一度きりです。",
+        saving: "{} (保存中...)",
+        please_wait: "お待ちください...",
+        press_enter: "ENTER でスタート",
+
+        click_to_start: "!!! クリックでスタート !!!",
+        space_to_start: "!!! スペースキーでスタート !!!",
+        keyboard_aim_hint: "マウスが検出されません: 矢印キーかhjklで狙い、スペースで撃ちます",
+        high_score_line: "ハイスコア: {}",
+        motd_title: " お知らせ ",
+
+        hud: "{} スコア: {} | コンボ {}{} ",
+        hud_daily: " デイリー |",
+        hud_practice: " 練習 |",
+        hud_ghost: " | ゴースト: {}",
+        hud_watching: " | 👁 {}人観戦中",
+        hud_duel: " 対戦 |",
+        hud_opponent: " | VS {}: {}",
+        duel_searching: "対戦相手を探しています... {}秒",
+        duel_found: "VS {}",
+        duel_starts_in: "{}秒後に開始",
+        duel_record: "対戦成績: {}勝 {}敗 {}分",
+        duel_rules: "同じ的、同じ時間。スコアが高い方の勝ち。",
+        duel_waiting: "{}の終了を待っています（現在 {}）",
+        duel_won: "{}に勝利！（相手 {}）",
+        duel_lost: "{}の勝ち（{}）",
+        duel_draw: "{}と引き分け（{}）",
+        chat_hint: "オンラインの全員にメッセージを送る",
+        chat_prompt: "発言: ",
+        chat_too_fast: "少し待ってから送ってください",
+        chat_offensive: "その言葉は使えません",
+        demo_header: " デモ | スコア: {} | コンボ {} ",
+        demo_hint: "キーを押してプレイ",
+        any_key: "任意のキー",
+        milestone: "*** {} コンボ! +{} ***",
+
+        tutorial_aim_title: " 1/3 エイム ",
+        tutorial_aim: [
+            "ターミナル上でマウスを動かしてください。",
+            "照準がマウスに追従します。",
+        ],
+        tutorial_shoot_title: " 2/3 射撃 ",
+        tutorial_shoot: [
+            "ターゲットをクリックして撃ちましょう。",
+            "{} / 3 命中。本番のターゲットは時間が経つと消えます。",
+        ],
+        tutorial_combo_title: " 3/3 コンボと減衰 ",
+        tutorial_combo: [
+            "連続で命中させるとコンボでスコアが倍増します。",
+            "ミスやターゲットの消滅でリセットされます。",
+            "コンボが増えるほどターゲットの寿命は短くなります。",
+            "クリックか ENTER で最初のゲームを開始!",
+        ],
+
+        calibration_title: " マウス補正 ",
+        calibration_prompt: "強調されたマーカーの中心をクリック ({}/{})",
+        calibration_offset: "現在の補正: x {:+}, y {:+}",
+        verify_title: " 確認 ",
+        verify_prompt: "自動操作の疑いがあるヒットがありました。表示されるターゲットをクリックしてください。",
+        verify_progress: "ターゲット {}/{}",
+
+        settings_title: " 設定 ",
+        settings_fields: [
+            "テーマ",
+            "カーソル",
+            "エフェクト",
+            "時間",
+            "言語",
+            "モーション",
+            "ヒット通知",
+            "地域",
+        ],
+        on: "オン",
+        off: "オフ",
+        motion_full: "通常",
+        motion_reduced: "控えめ",
+        bell: "ベル",
+        flash: "フラッシュ",
+        region_none: "なし",
+
+        replay_header: " リプレイ | スコア: {} | {}秒 / {}秒 ",
+
+        final_score: "最終スコア: {}",
+        new_high_score: "!!! ハイスコア更新 !!!",
+        challenge_complete: "デイリーチャレンジ完了",
+        try_again: "もう一度!",
+        click_to_return: "クリックでメニューへ",
+        podium_place: "*** ランキング {}位！ ***",
+        top_percent_today: "本日の上位{}%",
+
+        aim_map: " エイムマップ ",
+        aim_hit: " 命中 ",
+        aim_miss: " ミス ",
+        aim_both: " 両方",
+
+        danger_title: " 危険な操作 ",
+        reset_question: "本当に",
+        reset_target: "全てのデータを削除しますか?",
+        reset_confirm: " 実行: ",
+        reset_cancel: "  |  キャンセル: ",
+
+        tab_daily: " 今日 ",
+        tab_weekly: " 今週 ",
+        tab_all_time: " 全期間 ",
+        tab_challenge: " チャレンジ ",
+        loading: " 読み込み中... ",
+        no_entries: " 記録なし ",
+        you: "あなた",
+        table_header: ["順位", "名前", "スコア", "日付"],
+
+        activity_title: " アクティビティ ({}週) ",
+        activity_scrolled: " アクティビティ ({}週前) ",
+        less: "少 ",
+        more: " 多",
+    };
+
+    pub fn for_language(language: Language) -> &'static Self {
+        match language {
+            Language::Japanese => &Self::JAPANESE,
+            Language::English | Language::Auto => &Self::ENGLISH,
+        }
+    }
+}
+
+/// Substitutes each `{}` in `template` with the next argument; `{:+}`
+/// also signs a number.
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template;
+    while let Some((i, signed)) = next_placeholder(rest) {
+        out.push_str(&rest[..i]);
+        if let Some(arg) = args.next() {
+            if signed {
+                out.push_str(&format!("{arg:+}"));
+            } else {
+                out.push_str(&arg.to_string());
+            }
+        }
+        rest = &rest[i + if signed { 4 } else { 2 }..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Where the next placeholder starts, and whether it is `{:+}`.
+fn next_placeholder(template: &str) -> Option<(usize, bool)> {
+    let plain = template.find("{}").map(|i| (i, false));
+    let signed = template.find("{:+}").map(|i| (i, true));
+    plain.into_iter().chain(signed).min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_substitutes_in_order() {
+        assert_eq!(fill("{}x{} px", &[&80, &24]), "80x24 px");
+        assert_eq!(fill("no args", &[]), "no args");
+        assert_eq!(fill("{} and {}", &[&"one"]), "one and ");
+        assert_eq!(fill("x {:+}, y {:+}", &[&3, &-2]), "x +3, y -2");
+    }
+
+    #[test]
+    fn test_catalogs_keep_placeholder_counts() {
+        let en = Messages::ENGLISH;
+        let ja = Messages::JAPANESE;
+        let pairs = [
+            (en.terminal_too_small, ja.terminal_too_small),
+            (en.hud, ja.hud),
+            (en.milestone, ja.milestone),
+            (en.replay_header, ja.replay_header),
+            (en.demo_header, ja.demo_header),
+            (en.podium_place, ja.podium_place),
+            (en.top_percent_today, ja.top_percent_today),
+            (en.season_title, ja.season_title),
+            (en.link_code_line, ja.link_code_line),
+            (en.recovery_code_line, ja.recovery_code_line),
+            (en.hud_watching, ja.hud_watching),
+            (en.idle_countdown, ja.idle_countdown),
+            (en.hud_opponent, ja.hud_opponent),
+            (en.duel_record, ja.duel_record),
+            (en.duel_waiting, ja.duel_waiting),
+            (en.duel_won, ja.duel_won),
+            (en.duel_lost, ja.duel_lost),
+            (en.duel_draw, ja.duel_draw),
+            (en.calibration_prompt, ja.calibration_prompt),
+            (en.calibration_offset, ja.calibration_offset),
+            (en.verify_progress, ja.verify_progress),
+            (en.last_games, ja.last_games),
+            (en.tutorial_shoot[1], ja.tutorial_shoot[1]),
+        ];
+        for (a, b) in pairs {
+            let count = |s: &str| s.matches("{}").count() + s.matches("{:+}").count();
+            assert_eq!(count(a), count(b), "{a}");
+        }
+    }
+}
@@ -1,11 +1,24 @@
 pub mod anticheat;
 pub mod app;
+pub mod chat;
 pub mod db;
 pub mod domain;
+pub mod duel;
+pub mod i18n;
+pub mod names;
+pub mod preferences;
+pub mod region;
+pub mod remote;
+pub mod replay;
+pub mod signing;
+pub mod theme;
 pub mod ui;
 
-pub use anticheat::{AntiCheatConfig, BehaviorAnalyzer};
+pub use anticheat::{
+    AntiCheatConfig, BehaviorAnalyzer, Check, Interaction, LatencyClass, Strictness,
+};
 pub use app::{Action, App, RANKING_LIMIT, Scene};
 pub use db::{DbRequest, ScoreEntry};
-pub use domain::{MouseTrace, Point, Size, Target};
+pub use domain::{GameConfig, GameMode, MouseTrace, Point, Size, Target};
+pub use names::NameFilter;
 pub use ui::{MIN_HEIGHT, MIN_WIDTH};
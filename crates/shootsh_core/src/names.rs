@@ -0,0 +1,151 @@
+/// Names that would let a player pass as staff or the server itself.
+const DEFAULT_RESERVED: &[&str] = &[
+    "admin",
+    "administrator",
+    "root",
+    "server",
+    "system",
+    "moderator",
+    "mod",
+    "operator",
+    "staff",
+    "support",
+    "shootsh",
+];
+
+/// Blocked anywhere in a name, after undoing common digit substitutions.
+const DEFAULT_BLOCKED: &[&str] = &[
+    "fuck", "shit", "cunt", "bitch", "whore", "nigger", "nigga", "faggot", "retard", "nazi",
+];
+
+/// Why `NameFilter::check` refused a name; surfaced as-is to the naming screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameRejected {
+    Reserved,
+    Offensive,
+}
+
+impl std::fmt::Display for NameRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Reserved => f.write_str("That name is reserved"),
+            Self::Offensive => f.write_str("That name is not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for NameRejected {}
+
+/// Deny-list applied to player names before they are stored.
+///
+/// Reserved names match the whole name, ignoring trailing digits ("admin42").
+/// Blocked words match anywhere in the name.
+#[derive(Debug, Clone)]
+pub struct NameFilter {
+    reserved: Vec<String>,
+    blocked: Vec<String>,
+}
+
+impl Default for NameFilter {
+    fn default() -> Self {
+        Self {
+            reserved: DEFAULT_RESERVED.iter().map(|s| normalize(s)).collect(),
+            blocked: DEFAULT_BLOCKED.iter().map(|s| normalize(s)).collect(),
+        }
+    }
+}
+
+impl NameFilter {
+    /// Filter that accepts every name.
+    pub fn empty() -> Self {
+        Self {
+            reserved: Vec::new(),
+            blocked: Vec::new(),
+        }
+    }
+
+    pub fn with_reserved<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.reserved.extend(normalized(names));
+        self
+    }
+
+    pub fn with_blocked<I, S>(mut self, words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.blocked.extend(normalized(words));
+        self
+    }
+
+    pub fn check(&self, name: &str) -> Result<(), NameRejected> {
+        let name = normalize(name);
+        let stem = name.trim_end_matches(|c: char| c.is_ascii_digit());
+        if self.reserved.iter().any(|r| r == &name || r == stem) {
+            return Err(NameRejected::Reserved);
+        }
+        if self.blocked.iter().any(|w| name.contains(w.as_str())) {
+            return Err(NameRejected::Offensive);
+        }
+        Ok(())
+    }
+
+    /// Whether free text such as a chat line contains a blocked word.
+    pub fn is_offensive(&self, text: &str) -> bool {
+        let text = normalize(text);
+        self.blocked.iter().any(|w| text.contains(w.as_str()))
+    }
+}
+
+fn normalized<I, S>(items: I) -> impl Iterator<Item = String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    items
+        .into_iter()
+        .map(|s| normalize(s.as_ref()))
+        .filter(|s| !s.is_empty())
+}
+
+/// Lowercases and folds digits used as letters, so "4dm1n" reads as "admin".
+/// Trailing digits are kept so `check` can still strip them as a suffix.
+fn normalize(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    let body_len = name.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (body, suffix) = name.split_at(body_len);
+    body.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' => 'i',
+            '3' => 'e',
+            '4' => 'a',
+            '5' => 's',
+            '7' => 't',
+            '8' => 'b',
+            _ => c,
+        })
+        .chain(suffix.chars())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_rejects_reserved_and_offensive_names() {
+        let filter = NameFilter::default().with_reserved(["Owner"]);
+        assert_eq!(filter.check("Admin"), Err(NameRejected::Reserved));
+        assert_eq!(filter.check("4dm1n99"), Err(NameRejected::Reserved));
+        assert_eq!(filter.check("owner"), Err(NameRejected::Reserved));
+        assert_eq!(filter.check("xSh1tx"), Err(NameRejected::Offensive));
+        assert_eq!(filter.check("adminfan"), Ok(()));
+        assert_eq!(filter.check("Player1"), Ok(()));
+        assert_eq!(NameFilter::empty().check("admin"), Ok(()));
+    }
+}
@@ -0,0 +1,386 @@
+use crate::region::Region;
+use std::time::Duration;
+
+/// Per-user choices made in the settings scene.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Preferences {
+    pub theme: ThemeKind,
+    pub cursor_style: CursorStyle,
+    pub effects: bool,
+    pub game_duration: GameDuration,
+    pub language: Language,
+    /// Skips scene transitions.
+    pub reduced_motion: bool,
+    pub hit_feedback: HitFeedback,
+    /// Shown next to the player's name on the boards; stored on `users`.
+    pub region: Option<Region>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            theme: ThemeKind::default(),
+            cursor_style: CursorStyle::default(),
+            effects: true,
+            game_duration: GameDuration::default(),
+            language: Language::default(),
+            reduced_motion: false,
+            hit_feedback: HitFeedback::default(),
+            region: None,
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum ThemeKind {
+    #[default]
+    Classic,
+    Ocean,
+    Amber,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum CursorStyle {
+    #[default]
+    Crosshair,
+    Dot,
+    Large,
+    /// Braille crosshair eased toward the mouse at sub-cell resolution.
+    Smooth,
+}
+
+/// Round length; anything but the default is unranked practice.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum GameDuration {
+    #[default]
+    Short,
+    Medium,
+    Long,
+}
+
+/// Cue on hits and combo breaks. Off by default since bells are divisive.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum HitFeedback {
+    #[default]
+    Off,
+    Bell,
+    Flash,
+}
+
+/// UI language; `Auto` follows the client's locale.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum Language {
+    #[default]
+    Auto,
+    English,
+    Japanese,
+}
+
+impl Language {
+    /// Parses a POSIX locale such as `ja_JP.UTF-8`; unknown locales give `None`.
+    pub fn from_locale(locale: &str) -> Option<Self> {
+        let lang = locale.split(['_', '.', '@']).next()?.to_ascii_lowercase();
+        match lang.as_str() {
+            "ja" => Some(Self::Japanese),
+            "en" | "c" | "posix" => Some(Self::English),
+            _ => None,
+        }
+    }
+
+    /// Resolves `Auto` against the language detected from the client.
+    pub fn or_detected(self, detected: Self) -> Self {
+        match (self, detected) {
+            (Self::Auto, Self::Auto) => Self::English,
+            (Self::Auto, detected) => detected,
+            (chosen, _) => chosen,
+        }
+    }
+}
+
+/// Small cyclic option list stored by key in the `user_preferences` table.
+pub trait PreferenceOption: Sized + Copy + PartialEq + 'static {
+    const ALL: &'static [Self];
+
+    fn key(&self) -> &'static str;
+    fn label(&self) -> &'static str;
+
+    fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|o| o.key() == key)
+    }
+
+    fn next(&self) -> Self {
+        let i = Self::ALL.iter().position(|o| o == self).unwrap_or(0);
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    fn prev(&self) -> Self {
+        let i = Self::ALL.iter().position(|o| o == self).unwrap_or(0);
+        Self::ALL[(i + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl PreferenceOption for ThemeKind {
+    const ALL: &'static [Self] = &[
+        Self::Classic,
+        Self::Ocean,
+        Self::Amber,
+        Self::Deuteranopia,
+        Self::Protanopia,
+        Self::Tritanopia,
+    ];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Ocean => "ocean",
+            Self::Amber => "amber",
+            Self::Deuteranopia => "deuteranopia",
+            Self::Protanopia => "protanopia",
+            Self::Tritanopia => "tritanopia",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Classic => "Classic",
+            Self::Ocean => "Ocean",
+            Self::Amber => "Amber",
+            Self::Deuteranopia => "Deuteranopia",
+            Self::Protanopia => "Protanopia",
+            Self::Tritanopia => "Tritanopia",
+        }
+    }
+}
+
+impl PreferenceOption for CursorStyle {
+    const ALL: &'static [Self] = &[Self::Crosshair, Self::Dot, Self::Large, Self::Smooth];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Crosshair => "crosshair",
+            Self::Dot => "dot",
+            Self::Large => "large",
+            Self::Smooth => "smooth",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Crosshair => "Crosshair",
+            Self::Dot => "Dot",
+            Self::Large => "Large",
+            Self::Smooth => "Smooth",
+        }
+    }
+}
+
+impl PreferenceOption for GameDuration {
+    const ALL: &'static [Self] = &[Self::Short, Self::Medium, Self::Long];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Short => "15",
+            Self::Medium => "30",
+            Self::Long => "60",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Short => "15s (ranked)",
+            Self::Medium => "30s (practice)",
+            Self::Long => "60s (practice)",
+        }
+    }
+}
+
+impl PreferenceOption for HitFeedback {
+    const ALL: &'static [Self] = &[Self::Off, Self::Bell, Self::Flash];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Bell => "bell",
+            Self::Flash => "flash",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Bell => "Bell",
+            Self::Flash => "Flash",
+        }
+    }
+}
+
+impl PreferenceOption for Language {
+    const ALL: &'static [Self] = &[Self::Auto, Self::English, Self::Japanese];
+
+    fn key(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::English => "en",
+            Self::Japanese => "ja",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Auto => "Auto",
+            Self::English => "English",
+            Self::Japanese => "日本語",
+        }
+    }
+}
+
+impl GameDuration {
+    pub fn as_duration(&self) -> Duration {
+        match self {
+            Self::Short => Duration::from_secs(15),
+            Self::Medium => Duration::from_secs(30),
+            Self::Long => Duration::from_secs(60),
+        }
+    }
+}
+
+/// Rows of the settings scene, in display order.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum SettingsField {
+    Theme,
+    CursorStyle,
+    Effects,
+    GameDuration,
+    Language,
+    Motion,
+    Feedback,
+    Region,
+}
+
+impl SettingsField {
+    pub const ALL: [Self; 8] = [
+        Self::Theme,
+        Self::CursorStyle,
+        Self::Effects,
+        Self::GameDuration,
+        Self::Language,
+        Self::Motion,
+        Self::Feedback,
+        Self::Region,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Theme => "Theme",
+            Self::CursorStyle => "Cursor",
+            Self::Effects => "Effects",
+            Self::GameDuration => "Duration",
+            Self::Language => "Language",
+            Self::Motion => "Motion",
+            Self::Feedback => "Feedback",
+            Self::Region => "Region",
+        }
+    }
+
+    pub fn value_label(&self, prefs: &Preferences) -> &'static str {
+        match self {
+            Self::Theme => prefs.theme.label(),
+            Self::CursorStyle => prefs.cursor_style.label(),
+            Self::Effects => {
+                if prefs.effects {
+                    "On"
+                } else {
+                    "Off"
+                }
+            }
+            Self::GameDuration => prefs.game_duration.label(),
+            Self::Language => prefs.language.label(),
+            Self::Motion => {
+                if prefs.reduced_motion {
+                    "Reduced"
+                } else {
+                    "Full"
+                }
+            }
+            Self::Feedback => prefs.hit_feedback.label(),
+            Self::Region => prefs.region.map_or("None", |r| r.code()),
+        }
+    }
+
+    pub fn cycle(&self, prefs: &mut Preferences, forward: bool) {
+        match self {
+            Self::Theme => {
+                prefs.theme = if forward {
+                    prefs.theme.next()
+                } else {
+                    prefs.theme.prev()
+                }
+            }
+            Self::CursorStyle => {
+                prefs.cursor_style = if forward {
+                    prefs.cursor_style.next()
+                } else {
+                    prefs.cursor_style.prev()
+                }
+            }
+            Self::Effects => prefs.effects = !prefs.effects,
+            Self::GameDuration => {
+                prefs.game_duration = if forward {
+                    prefs.game_duration.next()
+                } else {
+                    prefs.game_duration.prev()
+                }
+            }
+            Self::Language => {
+                prefs.language = if forward {
+                    prefs.language.next()
+                } else {
+                    prefs.language.prev()
+                }
+            }
+            Self::Motion => prefs.reduced_motion = !prefs.reduced_motion,
+            Self::Feedback => {
+                prefs.hit_feedback = if forward {
+                    prefs.hit_feedback.next()
+                } else {
+                    prefs.hit_feedback.prev()
+                }
+            }
+            Self::Region => prefs.region = Region::cycle(prefs.region, forward),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_option_cycle_wraps() {
+        assert_eq!(ThemeKind::Tritanopia.next(), ThemeKind::Classic);
+        assert_eq!(ThemeKind::Classic.prev(), ThemeKind::Tritanopia);
+        assert_eq!(CursorStyle::from_key("dot"), Some(CursorStyle::Dot));
+        assert_eq!(GameDuration::from_key("nope"), None);
+    }
+
+    #[test]
+    fn test_language_from_locale() {
+        assert_eq!(
+            Language::from_locale("ja_JP.UTF-8"),
+            Some(Language::Japanese)
+        );
+        assert_eq!(Language::from_locale("C.UTF-8"), Some(Language::English));
+        assert_eq!(Language::from_locale("de_DE"), None);
+        assert_eq!(
+            Language::Auto.or_detected(Language::Japanese),
+            Language::Japanese
+        );
+        assert_eq!(
+            Language::English.or_detected(Language::Japanese),
+            Language::English
+        );
+    }
+}
@@ -0,0 +1,62 @@
+/// ISO 3166-1 alpha-2 codes, in settings cycle order.
+const CODES: &str = "\
+AD AE AF AG AI AL AM AO AQ AR AS AT AU AW AX AZ BA BB BD BE BF BG BH BI BJ BL BM BN BO BQ BR BS \
+BT BV BW BY BZ CA CC CD CF CG CH CI CK CL CM CN CO CR CU CV CW CX CY CZ DE DJ DK DM DO DZ EC EE \
+EG EH ER ES ET FI FJ FK FM FO FR GA GB GD GE GF GG GH GI GL GM GN GP GQ GR GS GT GU GW GY HK HM \
+HN HR HT HU ID IE IL IM IN IO IQ IR IS IT JE JM JO JP KE KG KH KI KM KN KP KR KW KY KZ LA LB LC \
+LI LK LR LS LT LU LV LY MA MC MD ME MF MG MH MK ML MM MN MO MP MQ MR MS MT MU MV MW MX MY MZ NA \
+NC NE NF NG NI NL NO NP NR NU NZ OM PA PE PF PG PH PK PL PM PN PR PS PT PW PY QA RE RO RS RU RW \
+SA SB SC SD SE SG SH SI SJ SK SL SM SN SO SR SS ST SV SX SY SZ TC TD TF TG TH TJ TK TL TM TN TO \
+TR TT TV TW TZ UA UG UM US UY UZ VA VC VE VG VI VN VU WF WS YE YT ZA ZM ZW";
+
+/// Two-letter country or region tag shown next to a player's name.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Region(&'static str);
+
+impl Region {
+    /// Case-insensitive; anything but a known ISO 3166-1 code gives `None`.
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.trim();
+        CODES
+            .split_ascii_whitespace()
+            .find(|c| c.eq_ignore_ascii_case(code))
+            .map(Self)
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.0
+    }
+
+    /// Next setting in the cycle; "no region" sits between the last and first code.
+    pub fn cycle(current: Option<Self>, forward: bool) -> Option<Self> {
+        let codes: Vec<&'static str> = CODES.split_ascii_whitespace().collect();
+        let pos = current.and_then(|r| codes.iter().position(|c| *c == r.0));
+        let next = match (pos, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(codes.len() - 1),
+            (Some(i), true) => (i + 1 < codes.len()).then_some(i + 1),
+            (Some(i), false) => i.checked_sub(1),
+        };
+        next.map(|i| Self(codes[i]))
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_cycle() {
+        assert_eq!(Region::parse(" jp").map(|r| r.code()), Some("JP"));
+        assert_eq!(Region::parse("XX"), None);
+        assert_eq!(Region::cycle(None, true), Region::parse("AD"));
+        assert_eq!(Region::cycle(Region::parse("ZW"), true), None);
+        assert_eq!(Region::cycle(Region::parse("AD"), false), None);
+    }
+}
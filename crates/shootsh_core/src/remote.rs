@@ -0,0 +1,197 @@
+//! Shared by frontends that send the terminal's bytes over a network stream
+//! (SSH, WebSocket) rather than drawing to a local tty: the escape sequences
+//! around a session, rendering into a byte buffer and parsing client input.
+
+use crate::{Action, App, ui};
+use ratatui::{Terminal, backend::CrosstermBackend};
+use std::sync::{Arc, Mutex};
+use termwiz::input::{InputEvent, InputParser, KeyCode, Modifiers, MouseButtons};
+
+pub const SETUP_SEQ: &[u8] = concat!(
+    "\x1b[?1049h", // EnterAlternateScreen
+    "\x1b[?1000h", // EnableMouseCapture (Normal)
+    "\x1b[?1002h", // EnableMouseCapture (Button)
+    "\x1b[?1003h", // EnableMouseCapture (Any)
+    "\x1b[?1015h", // EnableMouseCapture (URXVT)
+    "\x1b[?1006h", // EnableMouseCapture (SGR)
+    "\x1b[?2004h", // EnableBracketedPaste
+    "\x1b[?25l"    // HideCursor
+)
+.as_bytes();
+
+pub const CLEANUP_SEQ: &[u8] = concat!(
+    "\x1b[?2004l", // DisableBracketedPaste
+    "\x1b[?1006l", // DisableMouseCapture (SGR)
+    "\x1b[?1015l", // DisableMouseCapture (URXVT)
+    "\x1b[?1003l", // DisableMouseCapture (Any)
+    "\x1b[?1002l", // DisableMouseCapture (Button)
+    "\x1b[?1000l", // DisableMouseCapture (Normal)
+    "\x1b[?1049l", // LeaveAlternateScreen
+    "\x1b[?25h"    // ShowCursor
+)
+.as_bytes();
+
+pub const CURSOR_HIDE: &[u8] = b"\x1b[?25l";
+
+/// A thread-safe wrapper around a byte buffer to capture TUI draw calls.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+pub type RemoteTerminal = Terminal<CrosstermBackend<SharedBuffer>>;
+
+/// Draws `app` and returns the bytes that bring the client's screen up to date.
+pub fn render_frame(
+    app: &App,
+    terminal: &mut RemoteTerminal,
+    shared_output: &SharedBuffer,
+) -> Vec<u8> {
+    terminal
+        .draw(|f| {
+            ui::render(app, &app.db_cache, f);
+            f.set_cursor_position(ratatui::layout::Position::new(0, 0));
+        })
+        .expect("Failed to draw frame");
+
+    let mut output = Vec::from(CURSOR_HIDE);
+    let mut internal_vec = shared_output.0.lock().unwrap();
+    output.extend(std::mem::take(&mut *internal_vec));
+
+    output
+}
+
+pub struct InputTransformer {
+    parser: InputParser,
+    last_mouse_buttons: MouseButtons,
+}
+
+impl Default for InputTransformer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputTransformer {
+    pub fn new() -> Self {
+        Self {
+            parser: InputParser::new(),
+            last_mouse_buttons: MouseButtons::NONE,
+        }
+    }
+
+    pub fn handle_input(&mut self, data: &[u8]) -> Vec<(InputEvent, MouseButtons)> {
+        let mut results = Vec::new();
+        self.parser.parse(
+            data,
+            |event| {
+                results.push((event.clone(), self.last_mouse_buttons.clone()));
+
+                if let InputEvent::Mouse(m) = &event {
+                    self.last_mouse_buttons = m.mouse_buttons.clone();
+                }
+            },
+            false,
+        );
+        results
+    }
+}
+
+pub fn map_input_to_action(
+    event: InputEvent,
+    captured: bool,
+    last_mouse_buttons: &MouseButtons,
+) -> Option<Action> {
+    match event {
+        InputEvent::Key(k) => {
+            let is_ctrl = k.modifiers.contains(Modifiers::CTRL);
+            if is_ctrl {
+                return match k.key {
+                    KeyCode::Char('c') | KeyCode::Char('d') => Some(Action::Quit),
+                    KeyCode::Char('k') => Some(Action::RequestReset),
+                    _ => None,
+                };
+            }
+
+            if k.key == KeyCode::Function(3) {
+                return Some(Action::ToggleDebug);
+            }
+
+            if captured {
+                match k.key {
+                    KeyCode::Enter => Some(Action::SubmitInput),
+                    KeyCode::Backspace => Some(Action::DeleteCharacter),
+                    KeyCode::Escape => Some(Action::BackToMenu),
+                    KeyCode::Tab => Some(Action::ToggleLinkMode),
+                    KeyCode::Char(c) => Some(Action::AppendCharacter(c)),
+                    _ => None,
+                }
+            } else {
+                match k.key {
+                    KeyCode::Char('q') => Some(Action::Quit),
+                    KeyCode::Char('r') => Some(Action::Restart),
+                    KeyCode::Char('c') => Some(Action::StartDailyChallenge),
+                    KeyCode::Char('u') => Some(Action::FindDuel),
+                    KeyCode::Char('t') => Some(Action::OpenChat),
+                    KeyCode::Char('v') => Some(Action::WatchReplay),
+                    KeyCode::Char('s') => Some(Action::OpenSettings),
+                    KeyCode::Char('p') => Some(Action::OpenProfile),
+                    KeyCode::Char('e') => Some(Action::ExportData),
+                    KeyCode::Char('d') => Some(Action::LinkDevice),
+                    KeyCode::Char('g') => Some(Action::CreateRecoveryCode),
+                    KeyCode::Char('m') => Some(Action::OpenCalibration),
+                    KeyCode::Char('?') => Some(Action::ToggleHelp),
+                    KeyCode::Char('y') => Some(Action::ConfirmReset),
+                    KeyCode::Char('n') => Some(Action::CancelReset),
+
+                    KeyCode::Char('h') => Some(Action::PreviousTab),
+                    KeyCode::Char('l') => Some(Action::OpenLeaderboard),
+                    KeyCode::Char('a') => Some(Action::CycleSeason),
+                    KeyCode::Char('k') => Some(Action::NavigateUp),
+                    KeyCode::Char('j') => Some(Action::NavigateDown),
+                    KeyCode::LeftArrow => Some(Action::NavigateLeft),
+                    KeyCode::RightArrow => Some(Action::NavigateRight),
+                    KeyCode::UpArrow => Some(Action::NavigateUp),
+                    KeyCode::DownArrow => Some(Action::NavigateDown),
+
+                    KeyCode::Enter => Some(Action::SubmitInput),
+                    KeyCode::Backspace => Some(Action::DeleteCharacter),
+                    KeyCode::Escape => Some(Action::BackToMenu),
+                    KeyCode::Char(c) => Some(Action::AppendCharacter(c)),
+                    _ => None,
+                }
+            }
+        }
+        InputEvent::Mouse(m) => {
+            // 1-index to 0-index
+            let x = m.x.saturating_sub(1);
+            let y = m.y.saturating_sub(1);
+
+            if m.mouse_buttons.contains(MouseButtons::VERT_WHEEL) {
+                return if m.mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                    Some(Action::NavigateUp)
+                } else {
+                    Some(Action::NavigateDown)
+                };
+            }
+            let was_pressed = last_mouse_buttons.contains(MouseButtons::LEFT);
+            let is_pressed = m.mouse_buttons.contains(MouseButtons::LEFT);
+
+            if is_pressed && !was_pressed {
+                Some(Action::MouseClick(x, y))
+            } else {
+                Some(Action::MouseMove(x, y))
+            }
+        }
+        InputEvent::Paste(text) => Some(Action::Paste(text)),
+        _ => None,
+    }
+}
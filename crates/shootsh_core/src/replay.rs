@@ -0,0 +1,380 @@
+use crate::domain::{Point, Size};
+use anyhow::{Result, bail};
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// Version 2 added the seed and `Resize`; version 1 replays still load.
+const FORMAT_VERSION: u8 = 2;
+const TAG_TARGET_SPAWN: u8 = 0;
+const TAG_SCORE: u8 = 1;
+const TAG_MOUSE_MOVE: u8 = 2;
+const TAG_MOUSE_CLICK: u8 = 3;
+const TAG_RESIZE: u8 = 4;
+/// Upper bound on an inflated replay, so a corrupt blob can't exhaust memory.
+const MAX_INFLATED_BYTES: u64 = 16 * 1024 * 1024;
+/// Most runs, and bytes, one batch of uploaded replays may hold.
+pub const MAX_BATCH_RUNS: usize = 50;
+pub const MAX_BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayEvent {
+    TargetSpawn(Point),
+    Score(u32),
+    MouseMove(Point),
+    MouseClick(Point),
+    /// Screen size the next spawns are placed on; recorded before the first
+    /// spawn and again whenever the terminal was resized since.
+    Resize(Size),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayFrame {
+    /// Milliseconds since the run started.
+    pub at_ms: u32,
+    pub event: ReplayEvent,
+}
+
+impl ReplayFrame {
+    /// Appends the frame in the on-disk encoding.
+    pub(crate) fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.at_ms.to_le_bytes());
+        match self.event {
+            ReplayEvent::TargetSpawn(pos) => {
+                out.push(TAG_TARGET_SPAWN);
+                out.extend_from_slice(&pos.x.to_le_bytes());
+                out.extend_from_slice(&pos.y.to_le_bytes());
+            }
+            ReplayEvent::Score(score) => {
+                out.push(TAG_SCORE);
+                out.extend_from_slice(&score.to_le_bytes());
+            }
+            ReplayEvent::MouseMove(pos) => {
+                out.push(TAG_MOUSE_MOVE);
+                out.extend_from_slice(&pos.x.to_le_bytes());
+                out.extend_from_slice(&pos.y.to_le_bytes());
+            }
+            ReplayEvent::MouseClick(pos) => {
+                out.push(TAG_MOUSE_CLICK);
+                out.extend_from_slice(&pos.x.to_le_bytes());
+                out.extend_from_slice(&pos.y.to_le_bytes());
+            }
+            ReplayEvent::Resize(size) => {
+                out.push(TAG_RESIZE);
+                out.extend_from_slice(&size.width.to_le_bytes());
+                out.extend_from_slice(&size.height.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Timestamped event log of a single run, in recording order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Replay {
+    /// Seed of the run's target positions, so a server can place them again;
+    /// `None` for replays recorded before it was kept.
+    pub seed: Option<u64>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn record(&mut self, elapsed: Duration, event: ReplayEvent) {
+        let at_ms = elapsed.as_millis().min(u32::MAX as u128) as u32;
+        self.frames.push(ReplayFrame { at_ms, event });
+    }
+
+    /// Position of the target that was on screen at `elapsed`.
+    pub fn target_at(&self, elapsed: Duration) -> Option<Point> {
+        self.frames_until(elapsed)
+            .filter_map(|f| match f.event {
+                ReplayEvent::TargetSpawn(pos) => Some(pos),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Score the run had reached at `elapsed`.
+    pub fn score_at(&self, elapsed: Duration) -> u32 {
+        self.frames_until(elapsed)
+            .filter_map(|f| match f.event {
+                ReplayEvent::Score(score) => Some(score),
+                _ => None,
+            })
+            .last()
+            .unwrap_or(0)
+    }
+
+    /// Last known cursor position at `elapsed`.
+    pub fn cursor_at(&self, elapsed: Duration) -> Option<Point> {
+        self.frames_until(elapsed)
+            .filter_map(|f| match f.event {
+                ReplayEvent::MouseMove(pos) | ReplayEvent::MouseClick(pos) => Some(pos),
+                _ => None,
+            })
+            .last()
+    }
+
+    /// Clicks recorded within `window` before `elapsed`.
+    pub fn clicks_within(&self, elapsed: Duration, window: Duration) -> Vec<Point> {
+        let from = elapsed.saturating_sub(window).as_millis();
+        self.frames_until(elapsed)
+            .filter(|f| f.at_ms as u128 >= from)
+            .filter_map(|f| match f.event {
+                ReplayEvent::MouseClick(pos) => Some(pos),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn duration(&self) -> Duration {
+        let last = self.frames.last().map_or(0, |f| f.at_ms);
+        Duration::from_millis(last.into())
+    }
+
+    fn frames_until(&self, elapsed: Duration) -> impl Iterator<Item = &ReplayFrame> {
+        let at_ms = elapsed.as_millis();
+        self.frames
+            .iter()
+            .take_while(move |f| f.at_ms as u128 <= at_ms)
+    }
+
+    /// [version u8][has seed u8][seed u64, if any] then per frame:
+    /// [at_ms u32][tag u8][payload], little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(10 + self.frames.len() * 9);
+        out.push(FORMAT_VERSION);
+        match self.seed {
+            Some(seed) => {
+                out.push(1);
+                out.extend_from_slice(&seed.to_le_bytes());
+            }
+            None => out.push(0),
+        }
+
+        for frame in &self.frames {
+            frame.write_to(&mut out);
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader { data, pos: 0 };
+
+        let seed = match reader.u8()? {
+            1 => None,
+            FORMAT_VERSION => match reader.u8()? {
+                0 => None,
+                _ => Some(u64::from_le_bytes(reader.take()?)),
+            },
+            version => bail!("Unsupported replay version: {}", version),
+        };
+
+        let mut frames = Vec::new();
+        while !reader.is_empty() {
+            let at_ms = reader.u32()?;
+            let event = match reader.u8()? {
+                TAG_TARGET_SPAWN => ReplayEvent::TargetSpawn(reader.point()?),
+                TAG_SCORE => ReplayEvent::Score(reader.u32()?),
+                TAG_MOUSE_MOVE => ReplayEvent::MouseMove(reader.point()?),
+                TAG_MOUSE_CLICK => ReplayEvent::MouseClick(reader.point()?),
+                TAG_RESIZE => ReplayEvent::Resize(Size {
+                    width: reader.u16()?,
+                    height: reader.u16()?,
+                }),
+                tag => bail!("Unknown replay event tag: {}", tag),
+            };
+            frames.push(ReplayFrame { at_ms, event });
+        }
+
+        Ok(Self { seed, frames })
+    }
+
+    /// SHA-256 of `to_bytes`, the same for every copy of a run.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+
+    /// Deflated `to_bytes` output, as kept in the `replays` table.
+    pub fn compress(&self) -> Result<Vec<u8>> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&self.to_bytes())?;
+        Ok(encoder.finish()?)
+    }
+
+    pub fn decompress(data: &[u8]) -> Result<Self> {
+        let mut bytes = Vec::new();
+        DeflateDecoder::new(data)
+            .take(MAX_INFLATED_BYTES)
+            .read_to_end(&mut bytes)?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// Compressed replays, each behind its length as a little-endian u32; the
+/// payload of a server's `upload` command.
+pub fn encode_batch(compressed: &[&[u8]]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for replay in compressed {
+        out.extend_from_slice(&(replay.len() as u32).to_le_bytes());
+        out.extend_from_slice(replay);
+    }
+    out
+}
+
+pub fn decode_batch(data: &[u8]) -> Result<Vec<Replay>> {
+    if data.len() > MAX_BATCH_BYTES {
+        bail!("Upload too large");
+    }
+    let mut reader = ByteReader { data, pos: 0 };
+    let mut replays = Vec::new();
+    while !reader.is_empty() {
+        if replays.len() == MAX_BATCH_RUNS {
+            bail!("Too many runs in one upload");
+        }
+        let len = reader.u32()? as usize;
+        let Some(blob) = data.get(reader.pos..reader.pos + len) else {
+            bail!("Truncated upload");
+        };
+        reader.pos += len;
+        replays.push(Replay::decompress(blob)?);
+    }
+    Ok(replays)
+}
+
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl ByteReader<'_> {
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let Some(bytes) = self.data.get(self.pos..self.pos + N) else {
+            bail!("Truncated replay data");
+        };
+        self.pos += N;
+        Ok(bytes.try_into()?)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn point(&mut self) -> Result<Point> {
+        Ok(Point {
+            x: self.u16()?,
+            y: self.u16()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Replay {
+        let mut replay = Replay {
+            seed: Some(42),
+            ..Replay::default()
+        };
+        replay.record(
+            Duration::ZERO,
+            ReplayEvent::Resize(Size {
+                width: 80,
+                height: 24,
+            }),
+        );
+        replay.record(
+            Duration::from_millis(0),
+            ReplayEvent::TargetSpawn(Point { x: 10, y: 5 }),
+        );
+        replay.record(
+            Duration::from_millis(350),
+            ReplayEvent::MouseMove(Point { x: 11, y: 6 }),
+        );
+        replay.record(
+            Duration::from_millis(400),
+            ReplayEvent::MouseClick(Point { x: 11, y: 6 }),
+        );
+        replay.record(Duration::from_millis(400), ReplayEvent::Score(120));
+        replay.record(
+            Duration::from_millis(400),
+            ReplayEvent::TargetSpawn(Point { x: 30, y: 12 }),
+        );
+        replay
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let replay = sample();
+        let decoded = Replay::from_bytes(&replay.to_bytes()).unwrap();
+        assert_eq!(replay, decoded);
+    }
+
+    #[test]
+    fn test_compressed_roundtrip() {
+        let replay = sample();
+        let decoded = Replay::decompress(&replay.compress().unwrap()).unwrap();
+        assert_eq!(replay, decoded);
+    }
+
+    #[test]
+    fn test_batch_roundtrip() {
+        let one = sample().compress().unwrap();
+        let two = Replay::default().compress().unwrap();
+        let batch = encode_batch(&[&one, &two]);
+        assert_eq!(decode_batch(&batch).unwrap(), [sample(), Replay::default()]);
+        assert!(decode_batch(&batch[..batch.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_reads_version_one() {
+        let mut bytes = vec![1];
+        ReplayFrame {
+            at_ms: 0,
+            event: ReplayEvent::Score(120),
+        }
+        .write_to(&mut bytes);
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        assert_eq!(replay.seed, None);
+        assert_eq!(replay.score_at(Duration::ZERO), 120);
+    }
+
+    #[test]
+    fn test_truncated_data_is_rejected() {
+        let bytes = sample().to_bytes();
+        assert!(Replay::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_state_at_elapsed() {
+        let replay = sample();
+        let early = Duration::from_millis(100);
+        let late = Duration::from_millis(500);
+
+        assert_eq!(replay.target_at(early), Some(Point { x: 10, y: 5 }));
+        assert_eq!(replay.score_at(early), 0);
+        assert_eq!(replay.target_at(late), Some(Point { x: 30, y: 12 }));
+        assert_eq!(replay.score_at(late), 120);
+        assert_eq!(replay.cursor_at(early), None);
+        assert_eq!(replay.cursor_at(late), Some(Point { x: 11, y: 6 }));
+        assert_eq!(
+            replay.clicks_within(late, Duration::from_millis(200)),
+            vec![Point { x: 11, y: 6 }]
+        );
+    }
+}
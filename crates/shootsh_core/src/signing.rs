@@ -0,0 +1,94 @@
+use crate::replay::{Replay, ReplayEvent, ReplayFrame};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// HMAC-SHA256 over a run's game events.
+pub type Signature = [u8; 32];
+
+/// Secret shared by a host's frontends and its DB worker. It never leaves
+/// the process, so a fresh one per start is enough.
+#[derive(Clone)]
+pub struct SigningKey([u8; 32]);
+
+impl SigningKey {
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+}
+
+impl std::fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SigningKey(..)")
+    }
+}
+
+/// Running MAC over target spawns and hits, with their timestamps, in
+/// recording order. Mouse input isn't a game event and is skipped.
+#[derive(Clone)]
+pub struct EventSigner {
+    mac: HmacSha256,
+}
+
+impl EventSigner {
+    pub fn new(key: &SigningKey) -> Self {
+        Self {
+            mac: HmacSha256::new_from_slice(&key.0).expect("HMAC accepts any key length"),
+        }
+    }
+
+    pub fn update(&mut self, frame: &ReplayFrame) {
+        if matches!(
+            frame.event,
+            ReplayEvent::TargetSpawn(_) | ReplayEvent::Score(_)
+        ) {
+            let mut bytes = Vec::with_capacity(9);
+            frame.write_to(&mut bytes);
+            self.mac.update(&bytes);
+        }
+    }
+
+    pub fn finish(self) -> Signature {
+        self.mac.finalize().into_bytes().into()
+    }
+}
+
+/// Whether `signature` covers exactly the game events in `replay`.
+pub fn verify(key: &SigningKey, replay: &Replay, signature: &Signature) -> bool {
+    let mut signer = EventSigner::new(key);
+    for frame in &replay.frames {
+        signer.update(frame);
+    }
+    // constant time
+    signer.mac.verify_slice(signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Point;
+    use std::time::Duration;
+
+    #[test]
+    fn test_tampered_replay_fails_verification() {
+        let key = SigningKey::random();
+        let mut signer = EventSigner::new(&key);
+        let mut replay = Replay::default();
+        let events = [
+            ReplayEvent::TargetSpawn(Point { x: 1, y: 1 }),
+            ReplayEvent::MouseMove(Point { x: 2, y: 1 }),
+            ReplayEvent::Score(100),
+        ];
+        for (i, event) in events.into_iter().enumerate() {
+            replay.record(Duration::from_millis(i as u64 * 200), event);
+            signer.update(replay.frames.last().unwrap());
+        }
+        let signature = signer.finish();
+        assert!(verify(&key, &replay, &signature));
+        assert!(!verify(&SigningKey::random(), &replay, &signature));
+
+        replay.frames[2].event = ReplayEvent::Score(900);
+        assert!(!verify(&key, &replay, &signature));
+    }
+}
@@ -0,0 +1,413 @@
+use crate::preferences::ThemeKind;
+use ratatui::style::Color;
+
+/// Palette consumed by every render function in `ui`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub accent: Color,
+    pub text: Color,
+    pub muted: Color,
+    pub score: Color,
+    pub success: Color,
+    pub combo: Color,
+    pub danger: Color,
+    pub target: Color,
+    pub cursor: Color,
+    /// Background of the player's own leaderboard row.
+    pub highlight: Color,
+    pub background: Color,
+    pub footer_bg: Color,
+    /// Mark the player's own leaderboard row with reverse video instead of `highlight`.
+    pub highlight_reversed: bool,
+    /// Activity graph intensity, from no games to many.
+    pub activity: [Color; 5],
+    /// Heatmap cells, low and high density.
+    pub hit: [Color; 2],
+    pub miss: [Color; 2],
+    /// Shape cues so the palette does not rely on hue alone.
+    pub glyphs: Glyphs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetFill {
+    /// Background-colored block.
+    Solid,
+    /// Glyph drawn over the background-colored block.
+    Pattern(char),
+    /// Foreground glyphs only, for terminals with unreliable backgrounds.
+    Chars(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Glyphs {
+    pub target: TargetFill,
+    /// Cell fill per activity level; `None` shows the game count instead.
+    pub activity: Option<[char; 5]>,
+    pub hit: char,
+    pub miss: char,
+    pub both: char,
+}
+
+impl Glyphs {
+    pub const SOLID: Self = Self {
+        target: TargetFill::Solid,
+        activity: None,
+        hit: '█',
+        miss: '█',
+        both: '█',
+    };
+
+    pub const SHAPED: Self = Self {
+        target: TargetFill::Pattern('╳'),
+        activity: Some([' ', '░', '▒', '▓', '█']),
+        hit: 'o',
+        miss: 'x',
+        both: '*',
+    };
+}
+
+impl Theme {
+    pub const CLASSIC: Self = Self {
+        accent: Color::Yellow,
+        text: Color::White,
+        muted: Color::DarkGray,
+        score: Color::Cyan,
+        success: Color::Green,
+        combo: Color::Magenta,
+        danger: Color::Red,
+        target: Color::Red,
+        cursor: Color::LightGreen,
+        highlight: Color::DarkGray,
+        background: Color::Black,
+        footer_bg: Color::Indexed(234),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::DarkGray,
+            Color::Green,
+            Color::LightGreen,
+            Color::White,
+        ],
+        hit: [Color::Green, Color::LightGreen],
+        miss: [Color::Red, Color::LightRed],
+        glyphs: Glyphs::SOLID,
+    };
+
+    pub const OCEAN: Self = Self {
+        accent: Color::LightCyan,
+        text: Color::White,
+        muted: Color::Indexed(244),
+        score: Color::Cyan,
+        success: Color::Indexed(43),
+        combo: Color::LightBlue,
+        danger: Color::LightRed,
+        target: Color::Blue,
+        cursor: Color::LightCyan,
+        highlight: Color::Indexed(24),
+        background: Color::Indexed(17),
+        footer_bg: Color::Indexed(17),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::Indexed(24),
+            Color::Indexed(31),
+            Color::Indexed(39),
+            Color::Indexed(51),
+        ],
+        hit: [Color::Indexed(37), Color::Indexed(51)],
+        miss: [Color::Indexed(168), Color::Indexed(211)],
+        glyphs: Glyphs::SOLID,
+    };
+
+    pub const AMBER: Self = Self {
+        accent: Color::Indexed(214),
+        text: Color::Indexed(223),
+        muted: Color::Indexed(242),
+        score: Color::Indexed(220),
+        success: Color::Indexed(178),
+        combo: Color::Indexed(202),
+        danger: Color::Red,
+        target: Color::Indexed(208),
+        cursor: Color::White,
+        highlight: Color::Indexed(94),
+        background: Color::Black,
+        footer_bg: Color::Indexed(234),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::Indexed(94),
+            Color::Indexed(130),
+            Color::Indexed(172),
+            Color::Indexed(214),
+        ],
+        hit: [Color::Indexed(178), Color::Indexed(226)],
+        miss: [Color::Indexed(124), Color::Indexed(196)],
+        glyphs: Glyphs::SOLID,
+    };
+
+    // The colorblind palettes avoid the confused hue pair and ramp intensity
+    // by brightness, with `Glyphs::SHAPED` as a second cue.
+
+    pub const DEUTERANOPIA: Self = Self {
+        accent: Color::Indexed(220),
+        text: Color::White,
+        muted: Color::Indexed(244),
+        score: Color::Indexed(117),
+        success: Color::Indexed(39),
+        combo: Color::Indexed(214),
+        danger: Color::Indexed(208),
+        target: Color::Indexed(214),
+        cursor: Color::Indexed(117),
+        highlight: Color::Indexed(24),
+        background: Color::Black,
+        footer_bg: Color::Indexed(234),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::Indexed(17),
+            Color::Indexed(25),
+            Color::Indexed(33),
+            Color::Indexed(153),
+        ],
+        hit: [Color::Indexed(33), Color::Indexed(117)],
+        miss: [Color::Indexed(172), Color::Indexed(220)],
+        glyphs: Glyphs::SHAPED,
+    };
+
+    /// Reds read as near-black for protanopes, so the target is a bright yellow.
+    pub const PROTANOPIA: Self = Self {
+        accent: Color::Indexed(226),
+        text: Color::White,
+        muted: Color::Indexed(244),
+        score: Color::Indexed(117),
+        success: Color::Indexed(39),
+        combo: Color::Indexed(229),
+        danger: Color::Indexed(226),
+        target: Color::Indexed(226),
+        cursor: Color::Indexed(51),
+        highlight: Color::Indexed(24),
+        background: Color::Black,
+        footer_bg: Color::Indexed(234),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::Indexed(17),
+            Color::Indexed(25),
+            Color::Indexed(33),
+            Color::Indexed(153),
+        ],
+        hit: [Color::Indexed(33), Color::Indexed(117)],
+        miss: [Color::Indexed(178), Color::Indexed(228)],
+        glyphs: Glyphs::SHAPED,
+    };
+
+    pub const TRITANOPIA: Self = Self {
+        accent: Color::Indexed(204),
+        text: Color::White,
+        muted: Color::Indexed(244),
+        score: Color::Indexed(44),
+        success: Color::Indexed(44),
+        combo: Color::Indexed(211),
+        danger: Color::Indexed(197),
+        target: Color::Indexed(197),
+        cursor: Color::Indexed(87),
+        highlight: Color::Indexed(238),
+        background: Color::Black,
+        footer_bg: Color::Indexed(234),
+        highlight_reversed: false,
+        activity: [
+            Color::Indexed(235),
+            Color::Indexed(240),
+            Color::Indexed(245),
+            Color::Indexed(250),
+            Color::Indexed(255),
+        ],
+        hit: [Color::Indexed(30), Color::Indexed(87)],
+        miss: [Color::Indexed(161), Color::Indexed(211)],
+        glyphs: Glyphs::SHAPED,
+    };
+
+    pub fn from_kind(kind: ThemeKind) -> Self {
+        match kind {
+            ThemeKind::Classic => Self::CLASSIC,
+            ThemeKind::Ocean => Self::OCEAN,
+            ThemeKind::Amber => Self::AMBER,
+            ThemeKind::Deuteranopia => Self::DEUTERANOPIA,
+            ThemeKind::Protanopia => Self::PROTANOPIA,
+            ThemeKind::Tritanopia => Self::TRITANOPIA,
+        }
+    }
+}
+
+/// How many colors the player's terminal can be trusted with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorSupport {
+    #[default]
+    Full,
+    Basic,
+    Monochrome,
+}
+
+impl ColorSupport {
+    /// Guess from the `TERM` / `COLORTERM` pair a client reports.
+    pub fn detect(term: &str, colorterm: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return Self::Full;
+        }
+        if term.contains("256color") || term.contains("direct") {
+            return Self::Full;
+        }
+        if term.is_empty() || term == "dumb" || term.starts_with("vt") {
+            return Self::Monochrome;
+        }
+        Self::Basic
+    }
+
+    /// From a terminfo `colors` capability.
+    pub fn from_color_count(colors: u32) -> Self {
+        match colors {
+            256.. => Self::Full,
+            8.. => Self::Basic,
+            _ => Self::Monochrome,
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "full" | "256" => Some(Self::Full),
+            "basic" | "16" => Some(Self::Basic),
+            "mono" | "none" => Some(Self::Monochrome),
+            _ => None,
+        }
+    }
+}
+
+impl Theme {
+    /// Reduce the palette to what the terminal can show. Backgrounds become
+    /// the terminal default and the target is drawn with characters.
+    pub fn for_terminal(mut self, support: ColorSupport) -> Self {
+        let map: fn(Color) -> Color = match support {
+            ColorSupport::Full => return self,
+            ColorSupport::Basic => to_ansi16,
+            ColorSupport::Monochrome => |_| Color::Reset,
+        };
+
+        for color in [
+            &mut self.accent,
+            &mut self.text,
+            &mut self.muted,
+            &mut self.score,
+            &mut self.success,
+            &mut self.combo,
+            &mut self.danger,
+            &mut self.target,
+            &mut self.cursor,
+            &mut self.highlight,
+        ]
+        .into_iter()
+        .chain(self.activity.iter_mut())
+        .chain(self.hit.iter_mut())
+        .chain(self.miss.iter_mut())
+        {
+            *color = map(*color);
+        }
+
+        self.background = Color::Reset;
+        self.footer_bg = Color::Reset;
+        self.activity[0] = Color::Reset;
+        self.highlight_reversed = support == ColorSupport::Monochrome;
+        self.glyphs = Glyphs {
+            target: TargetFill::Chars('█'),
+            activity: Some(['·', '░', '▒', '▓', '█']),
+            hit: 'o',
+            miss: 'x',
+            both: '*',
+        };
+        self
+    }
+}
+
+/// Nearest of the 16 ANSI colors, as rendered by a stock xterm.
+fn to_ansi16(color: Color) -> Color {
+    const ANSI: [(Color, (u8, u8, u8)); 16] = [
+        (Color::Black, (0, 0, 0)),
+        (Color::Red, (205, 0, 0)),
+        (Color::Green, (0, 205, 0)),
+        (Color::Yellow, (205, 205, 0)),
+        (Color::Blue, (0, 0, 238)),
+        (Color::Magenta, (205, 0, 205)),
+        (Color::Cyan, (0, 205, 205)),
+        (Color::Gray, (229, 229, 229)),
+        (Color::DarkGray, (127, 127, 127)),
+        (Color::LightRed, (255, 0, 0)),
+        (Color::LightGreen, (0, 255, 0)),
+        (Color::LightYellow, (255, 255, 0)),
+        (Color::LightBlue, (92, 92, 255)),
+        (Color::LightMagenta, (255, 0, 255)),
+        (Color::LightCyan, (0, 255, 255)),
+        (Color::White, (255, 255, 255)),
+    ];
+
+    let (r, g, b) = match color {
+        Color::Indexed(n) if n < 16 => return ANSI[n as usize].0,
+        Color::Indexed(n) if n < 232 => {
+            const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+            let n = n - 16;
+            (
+                LEVELS[(n / 36) as usize],
+                LEVELS[(n / 6 % 6) as usize],
+                LEVELS[(n % 6) as usize],
+            )
+        }
+        Color::Indexed(n) => {
+            let v = 8 + (n - 232) * 10;
+            (v, v, v)
+        }
+        Color::Rgb(r, g, b) => (r, g, b),
+        named => return named,
+    };
+
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    ANSI.iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or(Color::Reset, |(c, _)| *c)
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi16_mapping() {
+        assert_eq!(to_ansi16(Color::Indexed(196)), Color::LightRed);
+        assert_eq!(to_ansi16(Color::Indexed(235)), Color::Black);
+        assert_eq!(to_ansi16(Color::Indexed(9)), Color::LightRed);
+        assert_eq!(to_ansi16(Color::Green), Color::Green);
+    }
+
+    #[test]
+    fn test_detect_color_support() {
+        assert_eq!(
+            ColorSupport::detect("xterm-256color", None),
+            ColorSupport::Full
+        );
+        assert_eq!(
+            ColorSupport::detect("xterm", Some("truecolor")),
+            ColorSupport::Full
+        );
+        assert_eq!(ColorSupport::detect("linux", None), ColorSupport::Basic);
+        assert_eq!(
+            ColorSupport::detect("vt100", None),
+            ColorSupport::Monochrome
+        );
+    }
+}
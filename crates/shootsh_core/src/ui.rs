@@ -1,23 +1,53 @@
-use crate::app::{App, LeaderboardTab, NamingState, PlayingState, Scene};
-use crate::db::DbCache;
+use crate::app::{
+    App, CalibrationState, DemoState, DuelLobbyState, EffectKind, FeedbackEvent, GameOverState,
+    LEADERBOARD_PAGE_SIZE, LeaderboardState, LeaderboardTab, MenuFocus, NamingState, PlayingState,
+    Podium, ReplayState, Scene, SettingsState, Transition, TransitionKind, TutorialState,
+    TutorialStep, VERIFY_PANEL_HEIGHT, VerificationState, calibration_marker,
+};
+use crate::chat::ChatRejected;
+use crate::db::{DbCache, LINK_CODE_TTL, ScoreEntry, UserRank};
+use crate::domain::{CombatStats, GameMode, Heatmap};
+use crate::duel::DuelOutcome;
+use crate::i18n::{Messages, fill};
+use crate::preferences::{CursorStyle, HitFeedback, SettingsField};
+use crate::theme::{TargetFill, Theme};
 use chrono::{Datelike, Utc};
 use ratatui::{prelude::*, widgets::*};
-use std::time::Duration;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 const LOGO: &str = include_str!("./logo.txt");
 pub const MIN_WIDTH: u16 = 80;
 pub const MIN_HEIGHT: u16 = 24;
 const TABLE_WIDTH: u16 = 50;
+/// Display columns reserved for player names in score tables.
+const NAME_COLUMN_WIDTH: u16 = 16;
 const NAMING_INPUT_WIDTH: u16 = 40;
 
+const HEATMAP_COLS: u16 = 24;
+const HEATMAP_ROWS: u16 = 8;
+
 const DAYS_IN_WEEK: u16 = 7;
-const WEEKS_TO_DISPLAY: u16 = 15;
+pub(crate) const WEEKS_TO_DISPLAY: u16 = 15;
+const MILESTONE_FLOURISH_MS: u128 = 1200;
+const REPLAY_CLICK_FLASH: std::time::Duration = std::time::Duration::from_millis(150);
+const COMBO_GAUGE_WIDTH: u16 = 30;
+const TIME_GAUGE_WIDTH: u16 = 24;
+const TIME_WARNING_SECS: u64 = 5;
+const TIME_CRITICAL_SECS: u64 = 3;
+const TARGET_GROW_MS: u128 = 120;
+const TARGET_EXPIRY_WARNING_MS: u128 = 300;
+const TARGET_FLASH_MS: u128 = 75;
+const PODIUM_RISE_MS: u128 = 900;
+const PODIUM_STEP_WIDTH: u16 = 14;
+const CONFETTI_COUNT: u32 = 48;
 
 pub fn render(app: &App, cache: &DbCache, f: &mut Frame) {
     let area = f.area();
+    let theme = Theme::from_kind(app.user.preferences.theme).for_terminal(app.color_support);
+    let msg = app.messages();
 
     if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
-        render_size_error(f, area);
+        render_size_error(&theme, msg, f, area);
         return;
     }
 
@@ -33,35 +63,56 @@ pub fn render(app: &App, cache: &DbCache, f: &mut Frame) {
     let footer_area = main_layout[1];
 
     match &app.scene {
-        Scene::Naming(state) => render_naming(app, state, f, main_area),
-        Scene::Menu => render_menu(app, cache, f, main_area),
-        Scene::Playing(state) => render_playing(state, f, main_area),
-        Scene::GameOver {
-            final_score,
-            is_new_record,
-        } => render_game_over(app, cache, *final_score, *is_new_record, f, main_area),
-        Scene::ResetConfirmation => render_reset_confirmation(f, main_area),
+        Scene::Naming(state) => render_naming(&theme, msg, state, f, main_area),
+        Scene::Menu => render_menu(app, &theme, cache, f, main_area),
+        Scene::Playing(state) => render_playing(app, &theme, state, f, main_area),
+        Scene::GameOver(state) => render_game_over(app, &theme, cache, state, f, main_area),
+        Scene::ResetConfirmation => render_reset_confirmation(&theme, msg, f, main_area),
+        Scene::Replay(state) => render_replay(&theme, msg, state, f, main_area),
+        Scene::Settings(state) => render_settings(app, &theme, state, f, main_area),
+        Scene::Profile => render_profile(app, &theme, f, main_area),
+        Scene::Tutorial(state) => render_tutorial(&theme, msg, state, f, main_area),
+        Scene::Leaderboard(state) => render_leaderboard_scene(app, &theme, state, f, main_area),
+        Scene::Calibration(state) => render_calibration(app, &theme, state, f, main_area),
+        Scene::Demo(state) => render_demo(app, &theme, state, f, main_area),
+        Scene::Verification(state) => render_verification(&theme, msg, state, f, main_area),
+        Scene::DuelLobby(state) => render_duel_lobby(&theme, msg, state, f, main_area),
+    }
+    if let Some(transition) = app.transition {
+        render_transition(&theme, transition, f, main_area);
+    }
+    if let Some(event) = app.feedback.flash() {
+        render_feedback_flash(&theme, event, f, main_area);
     }
 
-    render_footer(app, f, footer_area);
-    render_warning(app, f, main_area);
-    render_cursor(app, f);
+    render_footer(app, &theme, f, footer_area);
+    render_warning(app, &theme, f, main_area);
+    render_idle_warning(app, &theme, f, main_area);
+    render_announcement(app, &theme, f, main_area, footer_area);
+    if app.show_help {
+        render_help(&theme, msg, f, main_area);
+    }
+    if app.show_debug {
+        render_debug(app, &theme, f, main_area);
+    }
+    render_cursor(app, &theme, f);
 }
 
-fn render_warning(app: &App, f: &mut Frame, area: Rect) {
-    if let Some(_) = app.last_cheat_warning {
+fn render_warning(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    if app.last_cheat_warning.is_some() {
+        let msg = app.messages();
         let warning_area = absolute_centered_rect(45, 5, area);
 
         f.render_widget(Clear, warning_area);
 
         let block = Block::default()
             .borders(Borders::ALL)
-            .border_style(Style::default().fg(Color::Red).bold())
-            .bg(Color::Black);
+            .border_style(Style::default().fg(theme.danger).bold())
+            .bg(theme.background);
 
         let text = Paragraph::new(vec![
-            Line::from("!! ABNORMAL BEHAVIOR DETECTED !!").red().bold(),
-            Line::from("The interaction was discarded.").dark_gray(),
+            Line::from(msg.cheat_warning).fg(theme.danger).bold(),
+            Line::from(msg.cheat_discarded).fg(theme.muted),
         ])
         .alignment(Alignment::Center)
         .block(block);
@@ -69,18 +120,165 @@ fn render_warning(app: &App, f: &mut Frame, area: Rect) {
         f.render_widget(text, warning_area);
     }
 }
-fn render_cursor(app: &App, f: &mut Frame) {
+fn render_idle_warning(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let Some(left) = app.idle_warning else {
+        return;
+    };
+    let msg = app.messages();
+    let warning_area = absolute_centered_rect(50, 5, area);
+    f.render_widget(Clear, warning_area);
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(theme.accent).bold())
+        .bg(theme.background);
+    let text = Paragraph::new(vec![
+        Line::from(msg.idle_warning).fg(theme.accent).bold(),
+        Line::from(fill(msg.idle_countdown, &[&left.as_secs().max(1)])).fg(theme.muted),
+    ])
+    .alignment(Alignment::Center)
+    .block(block);
+    f.render_widget(text, warning_area);
+}
+
+/// A box along the top edge, or over the footer during a round, where a box
+/// would cover targets.
+fn render_announcement(app: &App, theme: &Theme, f: &mut Frame, area: Rect, footer: Rect) {
+    let Some(text) = &app.announcement else {
+        return;
+    };
+    let msg = app.messages();
+    if matches!(app.scene, Scene::Playing(_)) {
+        let line = Line::from(vec![
+            msg.announcement_title.fg(theme.combo).bold(),
+            format!(" {text} ").fg(theme.text),
+            msg.announcement_close.fg(theme.muted),
+        ]);
+        f.render_widget(Clear, footer);
+        f.render_widget(
+            Paragraph::new(line).style(Style::default().bg(theme.footer_bg)),
+            footer,
+        );
+        return;
+    }
+    let width = (text.width() as u16 + 4).clamp(msg.announcement_close.width() as u16 + 4, 72);
+    let rect = horizontal_centered_rect(width, 5, area);
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(text.as_str())
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .fg(theme.text)
+            .block(
+                Block::default()
+                    .title(msg.announcement_title)
+                    .title_alignment(Alignment::Center)
+                    .title_bottom(Line::from(msg.announcement_close).centered())
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.combo).bold())
+                    .bg(theme.background),
+            ),
+        rect,
+    );
+}
+
+fn render_debug(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let perf = &app.perf;
+    let round_trip = match perf.round_trip {
+        Some(rtt) => format!("{}ms", rtt.as_millis()),
+        None => "n/a".to_string(),
+    };
+    let lines = vec![
+        Line::from(format!("FPS   {}", perf.fps())),
+        Line::from(format!("DRIFT +{}ms", perf.tick_drift.as_millis())),
+        Line::from(format!("RTT   {}", round_trip)),
+    ];
+
+    let width = 18.min(area.width);
+    let rect = Rect::new(area.right() - width, area.y, width, 5.min(area.height));
+    f.render_widget(Clear, rect);
+    f.render_widget(
+        Paragraph::new(lines).fg(theme.text).block(
+            Block::default()
+                .title(" DEBUG ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(theme.muted))
+                .bg(theme.background),
+        ),
+        rect,
+    );
+}
+
+fn render_help(theme: &Theme, msg: &Messages, f: &mut Frame, area: Rect) {
+    // descriptions come from the catalog in the same order
+    let keys = [
+        "click",
+        "r",
+        "ESC",
+        "c",
+        "v",
+        "p",
+        "s",
+        "l",
+        "m",
+        "h, <-/->",
+        "j/k, wheel",
+        "Ctrl-K",
+        "?",
+        "F3",
+        "q, Ctrl-C",
+    ];
+
+    let mut lines = vec![Line::from("")];
+    for (key, desc) in keys.into_iter().zip(msg.help) {
+        let key_style = if key == "Ctrl-K" {
+            Style::default().fg(theme.danger).bold()
+        } else {
+            Style::default().fg(theme.accent)
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {:>14}", key), key_style),
+            Span::raw(format!("  {}", desc)),
+        ]));
+    }
+
+    let popup = absolute_centered_rect(50, keys.len() as u16 + 4, area);
+    f.render_widget(Clear, popup);
+    f.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .title(msg.help_title)
+                .title_alignment(Alignment::Center)
+                .title_bottom(Line::from(msg.help_close).centered())
+                .borders(Borders::ALL)
+                .bg(theme.background),
+        ),
+        popup,
+    );
+}
+
+fn render_cursor(app: &App, theme: &Theme, f: &mut Frame) {
     let area = f.area();
 
-    let mut style = Style::default().fg(Color::LightGreen);
+    // the demo draws its own cursor
+    if matches!(app.scene, Scene::Demo(_)) {
+        return;
+    }
 
-    if let Scene::Playing(state) = &app.scene {
-        if state.target.is_hit(app.mouse_pos.x, app.mouse_pos.y) {
-            style = style.fg(Color::Yellow).add_modifier(Modifier::BOLD);
-        }
+    let mut style = Style::default().fg(theme.cursor);
+
+    if let Scene::Playing(state) = &app.scene
+        && state.target.is_hit(app.mouse_pos.x, app.mouse_pos.y)
+    {
+        style = style.fg(theme.accent).add_modifier(Modifier::BOLD);
     }
 
-    let cursor_lines = vec!["  v  ", "- + -", "  ^  "];
+    let cursor_lines = match app.user.preferences.cursor_style {
+        CursorStyle::Crosshair => vec!["  v  ", "- + -", "  ^  "],
+        CursorStyle::Dot => vec!["+"],
+        CursorStyle::Large => vec!["   |   ", "   v   ", "--> <--", "   ^   ", "   |   "],
+        CursorStyle::Smooth => return render_braille_cursor(app, style, f),
+    };
     let cursor_height = cursor_lines.len() as u16;
     let cursor_width = cursor_lines.iter().map(|s| s.len()).max().unwrap_or(0) as u16;
 
@@ -92,76 +290,251 @@ fn render_cursor(app: &App, f: &mut Frame) {
             let x = app.mouse_pos.x as i32 + j as i32 - offset_x as i32;
             let y = app.mouse_pos.y as i32 + i as i32 - offset_y as i32;
 
-            if x >= 0 && x < area.width as i32 && y >= 0 && y < area.height as i32 {
-                if ch != ' ' {
-                    f.render_widget(
-                        Span::styled(ch.to_string(), style),
-                        Rect::new(x as u16, y as u16, 1, 1),
-                    );
+            if x >= 0 && x < area.width as i32 && y >= 0 && y < area.height as i32 && ch != ' ' {
+                f.render_widget(
+                    Span::styled(ch.to_string(), style),
+                    Rect::new(x as u16, y as u16, 1, 1),
+                );
+            }
+        }
+    }
+}
+
+/// Braille cells hold a 2x4 dot grid, so the crosshair can sit between cells.
+fn render_braille_cursor(app: &App, style: Style, f: &mut Frame) {
+    const ARM: i32 = 3;
+    // bit of each dot, indexed by [column][row] inside a braille cell
+    const DOT_BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+    let area = f.area();
+    let cursor = app.smooth_cursor;
+    // dot coordinates of the cell center
+    let cx = (cursor.x * 2.0 + 1.0).round() as i32;
+    let cy = (cursor.y * 4.0 + 2.0).round() as i32;
+
+    let mut dots = Vec::new();
+    for d in 1..=ARM {
+        dots.extend([(cx - d, cy), (cx + d, cy), (cx, cy - d), (cx, cy + d)]);
+    }
+    dots.push((cx, cy));
+
+    let mut cells: Vec<((i32, i32), u8)> = Vec::new();
+    for (dx, dy) in dots {
+        if dx < 0 || dy < 0 {
+            continue;
+        }
+        let cell = (dx / 2, dy / 4);
+        let bit = DOT_BITS[(dx % 2) as usize][(dy % 4) as usize];
+        match cells.iter_mut().find(|(c, _)| *c == cell) {
+            Some((_, bits)) => *bits |= bit,
+            None => cells.push((cell, bit)),
+        }
+    }
+
+    for ((x, y), bits) in cells {
+        if x < area.width as i32 && y < area.height as i32 {
+            let ch = char::from_u32(0x2800 + bits as u32).unwrap_or(' ');
+            f.render_widget(
+                Span::styled(ch.to_string(), style),
+                Rect::new(x as u16, y as u16, 1, 1),
+            );
+        }
+    }
+}
+
+/// Post-processes the freshly drawn scene: slides it in from the right, or
+/// fades it up from the muted color.
+fn render_transition(theme: &Theme, transition: Transition, f: &mut Frame, area: Rect) {
+    let progress = transition.progress();
+    let buf = f.buffer_mut();
+
+    match transition.kind {
+        TransitionKind::Slide => {
+            let shift = ((1.0 - progress) * area.width as f32) as u16;
+            if shift == 0 {
+                return;
+            }
+            for y in area.top()..area.bottom() {
+                for x in (area.left()..area.right()).rev() {
+                    if x >= area.left() + shift {
+                        buf[(x, y)] = buf[(x - shift, y)].clone();
+                    } else {
+                        buf[(x, y)].reset();
+                        buf[(x, y)].set_bg(theme.background);
+                    }
                 }
             }
         }
+        TransitionKind::Fade => {
+            let style = if progress < 0.4 {
+                Style::default().fg(theme.muted).add_modifier(Modifier::DIM)
+            } else if progress < 0.8 {
+                Style::default().add_modifier(Modifier::DIM)
+            } else {
+                return;
+            };
+            buf.set_style(area, style);
+        }
     }
 }
 
-fn render_footer(app: &App, f: &mut Frame, area: Rect) {
-    let style = Style::default().bg(Color::Indexed(234)).fg(Color::DarkGray);
+fn render_feedback_flash(theme: &Theme, event: FeedbackEvent, f: &mut Frame, area: Rect) {
+    let color = match event {
+        FeedbackEvent::Hit => theme.accent,
+        FeedbackEvent::ComboBreak => theme.danger,
+    };
+    f.render_widget(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .border_style(Style::default().fg(color)),
+        area,
+    );
+}
+
+fn render_footer(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let style = Style::default().bg(theme.footer_bg).fg(theme.muted);
+    let msg = app.messages();
 
     let spans = match &app.scene {
-        Scene::Naming(_) => vec![" [ENTER]".yellow(), " Submit ".into()],
-        Scene::Menu => vec![
-            " [h/l]".yellow(),
-            " Switch Ranking ".into(),
-            " [Ctrl-K]".red(),
-            " Delete Account ".into(),
-            " [q]".yellow(),
-            " Quit ".into(),
+        Scene::Naming(_) => vec![
+            " [ENTER]".fg(theme.accent),
+            format!(" {} ", msg.submit).into(),
+            " [TAB]".fg(theme.accent),
+            format!(" {} ", msg.link_account).into(),
+        ],
+        Scene::Menu => {
+            let mut spans = vec![" [c]".fg(theme.accent), format!(" {}", msg.daily).into()];
+            // only hosts with a lobby can pair players
+            if app.duel_tx.is_some() {
+                spans.extend([" [u]".fg(theme.accent), format!(" {}", msg.duel).into()]);
+            }
+            spans.extend([
+                " [v]".fg(theme.accent),
+                format!(" {}", msg.replay).into(),
+                " [p]".fg(theme.accent),
+                format!(" {}", msg.profile).into(),
+                " [s]".fg(theme.accent),
+                format!(" {}", msg.prefs).into(),
+                " [l]".fg(theme.accent),
+                format!(" {}", msg.ranking).into(),
+                " [?]".fg(theme.accent),
+                format!(" {}", msg.help_short).into(),
+                " [q]".fg(theme.accent),
+                format!(" {}", msg.quit).into(),
+            ]);
+            spans
+        }
+        Scene::Playing(state) if state.duel.is_some() => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.forfeit).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::GameOver(state) if state.duel.is_some() => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [r]".fg(theme.accent),
+            format!(" {} ", msg.duel).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
         ],
         Scene::Playing(_) => vec![
-            " [ESC]".yellow(),
-            " Menu ".into(),
-            " [r]".yellow(),
-            " Restart ".into(),
-            " [q]".yellow(),
-            " Quit ".into(),
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [r]".fg(theme.accent),
+            format!(" {} ", msg.restart).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::GameOver(_) => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [r]".fg(theme.accent),
+            format!(" {} ", msg.retry).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::Settings(_) => vec![
+            " [j/k]".fg(theme.accent),
+            format!(" {} ", msg.select).into(),
+            " [h/l]".fg(theme.accent),
+            format!(" {} ", msg.change).into(),
+            " [g]".fg(theme.accent),
+            format!(" {} ", msg.recovery_code).into(),
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.save_and_back).into(),
+        ],
+        Scene::Leaderboard(_) => vec![
+            " [h/l]".fg(theme.accent),
+            format!(" {} ", msg.tab).into(),
+            " [j/k]".fg(theme.accent),
+            format!(" {} ", msg.page).into(),
+            " [a]".fg(theme.accent),
+            format!(" {} ", msg.seasons).into(),
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::Calibration(_) | Scene::Verification(_) | Scene::DuelLobby(_) => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.cancel).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::Tutorial(_) => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.skip_tutorial).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
         ],
-        Scene::GameOver { .. } => vec![
-            " [ESC]".yellow(),
-            " Menu ".into(),
-            " [r]".yellow(),
-            " Retry ".into(),
-            " [q]".yellow(),
-            " Quit ".into(),
+        Scene::Demo(_) => vec![
+            format!(" [{}]", msg.any_key).fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+        ],
+        Scene::Profile => vec![
+            " [e]".fg(theme.accent),
+            format!(" {} ", msg.export_data).into(),
+            " [d]".fg(theme.accent),
+            format!(" {} ", msg.link_device).into(),
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
+        ],
+        Scene::Replay(_) => vec![
+            " [ESC]".fg(theme.accent),
+            format!(" {} ", msg.menu).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
         ],
         Scene::ResetConfirmation => vec![
-            " [y]".red().bold(),
-            " Confirm RESET ".into(),
-            " [n/ESC]".yellow(),
-            " Cancel ".into(),
-            " [q]".yellow(),
-            " Quit ".into(),
+            " [y]".fg(theme.danger).bold(),
+            format!(" {} ", msg.confirm_reset).into(),
+            " [n/ESC]".fg(theme.accent),
+            format!(" {} ", msg.cancel).into(),
+            " [q]".fg(theme.accent),
+            format!(" {} ", msg.quit).into(),
         ],
     };
 
     f.render_widget(Paragraph::new(Line::from(spans)).style(style), area);
 }
 
-fn render_stats(app: &App, f: &mut Frame, area: Rect) {
+fn render_stats(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let msg = app.messages();
     let name = app.user.name.as_deref().unwrap_or("");
-    let title = format!(" [ {}'S STATS ] ", name);
+    let title = fill(msg.stats_title, &[&name]);
 
-    let total_shots = app.user.total_hits + app.user.total_misses;
-    let acc = if total_shots > 0 {
-        (app.user.total_hits as f64 / total_shots as f64) * 100.0
-    } else {
-        0.0
-    };
+    let acc = app.user.accuracy().unwrap_or(0.0);
 
     let stats_text = vec![
-        Line::from(title).yellow().bold(),
-        Line::from(format!(" Sessions:   {}", app.user.sessions)),
-        Line::from(format!(" High Score: {}", app.user.high_score)).cyan(),
-        Line::from(format!(" Accuracy:   {:.1}%", acc)).green(),
+        Line::from(title).fg(theme.accent).bold(),
+        stat_line(msg.sessions, 11, app.user.sessions),
+        stat_line(msg.high_score, 11, app.user.high_score).fg(theme.score),
+        stat_line(msg.accuracy, 11, format!("{:.1}%", acc)).fg(theme.success),
+        stat_line(msg.best_combo, 11, app.user.best_combo).fg(theme.combo),
     ];
 
     f.render_widget(
@@ -172,20 +545,29 @@ fn render_stats(app: &App, f: &mut Frame, area: Rect) {
     );
 }
 
-fn render_size_error(f: &mut Frame, area: Rect) {
-    let msg = format!(
-        "TERMINAL TOO SMALL\n\nRequired: {}x{}\nCurrent: {}x{}\n\nPlease resize!",
-        MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+/// ` label:   value` with the colon padded to `width` columns.
+fn stat_line(label: &str, width: usize, value: impl std::fmt::Display) -> Line<'static> {
+    Line::from(format!(
+        " {} {}",
+        pad_end(&format!("{}:", label), width),
+        value
+    ))
+}
+
+fn render_size_error(theme: &Theme, msg: &Messages, f: &mut Frame, area: Rect) {
+    let text = fill(
+        msg.terminal_too_small,
+        &[&MIN_WIDTH, &MIN_HEIGHT, &area.width, &area.height],
     );
     f.render_widget(
-        Paragraph::new(msg)
+        Paragraph::new(text)
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Red).bold()),
+            .style(Style::default().fg(theme.danger).bold()),
         area,
     );
 }
 
-fn render_naming(_app: &App, state: &NamingState, f: &mut Frame, area: Rect) {
+fn render_naming(theme: &Theme, msg: &Messages, state: &NamingState, f: &mut Frame, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -200,31 +582,37 @@ fn render_naming(_app: &App, state: &NamingState, f: &mut Frame, area: Rect) {
     let input_area = absolute_centered_rect(NAMING_INPUT_WIDTH, 3, chunks[1]);
 
     f.render_widget(
-        Paragraph::new("WELCOME TO SHOOT.SH")
+        Paragraph::new(msg.welcome)
             .alignment(Alignment::Center)
-            .yellow()
+            .fg(theme.accent)
             .bold(),
         chunks[0],
     );
 
     let input_text = if state.is_loading {
-        format!("{} (Saving...)", state.input)
+        fill(msg.saving, &[&state.input])
     } else {
         state.input.clone()
     };
 
+    // keep the caret end visible if the name is wider than the box
+    let input_text = tail_width(&input_text, NAMING_INPUT_WIDTH.saturating_sub(2) as usize);
     let input = Paragraph::new(input_text.as_str())
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" ENTER YOUR NAME ")
+                .title(if state.linking {
+                    msg.enter_link_code
+                } else {
+                    msg.enter_name
+                })
                 .title_alignment(Alignment::Center),
         )
         .alignment(Alignment::Center)
         .style(if state.is_loading {
-            Style::default().fg(Color::DarkGray)
+            Style::default().fg(theme.muted)
         } else {
-            Style::default().fg(Color::Yellow)
+            Style::default().fg(theme.accent)
         });
 
     f.render_widget(input, input_area);
@@ -232,35 +620,37 @@ fn render_naming(_app: &App, state: &NamingState, f: &mut Frame, area: Rect) {
     if let Some(ref err) = state.error {
         f.render_widget(
             Paragraph::new(err.as_str())
-                .style(Style::default().fg(Color::Red))
+                .style(Style::default().fg(theme.danger))
                 .alignment(Alignment::Center),
             chunks[2],
         );
     }
 
     let footer_text = if state.is_loading {
-        "Please wait..."
+        msg.please_wait
     } else {
-        "Press ENTER to start"
+        msg.press_enter
     };
 
     f.render_widget(
         Paragraph::new(footer_text)
             .alignment(Alignment::Center)
-            .dark_gray(),
+            .fg(theme.muted),
         chunks[3],
     );
 }
 
-fn render_menu(app: &App, cache: &DbCache, f: &mut Frame, area: Rect) {
+fn render_menu(app: &App, theme: &Theme, cache: &DbCache, f: &mut Frame, area: Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints([
-            Constraint::Length(7),  // logo
-            Constraint::Length(4),  // message
-            Constraint::Length(14), // leaderboard
-            Constraint::Min(0),     // activity & stats
+            Constraint::Length(7),                                        // logo
+            Constraint::Length(4),                                        // message
+            Constraint::Length(if cache.motd.is_some() { 3 } else { 0 }), // motd
+            Constraint::Length(if app.has_chat() { 1 } else { 0 }),       // chat
+            Constraint::Length(16),                                       // leaderboard
+            Constraint::Min(0),                                           // activity & stats
         ])
         .split(area);
 
@@ -268,20 +658,43 @@ fn render_menu(app: &App, cache: &DbCache, f: &mut Frame, area: Rect) {
     let logo_width = LOGO.lines().map(|l| l.len()).max().unwrap_or(0) as u16;
     let logo_height = LOGO.lines().count() as u16;
     let logo_area = horizontal_centered_rect(logo_width, logo_height, chunks[0]);
-    f.render_widget(Paragraph::new(LOGO).yellow().bold(), logo_area);
+    f.render_widget(Paragraph::new(LOGO).fg(theme.accent).bold(), logo_area);
 
     // message
-    let mut lines = vec![Line::from("!!! CLICK TO START !!!").bold().slow_blink()];
+    let msg = app.messages();
+    let mut lines = if app.keyboard_aim {
+        vec![
+            Line::from(msg.space_to_start).bold().slow_blink(),
+            Line::from(msg.keyboard_aim_hint).fg(theme.muted),
+        ]
+    } else {
+        vec![Line::from(msg.click_to_start).bold().slow_blink()]
+    };
     if app.user.high_score > 0 {
-        lines.push(Line::from(format!("HIGH SCORE: {}", app.user.high_score)).cyan());
+        lines.push(Line::from(fill(msg.high_score_line, &[&app.user.high_score])).fg(theme.score));
+    }
+    if let Some(modifier) = cache.weekly_modifier {
+        lines.push(Line::from(vec![
+            modifier.name().fg(theme.combo).bold(),
+            format!(" - {}", modifier.description()).fg(theme.muted),
+        ]));
     }
     f.render_widget(
         Paragraph::new(lines).alignment(Alignment::Center),
         chunks[1],
     );
 
+    // motd
+    if let Some(motd) = &cache.motd {
+        render_motd(theme, msg, motd, f, chunks[2]);
+    }
+
+    if app.has_chat() {
+        render_chat_line(app, theme, msg, f, chunks[3]);
+    }
+
     // leaderboard
-    render_leaderboard(app, cache, f, chunks[2], false);
+    render_leaderboard(app, theme, cache, f, chunks[4], false);
 
     // activity & stats
     let activity_stats_layout = Layout::default()
@@ -291,7 +704,7 @@ fn render_menu(app: &App, cache: &DbCache, f: &mut Frame, area: Rect) {
             Constraint::Length(80),
             Constraint::Min(0),
         ])
-        .split(chunks[3]);
+        .split(chunks[5]);
 
     let inner_layout = Layout::default()
         .direction(Direction::Horizontal)
@@ -303,27 +716,709 @@ fn render_menu(app: &App, cache: &DbCache, f: &mut Frame, area: Rect) {
         .split(activity_stats_layout[1]);
 
     // activity
-    render_activity_graph(app, f, inner_layout[0]);
+    render_activity_graph(app, theme, f, inner_layout[0]);
     // stats
-    render_stats(app, f, inner_layout[2]);
+    render_stats(app, theme, f, inner_layout[2]);
 }
 
-fn render_playing(state: &PlayingState, f: &mut Frame, area: Rect) {
-    let time_left = Duration::from_secs(crate::domain::PLAYING_TIME_SEC.into())
+/// The line being typed, or else the latest line anyone sent.
+fn render_chat_line(app: &App, theme: &Theme, msg: &Messages, f: &mut Frame, area: Rect) {
+    let line = if let Some(input) = &app.chat_input {
+        let mut spans = vec![
+            msg.chat_prompt.fg(theme.accent).bold(),
+            format!("{input}_").into(),
+        ];
+        if let Some(error) = app.chat_error {
+            let text = match error {
+                ChatRejected::TooFast => msg.chat_too_fast,
+                ChatRejected::Offensive | ChatRejected::Empty => msg.chat_offensive,
+            };
+            spans.push(format!("  {text}").fg(theme.danger));
+        }
+        Line::from(spans)
+    } else if let Some(chat) = &app.last_chat {
+        Line::from(vec![
+            "[t] ".fg(theme.accent),
+            format!("{}: ", chat.name).bold(),
+            chat.text.clone().fg(theme.muted),
+        ])
+    } else {
+        Line::from(vec!["[t] ".fg(theme.accent), msg.chat_hint.fg(theme.muted)])
+    };
+    f.render_widget(Paragraph::new(line).alignment(Alignment::Center), area);
+}
+
+fn render_motd(theme: &Theme, msg: &Messages, motd: &str, f: &mut Frame, area: Rect) {
+    let max_width = area.width.saturating_sub(4) as usize;
+    let text = fit_width(motd.lines().next().unwrap_or(""), max_width);
+    let width = (text.width() as u16 + 4).max(msg.motd_title.width() as u16 + 4);
+
+    f.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .fg(theme.text)
+            .block(
+                Block::default()
+                    .title(msg.motd_title)
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(theme.combo)),
+            ),
+        horizontal_centered_rect(width, 3, area),
+    );
+}
+
+fn render_playing(app: &App, theme: &Theme, state: &PlayingState, f: &mut Frame, area: Rect) {
+    let time_left = state
+        .config
+        .playing_time
         .saturating_sub(state.scene_start.elapsed());
 
     let score = state.combat_stats.current_score();
     let combo = state.combat_stats.current_combo();
 
-    let stats = Paragraph::new(format!(
-        " SCORE: {} | COMBO {} | TIME: {}s ",
-        score,
-        combo,
-        time_left.as_secs()
+    let msg = app.messages();
+    let mode_label = match state.mode {
+        _ if state.duel.is_some() => msg.hud_duel,
+        GameMode::Standard => "",
+        GameMode::DailyChallenge => msg.hud_daily,
+        GameMode::Practice => msg.hud_practice,
+    };
+
+    let elapsed = state.scene_start.elapsed();
+    let mut extras = match (&state.ghost, &state.duel) {
+        (_, Some(duel)) => fill(msg.hud_opponent, &[&duel.opponent, &duel.opponent_score()]),
+        (Some(ghost), None) => fill(msg.hud_ghost, &[&ghost.score_at(elapsed)]),
+        (None, None) => String::new(),
+    };
+    if app.spectators > 0 {
+        extras.push_str(&fill(msg.hud_watching, &[&app.spectators]));
+    }
+
+    let stats = Paragraph::new(fill(msg.hud, &[&mode_label, &score, &combo, &extras])).bold();
+
+    let [status_row, gauge_row, _] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(0),
+    ])
+    .areas(area);
+    let [stats_area, time_area] =
+        Layout::horizontal([Constraint::Min(0), Constraint::Length(TIME_GAUGE_WIDTH + 1)])
+            .areas(status_row);
+    let [_, combo_area, _] = Layout::horizontal([
+        Constraint::Length(1),
+        Constraint::Length(COMBO_GAUGE_WIDTH),
+        Constraint::Min(0),
+    ])
+    .areas(gauge_row);
+
+    f.render_widget(stats, stats_area);
+    render_time_gauge(
+        theme,
+        time_left,
+        state.config.playing_time,
+        f,
+        Rect {
+            width: time_area.width.saturating_sub(1),
+            ..time_area
+        },
+    );
+    render_combo_gauge(theme, &state.combat_stats, f, combo_area);
+
+    render_ghost(theme, state, f, area);
+
+    let target_rect = Rect::new(
+        state.target.pos.x,
+        state.target.pos.y,
+        state.target.visual_width,
+        state.target.visual_height,
+    );
+
+    let age = state.last_target_spawn.elapsed().as_millis();
+    let lifetime = state
+        .combat_stats
+        .get_target_lifetime(&state.config)
+        .as_millis();
+    let remaining = lifetime.saturating_sub(age);
+
+    // blink between the target and muted color in the last moments
+    let flash_off = remaining < TARGET_EXPIRY_WARNING_MS && (remaining / TARGET_FLASH_MS) % 2 == 1;
+    let target_theme = if flash_off {
+        Theme {
+            target: theme.muted,
+            ..*theme
+        }
+    } else {
+        *theme
+    };
+    render_target(
+        &target_theme,
+        grow_in(target_rect, age).intersection(area),
+        f,
+    );
+
+    if app.user.preferences.effects {
+        render_effects(theme, state, f, area);
+        render_milestone_flourish(theme, msg, state, f, area);
+    }
+}
+
+fn render_time_gauge(
+    theme: &Theme,
+    time_left: std::time::Duration,
+    total: std::time::Duration,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let ratio = if total.is_zero() {
+        0.0
+    } else {
+        (time_left.as_secs_f64() / total.as_secs_f64()).clamp(0.0, 1.0)
+    };
+    // round up so the gauge reads 1s until it is actually empty
+    let secs = time_left.as_millis().div_ceil(1000) as u64;
+    let color = if secs <= TIME_CRITICAL_SECS {
+        theme.danger
+    } else if secs <= TIME_WARNING_SECS {
+        theme.accent
+    } else {
+        theme.success
+    };
+    let mut label_style = Style::default().fg(theme.text).bold();
+    if secs <= TIME_CRITICAL_SECS {
+        label_style = label_style.add_modifier(Modifier::SLOW_BLINK);
+    }
+
+    f.render_widget(
+        Gauge::default()
+            .ratio(ratio)
+            .label(Span::styled(format!("{}s", secs), label_style))
+            .use_unicode(true)
+            .gauge_style(Style::default().fg(color).bg(theme.footer_bg)),
+        area,
+    );
+}
+
+fn render_combo_gauge(theme: &Theme, stats: &CombatStats, f: &mut Frame, area: Rect) {
+    let progress = stats.multiplier_progress();
+    let color = if progress >= 1.0 {
+        theme.combo
+    } else if progress >= 0.5 {
+        theme.accent
+    } else {
+        theme.success
+    };
+
+    f.render_widget(
+        LineGauge::default()
+            .ratio(progress)
+            .label(format!("x{:.1}", stats.current_multiplier()))
+            .filled_style(Style::default().fg(color).bold())
+            .unfilled_style(Style::default().fg(theme.muted)),
+        area,
+    );
+}
+
+fn render_effects(theme: &Theme, state: &PlayingState, f: &mut Frame, area: Rect) {
+    // cells are roughly twice as tall as wide, so the ring is stretched horizontally
+    const RING: [(f32, f32); 8] = [
+        (2.0, 0.0),
+        (1.4, 0.7),
+        (0.0, 1.0),
+        (-1.4, 0.7),
+        (-2.0, 0.0),
+        (-1.4, -0.7),
+        (0.0, -1.0),
+        (1.4, -0.7),
+    ];
+
+    for effect in &state.effects {
+        let progress = effect.progress();
+        let fading = progress > 0.5;
+
+        match effect.kind {
+            EffectKind::HitBurst => {
+                let radius = 1.0 + progress * 2.5;
+                let (ch, color) = if fading {
+                    ("·", theme.muted)
+                } else {
+                    ("*", theme.accent)
+                };
+                for (dx, dy) in RING {
+                    let x = effect.pos.x as f32 + dx * radius;
+                    let y = effect.pos.y as f32 + dy * radius;
+                    if x < 0.0 || y < 0.0 {
+                        continue;
+                    }
+                    let rect =
+                        Rect::new(x.round() as u16, y.round() as u16, 1, 1).intersection(area);
+                    if !rect.is_empty() {
+                        f.render_widget(Span::styled(ch, Style::default().fg(color)), rect);
+                    }
+                }
+            }
+            EffectKind::MissMarker => {
+                let style = if fading {
+                    Style::default().fg(theme.muted)
+                } else {
+                    Style::default().fg(theme.danger).bold()
+                };
+                let rect = Rect::new(effect.pos.x, effect.pos.y, 1, 1).intersection(area);
+                if !rect.is_empty() {
+                    f.render_widget(Span::styled("X", style), rect);
+                }
+            }
+        }
+    }
+}
+
+fn render_tutorial(
+    theme: &Theme,
+    msg: &Messages,
+    state: &TutorialState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let (title, lines) = match state.step {
+        TutorialStep::Aim => (
+            msg.tutorial_aim_title,
+            vec![
+                Line::from(msg.tutorial_aim[0]),
+                Line::from(msg.tutorial_aim[1]).fg(theme.muted),
+            ],
+        ),
+        TutorialStep::Shoot => (
+            msg.tutorial_shoot_title,
+            vec![
+                Line::from(msg.tutorial_shoot[0]),
+                Line::from(fill(msg.tutorial_shoot[1], &[&state.targets_hit])).fg(theme.muted),
+            ],
+        ),
+        TutorialStep::Combo => (
+            msg.tutorial_combo_title,
+            vec![
+                Line::from(msg.tutorial_combo[0]),
+                Line::from(msg.tutorial_combo[1]),
+                Line::from(msg.tutorial_combo[2]),
+                Line::from(""),
+                Line::from(msg.tutorial_combo[3]).fg(theme.accent),
+            ],
+        ),
+    };
+
+    let height = lines.len() as u16 + 2;
+    let panel = Rect::new(area.x, area.y, area.width, height.min(area.height));
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .title(title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL),
+        ),
+        panel,
+    );
+
+    if let Some(target) = &state.target {
+        let target_rect = Rect::new(
+            target.pos.x,
+            target.pos.y,
+            target.visual_width,
+            target.visual_height,
+        );
+        render_target(theme, target_rect.intersection(area), f);
+    }
+}
+
+fn render_verification(
+    theme: &Theme,
+    msg: &Messages,
+    state: &VerificationState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let round = (state.round + 1).min(state.round_count());
+    let lines = vec![
+        Line::from(msg.verify_prompt),
+        Line::from(fill(msg.verify_progress, &[&round, &state.round_count()])).fg(theme.muted),
+    ];
+    let panel = Rect::new(
+        area.x,
+        area.y,
+        area.width,
+        VERIFY_PANEL_HEIGHT.min(area.height),
+    );
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .title(msg.verify_title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL),
+        ),
+        panel,
+    );
+
+    if let Some(target) = &state.target {
+        let target_rect = Rect::new(
+            target.pos.x,
+            target.pos.y,
+            target.visual_width,
+            target.visual_height,
+        );
+        render_target(theme, target_rect.intersection(area), f);
+    }
+}
+
+/// Search timer while the lobby looks for an opponent, then who it found
+/// and the countdown to the round.
+fn render_duel_lobby(
+    theme: &Theme,
+    msg: &Messages,
+    state: &DuelLobbyState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let lines = match &state.link {
+        None => vec![
+            Line::from(""),
+            Line::from(fill(
+                msg.duel_searching,
+                &[&state.since.elapsed().as_secs()],
+            ))
+            .fg(theme.accent),
+            Line::from(msg.duel_rules).fg(theme.muted),
+        ],
+        Some(link) => {
+            let seconds = link.starts_in().as_secs_f32().ceil() as u64;
+            let record = link.record;
+            vec![
+                Line::from(fill(msg.duel_found, &[&link.opponent]))
+                    .bold()
+                    .fg(theme.accent),
+                Line::from(fill(msg.duel_starts_in, &[&seconds])).fg(theme.combo),
+                Line::from(fill(
+                    msg.duel_record,
+                    &[&record.wins, &record.losses, &record.draws],
+                ))
+                .fg(theme.muted),
+            ]
+        }
+    };
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .title(msg.duel)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL),
+        ),
+        absolute_centered_rect(50, 5, area),
+    );
+}
+
+fn render_calibration(
+    app: &App,
+    theme: &Theme,
+    state: &CalibrationState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let msg = app.messages();
+    let lines = vec![
+        Line::from(fill(
+            msg.calibration_prompt,
+            &[&(state.errors.len() + 1), &state.marker_count()],
+        )),
+        Line::from(fill(
+            msg.calibration_offset,
+            &[&app.mouse_offset.0, &app.mouse_offset.1],
+        ))
+        .fg(theme.muted),
+    ];
+    let panel = Rect::new(area.x, area.y, area.width, 4.min(area.height));
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center).block(
+            Block::default()
+                .title(msg.calibration_title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL),
+        ),
+        panel,
+    );
+
+    for index in 0..state.marker_count() {
+        let marker = calibration_marker(app.screen_size, index);
+        let style = match index.cmp(&state.errors.len()) {
+            std::cmp::Ordering::Less => Style::default().fg(theme.success),
+            std::cmp::Ordering::Equal => Style::default().fg(theme.target).bold(),
+            std::cmp::Ordering::Greater => Style::default().fg(theme.muted),
+        };
+        // a plus sign with the marker cell in its center
+        let arms = [
+            (0, 0, "+"),
+            (-1, 0, "-"),
+            (1, 0, "-"),
+            (0, -1, "|"),
+            (0, 1, "|"),
+        ];
+        for (dx, dy, ch) in arms {
+            let x = marker.x as i32 + dx;
+            let y = marker.y as i32 + dy;
+            if x < 0 || y < 0 {
+                continue;
+            }
+            let rect = Rect::new(x as u16, y as u16, 1, 1).intersection(area);
+            if !rect.is_empty() {
+                f.render_widget(Span::styled(ch, style), rect);
+            }
+        }
+    }
+}
+
+/// Scales a freshly spawned target up from its center over `TARGET_GROW_MS`.
+fn grow_in(rect: Rect, age_ms: u128) -> Rect {
+    if age_ms >= TARGET_GROW_MS {
+        return rect;
+    }
+    let t = (age_ms as f32 / TARGET_GROW_MS as f32).max(0.25);
+    let width = ((rect.width as f32 * t).ceil() as u16).max(1);
+    let height = ((rect.height as f32 * t).ceil() as u16).max(1);
+    Rect::new(
+        rect.x + rect.width.saturating_sub(width) / 2,
+        rect.y + rect.height.saturating_sub(height) / 2,
+        width,
+        height,
+    )
+}
+
+fn render_target(theme: &Theme, rect: Rect, f: &mut Frame) {
+    if rect.is_empty() {
+        return;
+    }
+    let (ch, style) = match theme.glyphs.target {
+        TargetFill::Solid => {
+            f.render_widget(Block::default().bg(theme.target), rect);
+            return;
+        }
+        TargetFill::Pattern(ch) => (ch, Style::default().fg(theme.background).bg(theme.target)),
+        TargetFill::Chars(ch) => (ch, Style::default().fg(theme.target)),
+    };
+    let fill = ch.to_string().repeat(rect.width as usize);
+    let lines = vec![Line::from(fill); rect.height as usize];
+    f.render_widget(Paragraph::new(lines).style(style), rect);
+}
+
+fn render_settings(app: &App, theme: &Theme, state: &SettingsState, f: &mut Frame, area: Rect) {
+    let msg = app.messages();
+    let prefs = &app.user.preferences;
+    let selected = state.field();
+
+    let mut lines = vec![Line::from("")];
+    for (field, label) in SettingsField::ALL.into_iter().zip(msg.settings_fields) {
+        let is_selected = field == selected;
+        let marker = if is_selected { "> " } else { "  " };
+        let value_label = match field {
+            SettingsField::Effects if prefs.effects => msg.on,
+            SettingsField::Effects => msg.off,
+            SettingsField::Motion if prefs.reduced_motion => msg.motion_reduced,
+            SettingsField::Motion => msg.motion_full,
+            SettingsField::Feedback => match prefs.hit_feedback {
+                HitFeedback::Off => msg.off,
+                HitFeedback::Bell => msg.bell,
+                HitFeedback::Flash => msg.flash,
+            },
+            SettingsField::Region => prefs.region.map_or(msg.region_none, |r| r.code()),
+            _ => field.value_label(prefs),
+        };
+        let value = format!("< {} >", value_label);
+
+        let style = if is_selected {
+            Style::default().fg(theme.accent).bold()
+        } else {
+            Style::default()
+        };
+
+        lines.push(
+            Line::from(vec![
+                Span::styled(format!("{}{}", marker, pad_end(label, 10)), style),
+                Span::styled(pad_start(&value, 20), style),
+            ])
+            .alignment(Alignment::Center),
+        );
+    }
+
+    let (mut width, mut height) = (44, SettingsField::ALL.len() as u16 + 4);
+    if let Some(code) = &app.recovery_code {
+        let grouped: Vec<&str> = code
+            .as_bytes()
+            .chunks(4)
+            .map(|c| std::str::from_utf8(c).unwrap_or_default())
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(
+            Line::from(fill(msg.recovery_code_line, &[&grouped.join("-")]))
+                .centered()
+                .fg(theme.combo)
+                .bold(),
+        );
+        lines.push(
+            Line::from(msg.recovery_code_hint)
+                .centered()
+                .fg(theme.muted),
+        );
+        width = 52;
+        height += 3;
+    }
+
+    let block = Block::default()
+        .title(msg.settings_title)
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL);
+
+    f.render_widget(
+        Paragraph::new(lines).block(block),
+        absolute_centered_rect(width, height, area),
+    );
+}
+
+fn render_profile(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let msg = app.messages();
+    let user = &app.user;
+    let name = user.name.as_deref().unwrap_or("");
+
+    let acc = user.accuracy().unwrap_or(0.0);
+    let reaction = user
+        .average_reaction_ms()
+        .map_or("-".to_string(), |ms| format!("{} ms", ms));
+    let best_acc = format!("{:.1}%", user.best_accuracy_bp as f64 / 100.0);
+    let best_reaction = user
+        .best_reaction_ms
+        .map_or("-".to_string(), |ms| format!("{} ms", ms));
+
+    let popup = absolute_centered_rect(52, 20, area);
+    let mut block = Block::default()
+        .title(fill(msg.profile_title, &[&name]))
+        .title_alignment(Alignment::Center)
+        .borders(Borders::ALL);
+    if let Some((code, issued)) = &app.link_code {
+        let minutes_left = LINK_CODE_TTL
+            .saturating_sub(issued.elapsed())
+            .as_secs()
+            .div_ceil(60);
+        block = block.title_bottom(
+            Line::from(fill(msg.link_code_line, &[code, &minutes_left]))
+                .centered()
+                .fg(theme.combo),
+        );
+    } else if app.takeout_failed {
+        block = block.title_bottom(Line::from(msg.export_failed).centered().fg(theme.danger));
+    }
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(11),
+            Constraint::Length(1),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    let stats_text = vec![
+        Line::from(""),
+        stat_line(msg.sessions, 14, user.sessions),
+        stat_line(msg.high_score, 14, user.high_score).fg(theme.score),
+        stat_line(msg.hits, 14, user.total_hits),
+        stat_line(msg.misses, 14, user.total_misses),
+        stat_line(msg.accuracy, 14, format!("{:.1}%", acc)).fg(theme.success),
+        stat_line(msg.best_combo, 14, user.best_combo).fg(theme.combo),
+        stat_line(msg.avg_reaction, 14, reaction).fg(theme.accent),
+        stat_line(msg.best_accuracy, 14, best_acc).fg(theme.success),
+        stat_line(msg.best_reaction, 14, best_reaction).fg(theme.accent),
+    ];
+    f.render_widget(Paragraph::new(stats_text), layout[0]);
+
+    f.render_widget(
+        Paragraph::new(fill(
+            msg.last_games,
+            &[
+                &user.score_history.len(),
+                user.score_history.iter().max().unwrap_or(&0),
+            ],
+        ))
+        .fg(theme.muted),
+        layout[1],
+    );
+
+    let history: Vec<u64> = user.score_history.iter().map(|&s| s as u64).collect();
+    f.render_widget(
+        Sparkline::default()
+            .data(&history)
+            .style(Style::default().fg(theme.target)),
+        layout[2].inner(Margin::new(1, 0)),
+    );
+}
+
+fn render_replay(theme: &Theme, msg: &Messages, state: &ReplayState, f: &mut Frame, area: Rect) {
+    let elapsed = state.started.elapsed();
+    let replay = &state.replay;
+
+    let header = Paragraph::new(fill(
+        msg.replay_header,
+        &[
+            &replay.score_at(elapsed),
+            &format!("{:.1}", elapsed.min(replay.duration()).as_secs_f64()),
+            &format!("{:.1}", replay.duration().as_secs_f64()),
+        ],
     ))
-    .bold();
+    .bold()
+    .fg(theme.combo);
+    f.render_widget(header, Rect::new(area.x, area.y, area.width, 1));
+
+    if let Some(pos) = replay.target_at(elapsed) {
+        let target_rect = Rect::new(
+            pos.x,
+            pos.y,
+            state.config.target_width,
+            state.config.target_height,
+        )
+        .intersection(area);
+        render_target(theme, target_rect, f);
+    }
+
+    for click in replay.clicks_within(elapsed, REPLAY_CLICK_FLASH) {
+        let click_rect = Rect::new(click.x, click.y, 1, 1).intersection(area);
+        if !click_rect.is_empty() {
+            f.render_widget(
+                Span::styled("*", Style::default().fg(theme.accent).bold()),
+                click_rect,
+            );
+        }
+    }
 
-    f.render_widget(stats, Rect::new(area.x, area.y, area.width, 1));
+    if let Some(cursor) = replay.cursor_at(elapsed) {
+        let cursor_rect = Rect::new(cursor.x, cursor.y, 1, 1).intersection(area);
+        if !cursor_rect.is_empty() {
+            f.render_widget(
+                Span::styled("+", Style::default().fg(theme.cursor).bold()),
+                cursor_rect,
+            );
+        }
+    }
+}
+
+fn render_demo(app: &App, theme: &Theme, state: &DemoState, f: &mut Frame, area: Rect) {
+    let msg = app.messages();
+    let header = Paragraph::new(fill(
+        msg.demo_header,
+        &[
+            &state.combat_stats.current_score(),
+            &state.combat_stats.current_combo(),
+        ],
+    ))
+    .bold()
+    .fg(theme.combo);
+    f.render_widget(header, Rect::new(area.x, area.y, area.width, 1));
 
     let target_rect = Rect::new(
         state.target.pos.x,
@@ -331,19 +1426,98 @@ fn render_playing(state: &PlayingState, f: &mut Frame, area: Rect) {
         state.target.visual_width,
         state.target.visual_height,
     );
+    let age = state.last_target_spawn.elapsed().as_millis();
+    render_target(theme, grow_in(target_rect, age).intersection(area), f);
+
+    let cursor_rect = Rect::new(
+        state.cursor.0.round() as u16,
+        state.cursor.1.round() as u16,
+        1,
+        1,
+    )
+    .intersection(area);
+    if !cursor_rect.is_empty() {
+        f.render_widget(
+            Span::styled("+", Style::default().fg(theme.cursor).bold()),
+            cursor_rect,
+        );
+    }
 
-    let visible_rect = target_rect.intersection(area);
+    if (app.last_scene_change.elapsed().as_millis() / 500).is_multiple_of(2) {
+        let hint = Paragraph::new(msg.demo_hint)
+            .alignment(Alignment::Center)
+            .bold()
+            .fg(theme.accent);
+        f.render_widget(
+            hint,
+            Rect::new(area.x, area.bottom().saturating_sub(2), area.width, 1),
+        );
+    }
+}
 
-    if !visible_rect.is_empty() {
-        f.render_widget(Block::default().bg(Color::Red), visible_rect);
+fn render_ghost(theme: &Theme, state: &PlayingState, f: &mut Frame, area: Rect) {
+    let Some(pos) = state
+        .ghost
+        .as_ref()
+        .and_then(|g| g.target_at(state.scene_start.elapsed()))
+    else {
+        return;
+    };
+
+    let ghost_rect = Rect::new(
+        pos.x,
+        pos.y,
+        state.config.target_width,
+        state.config.target_height,
+    )
+    .intersection(area);
+
+    if !ghost_rect.is_empty() {
+        let fill = "░".repeat(ghost_rect.width as usize);
+        let lines = vec![Line::from(fill); ghost_rect.height as usize];
+        f.render_widget(Paragraph::new(lines).fg(theme.muted), ghost_rect);
+    }
+}
+
+fn render_milestone_flourish(
+    theme: &Theme,
+    msg: &Messages,
+    state: &PlayingState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let Some((combo, at)) = state.last_milestone else {
+        return;
+    };
+    let elapsed = at.elapsed().as_millis();
+    if elapsed >= MILESTONE_FLOURISH_MS {
+        return;
     }
+
+    // alternate colors every 150ms for a bit of sparkle
+    let color = if (elapsed / 150) % 2 == 0 {
+        theme.accent
+    } else {
+        theme.combo
+    };
+    let text = fill(
+        msg.milestone,
+        &[&combo, &CombatStats::milestone_bonus(combo)],
+    );
+
+    f.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(color).bold()),
+        Rect::new(area.x, area.y + 2, area.width, 1),
+    );
 }
 
 fn render_game_over(
     app: &App,
+    theme: &Theme,
     cache: &DbCache,
-    score: u32,
-    is_new_record: bool,
+    state: &GameOverState,
     f: &mut Frame,
     area: Rect,
 ) {
@@ -353,46 +1527,218 @@ fn render_game_over(
         .constraints([Constraint::Length(5), Constraint::Min(0)])
         .split(area);
 
-    let msg = vec![
-        Line::from(format!("FINAL SCORE: {}", score).bold().green()),
-        Line::from(if is_new_record {
-            "!!! NEW HIGH SCORE !!!"
+    let msg = app.messages();
+    let mut lines = vec![
+        Line::from(
+            fill(msg.final_score, &[&state.final_score])
+                .bold()
+                .fg(theme.success),
+        ),
+        Line::from(if state.is_new_record {
+            msg.new_high_score
+        } else if state.mode == GameMode::DailyChallenge {
+            msg.challenge_complete
         } else {
-            "TRY AGAIN!"
+            msg.try_again
         })
-        .yellow(),
-        Line::from("Click to return Menu").italic(),
+        .fg(theme.accent),
+        Line::from(msg.click_to_return).italic(),
     ];
-    f.render_widget(Paragraph::new(msg).alignment(Alignment::Center), chunks[0]);
-    render_leaderboard(app, cache, f, chunks[1], true);
+    let percentile = cache
+        .score_percentiles
+        .get(&app.user.id)
+        .filter(|p| p.score == state.final_score && state.mode != GameMode::Practice);
+    if let Some(p) = percentile {
+        lines.insert(
+            1,
+            Line::from(fill(msg.top_percent_today, &[&p.top_percent])).fg(theme.score),
+        );
+    }
+    if let Some(duel) = &state.duel {
+        let args: [&dyn std::fmt::Display; 2] = [&duel.opponent, &duel.opponent_score()];
+        let line = match duel.outcome() {
+            None => Line::from(fill(msg.duel_waiting, &args)).fg(theme.muted),
+            Some(DuelOutcome::Won) => Line::from(fill(msg.duel_won, &args))
+                .bold()
+                .fg(theme.success),
+            Some(DuelOutcome::Lost) => Line::from(fill(msg.duel_lost, &args))
+                .bold()
+                .fg(theme.danger),
+            Some(DuelOutcome::Draw) => Line::from(fill(msg.duel_draw, &args))
+                .bold()
+                .fg(theme.combo),
+        };
+        lines.insert(1, line);
+    }
+    if let Some(podium) = state.podium {
+        lines.insert(
+            1,
+            Line::from(fill(msg.podium_place, &[&podium.rank]))
+                .bold()
+                .fg(theme.combo),
+        );
+    }
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center),
+        chunks[0],
+    );
+
+    if let Some(podium) = state.podium {
+        render_podium(app, theme, podium, f, chunks[1]);
+        return;
+    }
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(HEATMAP_COLS + 2), // borders
+        ])
+        .split(chunks[1]);
+
+    render_leaderboard(app, theme, cache, f, body[0], true);
+    render_heatmap(theme, msg, &state.heatmap, f, body[1]);
+}
+
+/// Steps rise first, then the player's name lands on theirs while confetti
+/// falls; the app drops the podium once its timeline ends.
+fn render_podium(app: &App, theme: &Theme, podium: Podium, f: &mut Frame, area: Rect) {
+    // display order left to right, with step heights
+    const STEPS: [(u32, u16); 3] = [(2, 5), (1, 7), (3, 3)];
+
+    let elapsed = podium.elapsed().as_millis();
+    let rise = (elapsed as f32 / PODIUM_RISE_MS as f32).min(1.0);
+    let left = horizontal_centered_rect(PODIUM_STEP_WIDTH * 3, area.height, area).x;
+
+    for (i, (place, height)) in STEPS.into_iter().enumerate() {
+        let height = ((height as f32 * rise).ceil() as u16).min(area.height.saturating_sub(1));
+        let step = Rect::new(
+            left + i as u16 * PODIUM_STEP_WIDTH,
+            area.bottom().saturating_sub(height),
+            PODIUM_STEP_WIDTH,
+            height,
+        )
+        .intersection(area);
+        let is_own = place == podium.rank;
+        let color = if is_own { theme.accent } else { theme.muted };
+
+        f.render_widget(
+            Paragraph::new(place.to_string())
+                .alignment(Alignment::Center)
+                .bold()
+                .fg(color)
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(color)),
+                ),
+            step,
+        );
+
+        if is_own && rise >= 1.0 && step.y > area.y {
+            let name = app.user.name.as_deref().unwrap_or("");
+            f.render_widget(
+                Paragraph::new(fit_width(name, PODIUM_STEP_WIDTH as usize))
+                    .alignment(Alignment::Center)
+                    .bold()
+                    .fg(theme.accent),
+                Rect::new(step.x, step.y - 1, step.width, 1),
+            );
+        }
+    }
+
+    if elapsed < PODIUM_RISE_MS / 2 || area.is_empty() {
+        return;
+    }
+    // deterministic pseudo-random column, speed and glyph per flake
+    let glyphs = ['*', '+', '.', 'o'];
+    let colors = [theme.accent, theme.success, theme.combo, theme.target];
+    let secs = (elapsed - PODIUM_RISE_MS / 2) as f32 / 1000.0;
+    for i in 0..CONFETTI_COUNT {
+        let hash = i.wrapping_mul(2_654_435_761);
+        let x = area.x + (hash % area.width as u32) as u16;
+        let speed = 6.0 + (hash >> 8) as f32 % 10.0;
+        let phase = (hash >> 16) as f32 % area.height as f32;
+        let y = area.y + ((secs * speed + phase) % area.height as f32) as u16;
+        f.render_widget(
+            Span::styled(
+                glyphs[(hash >> 4) as usize % glyphs.len()].to_string(),
+                Style::default().fg(colors[(hash >> 12) as usize % colors.len()]),
+            ),
+            Rect::new(x, y, 1, 1),
+        );
+    }
 }
 
-fn render_reset_confirmation(f: &mut Frame, area: Rect) {
+fn render_heatmap(theme: &Theme, msg: &Messages, heatmap: &Heatmap, f: &mut Frame, area: Rect) {
+    let grid = heatmap.grid(HEATMAP_COLS, HEATMAP_ROWS);
+
+    let mut lines: Vec<Line> = grid
+        .iter()
+        .map(|row| {
+            let spans: Vec<Span> = row
+                .iter()
+                .map(|&(hits, misses)| {
+                    let glyphs = &theme.glyphs;
+                    let (ch, color) = match (hits, misses) {
+                        (0, 0) => ('·', theme.activity[0]),
+                        (_, 0) => (glyphs.hit, heat_color(hits, theme.hit)),
+                        (0, _) => (glyphs.miss, heat_color(misses, theme.miss)),
+                        _ => (glyphs.both, theme.accent),
+                    };
+                    Span::styled(ch.to_string(), Style::default().fg(color))
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect();
+
+    lines.push(Line::from(vec![
+        theme.glyphs.hit.to_string().fg(theme.hit[0]),
+        msg.aim_hit.fg(theme.muted),
+        theme.glyphs.miss.to_string().fg(theme.miss[0]),
+        msg.aim_miss.fg(theme.muted),
+        theme.glyphs.both.to_string().fg(theme.accent),
+        msg.aim_both.fg(theme.muted),
+    ]));
+
+    let height = (HEATMAP_ROWS + 3).min(area.height);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().title(msg.aim_map).borders(Borders::ALL)),
+        Rect::new(area.x, area.y, area.width, height),
+    );
+}
+
+fn heat_color(count: u32, [low, high]: [Color; 2]) -> Color {
+    if count <= 2 { low } else { high }
+}
+
+fn render_reset_confirmation(theme: &Theme, msg: &Messages, f: &mut Frame, area: Rect) {
     let block_area = absolute_centered_rect(50, 10, area);
 
     f.render_widget(Clear, block_area);
 
     let block = Block::default()
-        .title(" DANGER ACTION ")
+        .title(msg.danger_title)
         .title_alignment(Alignment::Center)
         .borders(Borders::ALL)
-        .border_style(Style::default().fg(Color::Red).bold())
-        .bg(Color::Black);
+        .border_style(Style::default().fg(theme.danger).bold())
+        .bg(theme.background);
 
     let text = vec![
         Line::from(""),
-        Line::from("Are you sure you want to").alignment(Alignment::Center),
-        Line::from("DELETE ALL YOUR DATA?")
-            .red()
+        Line::from(msg.reset_question).alignment(Alignment::Center),
+        Line::from(msg.reset_target)
+            .fg(theme.danger)
             .bold()
             .alignment(Alignment::Center),
         Line::from(""),
         Line::from(""),
         Line::from(vec![
-            " Confirm: ".into(),
-            "y".yellow().bold(),
-            "  |  Cancel: ".into(),
-            "n / Esc".yellow().bold(),
+            msg.reset_confirm.into(),
+            "y".fg(theme.accent).bold(),
+            msg.reset_cancel.into(),
+            "n / Esc".fg(theme.accent).bold(),
         ])
         .alignment(Alignment::Center),
     ];
@@ -401,76 +1747,225 @@ fn render_reset_confirmation(f: &mut Frame, area: Rect) {
     f.render_widget(paragraph, block_area);
 }
 
-fn render_leaderboard(app: &App, cache: &DbCache, f: &mut Frame, area: Rect, _is_game_over: bool) {
-    let (scores, title) = match app.leaderboard_tab {
-        LeaderboardTab::Daily => (&cache.daily_scores, " DAILY RANKING "),
-        LeaderboardTab::Weekly => (&cache.weekly_scores, " WEEKLY RANKING "),
-        LeaderboardTab::AllTime => (&cache.all_time_scores, " OVERALL RANKING "),
+/// Tab strip drawn into the table border, the active tab highlighted.
+fn leaderboard_tabs(theme: &Theme, msg: &Messages, active: LeaderboardTab) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (i, tab) in LeaderboardTab::ALL.into_iter().enumerate() {
+        if i > 0 {
+            spans.push("|".fg(theme.muted));
+        }
+        let label = match tab {
+            LeaderboardTab::Daily => msg.tab_daily,
+            LeaderboardTab::Weekly => msg.tab_weekly,
+            LeaderboardTab::AllTime => msg.tab_all_time,
+            LeaderboardTab::DailyChallenge => msg.tab_challenge,
+        };
+        spans.push(if tab == active {
+            label.fg(theme.accent).bold().reversed()
+        } else {
+            label.fg(theme.muted)
+        });
+    }
+    Line::from(spans)
+}
+
+fn render_leaderboard(
+    app: &App,
+    theme: &Theme,
+    cache: &DbCache,
+    f: &mut Frame,
+    area: Rect,
+    _is_game_over: bool,
+) {
+    let scores = match app.leaderboard_tab {
+        LeaderboardTab::Daily => &cache.daily_scores,
+        LeaderboardTab::Weekly => &cache.weekly_scores,
+        LeaderboardTab::AllTime => &cache.all_time_scores,
+        LeaderboardTab::DailyChallenge => &cache.daily_challenge_scores,
+    };
+
+    let own_rank = app.own_ranks.get(&app.leaderboard_tab.period()).copied();
+    let table = score_table(app, theme, scores, 1, own_rank).block(
+        Block::default()
+            .title(leaderboard_tabs(theme, app.messages(), app.leaderboard_tab))
+            .borders(Borders::ALL),
+    );
+
+    // 3 = header, borders...
+    let table_height =
+        (scores.len() as u16 + 3 + own_row_height(app, scores, own_rank)).min(area.height);
+    f.render_widget(
+        table,
+        horizontal_centered_rect(TABLE_WIDTH, table_height, area),
+    );
+}
+
+fn render_leaderboard_scene(
+    app: &App,
+    theme: &Theme,
+    state: &LeaderboardState,
+    f: &mut Frame,
+    area: Rect,
+) {
+    let first_rank = state.offset as usize + 1;
+    let status = if state.is_loading {
+        app.messages().loading.to_string()
+    } else if state.entries.is_empty() {
+        app.messages().no_entries.to_string()
+    } else {
+        let last_rank = first_rank + state.entries.len() - 1;
+        match state.total {
+            Some(total) => format!(" #{}-#{} / {} ", first_rank, last_rank, total),
+            None => format!(" #{}-#{} ", first_rank, last_rank),
+        }
+    };
+
+    let season = state.season.and_then(|i| app.db_cache.past_seasons.get(i));
+    // archived boards are frozen, so the live rank would be misleading
+    let own_rank = match season {
+        Some(_) => None,
+        None => app.own_ranks.get(&app.leaderboard_tab.period()).copied(),
+    };
+    let title = match season {
+        Some(season) => Line::from(fill(
+            app.messages().season_title,
+            &[&season.name, &season.starts_on, &season.ends_on],
+        ))
+        .fg(theme.accent)
+        .bold(),
+        None => leaderboard_tabs(theme, app.messages(), app.leaderboard_tab),
+    };
+    let table = score_table(app, theme, &state.entries, first_rank, own_rank).block(
+        Block::default()
+            .title(title)
+            .title_alignment(Alignment::Center)
+            .title_bottom(Line::from(status).centered())
+            .borders(Borders::ALL),
+    );
+
+    let table_height =
+        (LEADERBOARD_PAGE_SIZE as u16 + 3 + own_row_height(app, &state.entries, own_rank))
+            .min(area.height);
+    f.render_widget(
+        table,
+        horizontal_centered_rect(TABLE_WIDTH, table_height, area),
+    );
+}
+
+/// Separator plus the player's own row, when they are not already listed.
+fn own_row_height(app: &App, scores: &[ScoreEntry], own_rank: Option<UserRank>) -> u16 {
+    let listed = scores
+        .iter()
+        .any(|entry| app.user.name.as_ref() == Some(&entry.name));
+    if own_rank.is_some() && !listed { 2 } else { 0 }
+}
+
+fn score_table<'a>(
+    app: &App,
+    theme: &Theme,
+    scores: &'a [ScoreEntry],
+    first_rank: usize,
+    own_rank: Option<UserRank>,
+) -> Table<'a> {
+    let own_style = if theme.highlight_reversed {
+        Style::default().reversed()
+    } else {
+        Style::default().bg(theme.highlight)
     };
 
-    let rows: Vec<Row> = scores
+    let mut rows: Vec<Row> = scores
         .iter()
         .enumerate()
         .map(|(i, entry)| {
-            let pos = i + 1;
+            let pos = first_rank + i;
             let is_own_entry = app.user.name.as_ref() == Some(&entry.name);
             let style = if is_own_entry {
-                Style::default().bg(Color::DarkGray)
+                own_style
             } else {
                 Style::default()
             };
 
             let pos_style = match pos {
-                1 => Style::default().fg(Color::Yellow).bold(),
-                2 => Style::default().fg(Color::Gray).bold(),
-                3 => Style::default().fg(Color::Magenta).bold(),
-                _ => Style::default().fg(Color::White),
+                1 => Style::default().fg(theme.accent).bold(),
+                2 => Style::default().fg(theme.text).bold(),
+                3 => Style::default().fg(theme.combo).bold(),
+                _ => Style::default().fg(theme.text),
             };
 
             Row::new(vec![
                 Cell::from(format!("#{}", pos)).style(pos_style),
-                Cell::from(entry.name.as_str()),
-                Cell::from(entry.score.to_string()).fg(Color::Green),
+                name_cell(theme, entry),
+                Cell::from(entry.score.to_string()).fg(theme.success),
                 Cell::from(entry.created_at.as_str()),
             ])
             .style(style)
         })
         .collect();
 
-    let table = Table::new(
+    if let Some(own) = own_rank.filter(|_| own_row_height(app, scores, own_rank) > 0) {
+        rows.push(Row::new(vec!["...", "", "", ""]).fg(theme.muted));
+        rows.push(
+            Row::new(vec![
+                Cell::from(format!("#{}", own.rank)),
+                Cell::from(app.messages().you),
+                Cell::from(own.score.to_string()).fg(theme.success),
+                Cell::from(""),
+            ])
+            .style(own_style),
+        );
+    }
+
+    Table::new(
         rows,
         [
             Constraint::Length(4),
-            Constraint::Min(12),
+            Constraint::Min(NAME_COLUMN_WIDTH),
             Constraint::Length(8),
             Constraint::Length(12),
         ],
     )
     .header(
-        Row::new(vec!["RANK", "NAME", "SCORE", "DATE"])
+        Row::new(app.messages().table_header.to_vec())
             .underlined()
-            .cyan(),
+            .fg(theme.score),
     )
-    .block(Block::default().title(title).borders(Borders::ALL));
+}
 
-    // 3 = header, borders...
-    let table_height = (cache.all_time_scores.len() as u16 + 3).min(area.height);
-    f.render_widget(
-        table,
-        horizontal_centered_rect(TABLE_WIDTH, table_height, area),
-    );
+/// Name with the player's region tag in front, e.g. "JP alice".
+fn name_cell<'a>(theme: &Theme, entry: &'a ScoreEntry) -> Cell<'a> {
+    let Some(region) = entry.region else {
+        return Cell::from(fit_width(&entry.name, NAME_COLUMN_WIDTH as usize));
+    };
+    Cell::from(Line::from(vec![
+        Span::styled(region.code(), Style::default().fg(theme.muted)),
+        Span::raw(" "),
+        Span::raw(fit_width(&entry.name, NAME_COLUMN_WIDTH as usize - 3)),
+    ]))
 }
 
-fn render_activity_graph(app: &App, f: &mut Frame, area: Rect) {
-    let title = format!(" ACTIVITY ({}weeks) ", WEEKS_TO_DISPLAY);
+fn render_activity_graph(app: &App, theme: &Theme, f: &mut Frame, area: Rect) {
+    let msg = app.messages();
+    let title = if app.activity_scroll == 0 {
+        fill(msg.activity_title, &[&WEEKS_TO_DISPLAY])
+    } else {
+        fill(msg.activity_scrolled, &[&app.activity_scroll])
+    };
     let label_width = 2; // "S ", "M ", ...
     let today = Utc::now().date_naive();
     let days_from_sunday = today.weekday().num_days_from_sunday() as i64;
     let total_days_to_show = WEEKS_TO_DISPLAY as i64 * 7;
-    let start_date = today - chrono::Duration::days(days_from_sunday + (total_days_to_show - 7));
+    let scrolled_days = app.activity_scroll as i64 * 7;
+    let start_date =
+        today - chrono::Duration::days(days_from_sunday + (total_days_to_show - 7) + scrolled_days);
+
+    // month labels and legend only when there is room for them
+    let detailed = area.height >= DAYS_IN_WEEK + 4;
 
     let labels = ["S", "M", "T", "W", "T", "F", "S"];
     let mut lines = Vec::new();
+    if detailed {
+        lines.push(month_labels(theme, start_date, label_width));
+    }
 
     for day_offset in 0..DAYS_IN_WEEK {
         let mut line_spans = Vec::new();
@@ -478,7 +1973,7 @@ fn render_activity_graph(app: &App, f: &mut Frame, area: Rect) {
         // S, M, T...
         line_spans.push(Span::styled(
             format!("{} ", labels[day_offset as usize]),
-            Style::default().dark_gray(),
+            Style::default().fg(theme.muted),
         ));
 
         for week in 0..WEEKS_TO_DISPLAY {
@@ -494,43 +1989,59 @@ fn render_activity_graph(app: &App, f: &mut Frame, area: Rect) {
                 .map(|a| a.count)
                 .unwrap_or(0);
 
-            let display_text = if current_date > today {
-                "  ".to_string()
-            } else if activity_count == 0 {
-                "  ".to_string()
-            } else {
-                format!("{:02}", activity_count % 100)
+            let level = match activity_count {
+                0 => 0,
+                1..=2 => 1,
+                3..=5 => 2,
+                6..=9 => 3,
+                _ => 4,
             };
 
-            let color = if current_date > today {
-                Color::Reset
+            let cell = if current_date > today {
+                Span::raw("  ")
+            } else if let Some(marks) = theme.glyphs.activity {
+                // shaped palettes encode the level in the glyph, not just the hue
+                Span::styled(
+                    marks[level].to_string().repeat(2),
+                    Style::default()
+                        .fg(theme.activity[level])
+                        .bg(theme.activity[0]),
+                )
             } else {
-                match activity_count {
-                    0 => Color::Indexed(235),
-                    1..=2 => Color::DarkGray,
-                    3..=5 => Color::Green,
-                    6..=9 => Color::LightGreen,
-                    _ => Color::White,
-                }
+                let display_text = if activity_count == 0 {
+                    "  ".to_string()
+                } else {
+                    format!("{:02}", activity_count % 100)
+                };
+                Span::styled(
+                    display_text,
+                    Style::default().fg(Color::Black).bg(theme.activity[level]),
+                )
             };
 
-            line_spans.push(Span::styled(
-                display_text,
-                Style::default().fg(Color::Black).bg(color),
-            ));
+            line_spans.push(cell);
             if week < WEEKS_TO_DISPLAY - 1 {
                 line_spans.push(Span::raw(" "));
             }
         }
         lines.push(Line::from(line_spans));
     }
+    if detailed {
+        lines.push(activity_legend(theme, msg));
+    }
 
     // 3 = [[SPACE][SPACE](cell)][SPACE(margin)] + 2(margin)
     let content_width = label_width + (WEEKS_TO_DISPLAY * 3).saturating_sub(1) + 2;
 
     // 2 = border
     let widget_width = std::cmp::max(content_width, title.len() as u16) + 2;
-    let centered_area = horizontal_centered_rect(widget_width, 9, area);
+    let centered_area = horizontal_centered_rect(widget_width, lines.len() as u16 + 2, area);
+
+    let border_style = if app.menu_focus == MenuFocus::Activity {
+        Style::default().fg(theme.accent)
+    } else {
+        Style::default()
+    };
 
     f.render_widget(
         Paragraph::new(lines)
@@ -538,13 +2049,57 @@ fn render_activity_graph(app: &App, f: &mut Frame, area: Rect) {
                 Block::default()
                     .title(title)
                     .borders(Borders::ALL)
-                    .border_type(BorderType::Plain),
+                    .border_type(BorderType::Plain)
+                    .border_style(border_style),
             )
             .alignment(Alignment::Center),
         centered_area,
     );
 }
 
+/// Month abbreviations above the first week column of each month.
+fn month_labels(theme: &Theme, start_date: chrono::NaiveDate, label_width: u16) -> Line<'static> {
+    let width = (label_width + (WEEKS_TO_DISPLAY * 3).saturating_sub(1)) as usize;
+    let mut row = vec![' '; width];
+    let mut free_from = 0;
+
+    for week in 0..WEEKS_TO_DISPLAY {
+        let date = start_date + chrono::Duration::days(week as i64 * 7);
+        let prev = date - chrono::Duration::days(7);
+        if week > 0 && date.month() == prev.month() {
+            continue;
+        }
+
+        let col = (label_width + week * 3) as usize;
+        let name = date.format("%b").to_string();
+        if col < free_from || col + name.len() > width {
+            continue;
+        }
+        for (i, ch) in name.chars().enumerate() {
+            row[col + i] = ch;
+        }
+        free_from = col + name.len() + 1;
+    }
+
+    Line::from(row.into_iter().collect::<String>()).fg(theme.muted)
+}
+
+fn activity_legend(theme: &Theme, msg: &Messages) -> Line<'static> {
+    let mut spans = vec![msg.less.fg(theme.muted)];
+    for (level, &color) in theme.activity.iter().enumerate() {
+        let cell = match theme.glyphs.activity {
+            Some(marks) => Span::styled(
+                marks[level].to_string().repeat(2),
+                Style::default().fg(color).bg(theme.activity[0]),
+            ),
+            None => Span::styled("  ", Style::default().bg(color)),
+        };
+        spans.push(cell);
+    }
+    spans.push(msg.more.fg(theme.muted));
+    Line::from(spans)
+}
+
 fn horizontal_centered_rect(width: u16, height: u16, area: Rect) -> Rect {
     Rect::new(
         area.x + area.width.saturating_sub(width) / 2,
@@ -564,3 +2119,63 @@ fn absolute_centered_rect(width: u16, height: u16, area: Rect) -> Rect {
         height.min(area.height),
     )
 }
+
+/// Cuts `text` to at most `width` display columns, ending in `…` when shortened.
+fn fit_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in text.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+/// Like `fit_width`, but keeps the end of `text`.
+fn tail_width(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for ch in text.chars().rev() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        kept.push(ch);
+        used += w;
+    }
+    std::iter::once('…').chain(kept.into_iter().rev()).collect()
+}
+
+fn pad_end(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+fn pad_start(text: &str, width: usize) -> String {
+    format!("{}{}", " ".repeat(width.saturating_sub(text.width())), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_width_helpers_count_columns() {
+        assert_eq!(fit_width("alice", 16), "alice");
+        assert_eq!(fit_width("あいうえおかきくけこ", 9), "あいうえ…");
+        assert_eq!(fit_width("あいうえおかきくけこ", 9).width(), 9);
+        assert_eq!(tail_width("abcdef", 4), "…def");
+        assert_eq!(pad_end("名前", 6), "名前  ");
+        assert_eq!(pad_start("日本語", 8), "  日本語");
+    }
+}
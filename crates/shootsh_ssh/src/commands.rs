@@ -0,0 +1,302 @@
+use shootsh_core::db::{RankingPeriod, ScoreEntry, Submission, UserContext, UserRank};
+use shootsh_core::domain::WeeklyModifier;
+use shootsh_core::region::Region;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// What `ssh <host> <command>` prints instead of starting the game.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecCommand {
+    Top,
+    Daily,
+    Stats,
+    /// Runs from `shootsh_cli sync`, read from stdin until EOF.
+    Upload,
+    /// Streams the screen of the player with this name.
+    Watch(String),
+    Help,
+    /// Only answered for admin fingerprints; `None` if the subcommand is unknown.
+    Admin(Option<AdminCommand>),
+}
+
+impl ExecCommand {
+    pub fn parse(command: &str) -> Option<Self> {
+        let command = command.trim();
+        let admin = command
+            .strip_prefix("admin")
+            .filter(|args| args.is_empty() || args.starts_with(char::is_whitespace));
+        if let Some(args) = admin {
+            return Some(Self::Admin(AdminCommand::parse(args)));
+        }
+        if let Some(name) = command.strip_prefix("watch ") {
+            return Some(Self::Watch(name.trim().to_string()));
+        }
+        match command {
+            "top" => Some(Self::Top),
+            "daily" => Some(Self::Daily),
+            "stats" => Some(Self::Stats),
+            "upload" => Some(Self::Upload),
+            "help" | "--help" => Some(Self::Help),
+            _ => None,
+        }
+    }
+}
+
+/// `ssh <host> admin <subcommand>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Sessions,
+    Kick(String),
+    /// Also kicks the key if it is playing.
+    Ban(String),
+    Unban(String),
+    /// `None` clears the message.
+    Motd(Option<String>),
+    /// Shown right away to everyone playing.
+    Broadcast(String),
+    /// Schedules a modifier for a `%Y-%W` week; `None` clears it.
+    Modifier {
+        week: String,
+        modifier: Option<WeeklyModifier>,
+    },
+    Refresh,
+    Help,
+}
+
+impl AdminCommand {
+    fn parse(args: &str) -> Option<Self> {
+        let args = args.trim();
+        let (sub, rest) = args
+            .split_once(char::is_whitespace)
+            .map_or((args, ""), |(sub, rest)| (sub, rest.trim()));
+        let fingerprint =
+            (!rest.is_empty() && !rest.contains(char::is_whitespace)).then(|| rest.to_string());
+        match sub {
+            "sessions" if rest.is_empty() => Some(Self::Sessions),
+            "kick" => fingerprint.map(Self::Kick),
+            "ban" => fingerprint.map(Self::Ban),
+            "unban" => fingerprint.map(Self::Unban),
+            "motd" if rest == "--clear" => Some(Self::Motd(None)),
+            "motd" if !rest.is_empty() => Some(Self::Motd(Some(rest.to_string()))),
+            "broadcast" if !rest.is_empty() => Some(Self::Broadcast(rest.to_string())),
+            "modifier" => Self::parse_modifier(rest),
+            "refresh" if rest.is_empty() => Some(Self::Refresh),
+            "" | "help" if rest.is_empty() => Some(Self::Help),
+            _ => None,
+        }
+    }
+
+    /// `<week> <modifier>` or `<week> --clear`, the week as in `2026-07`.
+    fn parse_modifier(args: &str) -> Option<Self> {
+        let (week, name) = args.split_once(char::is_whitespace)?;
+        let (year, number) = week.split_once('-')?;
+        let digits = |s: &str, len| s.len() == len && s.chars().all(|c| c.is_ascii_digit());
+        if !digits(year, 4) || !digits(number, 2) {
+            return None;
+        }
+        let modifier = match name.trim() {
+            "--clear" => None,
+            name => Some(WeeklyModifier::from_key(name)?),
+        };
+        Some(Self::Modifier {
+            week: week.to_string(),
+            modifier,
+        })
+    }
+}
+
+pub const HELP: &str = "\
+Usage: ssh <host> [command]
+
+Without a command, starts the game.
+
+Commands:
+  top     all-time leaderboard
+  daily   today's leaderboard
+  stats   your own stats (needs your public key)
+  upload  runs played offline, as sent by `shootsh_cli sync`
+  watch <name>
+          watch someone play (ssh -t); q stops
+  help    this message
+";
+
+pub const ADMIN_HELP: &str = "\
+Usage: ssh <host> admin <command>
+
+Commands:
+  sessions            players connected right now
+  kick <fingerprint>  disconnect a player
+  ban <fingerprint>   refuse a key from now on, and disconnect it
+  unban <fingerprint>
+  motd <text>         set the message of the day
+  motd --clear
+  broadcast <text>    show a message to everyone playing now
+  modifier <week> <name>
+                      schedule a weekly modifier, the week as in 2026-07:
+                      tiny_targets, double_decay or short_fuse
+  modifier <week> --clear
+  refresh             rebuild the cached leaderboards
+";
+
+/// One line of `admin sessions`.
+pub struct SessionRow {
+    pub fingerprint: String,
+    pub name: Option<String>,
+    pub addr: Option<IpAddr>,
+    pub country: Option<Region>,
+    pub connected: Duration,
+    pub spectators: usize,
+}
+
+/// Longest-connected first.
+pub fn sessions(rows: &mut [SessionRow]) -> String {
+    rows.sort_by_key(|row| std::cmp::Reverse(row.connected));
+    let mut out = format!("{} connected\n", rows.len());
+    for row in rows.iter() {
+        let secs = row.connected.as_secs();
+        out.push_str(&format!(
+            "  {:<16} {:>3}h{:02}m  {:<39} {:<2}  {}{}\n",
+            row.name.as_deref().unwrap_or("-"),
+            secs / 3600,
+            secs / 60 % 60,
+            row.addr.map_or("-".to_string(), |addr| addr.to_string()),
+            row.country.map_or("-", |c| c.code()),
+            row.fingerprint,
+            match row.spectators {
+                0 => String::new(),
+                n => format!("  ({n} watching)"),
+            }
+        ));
+    }
+    out
+}
+
+pub fn leaderboard(title: &str, scores: &[ScoreEntry]) -> String {
+    let mut out = format!("{title}\n");
+    if scores.is_empty() {
+        out.push_str("  no scores yet\n");
+    }
+    for (i, entry) in scores.iter().enumerate() {
+        out.push_str(&format!(
+            "{:>3}. {:<16} {:>7}  {}{}\n",
+            i + 1,
+            entry.name,
+            entry.score,
+            entry.created_at,
+            entry
+                .region
+                .map_or(String::new(), |r| format!("  {}", r.code()))
+        ));
+    }
+    out
+}
+
+pub fn stats(user: &UserContext, ranks: &HashMap<RankingPeriod, UserRank>) -> String {
+    let rank = |period| {
+        ranks
+            .get(&period)
+            .map_or("-".to_string(), |r| format!("#{}", r.rank))
+    };
+    let rows = [
+        ("Name", user.name.clone().unwrap_or_else(|| "-".to_string())),
+        ("High score", user.high_score.to_string()),
+        ("All-time rank", rank(RankingPeriod::AllTime)),
+        ("Today's rank", rank(RankingPeriod::Daily)),
+        ("Games", user.sessions.to_string()),
+        ("Hits", user.total_hits.to_string()),
+        ("Misses", user.total_misses.to_string()),
+        (
+            "Accuracy",
+            user.accuracy()
+                .map_or("-".to_string(), |a| format!("{a:.1}%")),
+        ),
+        ("Best combo", user.best_combo.to_string()),
+        (
+            "Avg reaction",
+            user.average_reaction_ms()
+                .map_or("-".to_string(), |ms| format!("{ms}ms")),
+        ),
+    ];
+    rows.iter()
+        .map(|(label, value)| format!("{label:<14}{value}\n"))
+        .collect()
+}
+
+/// One line of `upload` output per run, e.g. `accepted` or `rejected too_long`.
+pub fn upload_line(submission: &Submission) -> String {
+    match &submission.reason {
+        Some(reason) => format!("{} {reason}\n", submission.verdict.key()),
+        None => format!("{}\n", submission.verdict.key()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaderboard_lists_ranks() {
+        let scores = [ScoreEntry {
+            name: "alice".to_string(),
+            score: 1200,
+            created_at: "2026-01-01".to_string(),
+            region: None,
+        }];
+        assert_eq!(
+            leaderboard("Top", &scores),
+            "Top\n  1. alice               1200  2026-01-01\n"
+        );
+        assert_eq!(ExecCommand::parse("top\n"), Some(ExecCommand::Top));
+        assert_eq!(ExecCommand::parse("upload"), Some(ExecCommand::Upload));
+        assert_eq!(ExecCommand::parse("rm -rf /"), None);
+        assert_eq!(
+            ExecCommand::parse("watch alice\n"),
+            Some(ExecCommand::Watch("alice".to_string()))
+        );
+        assert_eq!(ExecCommand::parse("watch"), None);
+    }
+
+    #[test]
+    fn test_admin_commands_parse() {
+        let admin = |cmd| match ExecCommand::parse(cmd) {
+            Some(ExecCommand::Admin(admin)) => admin,
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(
+            admin("admin kick SHA256:abc"),
+            Some(AdminCommand::Kick("SHA256:abc".to_string()))
+        );
+        assert_eq!(
+            admin("admin motd  Season 3 starts Monday "),
+            Some(AdminCommand::Motd(Some(
+                "Season 3 starts Monday".to_string()
+            )))
+        );
+        assert_eq!(admin("admin motd --clear"), Some(AdminCommand::Motd(None)));
+        assert_eq!(
+            admin("admin broadcast Restart in 5 minutes"),
+            Some(AdminCommand::Broadcast("Restart in 5 minutes".to_string()))
+        );
+        assert_eq!(admin("admin broadcast"), None);
+        assert_eq!(
+            admin("admin modifier 2026-07 tiny_targets"),
+            Some(AdminCommand::Modifier {
+                week: "2026-07".to_string(),
+                modifier: Some(WeeklyModifier::TinyTargets),
+            })
+        );
+        assert_eq!(
+            admin("admin modifier 2026-07 --clear"),
+            Some(AdminCommand::Modifier {
+                week: "2026-07".to_string(),
+                modifier: None,
+            })
+        );
+        assert_eq!(admin("admin modifier 2026-07 sudden_death"), None);
+        assert_eq!(admin("admin modifier next tiny_targets"), None);
+        assert_eq!(admin("admin"), Some(AdminCommand::Help));
+        assert_eq!(admin("admin ban"), None);
+        assert_eq!(ExecCommand::parse("administer"), None);
+    }
+}
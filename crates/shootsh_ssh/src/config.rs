@@ -0,0 +1,611 @@
+use crate::limiter::AuthLimits;
+use crate::logging::{LogFormat, LogRotation, LogSettings};
+use crate::recorder::RecordingPolicy;
+use crate::server::{ConnectionLimits, KeyAccess, cert_authority_name};
+use anyhow::{Context, Result};
+use clap::builder::BoolishValueParser;
+use clap::{Parser, ValueEnum};
+use russh::keys::ssh_key::{AuthorizedKeys, Fingerprint, HashAlg};
+use serde::Deserialize;
+use shootsh_core::db::RetentionPolicy;
+use shootsh_core::domain::{
+    DEFAULT_HIT_MARGIN_X, DEFAULT_HIT_MARGIN_Y, DEFAULT_TARGET_HEIGHT, DEFAULT_TARGET_WIDTH,
+};
+use shootsh_core::theme::ColorSupport;
+use shootsh_core::{AntiCheatConfig, GameConfig, NameFilter};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_LISTEN_ADDRS: [&str; 2] = ["0.0.0.0:2222", "[::]:2222"];
+const DEFAULT_DB_PATH: &str = "shootsh.db";
+const DEFAULT_MAX_USERS: i64 = 100_000;
+const DEFAULT_INACTIVITY_TIMEOUT_SECS: u64 = 10 * 60;
+const DEFAULT_AUTH_REJECTION_SECS: u64 = 3;
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 15 * 60;
+const DEFAULT_FRAME_RATE: u32 = 30;
+const DEFAULT_MAX_CONNECTIONS: usize = 500;
+const DEFAULT_MAX_CONNECTIONS_PER_IP: usize = 5;
+const DEFAULT_AUTH_FAILURE_LIMIT: usize = 10;
+const DEFAULT_AUTH_BAN_SECS: u64 = 60;
+const DEFAULT_LOG_FILTER: &str = "info";
+const DEFAULT_RECORD_MAX_MB: u64 = 20;
+const DEFAULT_RECORD_KEEP_DAYS: u64 = 7;
+const DEFAULT_RETENTION_DAYS: u16 = 90;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DbBackend {
+    /// The file at `db_path`
+    Sqlite,
+    /// The server at `database_url` (requires the `postgres` feature)
+    Postgres,
+    /// Nothing survives a restart; for demo deployments
+    Memory,
+}
+
+/// Server settings. Flags win over environment variables, which win over
+/// the TOML file given by `--config`; its keys are the flag names with
+/// underscores. Maintenance tasks go after the flags and read the same
+/// settings, e.g. `shootsh_ssh --config shootsh.toml backup <path>`.
+#[derive(Parser, Deserialize, Debug, Default)]
+#[command(version, about = "shootsh over SSH")]
+#[serde(default, deny_unknown_fields)]
+pub struct Args {
+    /// TOML file with any of the settings below
+    #[arg(long, env = "SHOOTSH_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+    /// Run a maintenance task instead of the server: export, audit, checks,
+    /// shadowed, review, season, backup or restore, followed by its own arguments
+    #[arg(
+        trailing_var_arg = true,
+        allow_hyphen_values = true,
+        value_name = "TASK"
+    )]
+    #[serde(skip)]
+    pub task: Vec<String>,
+    /// Address to accept connections on; repeat the flag, or separate with
+    /// commas in the environment, to listen on several
+    #[arg(long, env = "LISTEN_ADDR", value_delimiter = ',')]
+    pub listen_addr: Option<Vec<String>>,
+    /// Address for the `/healthz` and `/readyz` HTTP probes; off when unset
+    #[arg(long, env = "HEALTH_ADDR")]
+    pub health_addr: Option<String>,
+    /// SQLite database file
+    #[arg(long, env = "DB_PATH")]
+    pub db_path: Option<String>,
+    /// Postgres connection string, used instead of `db_path`
+    #[arg(long, env = "DATABASE_URL")]
+    pub database_url: Option<String>,
+    /// Storage backend; Postgres when `database_url` is set, SQLite otherwise
+    #[arg(long, env = "DB_BACKEND", value_enum)]
+    pub db_backend: Option<DbBackend>,
+    /// Days without a game before an account that never scored is deleted
+    #[arg(long, env = "RETENTION_DAYS")]
+    pub retention_days: Option<u16>,
+    /// Store every hit the anticheat refuses, with what it measured
+    #[arg(long, env = "CHECK_TELEMETRY", value_parser = BoolishValueParser::new())]
+    pub check_telemetry: Option<bool>,
+    /// Name of this process among several sharing one database, e.g. the
+    /// host name; set on every node to keep their caches and sessions in step
+    #[arg(long, env = "NODE_ID")]
+    pub node_id: Option<String>,
+    /// Accounts kept before inactive ones are pruned
+    #[arg(long, env = "MAX_USERS")]
+    pub max_users: Option<i64>,
+    #[arg(long, env = "SSH_HOST_KEY_PATH")]
+    pub host_key_path: Option<PathBuf>,
+    /// Open connections before new ones are turned away
+    #[arg(long, env = "MAX_CONNECTIONS")]
+    pub max_connections: Option<usize>,
+    /// Open connections from one address before new ones are turned away
+    #[arg(long, env = "MAX_CONNECTIONS_PER_IP")]
+    pub max_connections_per_ip: Option<usize>,
+    /// Failed or odd logins from one address within a minute before it is banned
+    #[arg(long, env = "AUTH_FAILURE_LIMIT")]
+    pub auth_failure_limit: Option<usize>,
+    /// Seconds of an address's first ban; each further ban doubles it
+    #[arg(long, env = "AUTH_BAN_SECS")]
+    pub auth_ban_secs: Option<u64>,
+    /// Seconds without traffic before a connection is dropped
+    #[arg(long, env = "INACTIVITY_TIMEOUT")]
+    pub inactivity_timeout: Option<u64>,
+    /// Seconds without keyboard or mouse input before a player is warned and
+    /// disconnected; 0 never disconnects
+    #[arg(long, env = "IDLE_TIMEOUT")]
+    pub idle_timeout: Option<u64>,
+    /// Seconds a failed authentication is held before the reply
+    #[arg(long, env = "AUTH_REJECTION_TIME")]
+    pub auth_rejection_time: Option<u64>,
+    /// Frames drawn per second for each session; slow links drop to 10 or 5
+    #[arg(long, env = "FRAME_RATE")]
+    pub frame_rate: Option<u32>,
+    /// Fastest believable reaction, in milliseconds
+    #[arg(long, env = "MIN_REACTION_MS")]
+    pub min_reaction_ms: Option<u64>,
+    /// Most of a session's round trip excused from the reaction limit, in milliseconds
+    #[arg(long, env = "MAX_LATENCY_SLACK_MS")]
+    pub max_latency_slack_ms: Option<u64>,
+    /// Longest single cursor step, in cells
+    #[arg(long, env = "MAX_JUMP_CELLS")]
+    pub max_jump_cells: Option<u16>,
+    #[arg(long, env = "MAX_CELLS_PER_SEC")]
+    pub max_cells_per_sec: Option<f32>,
+    /// Refused hits before a session has to pass a verification round
+    #[arg(long, env = "VERIFY_AFTER_WARNINGS")]
+    pub verify_after_warnings: Option<u32>,
+    /// Key fingerprints (`SHA256:...`) allowed to run `ssh <host> admin`;
+    /// repeat the flag or separate with commas
+    #[arg(long, env = "ADMIN_FINGERPRINTS", value_delimiter = ',')]
+    pub admin_fingerprints: Option<Vec<String>>,
+    /// Target size in cells; sizes are capped at 40
+    #[arg(long, env = "TARGET_WIDTH")]
+    pub target_width: Option<u16>,
+    #[arg(long, env = "TARGET_HEIGHT")]
+    pub target_height: Option<u16>,
+    /// Cells around a target that still count as a hit; capped at 10
+    #[arg(long, env = "HIT_MARGIN_X")]
+    pub hit_margin_x: Option<u16>,
+    #[arg(long, env = "HIT_MARGIN_Y")]
+    pub hit_margin_y: Option<u16>,
+    /// Only these key fingerprints may play, e.g. for a private team server;
+    /// repeat the flag or separate with commas
+    #[arg(long, env = "ALLOWED_KEYS", value_delimiter = ',')]
+    pub allowed_keys: Option<Vec<String>>,
+    /// OpenSSH `authorized_keys` file whose keys may play, alongside `allowed_keys`
+    #[arg(long, env = "ALLOWED_KEYS_FILE")]
+    pub allowed_keys_file: Option<PathBuf>,
+    /// Key fingerprints that may not play, even if allowed
+    #[arg(long, env = "DENIED_KEYS", value_delimiter = ',')]
+    pub denied_keys: Option<Vec<String>>,
+    /// CA public keys, one per line as in sshd's `TrustedUserCAKeys`; user
+    /// certificates they sign log in as `cert:<CA fingerprint>:<key id>`
+    #[arg(long, env = "USER_CA_KEYS")]
+    pub user_ca_keys: Option<PathBuf>,
+    /// MaxMind GeoIP2/GeoLite2 Country database for tagging connections with
+    /// a country (requires the `geoip` feature)
+    #[arg(long, env = "GEOIP_DB")]
+    pub geoip_db: Option<PathBuf>,
+    /// Give new players the region of their address; they can change it in settings
+    #[arg(long, env = "GEOIP_REGIONS")]
+    pub geoip_regions: Option<bool>,
+    /// Log line format
+    #[arg(long, env = "LOG_FORMAT", value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// Directory for rotating log files; logs go to stdout when unset
+    #[arg(long, env = "LOG_DIR")]
+    pub log_dir: Option<PathBuf>,
+    /// How often a new log file is started in `log_dir`
+    #[arg(long, env = "LOG_ROTATION", value_enum)]
+    pub log_rotation: Option<LogRotation>,
+    /// Levels, overall and per module, e.g. `info,russh=warn,shootsh_ssh::server=debug`
+    #[arg(long, env = "RUST_LOG")]
+    pub log_filter: Option<String>,
+    /// Directory to record every game session to as an asciinema `.cast` file;
+    /// off when unset
+    #[arg(long, env = "RECORD_DIR")]
+    pub record_dir: Option<PathBuf>,
+    /// Size at which a session's recording stops, in megabytes
+    #[arg(long, env = "RECORD_MAX_MB")]
+    pub record_max_mb: Option<u64>,
+    /// Days recordings are kept before they are deleted
+    #[arg(long, env = "RECORD_KEEP_DAYS")]
+    pub record_keep_days: Option<u64>,
+    /// Palette for every client: full, basic (16 colors) or mono; detected
+    /// per client when unset
+    #[arg(long, env = "COLOR_SUPPORT")]
+    pub color_support: Option<String>,
+    /// Announcement on every player's menu; replaces the stored one at startup
+    #[arg(long, env = "MOTD")]
+    pub motd: Option<String>,
+    /// `off` drops the built-in lists of reserved names and blocked words
+    #[arg(long, env = "NAME_FILTER", value_parser = BoolishValueParser::new())]
+    pub name_filter: Option<bool>,
+    /// Names players can't pick, ignoring trailing digits; repeat the flag or
+    /// separate with commas
+    #[arg(long, env = "RESERVED_NAMES", value_delimiter = ',')]
+    pub reserved_names: Option<Vec<String>>,
+    /// Words no name may contain
+    #[arg(long, env = "BLOCKED_NAME_WORDS", value_delimiter = ',')]
+    pub blocked_name_words: Option<Vec<String>>,
+    /// Text shown before login, e.g. server rules, a privacy note and a link to the source
+    #[arg(long, env = "BANNER")]
+    pub banner: Option<String>,
+    /// File with the pre-login banner, instead of `banner`
+    #[arg(long, env = "BANNER_FILE")]
+    pub banner_file: Option<PathBuf>,
+}
+
+impl Args {
+    /// Fills whatever flags and environment left unset from `file`.
+    fn or(self, file: Args) -> Args {
+        Args {
+            config: self.config,
+            task: self.task,
+            listen_addr: self.listen_addr.or(file.listen_addr),
+            health_addr: self.health_addr.or(file.health_addr),
+            db_path: self.db_path.or(file.db_path),
+            database_url: self.database_url.or(file.database_url),
+            db_backend: self.db_backend.or(file.db_backend),
+            retention_days: self.retention_days.or(file.retention_days),
+            check_telemetry: self.check_telemetry.or(file.check_telemetry),
+            node_id: self.node_id.or(file.node_id),
+            max_users: self.max_users.or(file.max_users),
+            host_key_path: self.host_key_path.or(file.host_key_path),
+            max_connections: self.max_connections.or(file.max_connections),
+            max_connections_per_ip: self.max_connections_per_ip.or(file.max_connections_per_ip),
+            auth_failure_limit: self.auth_failure_limit.or(file.auth_failure_limit),
+            auth_ban_secs: self.auth_ban_secs.or(file.auth_ban_secs),
+            inactivity_timeout: self.inactivity_timeout.or(file.inactivity_timeout),
+            idle_timeout: self.idle_timeout.or(file.idle_timeout),
+            auth_rejection_time: self.auth_rejection_time.or(file.auth_rejection_time),
+            frame_rate: self.frame_rate.or(file.frame_rate),
+            min_reaction_ms: self.min_reaction_ms.or(file.min_reaction_ms),
+            max_latency_slack_ms: self.max_latency_slack_ms.or(file.max_latency_slack_ms),
+            max_jump_cells: self.max_jump_cells.or(file.max_jump_cells),
+            max_cells_per_sec: self.max_cells_per_sec.or(file.max_cells_per_sec),
+            verify_after_warnings: self.verify_after_warnings.or(file.verify_after_warnings),
+            admin_fingerprints: self.admin_fingerprints.or(file.admin_fingerprints),
+            target_width: self.target_width.or(file.target_width),
+            target_height: self.target_height.or(file.target_height),
+            hit_margin_x: self.hit_margin_x.or(file.hit_margin_x),
+            hit_margin_y: self.hit_margin_y.or(file.hit_margin_y),
+            allowed_keys: self.allowed_keys.or(file.allowed_keys),
+            allowed_keys_file: self.allowed_keys_file.or(file.allowed_keys_file),
+            denied_keys: self.denied_keys.or(file.denied_keys),
+            user_ca_keys: self.user_ca_keys.or(file.user_ca_keys),
+            geoip_db: self.geoip_db.or(file.geoip_db),
+            geoip_regions: self.geoip_regions.or(file.geoip_regions),
+            log_format: self.log_format.or(file.log_format),
+            log_dir: self.log_dir.or(file.log_dir),
+            log_rotation: self.log_rotation.or(file.log_rotation),
+            log_filter: self.log_filter.or(file.log_filter),
+            record_dir: self.record_dir.or(file.record_dir),
+            record_max_mb: self.record_max_mb.or(file.record_max_mb),
+            record_keep_days: self.record_keep_days.or(file.record_keep_days),
+            color_support: self.color_support.or(file.color_support),
+            motd: self.motd.or(file.motd),
+            name_filter: self.name_filter.or(file.name_filter),
+            reserved_names: self.reserved_names.or(file.reserved_names),
+            blocked_name_words: self.blocked_name_words.or(file.blocked_name_words),
+            banner: self.banner.or(file.banner),
+            banner_file: self.banner_file.or(file.banner_file),
+        }
+    }
+}
+
+/// `Args` with the config file merged in and defaults applied.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub listen_addr: Vec<String>,
+    pub health_addr: Option<String>,
+    pub db_path: String,
+    pub db_backend: DbBackend,
+    #[cfg(feature = "postgres")]
+    pub database_url: Option<String>,
+    pub retention: RetentionPolicy,
+    pub check_telemetry: bool,
+    pub node_id: Option<String>,
+    pub max_users: i64,
+    pub host_key_path: Option<PathBuf>,
+    pub connection_limits: ConnectionLimits,
+    pub auth_limits: AuthLimits,
+    pub inactivity_timeout: Duration,
+    pub idle_timeout: Option<Duration>,
+    pub auth_rejection_time: Duration,
+    pub tick_rate: Duration,
+    pub anticheat: AntiCheatConfig,
+    pub admin_fingerprints: HashSet<String>,
+    pub key_access: KeyAccess,
+    /// SHA256 fingerprints of the trusted user CAs.
+    pub user_cas: Vec<Fingerprint>,
+    pub geoip_db: Option<PathBuf>,
+    pub geoip_regions: bool,
+    pub log: LogSettings,
+    pub recording: Option<RecordingPolicy>,
+    pub banner: Option<String>,
+    pub game_config: GameConfig,
+    /// Palette forced on every client instead of detecting one.
+    pub color_support: Option<ColorSupport>,
+    pub motd: Option<String>,
+    pub name_filter: NameFilter,
+}
+
+impl Settings {
+    pub fn load(args: Args) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                toml::from_str(&text)
+                    .with_context(|| format!("Invalid config file {}", path.display()))?
+            }
+            None => Args::default(),
+        };
+        let args = args.or(file);
+
+        let banner = match (args.banner, &args.banner_file) {
+            (Some(_), Some(_)) => anyhow::bail!("Set either banner or banner_file, not both"),
+            (Some(text), None) => Some(text),
+            (None, Some(path)) => Some(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?,
+            ),
+            (None, None) => None,
+        };
+
+        let mut allowed = args.allowed_keys.map(fingerprints);
+        if let Some(path) = &args.allowed_keys_file {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let keys = authorized_key_fingerprints(&text)
+                .with_context(|| format!("Invalid key in {}", path.display()))?;
+            allowed.get_or_insert_with(HashSet::new).extend(keys);
+        }
+        let key_access = KeyAccess {
+            allowed,
+            denied: fingerprints(args.denied_keys.unwrap_or_default()),
+        };
+        let user_cas = match &args.user_ca_keys {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                key_fingerprints(&text)
+                    .with_context(|| format!("Invalid key in {}", path.display()))?
+            }
+            None => Vec::new(),
+        };
+
+        let defaults = AntiCheatConfig::default();
+        let millis = |ms: Option<u64>, default| ms.map_or(default, Duration::from_millis);
+        let anticheat = AntiCheatConfig {
+            min_reaction_time: millis(args.min_reaction_ms, defaults.min_reaction_time),
+            max_latency_slack: millis(args.max_latency_slack_ms, defaults.max_latency_slack),
+            max_jump_cells: args.max_jump_cells.unwrap_or(defaults.max_jump_cells),
+            max_cells_per_sec: args.max_cells_per_sec.unwrap_or(defaults.max_cells_per_sec),
+            verify_after_warnings: args
+                .verify_after_warnings
+                .unwrap_or(defaults.verify_after_warnings),
+            ..defaults
+        };
+        let db_backend = args.db_backend.unwrap_or(match args.database_url {
+            Some(_) => DbBackend::Postgres,
+            None => DbBackend::Sqlite,
+        });
+        anyhow::ensure!(
+            db_backend != DbBackend::Postgres || args.database_url.is_some(),
+            "db_backend = postgres needs database_url"
+        );
+        let color_support = match &args.color_support {
+            Some(key) => Some(
+                ColorSupport::from_key(key).context("color_support must be full, basic or mono")?,
+            ),
+            None => None,
+        };
+        let name_filter = match args.name_filter {
+            Some(false) => NameFilter::empty(),
+            _ => NameFilter::default(),
+        }
+        .with_reserved(args.reserved_names.unwrap_or_default())
+        .with_blocked(args.blocked_name_words.unwrap_or_default());
+
+        let frame_rate = args.frame_rate.unwrap_or(DEFAULT_FRAME_RATE);
+        anyhow::ensure!(
+            (1..=240).contains(&frame_rate),
+            "frame_rate must be between 1 and 240"
+        );
+
+        Ok(Self {
+            listen_addr: args
+                .listen_addr
+                .unwrap_or_else(|| DEFAULT_LISTEN_ADDRS.map(String::from).to_vec()),
+            health_addr: args.health_addr,
+            db_path: args.db_path.unwrap_or_else(|| DEFAULT_DB_PATH.to_string()),
+            db_backend,
+            #[cfg(feature = "postgres")]
+            database_url: args.database_url,
+            retention: RetentionPolicy {
+                inactive_days: args.retention_days.unwrap_or(DEFAULT_RETENTION_DAYS).into(),
+                ..RetentionPolicy::default()
+            },
+            check_telemetry: args.check_telemetry.unwrap_or(false),
+            node_id: args
+                .node_id
+                .map(|id| id.trim().to_string())
+                .filter(|id| !id.is_empty()),
+            max_users: args.max_users.unwrap_or(DEFAULT_MAX_USERS),
+            host_key_path: args.host_key_path,
+            connection_limits: ConnectionLimits {
+                per_ip: args
+                    .max_connections_per_ip
+                    .unwrap_or(DEFAULT_MAX_CONNECTIONS_PER_IP),
+                global: args.max_connections.unwrap_or(DEFAULT_MAX_CONNECTIONS),
+            },
+            auth_limits: AuthLimits {
+                max_failures: args
+                    .auth_failure_limit
+                    .unwrap_or(DEFAULT_AUTH_FAILURE_LIMIT)
+                    .max(1),
+                ban: Duration::from_secs(args.auth_ban_secs.unwrap_or(DEFAULT_AUTH_BAN_SECS)),
+            },
+            inactivity_timeout: Duration::from_secs(
+                args.inactivity_timeout
+                    .unwrap_or(DEFAULT_INACTIVITY_TIMEOUT_SECS),
+            ),
+            idle_timeout: Some(args.idle_timeout.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS))
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            auth_rejection_time: Duration::from_secs(
+                args.auth_rejection_time
+                    .unwrap_or(DEFAULT_AUTH_REJECTION_SECS),
+            ),
+            tick_rate: Duration::from_secs(1) / frame_rate,
+            anticheat,
+            game_config: GameConfig::default().with_target_geometry(
+                args.target_width.unwrap_or(DEFAULT_TARGET_WIDTH),
+                args.target_height.unwrap_or(DEFAULT_TARGET_HEIGHT),
+                args.hit_margin_x.unwrap_or(DEFAULT_HIT_MARGIN_X),
+                args.hit_margin_y.unwrap_or(DEFAULT_HIT_MARGIN_Y),
+            ),
+            admin_fingerprints: fingerprints(args.admin_fingerprints.unwrap_or_default()),
+            key_access,
+            user_cas,
+            geoip_db: args.geoip_db,
+            geoip_regions: args.geoip_regions.unwrap_or(false),
+            log: LogSettings {
+                format: args.log_format.unwrap_or_default(),
+                dir: args.log_dir,
+                rotation: args.log_rotation.unwrap_or_default(),
+                filter: args
+                    .log_filter
+                    .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string()),
+            },
+            recording: args.record_dir.map(|dir| RecordingPolicy {
+                dir,
+                max_bytes: args.record_max_mb.unwrap_or(DEFAULT_RECORD_MAX_MB) * 1024 * 1024,
+                keep: Duration::from_secs(
+                    args.record_keep_days.unwrap_or(DEFAULT_RECORD_KEEP_DAYS) * 24 * 60 * 60,
+                ),
+            }),
+            banner: banner.filter(|b| !b.trim().is_empty()).map(|b| {
+                // clients print it verbatim, so end on a new line
+                let b = b.trim_end();
+                format!("{b}\n")
+            }),
+            color_support,
+            motd: args.motd,
+            name_filter,
+        })
+    }
+}
+
+fn fingerprints(list: Vec<String>) -> HashSet<String> {
+    list.into_iter()
+        .map(|fp| fp.trim().to_string())
+        .filter(|fp| !fp.is_empty())
+        .collect()
+}
+
+/// SHA256 fingerprints of the keys in an `authorized_keys` file; options
+/// like `restrict` are allowed and ignored, except that a `cert-authority`
+/// line admits every certificate its CA signs.
+fn authorized_key_fingerprints(text: &str) -> Result<Vec<String>> {
+    AuthorizedKeys::new(text)
+        .map(|entry| {
+            let entry = entry?;
+            let fp = entry.public_key().fingerprint(HashAlg::Sha256);
+            let is_ca = entry
+                .config_opts()
+                .iter()
+                .any(|opt| opt.eq_ignore_ascii_case("cert-authority"));
+            Ok(if is_ca {
+                cert_authority_name(&fp)
+            } else {
+                fp.to_string()
+            })
+        })
+        .collect()
+}
+
+fn key_fingerprints(text: &str) -> Result<Vec<Fingerprint>> {
+    AuthorizedKeys::new(text)
+        .map(|entry| Ok(entry?.public_key().fingerprint(HashAlg::Sha256)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_win_over_the_file() {
+        let file: Args = toml::from_str("listen_addr = [\"[::]:22\"]\nframe_rate = 60").unwrap();
+        let args = Args {
+            frame_rate: Some(20),
+            ..Args::default()
+        }
+        .or(file);
+        assert_eq!(args.listen_addr, Some(vec!["[::]:22".to_string()]));
+        assert_eq!(args.frame_rate, Some(20));
+        assert!(toml::from_str::<Args>("listen_adr = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_log_settings_from_file() {
+        let file: Args =
+            toml::from_str("log_format = \"json\"\nlog_rotation = \"hourly\"").unwrap();
+        let settings = Settings::load(Args::default().or(file)).unwrap();
+        assert_eq!(settings.log.format, LogFormat::Json);
+        assert_eq!(settings.log.rotation, LogRotation::Hourly);
+        assert_eq!(settings.log.filter, "info");
+        assert!(toml::from_str::<Args>("log_format = \"xml\"").is_err());
+    }
+
+    #[test]
+    fn test_authorized_keys_file() {
+        let text = "# team\n\nrestrict ssh-ed25519 \
+            AAAAC3NzaC1lZDI1NTE5AAAAIKqcSLxW9YF6mFygpLbGKmml8qDMn1Ck4YlnMV2aSWFt alice\n";
+        let fp = "SHA256:7qZhsf8i8w8+d76ifweS5SgZ4KASvQkWHfPFf1zOoZ8";
+        assert_eq!(authorized_key_fingerprints(text).unwrap(), vec![fp]);
+        assert!(authorized_key_fingerprints("ssh-ed25519 not-a-key").is_err());
+        // CA files take the same format, `cert-authority` option and all
+        let key = text.lines().last().unwrap().trim_start_matches("restrict ");
+        let ca = format!("cert-authority,restrict {key}");
+        assert_eq!(key_fingerprints(&ca).unwrap()[0].to_string(), fp);
+        let ca_entry = format!("cert:{fp}");
+        assert_eq!(
+            authorized_key_fingerprints(&ca).unwrap(),
+            vec![ca_entry.clone()]
+        );
+
+        let access = KeyAccess {
+            allowed: Some(HashSet::from([fp.to_string()])),
+            denied: HashSet::new(),
+        };
+        assert!(access.permits(fp));
+        assert!(!access.permits("SHA256:other"));
+        assert!(KeyAccess::default().permits("SHA256:other"));
+
+        // certificates match by identity or by their CA
+        let alice = format!("{ca_entry}:alice");
+        let by_ca = KeyAccess {
+            allowed: Some(HashSet::from([ca_entry.clone()])),
+            denied: HashSet::from([format!("{ca_entry}:mallory")]),
+        };
+        assert!(by_ca.permits(&alice));
+        assert!(!by_ca.permits(&format!("{ca_entry}:mallory")));
+        assert!(!by_ca.permits("cert:SHA256:other:alice"));
+        let denied = KeyAccess {
+            allowed: None,
+            denied: HashSet::from([ca_entry]),
+        };
+        assert!(!denied.permits(&alice));
+    }
+
+    #[test]
+    fn test_storage_and_names_from_file() {
+        let load = |text: &str| Settings::load(Args::default().or(toml::from_str(text).unwrap()));
+        let settings = load("database_url = \"postgres://db\"\nretention_days = 30").unwrap();
+        assert_eq!(settings.db_backend, DbBackend::Postgres);
+        assert_eq!(settings.retention.inactive_days, 30);
+        let settings = load("db_backend = \"memory\"\ncolor_support = \"mono\"").unwrap();
+        assert_eq!(settings.db_backend, DbBackend::Memory);
+        assert_eq!(settings.color_support, Some(ColorSupport::Monochrome));
+        assert!(load("db_backend = \"postgres\"").is_err());
+        assert!(load("color_support = \"rainbow\"").is_err());
+
+        let settings = load("name_filter = false\nreserved_names = [\"owner\"]").unwrap();
+        assert!(settings.name_filter.check("admin").is_ok());
+        assert!(settings.name_filter.check("owner2").is_err());
+    }
+
+    #[test]
+    fn test_target_geometry_from_file() {
+        let file: Args = toml::from_str("target_width = 6\nhit_margin_x = 500").unwrap();
+        let settings = Settings::load(Args::default().or(file)).unwrap();
+        assert_eq!(settings.game_config.target_width, 6);
+        assert_eq!(settings.game_config.target_height, DEFAULT_TARGET_HEIGHT);
+        assert_eq!(settings.game_config.hit_margin_x, 10);
+    }
+}
@@ -0,0 +1,43 @@
+//! Country lookup for peer addresses, from a MaxMind GeoIP2 or GeoLite2
+//! Country database. Without the `geoip` feature nothing is resolved, and
+//! configuring a database is an error rather than silently ignored.
+
+use anyhow::Result;
+use shootsh_core::region::Region;
+use std::net::IpAddr;
+use std::path::Path;
+
+// never built without the feature: `open` refuses
+#[cfg_attr(not(feature = "geoip"), allow(dead_code))]
+pub struct GeoIp {
+    #[cfg(feature = "geoip")]
+    reader: maxminddb::Reader<Vec<u8>>,
+}
+
+impl GeoIp {
+    #[cfg(feature = "geoip")]
+    pub fn open(path: &Path) -> Result<Self> {
+        use anyhow::Context;
+        let reader = maxminddb::Reader::open_readfile(path)
+            .with_context(|| format!("Failed to open GeoIP database {}", path.display()))?;
+        Ok(Self { reader })
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn open(_path: &Path) -> Result<Self> {
+        anyhow::bail!("geoip_db is set but shootsh_ssh was built without the geoip feature")
+    }
+
+    /// `None` for private addresses and anything the database doesn't know.
+    #[cfg(feature = "geoip")]
+    pub fn country(&self, ip: IpAddr) -> Option<Region> {
+        let found = self.reader.lookup::<maxminddb::geoip2::Country>(ip);
+        let country = found.ok().flatten()?.country?;
+        Region::parse(country.iso_code?)
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn country(&self, _ip: IpAddr) -> Option<Region> {
+        None
+    }
+}
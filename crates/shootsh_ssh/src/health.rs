@@ -0,0 +1,176 @@
+use shootsh_core::db::DbRequest;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc, oneshot};
+
+/// Longest a probe may take to send its request line.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often the DB worker is pinged in the background.
+const DB_PING_INTERVAL: Duration = Duration::from_secs(5);
+/// How long the DB worker may go without answering before `/readyz` fails.
+/// A few pings long, so one stuck behind a batch of saves doesn't flap it.
+const DB_STALL_LIMIT: Duration = Duration::from_secs(20);
+/// Pause after a failed accept, e.g. when out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Startup progress `/readyz` reports on, set by `main`.
+#[derive(Debug, Default)]
+pub struct Readiness {
+    pub host_key_loaded: AtomicBool,
+    /// Cleared again when a shutdown starts, so no new players are sent here.
+    pub listening: AtomicBool,
+    /// When the DB worker last answered a ping.
+    db_answered_at: Mutex<Option<Instant>>,
+}
+
+impl Readiness {
+    fn db_reachable(&self) -> bool {
+        self.db_answered_at
+            .lock()
+            .unwrap()
+            .is_some_and(|at| at.elapsed() < DB_STALL_LIMIT)
+    }
+}
+
+/// Answers `GET /healthz` whenever the process runs, and `GET /readyz` once
+/// the host key is loaded, a listener is bound and the DB worker answered
+/// recently.
+pub async fn serve(
+    listener: TcpListener,
+    readiness: Arc<Readiness>,
+    db_tx: mpsc::Sender<DbRequest>,
+) {
+    tokio::spawn(watch_db(Arc::clone(&readiness), db_tx));
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!(error = %e, "Health check accept failed");
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let readiness = Arc::clone(&readiness);
+        tokio::spawn(async move {
+            let _ = tokio::time::timeout(PROBE_TIMEOUT, respond(stream, &readiness)).await;
+        });
+    }
+}
+
+/// Pings through the DB worker, so a stalled worker fails the probe too,
+/// without a probe waiting in its queue.
+async fn watch_db(readiness: Arc<Readiness>, db_tx: mpsc::Sender<DbRequest>) {
+    let mut interval = tokio::time::interval(DB_PING_INTERVAL);
+    loop {
+        interval.tick().await;
+        let (reply_tx, rx) = oneshot::channel();
+        if db_tx.send(DbRequest::Ping { reply_tx }).await.is_err() {
+            return;
+        }
+        if let Ok(Ok(Ok(()))) = tokio::time::timeout(DB_STALL_LIMIT, rx).await {
+            *readiness.db_answered_at.lock().unwrap() = Some(Instant::now());
+        }
+    }
+}
+
+async fn respond(mut stream: TcpStream, readiness: &Readiness) -> std::io::Result<()> {
+    let mut buf = [0; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/healthz")) => ("200 OK", "ok".to_string()),
+        (Some("GET"), Some("/readyz")) => {
+            let mut failing = Vec::new();
+            if !readiness.host_key_loaded.load(Ordering::Relaxed) {
+                failing.push("host_key");
+            }
+            if !readiness.listening.load(Ordering::Relaxed) {
+                failing.push("listener");
+            }
+            if !readiness.db_reachable() {
+                failing.push("db");
+            }
+            if failing.is_empty() {
+                ("200 OK", "ready".to_string())
+            } else {
+                (
+                    "503 Service Unavailable",
+                    format!("not ready: {}", failing.join(", ")),
+                )
+            }
+        }
+        _ => ("404 Not Found", "not found".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}\n",
+        body.len() + 1
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET {path} HTTP/1.1\r\n\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_ready_once_started_and_db_answers() {
+        let (db_tx, mut db_rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            while let Some(req) = db_rx.recv().await {
+                if let DbRequest::Ping { reply_tx } = req {
+                    let _ = reply_tx.send(Ok(()));
+                }
+            }
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let readiness = Arc::new(Readiness::default());
+        tokio::spawn(serve(listener, Arc::clone(&readiness), db_tx));
+
+        assert!(get(addr, "/healthz").await.starts_with("HTTP/1.1 200"));
+        assert!(get(addr, "/nope").await.starts_with("HTTP/1.1 404"));
+        let response = get(addr, "/readyz").await;
+        assert!(response.contains("not ready: host_key, listener"));
+
+        readiness.host_key_loaded.store(true, Ordering::Relaxed);
+        readiness.listening.store(true, Ordering::Relaxed);
+        while !readiness.db_reachable() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(get(addr, "/readyz").await.starts_with("HTTP/1.1 200"));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_db_is_not_ready() {
+        // the worker never answers, but keeps the channel open
+        let (db_tx, _db_rx) = mpsc::channel(8);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let readiness = Arc::new(Readiness::default());
+        readiness.host_key_loaded.store(true, Ordering::Relaxed);
+        readiness.listening.store(true, Ordering::Relaxed);
+        tokio::spawn(serve(listener, readiness, db_tx));
+
+        let response = get(addr, "/readyz").await;
+        assert!(response.starts_with("HTTP/1.1 503"));
+        assert!(response.contains("not ready: db"));
+    }
+}
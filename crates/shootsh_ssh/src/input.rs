@@ -1,93 +1,89 @@
-use shootsh_core::Action;
-use termwiz::input::{InputEvent, InputParser, KeyCode, Modifiers, MouseButtons};
+/// `TERM` values of terminals that can't report the mouse at all.
+const NO_MOUSE_TERMS: [&str; 5] = ["dumb", "linux", "vt100", "vt102", "vt220"];
 
-pub struct InputTransformer {
-    parser: InputParser,
-    last_mouse_buttons: MouseButtons,
+pub fn term_lacks_mouse(term: &str) -> bool {
+    NO_MOUSE_TERMS.contains(&term)
 }
 
-impl InputTransformer {
-    pub fn new() -> Self {
-        Self {
-            parser: InputParser::new(),
-            last_mouse_buttons: MouseButtons::NONE,
-        }
-    }
+/// Login names that belong to a machine rather than a person.
+const GENERIC_LOGINS: [&str; 8] = [
+    "root", "admin", "user", "ubuntu", "guest", "git", "pi", "shootsh",
+];
 
-    pub fn handle_input(&mut self, data: &[u8]) -> Vec<(InputEvent, MouseButtons)> {
-        let mut results = Vec::new();
-        self.parser.parse(
-            data,
-            |event| {
-                results.push((event.clone(), self.last_mouse_buttons.clone()));
+/// The SSH login name, as a suggestion for a new player's name.
+pub fn login_name_suggestion(user: &str) -> Option<&str> {
+    (!GENERIC_LOGINS.contains(&user.to_ascii_lowercase().as_str())).then_some(user)
+}
 
-                if let InputEvent::Mouse(m) = &event {
-                    self.last_mouse_buttons = m.mouse_buttons.clone();
-                }
-            },
-            false,
-        );
-        results
+/// Removes the first cursor position report (`ESC [ row ; col R`), which
+/// answers our latency probe, and tells whether there was one. A modified F3
+/// (`ESC [ 1 ; mod R`) reads the same, so only call this while a probe is
+/// outstanding; it answers once, so later ones are left as keys.
+pub fn take_cursor_report(data: &[u8]) -> (Vec<u8>, bool) {
+    let mut rest = Vec::with_capacity(data.len());
+    let mut found = false;
+    let mut i = 0;
+    while i < data.len() {
+        match cursor_report_len(&data[i..]) {
+            Some(len) if !found => {
+                found = true;
+                i += len;
+            }
+            _ => {
+                rest.push(data[i]);
+                i += 1;
+            }
+        }
     }
+    (rest, found)
 }
 
-pub fn map_input_to_action(
-    event: InputEvent,
-    captured: bool,
-    last_mouse_buttons: &MouseButtons,
-) -> Option<Action> {
-    match event {
-        InputEvent::Key(k) => {
-            let is_ctrl = k.modifiers.contains(Modifiers::CTRL);
-            if is_ctrl {
-                return match k.key {
-                    KeyCode::Char('c') | KeyCode::Char('d') => Some(Action::Quit),
-                    KeyCode::Char('k') => Some(Action::RequestReset),
-                    _ => None,
-                };
-            }
+fn cursor_report_len(data: &[u8]) -> Option<usize> {
+    let body = data.strip_prefix(b"\x1b[")?;
+    let end = body
+        .iter()
+        .position(|b| !b.is_ascii_digit() && *b != b';')?;
+    let params = &body[..end];
+    let (row, col) = params.split_at(params.iter().position(|b| *b == b';')?);
+    let valid = !row.is_empty() && col.len() > 1 && !col[1..].contains(&b';');
+    (valid && body[end] == b'R').then_some(2 + end + 1)
+}
 
-            if captured {
-                match k.key {
-                    KeyCode::Enter => Some(Action::SubmitInput),
-                    KeyCode::Backspace => Some(Action::DeleteCharacter),
-                    KeyCode::Escape => Some(Action::BackToMenu),
-                    KeyCode::Char(c) => Some(Action::AppendCharacter(c)),
-                    _ => None,
-                }
-            } else {
-                match k.key {
-                    KeyCode::Char('q') => Some(Action::Quit),
-                    KeyCode::Char('r') => Some(Action::Restart),
-                    KeyCode::Char('y') => Some(Action::ConfirmReset),
-                    KeyCode::Char('n') => Some(Action::CancelReset),
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                    KeyCode::Char('h') => Some(Action::NavigateLeft),
-                    KeyCode::Char('l') => Some(Action::NavigateRight),
-                    KeyCode::LeftArrow => Some(Action::NavigateLeft),
-                    KeyCode::RightArrow => Some(Action::NavigateRight),
+    #[test]
+    fn test_cursor_report_is_taken_from_between_keys() {
+        let (rest, found) = take_cursor_report(b"a\x1b[24;80Rb");
+        assert!(found);
+        assert_eq!(rest, b"ab");
+    }
 
-                    KeyCode::Enter => Some(Action::SubmitInput),
-                    KeyCode::Backspace => Some(Action::DeleteCharacter),
-                    KeyCode::Escape => Some(Action::BackToMenu),
-                    KeyCode::Char(c) => Some(Action::AppendCharacter(c)),
-                    _ => None,
-                }
-            }
-        }
-        InputEvent::Mouse(m) => {
-            // 1-index to 0-index
-            let x = m.x.saturating_sub(1);
-            let y = m.y.saturating_sub(1);
-            let was_pressed = last_mouse_buttons.contains(MouseButtons::LEFT);
-            let is_pressed = m.mouse_buttons.contains(MouseButtons::LEFT);
+    #[test]
+    fn test_only_one_report_is_taken() {
+        // the probe's answer, then Shift+F3
+        let (rest, found) = take_cursor_report(b"\x1b[12;40R\x1b[1;2R");
+        assert!(found);
+        assert_eq!(rest, b"\x1b[1;2R");
+    }
 
-            if is_pressed && !was_pressed {
-                Some(Action::MouseClick(x, y))
-            } else {
-                Some(Action::MouseMove(x, y))
-            }
+    #[test]
+    fn test_other_sequences_are_left_alone() {
+        for keys in [
+            &b"\x1b[A"[..],
+            b"\x1b[1;2A",
+            b"\x1b[;5R",
+            b"\x1b[1;2;3R",
+            b"\x1b[",
+        ] {
+            assert_eq!(take_cursor_report(keys), (keys.to_vec(), false));
         }
-        _ => None,
+    }
+
+    #[test]
+    fn test_generic_logins_are_not_suggested() {
+        assert_eq!(login_name_suggestion("alice"), Some("alice"));
+        assert_eq!(login_name_suggestion("Root"), None);
     }
 }
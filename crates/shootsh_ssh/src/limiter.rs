@@ -0,0 +1,149 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Failures further apart than this don't add up.
+const WINDOW: Duration = Duration::from_secs(60);
+/// First delay added to a failed attempt; doubles with each further failure.
+const BASE_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BAN: Duration = Duration::from_secs(24 * 60 * 60);
+/// An address that stays clean this long after its last ban starts over.
+const FORGET_AFTER: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+pub struct AuthLimits {
+    /// Failures within a minute that ban the address.
+    pub max_failures: usize,
+    /// First ban; each further one doubles it.
+    pub ban: Duration,
+}
+
+#[derive(Default)]
+struct Record {
+    failures: VecDeque<Instant>,
+    bans: u32,
+    banned_until: Option<Instant>,
+}
+
+/// Outcome of a recorded failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// Hold the reply this long.
+    Backoff(Duration),
+    /// The address is banned for this long.
+    Ban(Duration),
+}
+
+/// Per-address sliding window over failed and odd authentication attempts:
+/// password and keyboard-interactive logins, and connections that close
+/// without authenticating. Too many in a window ban the address, for
+/// twice as long each time.
+pub struct AuthLimiter {
+    limits: AuthLimits,
+    records: Mutex<HashMap<IpAddr, Record>>,
+}
+
+impl AuthLimiter {
+    pub fn new(limits: AuthLimits) -> Self {
+        Self {
+            limits,
+            records: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_banned(&self, ip: IpAddr, now: Instant) -> bool {
+        self.records
+            .lock()
+            .unwrap()
+            .get(&ip)
+            .and_then(|r| r.banned_until)
+            .is_some_and(|until| now < until)
+    }
+
+    pub fn record_failure(&self, ip: IpAddr, now: Instant) -> Penalty {
+        let mut records = self.records.lock().unwrap();
+        let record = records.entry(ip).or_default();
+        while record
+            .failures
+            .front()
+            .is_some_and(|&at| now.duration_since(at) > WINDOW)
+        {
+            record.failures.pop_front();
+        }
+        record.failures.push_back(now);
+        if record.failures.len() >= self.limits.max_failures {
+            let ban = self
+                .limits
+                .ban
+                .saturating_mul(2u32.saturating_pow(record.bans))
+                .min(MAX_BAN);
+            record.bans += 1;
+            record.banned_until = Some(now + ban);
+            record.failures.clear();
+            return Penalty::Ban(ban);
+        }
+        let doublings = record.failures.len().min(16) as u32 - 1;
+        Penalty::Backoff(BASE_BACKOFF.saturating_mul(1 << doublings).min(MAX_BACKOFF))
+    }
+
+    /// Drops addresses with nothing left to remember; returns how many are banned.
+    pub fn prune(&self, now: Instant) -> usize {
+        let mut records = self.records.lock().unwrap();
+        records.retain(|_, r| {
+            let recent = r
+                .failures
+                .back()
+                .is_some_and(|&at| now.duration_since(at) <= WINDOW);
+            let remembered = r
+                .banned_until
+                .is_some_and(|until| now < until + FORGET_AFTER);
+            recent || remembered
+        });
+        records
+            .values()
+            .filter(|r| r.banned_until.is_some_and(|until| now < until))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeat_offenders_are_banned_longer() {
+        let limiter = AuthLimiter::new(AuthLimits {
+            max_failures: 3,
+            ban: Duration::from_secs(60),
+        });
+        let ip = IpAddr::from([192, 0, 2, 1]);
+        let start = Instant::now();
+        assert_eq!(
+            limiter.record_failure(ip, start),
+            Penalty::Backoff(BASE_BACKOFF)
+        );
+        assert_eq!(
+            limiter.record_failure(ip, start),
+            Penalty::Backoff(BASE_BACKOFF * 2)
+        );
+        assert_eq!(
+            limiter.record_failure(ip, start),
+            Penalty::Ban(Duration::from_secs(60))
+        );
+        assert!(limiter.is_banned(ip, start));
+        assert!(!limiter.is_banned(IpAddr::from([192, 0, 2, 2]), start));
+
+        let later = start + Duration::from_secs(61);
+        assert!(!limiter.is_banned(ip, later));
+        for _ in 0..2 {
+            limiter.record_failure(ip, later);
+        }
+        assert_eq!(
+            limiter.record_failure(ip, later),
+            Penalty::Ban(Duration::from_secs(120))
+        );
+        assert_eq!(limiter.prune(later), 1);
+    }
+}
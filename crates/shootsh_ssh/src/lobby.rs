@@ -0,0 +1,159 @@
+use shootsh_core::db::{DbRequest, HeadToHead};
+use shootsh_core::duel::{DuelBoard, DuelLink, DuelOutcome, DuelTicket};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+
+/// Between pairing and the first target, so both rounds start together.
+const COUNTDOWN: Duration = Duration::from_secs(3);
+/// Past the end of the round, a side that hasn't finished (e.g. its
+/// connection dropped) forfeits.
+const FINISH_GRACE: Duration = Duration::from_secs(15);
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// The countdown doesn't wait longer than this for the head-to-head record.
+const RECORD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pairs players who asked for a duel, first come first served, and runs
+/// each match until its result is recorded. `round` is the length of a round.
+pub async fn run(
+    mut tickets: mpsc::Receiver<DuelTicket>,
+    db_tx: mpsc::Sender<DbRequest>,
+    round: Duration,
+) {
+    let mut waiting = None;
+    while let Some(ticket) = tickets.recv().await {
+        if let Some((a, b)) = pair(&mut waiting, ticket) {
+            tokio::spawn(play(a, b, db_tx.clone(), round));
+        }
+    }
+}
+
+/// Matches `ticket` against the waiting one, or makes it the waiting one.
+/// Players who left the lobby are dropped, and nobody is paired with their
+/// own account from another session.
+fn pair(waiting: &mut Option<DuelTicket>, ticket: DuelTicket) -> Option<(DuelTicket, DuelTicket)> {
+    let first = waiting
+        .take()
+        .filter(|w| !w.reply_tx.is_closed() && w.user_id != ticket.user_id);
+    match first {
+        Some(first) => Some((first, ticket)),
+        None => {
+            *waiting = Some(ticket);
+            None
+        }
+    }
+}
+
+async fn play(a: DuelTicket, b: DuelTicket, db_tx: mpsc::Sender<DbRequest>, round: Duration) {
+    let record = head_to_head(&db_tx, a.user_id, b.user_id).await;
+    let board = Arc::new(DuelBoard::default());
+    let seed = rand::random();
+    let starts_at = Instant::now() + COUNTDOWN;
+    let link = |opponent: &DuelTicket, side, record| DuelLink {
+        opponent: opponent.name.clone(),
+        seed,
+        side,
+        board: Arc::clone(&board),
+        record,
+        starts_at,
+    };
+    let (link_a, link_b) = (link(&b, 0, record), link(&a, 1, record.flipped()));
+
+    // whoever left while the record was looked up loses, but it isn't recorded
+    let sent = [
+        a.reply_tx.send(link_a).is_ok(),
+        b.reply_tx.send(link_b).is_ok(),
+    ];
+    if sent != [true, true] {
+        for (side, _) in sent.iter().enumerate().filter(|(_, sent)| !**sent) {
+            board.forfeit(side);
+        }
+        return;
+    }
+    tracing::info!(a = %a.name, b = %b.name, "Duel started");
+
+    let deadline = starts_at + round + FINISH_GRACE;
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    while !(board.is_finished(0) && board.is_finished(1)) {
+        if Instant::now() >= deadline {
+            for side in (0..2).filter(|&side| !board.is_finished(side)) {
+                board.forfeit(side);
+            }
+            break;
+        }
+        interval.tick().await;
+    }
+
+    let scores = [board.score(0), board.score(1)];
+    let winner = match board.outcome(0) {
+        Some(DuelOutcome::Won) => Some(a.user_id),
+        Some(DuelOutcome::Lost) => Some(b.user_id),
+        Some(DuelOutcome::Draw) | None => None,
+    };
+    tracing::info!(a = %a.name, b = %b.name, ?scores, ?winner, "Duel finished");
+    let _ = db_tx
+        .send(DbRequest::RecordDuel {
+            players: [a.user_id, b.user_id],
+            scores,
+            winner,
+        })
+        .await;
+}
+
+async fn head_to_head(
+    db_tx: &mpsc::Sender<DbRequest>,
+    user_id: i64,
+    opponent_id: i64,
+) -> HeadToHead {
+    let (reply_tx, rx) = oneshot::channel();
+    let req = DbRequest::GetHeadToHead {
+        user_id,
+        opponent_id,
+        reply_tx,
+    };
+    if db_tx.send(req).await.is_err() {
+        return HeadToHead::default();
+    }
+    tokio::time::timeout(RECORD_TIMEOUT, rx)
+        .await
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(user_id: i64) -> (DuelTicket, oneshot::Receiver<DuelLink>) {
+        let (reply_tx, rx) = oneshot::channel();
+        let name = format!("player{user_id}");
+        (
+            DuelTicket {
+                user_id,
+                name,
+                reply_tx,
+            },
+            rx,
+        )
+    }
+
+    #[test]
+    fn test_pair_skips_departed_and_same_account() {
+        let mut waiting = None;
+        let (first, first_rx) = ticket(1);
+        assert!(pair(&mut waiting, first).is_none());
+        drop(first_rx);
+
+        // the first player left, so the second one waits instead
+        let (second, _second_rx) = ticket(2);
+        assert!(pair(&mut waiting, second).is_none());
+        let (same, _same_rx) = ticket(2);
+        assert!(pair(&mut waiting, same).is_none());
+
+        let (third, _third_rx) = ticket(3);
+        let (a, b) = pair(&mut waiting, third).unwrap();
+        assert_eq!((a.user_id, b.user_id), (2, 3));
+        assert!(waiting.is_none());
+    }
+}
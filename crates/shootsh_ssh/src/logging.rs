@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::path::PathBuf;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines, colored on a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per line, with the fields of every enclosing span
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogSettings {
+    pub format: LogFormat,
+    /// Writes `shootsh.<date>.log` files here instead of to stdout.
+    pub dir: Option<PathBuf>,
+    pub rotation: LogRotation,
+    /// `EnvFilter` directives, e.g. `info,russh=warn`.
+    pub filter: String,
+}
+
+/// Installs the global subscriber. Lines are written on a background thread;
+/// hold the guard until exit so the last ones are flushed.
+pub fn init(settings: &LogSettings) -> Result<WorkerGuard> {
+    let filter = EnvFilter::try_new(&settings.filter)
+        .with_context(|| format!("Invalid log filter {:?}", settings.filter))?;
+    let (writer, guard) = match &settings.dir {
+        Some(dir) => {
+            let rotation = match settings.rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            };
+            let appender = RollingFileAppender::builder()
+                .rotation(rotation)
+                .filename_prefix("shootsh")
+                .filename_suffix("log")
+                .build(dir)
+                .with_context(|| format!("Cannot write logs to {}", dir.display()))?;
+            tracing_appender::non_blocking(appender)
+        }
+        None => tracing_appender::non_blocking(std::io::stdout()),
+    };
+
+    // session lines carry the `client` span, so grepping for a fingerprint finds them all
+    let layer = fmt::layer().with_writer(writer);
+    let layer = match settings.format {
+        LogFormat::Pretty => layer.with_ansi(settings.dir.is_none()).boxed(),
+        LogFormat::Json => layer
+            .json()
+            .with_current_span(false)
+            .with_span_list(true)
+            .boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(layer)
+        .with(filter)
+        .try_init()
+        .context("Logging was already set up")?;
+    Ok(guard)
+}
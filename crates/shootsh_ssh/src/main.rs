@@ -1,117 +1,672 @@
+mod commands;
+mod config;
+mod geoip;
+mod health;
 mod input;
+mod limiter;
+mod lobby;
+mod logging;
+mod pacing;
+mod recorder;
 mod server;
-use crate::server::MyServer;
+mod spectate;
+use crate::config::{Args, DbBackend, Settings};
+use crate::geoip::GeoIp;
+use crate::health::Readiness;
+use crate::limiter::AuthLimiter;
+use crate::server::{MyServer, SessionInfo};
 use anyhow::{Context, Result};
 use arc_swap::ArcSwap;
+use clap::Parser;
 use rusqlite::Connection;
 use russh::keys::load_secret_key;
 use russh::server::Server as _;
-use shootsh_core::db::{DbCache, DbRequest, Repository};
+use shootsh_core::chat::ChatBus;
+use shootsh_core::db::{
+    ClusterEvent, DbCache, DbQueue, DbRequest, ExportFormat, RankingPeriod, Repository,
+    SqliteStore, backup_database, restore_database,
+};
+use shootsh_core::signing::SigningKey;
+use socket2::{Domain, Protocol, Socket, Type};
 use std::collections::HashMap;
-use std::env;
-use std::sync::Arc;
+use std::net::SocketAddr;
+use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::mpsc;
-use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinSet;
+use tracing::Instrument;
 
-const DEFAULT_MAX_USERS: i64 = 100_000;
+/// How often the shared cache is rebuilt to pick up edits made directly in the DB.
+const CACHE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// How often recordings past their retention are looked for.
+const RECORDING_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Longest a shutdown waits for sessions to be told and queued writes to land.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    tracing_subscriber::registry()
-        .with(fmt::layer().with_ansi(true))
-        .with(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
-        .init();
+    let mut args = Args::parse();
+    let task = std::mem::take(&mut args.task);
+    let settings = Settings::load(args)?;
+    if let Some((name, args)) = task.split_first() {
+        return match name.as_str() {
+            "export" => export(&settings, args),
+            "audit" => audit(&settings, args),
+            "checks" => checks(&settings, args),
+            "shadowed" => shadowed(&settings, args),
+            "review" => review(&settings, args),
+            "season" => season(&settings, args),
+            "backup" => backup(&settings, args),
+            "restore" => restore(&settings, args),
+            _ => anyhow::bail!("Unknown task '{name}'; see shootsh_ssh --help"),
+        };
+    }
+
+    let _log_guard = logging::init(&settings.log)?;
 
     tracing::info!("Starting shootsh_ssh server...");
 
-    let db_path = std::env::var("DB_PATH").unwrap_or_else(|_| "shootsh.db".to_string());
-    let conn = Connection::open(db_path).context("Failed to open DB")?;
-    let repo = Repository::new(conn, DEFAULT_MAX_USERS).context("Failed to init repo")?;
+    let signing_key = SigningKey::random();
+    let repo = open_repository(&settings)?
+        .with_anticheat(settings.anticheat.clone())
+        .with_name_filter(settings.name_filter.clone())
+        .with_signing_key(signing_key.clone())
+        .with_check_telemetry(settings.check_telemetry)
+        .with_retention(settings.retention);
+    if let Some(motd) = &settings.motd {
+        repo.store()
+            .set_motd(Some(motd))
+            .context("Failed to store MOTD")?;
+        tracing::info!(motd = %motd, "MOTD set from settings");
+    }
+    let (cluster_tx, cluster_rx) = mpsc::unbounded_channel();
+    let repo = match &settings.node_id {
+        Some(node_id) => {
+            tracing::info!(node_id = %node_id, "Sharing the database with other nodes");
+            repo.with_cluster(node_id.clone(), cluster_tx)
+        }
+        None => repo,
+    };
     let shared_cache = Arc::new(ArcSwap::from_pointee(repo.get_current_cache()));
     let (db_tx, db_rx) = mpsc::channel::<DbRequest>(100);
-    spawn_db_worker(repo, Arc::clone(&shared_cache), db_rx);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let db_worker = spawn_db_worker(repo, Arc::clone(&shared_cache), db_rx, shutdown_rx);
+
+    let readiness = Arc::new(Readiness::default());
+    if let Some(addr) = &settings.health_addr {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Cannot listen for health checks on {addr}"))?;
+        tracing::info!(health_addr = %addr, "Health checks listening");
+        tokio::spawn(health::serve(
+            listener,
+            Arc::clone(&readiness),
+            db_tx.clone(),
+        ));
+    }
+
+    let refresh_tx = db_tx.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CACHE_REFRESH_INTERVAL);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if refresh_tx.send(DbRequest::RefreshCache).await.is_err() {
+                break;
+            }
+        }
+    });
 
     let connection_count = Arc::new(AtomicUsize::new(0));
+    let connections_per_ip = Arc::new(Mutex::new(HashMap::new()));
+    let refused_connections = Arc::new(AtomicUsize::new(0));
     let count_for_log = Arc::clone(&connection_count);
+    let ips_for_log = Arc::clone(&connections_per_ip);
+    let refused_for_log = Arc::clone(&refused_connections);
+    let auth_limiter = Arc::new(AuthLimiter::new(settings.auth_limits));
+    let limiter_for_log = Arc::clone(&auth_limiter);
     tokio::spawn(async move {
         let mut interval = tokio::time::interval(Duration::from_secs(5));
         loop {
             interval.tick().await;
             let count = count_for_log.load(Ordering::Relaxed);
-            tracing::info!(active_connections = count, "Connection stats");
+            let source_ips = ips_for_log.lock().unwrap().len();
+            let refused = refused_for_log.load(Ordering::Relaxed);
+            let banned_ips = limiter_for_log.prune(std::time::Instant::now());
+            tracing::info!(
+                active_connections = count,
+                source_ips,
+                refused_total = refused,
+                banned_ips,
+                "Connection stats"
+            );
         }
     });
 
-    let key_path = env::var("SSH_HOST_KEY_PATH").context("SSH_HOST_KEY_PATH is not set")?;
+    let key_path = settings
+        .host_key_path
+        .as_ref()
+        .context("No SSH host key; set --host-key-path or SSH_HOST_KEY_PATH")?;
     let host_key = load_secret_key(key_path, None).context("Failed to load SSH host key")?;
+    readiness.host_key_loaded.store(true, Ordering::Relaxed);
 
     let config = Arc::new(russh::server::Config {
-        inactivity_timeout: Some(Duration::from_secs(60 * 10)),
-        auth_rejection_time: Duration::from_secs(3),
+        inactivity_timeout: Some(settings.inactivity_timeout),
+        auth_rejection_time: settings.auth_rejection_time,
         nodelay: true,
         keys: vec![host_key],
         ..Default::default()
     });
 
+    let game_config = settings.game_config.clone();
+    tracing::info!(?game_config, "Game config loaded");
+
+    let color_override = settings.color_support;
+
+    let geoip = match &settings.geoip_db {
+        Some(path) => Some(Arc::new(GeoIp::open(path)?)),
+        None => None,
+    };
+
+    let recording = settings.recording.clone();
+    if let Some(policy) = &recording {
+        std::fs::create_dir_all(&policy.dir)
+            .with_context(|| format!("Cannot create {}", policy.dir.display()))?;
+        let policy = policy.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RECORDING_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                match recorder::prune(&policy) {
+                    Ok(0) => {}
+                    Ok(removed) => tracing::info!(removed, "Old session recordings deleted"),
+                    Err(e) => tracing::warn!(error = %e, "Failed to prune session recordings"),
+                }
+            }
+        });
+    }
+
+    let (duel_tx, duel_rx) = mpsc::channel(32);
+    tokio::spawn(lobby::run(duel_rx, db_tx.clone(), game_config.playing_time));
+
     let sh = MyServer {
         db_tx,
+        game_config,
+        color_override,
+        signing_key,
+        anticheat: settings.anticheat.clone(),
+        tick_rate: settings.tick_rate,
+        idle_timeout: settings.idle_timeout,
         shared_cache,
         connection_count,
-        active_sessions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+        connections_per_ip,
+        refused_connections,
+        limits: settings.connection_limits,
+        auth_limiter,
+        active_sessions: Arc::new(Mutex::new(HashMap::new())),
+        admin_fingerprints: Arc::new(settings.admin_fingerprints.clone()),
+        key_access: Arc::new(settings.key_access.clone()),
+        user_cas: Arc::new(settings.user_cas.clone()),
+        geoip,
+        geoip_regions: settings.geoip_regions,
+        recording: recording.map(Arc::new),
+        banner: settings.banner.as_deref().map(Arc::from),
+        duel_tx,
+        chat: Arc::new(ChatBus::new(settings.name_filter.clone())),
+        clustered: settings.node_id.is_some(),
     };
+    tokio::spawn(follow_cluster(cluster_rx, Arc::clone(&sh.active_sessions)));
 
-    let addr = env::var("LISTEN_ADDR").unwrap_or_else(|_| "0.0.0.0:2222".to_string());
-    tracing::info!(listen_addr = %addr, "SSH server listening");
-    let socket = TcpListener::bind(addr).await?;
-
-    let mut sh_clone = sh.clone();
-    let server_task = tokio::spawn(async move { sh_clone.run_on_socket(config, &socket).await });
+    let mut acceptors = JoinSet::new();
+    for socket in bind_all(&settings.listen_addr).await? {
+        let mut sh_clone = sh.clone();
+        let config = Arc::clone(&config);
+        acceptors.spawn(async move { sh_clone.run_on_socket(config, &socket).await });
+    }
+    readiness.listening.store(true, Ordering::Relaxed);
 
     tokio::select! {
-        res = server_task => {
+        // one acceptor stopping takes the whole server down, as a single one used to
+        Some(res) = acceptors.join_next() => {
             match res {
                 Ok(Ok(())) => tracing::info!("Server stopped normally"),
                 Ok(Err(e)) => tracing::error!(error = ?e, "Server error occurred"),
                 Err(e) => tracing::error!(error = ?e, "Server task panicked"),
             }
         },
-        _ = tokio::signal::ctrl_c() => {
+        _ = shutdown_signal() => {
             tracing::warn!("Shutdown signal received. Starting cleanup...");
+            readiness.listening.store(false, Ordering::Relaxed);
+
+            let drain = async {
+                sh.cleanup_all_sessions().await;
+                // wait for cleanup
+                tokio::time::sleep(Duration::from_millis(500)).await;
+                let _ = shutdown_tx.send(true);
+                let _ = db_worker.await;
+            };
+            if tokio::time::timeout(DRAIN_TIMEOUT, drain).await.is_err() {
+                tracing::warn!("Shutdown drain timed out; pending DB writes may be lost");
+            } else {
+                tracing::info!("Graceful shutdown complete");
+            }
+        }
+    }
 
-            sh.cleanup_all_sessions().await;
+    Ok(())
+}
+
+/// Binds every address `addrs` resolve to. One that can't be bound, such as
+/// `[::]` on a host without IPv6, is skipped as long as another one works.
+async fn bind_all(addrs: &[String]) -> Result<Vec<TcpListener>> {
+    let mut listeners = Vec::new();
+    for addr in addrs {
+        let resolved = tokio::net::lookup_host(addr)
+            .await
+            .with_context(|| format!("Cannot resolve listen address {addr}"))?;
+        for socket_addr in resolved {
+            match bind(socket_addr) {
+                Ok(listener) => {
+                    tracing::info!(listen_addr = %socket_addr, "SSH server listening");
+                    listeners.push(listener);
+                }
+                Err(e) => {
+                    tracing::warn!(listen_addr = %socket_addr, error = %e, "Cannot listen")
+                }
+            }
+        }
+    }
+    anyhow::ensure!(!listeners.is_empty(), "None of {addrs:?} could be bound");
+    Ok(listeners)
+}
+
+/// IPv6 sockets are v6-only, so `[::]` and `0.0.0.0` can share a port.
+fn bind(addr: SocketAddr) -> std::io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Ctrl-C, or SIGTERM from systemd or a container runtime.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut term) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = term.recv() => {}
+                }
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Cannot listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
 
-            // wait for cleanup
-            tokio::time::sleep(Duration::from_millis(500)).await;
-            tracing::info!("Graceful shutdown complete");
+/// `shootsh_ssh export [--format csv|json] [--period daily|weekly|all_time|daily_challenge]`
+/// writes a leaderboard to stdout. Runs before logging is set up so the output stays clean.
+fn export(settings: &Settings, args: &[String]) -> Result<()> {
+    let mut format = ExportFormat::Csv;
+    let mut period = RankingPeriod::AllTime;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .with_context(|| format!("Missing value for {flag}"))?;
+        match flag.as_str() {
+            "--format" => format = value.parse()?,
+            "--period" => {
+                period = RankingPeriod::from_key(value)
+                    .with_context(|| format!("Unknown period '{value}'"))?
+            }
+            _ => anyhow::bail!("Unknown export option '{flag}'"),
         }
     }
 
+    let repo = open_repository(settings)?;
+    let stdout = std::io::stdout().lock();
+    repo.export_scores(std::io::BufWriter::new(stdout), format, period)?;
     Ok(())
 }
 
+/// `shootsh_ssh audit [--user <id>] [--limit <n>]` prints score submissions, newest first.
+fn audit(settings: &Settings, args: &[String]) -> Result<()> {
+    let mut user_id = None;
+    let mut limit = 50;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .with_context(|| format!("Missing value for {flag}"))?;
+        match flag.as_str() {
+            "--user" => user_id = Some(value.parse().context("--user expects a user id")?),
+            "--limit" => limit = value.parse().context("--limit expects a number")?,
+            _ => anyhow::bail!("Unknown audit option '{flag}'"),
+        }
+    }
+
+    let repo = open_repository(settings)?;
+    for entry in repo.audit_log(user_id, limit)? {
+        let s = &entry.submission;
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\tflagged={}\tgame={}\t{}",
+            entry.created_at,
+            entry.user_id,
+            entry.name.as_deref().unwrap_or("-"),
+            s.mode.key(),
+            s.score,
+            s.client.key(),
+            s.verdict.key(),
+            s.flagged_clicks,
+            s.game_id.map_or("-".to_string(), |id| id.to_string()),
+            s.reason.as_deref().unwrap_or("-")
+        );
+    }
+    Ok(())
+}
+
+/// `shootsh_ssh checks [--check <name>] [--limit <n>]` prints hits refused by
+/// the anticheat with what was measured, newest first.
+fn checks(settings: &Settings, args: &[String]) -> Result<()> {
+    let mut check = None;
+    let mut limit = 50;
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .with_context(|| format!("Missing value for {flag}"))?;
+        match flag.as_str() {
+            "--check" => check = Some(value.as_str()),
+            "--limit" => limit = value.parse().context("--limit expects a number")?,
+            _ => anyhow::bail!("Unknown checks option '{flag}'"),
+        }
+    }
+
+    let repo = open_repository(settings)?;
+    let number = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{v:.3}"));
+    for record in repo.check_log(check, limit)? {
+        println!(
+            "{}	{}	game={}	{}	value={}	limit={}",
+            record.created_at,
+            record.user_id,
+            record.game_id.map_or("-".to_string(), |id| id.to_string()),
+            record.check,
+            number(record.value),
+            number(record.limit)
+        );
+    }
+    Ok(())
+}
+
+/// `shootsh_ssh review [--limit <n>]` lists held and flagged runs awaiting a
+/// decision; `shootsh_ssh review approve|reject <game id>` settles one.
+fn review(settings: &Settings, args: &[String]) -> Result<()> {
+    let repo = open_repository(settings)?;
+    let limit = match args {
+        [action, id] if action == "approve" || action == "reject" => {
+            let game_id = id.parse().context("Expected a game id")?;
+            return repo.review(game_id, action == "approve");
+        }
+        [] => 50,
+        [flag, n] if flag == "--limit" => n.parse().context("--limit expects a number")?,
+        _ => anyhow::bail!(
+            "Usage: shootsh_ssh review [--limit <n>] | approve <game id> | reject <game id>"
+        ),
+    };
+
+    for item in repo.review_queue(limit)? {
+        let (entry, s) = (&item.entry, &item.entry.submission);
+        let game = item.game.as_ref().map_or("-".to_string(), |g| {
+            format!(
+                "hits={} misses={} reaction={}",
+                g.hits,
+                g.misses,
+                g.reaction_ms
+                    .map_or("-".to_string(), |ms| format!("{ms}ms"))
+            )
+        });
+        let replay = item.replay.map_or("no replay".to_string(), |r| {
+            format!(
+                "replay {:.1}s targets={} moves={} clicks={} hits={}",
+                r.duration.as_secs_f32(),
+                r.targets,
+                r.moves,
+                r.clicks,
+                r.hits
+            )
+        });
+        println!(
+            "game={}\t{}\t{}\t{}\t{}\t{}\t{}\treason={}\t{}\t{}",
+            s.game_id.unwrap_or_default(),
+            entry.created_at,
+            entry.user_id,
+            entry.name.as_deref().unwrap_or("-"),
+            s.mode.key(),
+            s.score,
+            s.verdict.key(),
+            s.reason.as_deref().unwrap_or("-"),
+            game,
+            replay
+        );
+    }
+    Ok(())
+}
+
+/// `shootsh_ssh shadowed [--clear <id>]` lists players hidden from the public
+/// boards for low trust, or puts one back after review.
+fn shadowed(settings: &Settings, args: &[String]) -> Result<()> {
+    let repo = open_repository(settings)?;
+    match args {
+        [] => {
+            for user in repo.shadowed_users()? {
+                println!(
+                    "{}\t{}\ttrust={}",
+                    user.user_id,
+                    user.name.as_deref().unwrap_or("-"),
+                    user.trust
+                );
+            }
+            Ok(())
+        }
+        [flag, id] if flag == "--clear" => {
+            repo.clear_shadow(id.parse().context("--clear expects a user id")?)
+        }
+        _ => anyhow::bail!("Usage: shootsh_ssh shadowed [--clear <user id>]"),
+    }
+}
+
+/// `shootsh_ssh season create <name> <start> <end>` schedules a season; the
+/// running server archives the all-time board once its end date arrives.
+fn season(settings: &Settings, args: &[String]) -> Result<()> {
+    let [action, name, starts_on, ends_on] = args else {
+        anyhow::bail!("Usage: shootsh_ssh season create <name> <start> <end>");
+    };
+    anyhow::ensure!(
+        action == "create",
+        "Usage: shootsh_ssh season create <name> <start> <end>"
+    );
+    let repo = open_repository(settings)?;
+    let id = repo.create_season(name, starts_on, ends_on)?;
+    println!("Created season {id} '{name}' ({starts_on} - {ends_on})");
+    Ok(())
+}
+
+/// `shootsh_ssh backup <path>` snapshots the database while the server keeps running.
+fn backup(settings: &Settings, args: &[String]) -> Result<()> {
+    let [dest] = args else {
+        anyhow::bail!("Usage: shootsh_ssh backup <path>");
+    };
+    let db_path = &settings.db_path;
+    backup_database(Path::new(db_path), Path::new(dest))?;
+    println!("Backed up {db_path} to {dest} (integrity ok)");
+    Ok(())
+}
+
+/// `shootsh_ssh restore <path>` overwrites the database; stop the server first.
+fn restore(settings: &Settings, args: &[String]) -> Result<()> {
+    let [src] = args else {
+        anyhow::bail!("Usage: shootsh_ssh restore <path>");
+    };
+    let db_path = &settings.db_path;
+    restore_database(Path::new(db_path), Path::new(src))?;
+    println!("Restored {db_path} from {src} (integrity ok)");
+    Ok(())
+}
+
+/// Opens the store `db_backend` names; Postgres requires the `postgres` feature.
+fn open_repository(settings: &Settings) -> Result<Repository> {
+    match settings.db_backend {
+        DbBackend::Memory => {
+            tracing::warn!("Using in-memory storage; scores are lost on restart");
+            return Ok(Repository::in_memory(settings.max_users));
+        }
+        #[cfg(feature = "postgres")]
+        DbBackend::Postgres => {
+            let url = settings.database_url.as_deref().unwrap_or_default();
+            let store = shootsh_core::db::PostgresStore::connect(url)
+                .context("Failed to connect to Postgres")?;
+            tracing::info!("Using Postgres storage backend");
+            return Ok(Repository::with_store(store, settings.max_users));
+        }
+        #[cfg(not(feature = "postgres"))]
+        DbBackend::Postgres => {
+            anyhow::bail!(
+                "database_url is set but shootsh_ssh was built without the postgres feature"
+            );
+        }
+        DbBackend::Sqlite => {}
+    }
+
+    let db_path = &settings.db_path;
+    let conn = Connection::open(db_path).context("Failed to open DB")?;
+    let store = SqliteStore::new(conn).context("Failed to init repo")?;
+    let journal_mode = store.journal_mode()?;
+    if journal_mode.eq_ignore_ascii_case("wal") {
+        tracing::info!(path = %db_path, journal_mode = %journal_mode, "SQLite storage ready");
+    } else {
+        tracing::warn!(
+            path = %db_path,
+            journal_mode = %journal_mode,
+            "SQLite is not in WAL mode; readers may block the DB worker"
+        );
+    }
+    Ok(Repository::with_store(store, settings.max_users))
+}
+
+/// Ends local sessions that other nodes took over or kicked.
+async fn follow_cluster(
+    mut rx: mpsc::UnboundedReceiver<ClusterEvent>,
+    sessions: Arc<Mutex<HashMap<String, SessionInfo>>>,
+) {
+    while let Some(event) = rx.recv().await {
+        let (fp, msg) = match &event {
+            ClusterEvent::SessionStarted(fp) => {
+                (fp, "You started playing from another connection.")
+            }
+            ClusterEvent::Kicked(fp) => (fp, "You were disconnected by an operator."),
+            ClusterEvent::CacheChanged => continue,
+        };
+        if server::kick_session(&sessions, fp, msg).await {
+            tracing::info!(fingerprint = %fp, ?event, "Session ended by another node");
+        }
+    }
+}
+
+/// Requests buffered inside the worker; beyond this the channel applies backpressure.
+const DB_QUEUE_LIMIT: usize = 1024;
+
+/// Queries run on the blocking pool one at a time, but everything already
+/// sent is pulled into a `DbQueue` first so logins skip past queued saves.
+/// Once `shutdown` turns true the worker finishes what was sent and exits.
 fn spawn_db_worker(
     repo: Repository,
     cache: Arc<ArcSwap<DbCache>>,
     mut rx: mpsc::Receiver<DbRequest>,
-) {
-    std::thread::spawn(move || {
-        let span = tracing::info_span!("db_worker");
-        let _enter = span.enter();
-        tracing::info!("DB worker thread started");
-
-        while let Some(req) = rx.blocking_recv() {
-            tracing::debug!(request = ?req, "Handling DB request");
-            match repo.handle_request(req) {
-                Some(new_cache) => {
-                    cache.store(Arc::new(new_cache));
-                    tracing::debug!("DB cache updated");
+    mut shutdown: watch::Receiver<bool>,
+) -> tokio::task::JoinHandle<()> {
+    let worker = async move {
+        tracing::info!("DB worker started");
+        let mut repo = repo;
+        let mut queue = DbQueue::default();
+        let idle_interval = repo.idle_interval();
+
+        loop {
+            if queue.is_empty() && !*shutdown.borrow() {
+                tokio::select! {
+                    res = tokio::time::timeout(idle_interval, rx.recv()) => match res {
+                        Ok(Some(req)) => queue.push(req),
+                        Ok(None) => break,
+                        // Idle: fall through with an empty queue to run scheduled jobs.
+                        Err(_) => {}
+                    },
+                    // wakes up to drain what's left
+                    _ = shutdown.changed() => {}
+                }
+            }
+            while queue.len() < DB_QUEUE_LIMIT {
+                match rx.try_recv() {
+                    Ok(req) => queue.push(req),
+                    Err(_) => break,
+                }
+            }
+            if *shutdown.borrow() && queue.is_empty() {
+                break;
+            }
+
+            let req = queue.pop();
+            if let Some(req) = &req {
+                tracing::debug!(request = ?req, queued = queue.len(), "Handling DB request");
+            }
+            let pruned_before = repo.pruned_users();
+            let handled = tokio::task::spawn_blocking(move || {
+                let new_cache = match req {
+                    Some(req) => repo.handle_request(req),
+                    None => repo.run_idle(),
+                };
+                (repo, new_cache)
+            })
+            .await;
+
+            match handled {
+                Ok((returned, new_cache)) => {
+                    repo = returned;
+                    let pruned = repo.pruned_users() - pruned_before;
+                    if pruned > 0 {
+                        tracing::info!(
+                            pruned,
+                            pruned_total = repo.pruned_users(),
+                            "Pruned inactive users"
+                        );
+                    }
+                    if let Some(new_cache) = new_cache {
+                        cache.store(Arc::new(new_cache));
+                        tracing::debug!("DB cache updated");
+                    }
+                }
+                Err(e) => {
+                    // The repository went down with the task; without it every
+                    // score would be lost, so let the supervisor start over.
+                    tracing::error!(error = %e, "DB worker panicked, exiting");
+                    std::process::exit(1);
                 }
-                None => {}
             }
         }
-        tracing::info!("DB worker thread shutting down");
-    });
+        tracing::info!("DB worker shutting down");
+    };
+    tokio::spawn(worker.instrument(tracing::info_span!("db_worker")))
 }
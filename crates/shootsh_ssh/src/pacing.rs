@@ -0,0 +1,101 @@
+use std::time::{Duration, Instant};
+
+/// Slower steps a session falls back to, as frame intervals (10 and 5 fps).
+const FALLBACK_INTERVALS: [Duration; 2] = [Duration::from_millis(100), Duration::from_millis(200)];
+/// Least time between two changes, so one slow frame doesn't flap the rate.
+const HOLD: Duration = Duration::from_secs(2);
+/// Weight of the newest sample in the moving average of send times.
+const SMOOTHING: f64 = 0.2;
+
+/// Picks a session's frame interval from how long its frames take to be
+/// accepted by the SSH channel: a link that can't keep up gets fewer frames
+/// instead of a growing backlog, and the full rate once it recovers.
+pub struct FramePacer {
+    intervals: [Duration; 3],
+    level: usize,
+    /// Moving average of send times.
+    send_time: Duration,
+    changed_at: Instant,
+}
+
+impl FramePacer {
+    pub fn new(base: Duration, now: Instant) -> Self {
+        Self {
+            intervals: [
+                base,
+                base.max(FALLBACK_INTERVALS[0]),
+                base.max(FALLBACK_INTERVALS[1]),
+            ],
+            level: 0,
+            send_time: Duration::ZERO,
+            changed_at: now,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.intervals[self.level]
+    }
+
+    /// Records how long a frame took to send; returns the new interval if it changed.
+    pub fn record_send(&mut self, took: Duration, now: Instant) -> Option<Duration> {
+        self.send_time = self
+            .send_time
+            .mul_f64(1.0 - SMOOTHING)
+            .saturating_add(took.mul_f64(SMOOTHING));
+        if now.duration_since(self.changed_at) < HOLD {
+            return None;
+        }
+
+        let current = self.interval();
+        let level = if self.send_time > current / 2 {
+            // skip steps that are no slower, e.g. when the base rate is already low
+            (self.level + 1..self.intervals.len())
+                .find(|&l| self.intervals[l] > current)
+                .unwrap_or(self.level)
+        } else if self.level > 0 && self.send_time < self.intervals[self.level - 1] / 4 {
+            self.level - 1
+        } else {
+            self.level
+        };
+        if level == self.level || self.intervals[level] == current {
+            self.level = level;
+            return None;
+        }
+        self.level = level;
+        self.changed_at = now;
+        Some(self.interval())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_link_steps_down_and_recovers() {
+        let start = Instant::now();
+        let base = Duration::from_secs(1) / 30;
+        let mut pacer = FramePacer::new(base, start);
+        let mut now = start;
+        let mut feed = |pacer: &mut FramePacer, took: Duration, frames: u32| {
+            let mut changed = None;
+            for _ in 0..frames {
+                now += pacer.interval();
+                changed = pacer.record_send(took, now).or(changed);
+            }
+            changed
+        };
+
+        assert_eq!(feed(&mut pacer, Duration::from_millis(1), 120), None);
+        assert_eq!(
+            feed(&mut pacer, Duration::from_millis(60), 10),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            feed(&mut pacer, Duration::from_millis(80), 30),
+            Some(Duration::from_millis(200))
+        );
+        feed(&mut pacer, Duration::from_millis(1), 60);
+        assert_eq!(pacer.interval(), base);
+    }
+}
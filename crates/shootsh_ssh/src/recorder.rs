@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Where session recordings go and how much of them is kept.
+#[derive(Debug, Clone)]
+pub struct RecordingPolicy {
+    pub dir: PathBuf,
+    /// A recording stops growing past this, the session carries on.
+    pub max_bytes: u64,
+    /// Recordings older than this are deleted by `prune`.
+    pub keep: Duration,
+}
+
+/// Writes what a session is sent as an asciinema v2 `.cast` file, which
+/// `asciinema play` replays with the original timing.
+pub struct CastRecorder {
+    out: BufWriter<File>,
+    path: PathBuf,
+    started: Instant,
+    written: u64,
+    max_bytes: u64,
+}
+
+impl CastRecorder {
+    pub fn create(
+        policy: &RecordingPolicy,
+        fingerprint: &str,
+        size: (u16, u16),
+        term: &str,
+    ) -> Result<Self> {
+        let now = chrono::Utc::now();
+        // base64 fingerprints contain `/`; a prefix is enough to tell players apart
+        let key: String = fingerprint
+            .trim_start_matches("SHA256:")
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .take(12)
+            .collect();
+        let path = policy
+            .dir
+            .join(format!("{}-{key}.cast", now.format("%Y%m%d-%H%M%S")));
+        let file =
+            File::create(&path).with_context(|| format!("Cannot create {}", path.display()))?;
+        let header = serde_json::json!({
+            "version": 2,
+            "width": size.0,
+            "height": size.1,
+            "timestamp": now.timestamp(),
+            "env": { "TERM": term },
+        });
+        let mut recorder = Self {
+            out: BufWriter::new(file),
+            path,
+            started: Instant::now(),
+            written: 0,
+            max_bytes: policy.max_bytes,
+        };
+        recorder.line(&header.to_string())?;
+        Ok(recorder)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records bytes sent to the client; `false` once the recording is full
+    /// or can't be written, and it should be dropped.
+    pub fn output(&mut self, data: &[u8]) -> bool {
+        self.event("o", &String::from_utf8_lossy(data))
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> bool {
+        self.event("r", &format!("{width}x{height}"))
+    }
+
+    fn event(&mut self, kind: &str, data: &str) -> bool {
+        let at = self.started.elapsed().as_secs_f64();
+        let event = serde_json::json!([(at * 1000.0).round() / 1000.0, kind, data]);
+        self.line(&event.to_string()).is_ok() && self.written < self.max_bytes
+    }
+
+    fn line(&mut self, line: &str) -> Result<()> {
+        writeln!(self.out, "{line}")?;
+        self.written += line.len() as u64 + 1;
+        Ok(())
+    }
+}
+
+impl Drop for CastRecorder {
+    fn drop(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+/// Deletes recordings in `policy.dir` older than `policy.keep`; returns how many.
+pub fn prune(policy: &RecordingPolicy) -> Result<usize> {
+    let cutoff = SystemTime::now() - policy.keep;
+    let mut removed = 0;
+    for entry in fs::read_dir(&policy.dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "cast") {
+            continue;
+        }
+        let modified = fs::metadata(&path)?.modified()?;
+        if modified <= cutoff && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cast_file_and_size_cap() {
+        let dir = std::env::temp_dir().join(format!("shootsh-cast-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let policy = RecordingPolicy {
+            dir: dir.clone(),
+            max_bytes: 200,
+            keep: Duration::ZERO,
+        };
+        let mut recorder =
+            CastRecorder::create(&policy, "SHA256:ab/cd+ef", (80, 24), "xterm").unwrap();
+        assert!(recorder.output(b"\x1b[2Jhi\n"));
+        assert!(!recorder.output(&[b'x'; 200]));
+        let path = recorder.path().to_path_buf();
+        drop(recorder);
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert!(path.to_string_lossy().ends_with("-abcdef.cast"));
+        assert!(lines[0].contains("\"version\":2"));
+        assert!(lines[1].ends_with(",\"o\",\"\\u001b[2Jhi\\n\"]"));
+
+        assert_eq!(prune(&policy).unwrap(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,92 +1,199 @@
-use crate::input::InputTransformer;
+use crate::commands::{self, AdminCommand, ExecCommand, SessionRow};
+use crate::geoip::GeoIp;
+use crate::limiter::{AuthLimiter, Penalty};
+use crate::pacing::FramePacer;
+use crate::recorder::{CastRecorder, RecordingPolicy};
+use crate::spectate::Stream;
 use arc_swap::ArcSwap;
 use crossterm::style::{Color, Stylize};
 use futures::future::join_all;
 use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend, layout::Rect};
-use russh::keys::ssh_key::PublicKey;
+use russh::keys::ssh_key::certificate::CertType;
+use russh::keys::ssh_key::{Certificate, Fingerprint, HashAlg, PublicKey};
 use russh::server::{Auth, Handler, Msg, Session};
 use russh::*;
-use shootsh_core::db::{DbCache, DbRequest};
-use shootsh_core::{Action, App, Scene, domain, ui};
-use std::collections::HashMap;
-use std::net::SocketAddr;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use shootsh_core::chat::ChatBus;
+use shootsh_core::db::{ClusterEvent, DbCache, DbRequest, RankingPeriod, UserRank};
+use shootsh_core::duel::DuelTicket;
+use shootsh_core::preferences::Language;
+use shootsh_core::region::Region;
+use shootsh_core::remote::{
+    self, CLEANUP_SEQ, InputTransformer, RemoteTerminal, SETUP_SEQ, SharedBuffer,
+};
+use shootsh_core::replay::{self, MAX_BATCH_BYTES};
+use shootsh_core::signing::SigningKey;
+use shootsh_core::theme::ColorSupport;
+use shootsh_core::{Action, AntiCheatConfig, App, GameConfig, domain};
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tokio::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use termwiz::input::InputEvent;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::MissedTickBehavior;
 use tracing::Instrument;
 
-const SETUP_SEQ: &[u8] = concat!(
-    "\x1b[?1049h", // EnterAlternateScreen
-    "\x1b[?1000h", // EnableMouseCapture (Normal)
-    "\x1b[?1002h", // EnableMouseCapture (Button)
-    "\x1b[?1003h", // EnableMouseCapture (Any)
-    "\x1b[?1015h", // EnableMouseCapture (URXVT)
-    "\x1b[?1006h", // EnableMouseCapture (SGR)
-    "\x1b[?25l"    // HideCursor
-)
-.as_bytes();
-
-const CLEANUP_SEQ: &[u8] = concat!(
-    "\x1b[?1006l", // DisableMouseCapture (SGR)
-    "\x1b[?1015l", // DisableMouseCapture (URXVT)
-    "\x1b[?1003l", // DisableMouseCapture (Any)
-    "\x1b[?1002l", // DisableMouseCapture (Button)
-    "\x1b[?1000l", // DisableMouseCapture (Normal)
-    "\x1b[?1049l", // LeaveAlternateScreen
-    "\x1b[?25h"    // ShowCursor
-)
-.as_bytes();
-
-const CURSOR_HIDE: &[u8] = b"\x1b[?25l";
-
-/// A thread-safe wrapper around a byte buffer to capture TUI draw calls.
-#[derive(Clone, Default)]
-struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
-
-impl std::io::Write for SharedBuffer {
-    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.0.lock().unwrap().write(buf)
-    }
-
-    fn flush(&mut self) -> std::io::Result<()> {
-        self.0.lock().unwrap().flush()
-    }
-}
+/// Device status report; the terminal answers with its cursor position.
+const CURSOR_POSITION_QUERY: &[u8] = b"\x1b[6n";
+const BELL: &[u8] = b"\x07";
+/// A viewer gets the player's screen, but none of the mouse reporting.
+const WATCH_SETUP_SEQ: &[u8] = b"\x1b[?1049h\x1b[?25l";
+/// A session that hasn't sent a mouse event by then gets keyboard aiming.
+const MOUSE_GRACE: Duration = Duration::from_secs(8);
+/// How long before an idle session is closed the player is warned.
+const IDLE_WARNING: Duration = Duration::from_secs(30);
 
 pub struct SessionInfo {
     pub handle: russh::server::Handle,
     pub channel_id: ChannelId,
+    /// Frames for `watch`, and how many are watching, shown in the player's HUD.
+    pub stream: Stream,
+    /// Unset until the account is loaded.
+    pub name: Option<String>,
+    pub addr: Option<IpAddr>,
+    pub country: Option<Region>,
+    pub connected_at: Instant,
+    /// Operator broadcast waiting for the render loop to hand it to the app.
+    pub announcement: Arc<Mutex<Option<String>>>,
+}
+
+impl SessionInfo {
+    /// Restores the player's terminal, prints `msg` and closes the channel.
+    async fn disconnect(&self, msg: &str) {
+        let mut payload = Vec::from(CLEANUP_SEQ);
+        payload.extend_from_slice(format!("\r\n{}\r\n", msg.red().bold()).as_bytes());
+        let _ = self.handle.data(self.channel_id, payload.into()).await;
+        let _ = self.handle.eof(self.channel_id).await;
+        let _ = self.handle.close(self.channel_id).await;
+    }
+}
+
+/// Ends the session playing as `fp` on this node, if any.
+pub async fn kick_session(
+    sessions: &Mutex<HashMap<String, SessionInfo>>,
+    fp: &str,
+    msg: &str,
+) -> bool {
+    let session = sessions.lock().unwrap().remove(fp);
+    let Some(session) = session else {
+        return false;
+    };
+    session.disconnect(msg).await;
+    true
+}
+
+/// Caps on open connections, checked as each one arrives.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionLimits {
+    pub per_ip: usize,
+    pub global: usize,
+}
+
+/// Which keys may play, for private instances. Admin keys are always let in.
+#[derive(Debug, Clone, Default)]
+pub struct KeyAccess {
+    /// `None` lets in every key that isn't denied.
+    pub allowed: Option<HashSet<String>>,
+    pub denied: HashSet<String>,
+}
+
+impl KeyAccess {
+    /// Certificates also match an entry for their CA, `cert:<CA fingerprint>`.
+    pub fn permits(&self, fingerprint: &str) -> bool {
+        !lists(&self.denied, fingerprint)
+            && self
+                .allowed
+                .as_ref()
+                .is_none_or(|allowed| lists(allowed, fingerprint))
+    }
+}
+
+/// Why a connection was turned away before its session started.
+#[derive(Debug, Clone, Copy)]
+enum Refusal {
+    ServerFull,
+    TooManyFromIp(usize),
+    /// Too many failed logins; never gets as far as a session.
+    Banned,
+    /// The key isn't on this private server's access list.
+    NotAllowed,
+}
+
+impl Refusal {
+    fn message(self) -> String {
+        match self {
+            Self::ServerFull => {
+                "The server is full right now. Please try again in a few minutes.".to_string()
+            }
+            Self::TooManyFromIp(limit) => format!(
+                "Too many connections from your address (limit {limit}). \
+                Close another session and try again."
+            ),
+            Self::Banned => "Too many failed logins. Please try again later.".to_string(),
+            Self::NotAllowed => "This is a private server and your key is not on its access list. \
+                Ask the operator to add it, quoting the fingerprint `ssh-keygen -lf` prints."
+                .to_string(),
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct MyServer {
     pub db_tx: mpsc::Sender<DbRequest>,
+    pub game_config: GameConfig,
+    /// Forces a palette for every client instead of guessing from `TERM`.
+    pub color_override: Option<ColorSupport>,
+    /// Signs every session's game events for the DB worker.
+    pub signing_key: SigningKey,
+    pub anticheat: AntiCheatConfig,
+    /// Time between frames of every session's render loop.
+    pub tick_rate: Duration,
+    /// Sessions without input this long are closed; `None` keeps them open.
+    pub idle_timeout: Option<Duration>,
     pub shared_cache: Arc<ArcSwap<DbCache>>,
     pub connection_count: Arc<AtomicUsize>,
+    pub connections_per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    /// Connections turned away by `limits` since startup.
+    pub refused_connections: Arc<AtomicUsize>,
+    pub limits: ConnectionLimits,
+    pub auth_limiter: Arc<AuthLimiter>,
     pub active_sessions: Arc<Mutex<HashMap<String, SessionInfo>>>,
+    /// Key fingerprints allowed to run `ssh <host> admin ...`.
+    pub admin_fingerprints: Arc<HashSet<String>>,
+    /// Keys that may play; everyone else is told so and disconnected.
+    pub key_access: Arc<KeyAccess>,
+    /// CAs whose user certificates log in as `cert:<key id>` instead of by key.
+    pub user_cas: Arc<Vec<Fingerprint>>,
+    /// Resolves the country of each connection, for the logs.
+    pub geoip: Option<Arc<GeoIp>>,
+    /// New accounts get the country as their region.
+    pub geoip_regions: bool,
+    /// Where game sessions are recorded to, if anywhere.
+    pub recording: Option<Arc<RecordingPolicy>>,
+    /// Text clients show before authenticating, e.g. rules and a privacy note.
+    pub banner: Option<Arc<str>>,
+    /// Queue of the duel lobby shared by every session.
+    pub duel_tx: mpsc::Sender<DuelTicket>,
+    /// Menu chat shared by every session.
+    pub chat: Arc<ChatBus>,
+    /// Other nodes share the database; logins and kicks are told to them.
+    pub clustered: bool,
 }
 
 impl MyServer {
     pub async fn cleanup_all_sessions(&self) {
-        let mut sessions = self.active_sessions.lock().unwrap();
-        let session_list: Vec<_> = sessions.drain().collect();
-        drop(sessions);
-
-        let shutdown_msg = format!("\r\n{}\r\n", "Server is shutting down.".red().bold());
+        let session_list: Vec<_> = self.active_sessions.lock().unwrap().drain().collect();
 
-        let tasks = session_list.into_iter().map(|(fp, info)| {
-            let msg = shutdown_msg.clone();
-            async move {
-                let mut payload = Vec::from(CLEANUP_SEQ);
-                payload.extend_from_slice(msg.as_bytes());
-                let _ = info.handle.data(info.channel_id, payload.into()).await;
-                let _ = info.handle.eof(info.channel_id).await;
+        let tasks = session_list.into_iter().map(|(fp, info)| async move {
+            info.disconnect("Server is shutting down.").await;
 
-                let _ = info.handle.close(info.channel_id).await;
-
-                tracing::info!(fingerprint = %fp, "Cleanup task finished");
-            }
+            tracing::info!(
+                fingerprint = %fp,
+                spectators = info.stream.viewers(),
+                "Cleanup task finished"
+            );
         });
 
         join_all(tasks).await;
@@ -97,14 +204,69 @@ impl russh::server::Server for MyServer {
     type Handler = ClientHandler;
     fn new_client(&mut self, peer_addr: Option<SocketAddr>) -> Self::Handler {
         let count = self.connection_count.fetch_add(1, Ordering::Relaxed) + 1;
+        let peer_ip = peer_addr.map(|addr| addr.ip());
+        let from_ip = peer_ip.map_or(0, |ip| {
+            let mut per_ip = self.connections_per_ip.lock().unwrap();
+            let n = per_ip.entry(ip).or_default();
+            *n += 1;
+            *n
+        });
+        // the connection is still counted until it drops, so its handler can refuse it
+        let banned = peer_ip.is_some_and(|ip| self.auth_limiter.is_banned(ip, Instant::now()));
+        let refusal = if banned {
+            Some(Refusal::Banned)
+        } else if count > self.limits.global {
+            Some(Refusal::ServerFull)
+        } else if from_ip > self.limits.per_ip {
+            Some(Refusal::TooManyFromIp(self.limits.per_ip))
+        } else {
+            None
+        };
 
-        let span = tracing::info_span!("client", addr = ?peer_addr, fp = tracing::field::Empty);
+        let country = self
+            .geoip
+            .as_ref()
+            .zip(peer_ip)
+            .and_then(|(geoip, ip)| geoip.country(ip));
+        let span = tracing::info_span!(
+            "client",
+            addr = ?peer_addr,
+            country = country.map(|c| c.code()),
+            fp = tracing::field::Empty
+        );
         let _enter = span.enter();
-        tracing::info!(active_connections = count, "New connection");
+        if banned {
+            // scanners come back often; keep them out of the info log
+            self.refused_connections.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Connection from banned address");
+        } else if let Some(refusal) = refusal {
+            let refused = self.refused_connections.fetch_add(1, Ordering::Relaxed) + 1;
+            tracing::warn!(
+                active_connections = count,
+                from_ip,
+                ?refusal,
+                refused_total = refused,
+                "Connection over limit"
+            );
+        } else {
+            tracing::info!(active_connections = count, from_ip, "New connection");
+        }
 
         let (update_tx, update_rx) = mpsc::unbounded_channel();
         ClientHandler {
             db_tx: self.db_tx.clone(),
+            game_config: self.game_config.clone(),
+            color_override: self.color_override,
+            signing_key: self.signing_key.clone(),
+            anticheat: self.anticheat.clone(),
+            tick_rate: self.tick_rate,
+            idle_timeout: self.idle_timeout,
+            last_input: Arc::new(Mutex::new(Instant::now())),
+            mouse_seen: Arc::new(AtomicBool::new(false)),
+            term: String::new(),
+            colorterm: None,
+            no_color: false,
+            locale_vars: HashMap::new(),
             shared_cache: self.shared_cache.clone(),
             app: None,
             input_transformer: InputTransformer::new(),
@@ -115,10 +277,30 @@ impl russh::server::Server for MyServer {
             update_tx,
             update_rx: Some(update_rx),
             connection_count: self.connection_count.clone(),
+            connections_per_ip: self.connections_per_ip.clone(),
+            peer_ip,
+            refusal,
+            auth_limiter: self.auth_limiter.clone(),
             terminal: None,
             output_buffer: SharedBuffer::default(),
             fingerprint: None,
+            login_user: None,
+            stream: Stream::default(),
+            watching: None,
+            announcement: Arc::new(Mutex::new(None)),
             active_sessions: self.active_sessions.clone(),
+            channels: HashMap::new(),
+            upload: None,
+            admin_fingerprints: self.admin_fingerprints.clone(),
+            key_access: self.key_access.clone(),
+            user_cas: self.user_cas.clone(),
+            country,
+            geoip_regions: self.geoip_regions,
+            recording: self.recording.clone(),
+            banner: self.banner.clone(),
+            duel_tx: self.duel_tx.clone(),
+            chat: self.chat.clone(),
+            clustered: self.clustered,
             span: span.clone(),
         }
     }
@@ -126,6 +308,19 @@ impl russh::server::Server for MyServer {
 
 pub struct ClientHandler {
     db_tx: mpsc::Sender<DbRequest>,
+    game_config: GameConfig,
+    color_override: Option<ColorSupport>,
+    signing_key: SigningKey,
+    anticheat: AntiCheatConfig,
+    tick_rate: Duration,
+    idle_timeout: Option<Duration>,
+    /// Last keystroke or mouse event; our own output and cursor reports don't count.
+    last_input: Arc<Mutex<Instant>>,
+    mouse_seen: Arc<AtomicBool>,
+    term: String,
+    colorterm: Option<String>,
+    no_color: bool,
+    locale_vars: HashMap<String, String>,
     pub shared_cache: Arc<ArcSwap<DbCache>>,
     app: Option<Arc<Mutex<App>>>,
     input_transformer: InputTransformer,
@@ -133,31 +328,429 @@ pub struct ClientHandler {
     update_tx: mpsc::UnboundedSender<()>,
     update_rx: Option<mpsc::UnboundedReceiver<()>>,
     connection_count: Arc<AtomicUsize>,
-    terminal: Option<Terminal<CrosstermBackend<SharedBuffer>>>,
+    connections_per_ip: Arc<Mutex<HashMap<IpAddr, usize>>>,
+    peer_ip: Option<IpAddr>,
+    refusal: Option<Refusal>,
+    auth_limiter: Arc<AuthLimiter>,
+    terminal: Option<RemoteTerminal>,
     output_buffer: SharedBuffer,
     pub fingerprint: Option<String>,
+    /// Name the client logged in as, `alice` in `ssh alice@host`.
+    login_user: Option<String>,
+    stream: Stream,
+    /// Channel of a `watch` command, and how to stop it.
+    watching: Option<(ChannelId, oneshot::Sender<()>)>,
+    announcement: Arc<Mutex<Option<String>>>,
     pub active_sessions: Arc<Mutex<HashMap<String, SessionInfo>>>,
+    /// Opened but not yet used for a shell or command.
+    channels: HashMap<ChannelId, Channel<Msg>>,
+    /// Channel of an `upload` command and what it sent so far.
+    upload: Option<(ChannelId, Vec<u8>)>,
+    admin_fingerprints: Arc<HashSet<String>>,
+    key_access: Arc<KeyAccess>,
+    user_cas: Arc<Vec<Fingerprint>>,
+    country: Option<Region>,
+    geoip_regions: bool,
+    recording: Option<Arc<RecordingPolicy>>,
+    banner: Option<Arc<str>>,
+    duel_tx: mpsc::Sender<DuelTicket>,
+    chat: Arc<ChatBus>,
+    clustered: bool,
     pub span: tracing::Span,
 }
 
 impl ClientHandler {
-    fn render_frame(
-        app: &App,
-        terminal: &mut Terminal<CrosstermBackend<SharedBuffer>>,
-        shared_output: &SharedBuffer,
-    ) -> Vec<u8> {
-        terminal
-            .draw(|f| {
-                ui::render(app, &app.db_cache, f);
-                f.set_cursor_position(ratatui::layout::Position::new(0, 0));
-            })
-            .expect("Failed to draw frame");
+    fn is_banned(&self) -> bool {
+        matches!(self.refusal, Some(Refusal::Banned))
+    }
 
-        let mut output = Vec::from(CURSOR_HIDE);
-        let mut internal_vec = shared_output.0.lock().unwrap();
-        output.extend(std::mem::take(&mut *internal_vec));
+    /// Counts an odd login against the address; `false` if that got it banned.
+    async fn penalize(&self) -> bool {
+        let Some(ip) = self.peer_ip else {
+            return true;
+        };
+        match self.auth_limiter.record_failure(ip, Instant::now()) {
+            Penalty::Backoff(delay) => {
+                tokio::time::sleep(delay).await;
+                true
+            }
+            Penalty::Ban(duration) => {
+                self.span.in_scope(|| {
+                    tracing::warn!(%ip, ban_secs = duration.as_secs(), "Address banned");
+                });
+                false
+            }
+        }
+    }
+
+    /// Tells a connection over a limit why, and closes its channel.
+    fn refuse(&self, channel: ChannelId, session: &mut Session) -> bool {
+        let Some(refusal) = self.refusal else {
+            return false;
+        };
+        let msg = format!(
+            "\r\n{}\r\n\r\n",
+            refusal.message().with(Color::Yellow).bold()
+        );
+        let _ = session.data(channel, msg.into());
+        let _ = session.channel_success(channel);
+        let _ = session.close(channel);
+        true
+    }
+
+    /// Lets `fp` in unless it is banned; keys off the access list are let in
+    /// to be told so.
+    async fn log_in(&mut self, user: &str, fp: String) -> Auth {
+        self.span.record("fp", &fp);
+        if lists(&self.shared_cache.load().banned_keys, &fp) {
+            self.span.in_scope(|| {
+                tracing::info!("Banned key refused");
+            });
+            self.penalize().await;
+            return reject();
+        }
+        // accepted anyway, so the shell can say why the player is turned away
+        if !self.key_access.permits(&fp) && !self.admin_fingerprints.contains(&fp) {
+            self.span.in_scope(|| {
+                tracing::info!("Key not on the access list");
+            });
+            self.refusal = Some(Refusal::NotAllowed);
+        }
+        self.span.in_scope(|| {
+            tracing::info!("Public key authenticated");
+        });
 
-        output
+        self.fingerprint = Some(fp);
+        self.login_user = Some(user.to_string());
+        Auth::Accept
+    }
+
+    /// Sends a command's output and exit status, and closes its channel.
+    fn finish_command(
+        &self,
+        channel: ChannelId,
+        mut output: String,
+        status: u32,
+        session: &mut Session,
+    ) {
+        // with `ssh -t` the client's terminal is in raw mode
+        if !self.term.is_empty() {
+            output = output.replace('\n', "\r\n");
+        }
+        let _ = session.data(channel, output.into_bytes().into());
+        let _ = session.exit_status_request(channel, status);
+        let _ = session.eof(channel);
+        let _ = session.close(channel);
+    }
+
+    /// Streams the screen of the player called `name` to `channel` until they
+    /// stop playing or the viewer presses q.
+    fn start_watching(&mut self, channel: ChannelId, name: &str, session: &mut Session) {
+        let viewer = self
+            .active_sessions
+            .lock()
+            .unwrap()
+            .values()
+            .find(|info| info.name.as_deref() == Some(name))
+            .and_then(|info| info.stream.watch());
+        let Some(mut viewer) = viewer else {
+            let output = format!("Nobody called {name} is playing here right now\n");
+            self.finish_command(channel, output, 1, session);
+            return;
+        };
+        let _ = session.channel_success(channel);
+        let _ = session.data(channel, WATCH_SETUP_SEQ.into());
+
+        let (stop_tx, mut stop_rx) = oneshot::channel();
+        self.watching = Some((channel, stop_tx));
+        let handle = session.handle();
+        let name = name.to_string();
+        tokio::spawn(
+            async move {
+                loop {
+                    let frame = tokio::select! {
+                        frame = viewer.next() => frame,
+                        _ = &mut stop_rx => None,
+                    };
+                    let Some(frame) = frame else { break };
+                    if handle.data(channel, frame[..].into()).await.is_err() {
+                        break;
+                    }
+                }
+                drop(viewer);
+                let mut payload = Vec::from(CLEANUP_SEQ);
+                payload.extend_from_slice(format!("\r\nStopped watching {name}\r\n").as_bytes());
+                let _ = handle.data(channel, payload.into()).await;
+                let _ = handle.exit_status_request(channel, 0).await;
+                let _ = handle.eof(channel).await;
+                let _ = handle.close(channel).await;
+            }
+            .instrument(self.span.clone()),
+        );
+    }
+
+    fn is_admin(&self) -> bool {
+        self.fingerprint
+            .as_ref()
+            .is_some_and(|fp| self.admin_fingerprints.contains(fp))
+    }
+
+    /// Output and exit status of a non-interactive command.
+    async fn run_command(&self, command: &str) -> (String, u32) {
+        let cache = self.shared_cache.load_full();
+        let unknown = || {
+            (
+                format!("Unknown command '{}'\n\n{}", command.trim(), commands::HELP),
+                1,
+            )
+        };
+        match ExecCommand::parse(command) {
+            Some(ExecCommand::Top) => (
+                commands::leaderboard("All-time top", &cache.all_time_scores),
+                0,
+            ),
+            Some(ExecCommand::Daily) => {
+                (commands::leaderboard("Today's top", &cache.daily_scores), 0)
+            }
+            Some(ExecCommand::Stats) => {
+                let Some(fp) = &self.fingerprint else {
+                    return (
+                        "stats needs public key authentication (ssh-keygen -t ed25519)\n"
+                            .to_string(),
+                        1,
+                    );
+                };
+                match self.find_user(fp).await {
+                    Some(Ok(Some(user))) => {
+                        let ranks = self.fetch_user_ranks(user.id).await;
+                        (commands::stats(&user, &ranks), 0)
+                    }
+                    Some(Ok(None)) => ("No games played with this key yet\n".to_string(), 1),
+                    _ => (
+                        "Could not load your stats, try again later\n".to_string(),
+                        1,
+                    ),
+                }
+            }
+            // with a key the upload is read from stdin first, see `exec_request`
+            Some(ExecCommand::Upload) => (
+                "upload needs public key authentication (ssh-keygen -t ed25519)\n".to_string(),
+                1,
+            ),
+            // streamed rather than printed, see `exec_request`
+            Some(ExecCommand::Watch(_)) => unknown(),
+            Some(ExecCommand::Help) => (commands::HELP.to_string(), 0),
+            // to everyone else the admin commands don't exist
+            Some(ExecCommand::Admin(_)) if !self.is_admin() => unknown(),
+            Some(ExecCommand::Admin(Some(admin))) => self.run_admin(admin).await,
+            Some(ExecCommand::Admin(None)) => (
+                format!("Unknown admin command\n\n{}", commands::ADMIN_HELP),
+                1,
+            ),
+            None => unknown(),
+        }
+    }
+
+    /// Submits the runs `shootsh_cli sync` sent, answering a line per run in order.
+    async fn run_upload(&self, payload: &[u8]) -> (String, u32) {
+        let Some(fp) = &self.fingerprint else {
+            return ("upload needs public key authentication\n".to_string(), 1);
+        };
+        let replays = match replay::decode_batch(payload) {
+            Ok(replays) => replays,
+            Err(e) => return (format!("{e:#}\n"), 1),
+        };
+        let user = match self.fetch_user_context(fp).await {
+            Ok(user) => user,
+            Err(_) => {
+                return (
+                    "Could not load your account, try again later\n".to_string(),
+                    1,
+                );
+            }
+        };
+        if user.name.is_none() {
+            return (
+                "Pick a name first: play once with `ssh <host>`\n".to_string(),
+                1,
+            );
+        }
+        self.span.in_scope(|| {
+            tracing::info!(runs = replays.len(), "Upload");
+        });
+
+        let mut output = String::new();
+        for replay in replays {
+            let (reply_tx, reply_rx) = oneshot::channel();
+            let req = DbRequest::UploadGame {
+                user_id: user.id,
+                replay: Arc::new(replay),
+                reply_tx,
+            };
+            if self.db_tx.send(req).await.is_err() {
+                break;
+            }
+            match reply_rx.await {
+                Ok(submission) => output.push_str(&commands::upload_line(&submission)),
+                Err(_) => break,
+            }
+        }
+        (output, 0)
+    }
+
+    async fn run_admin(&self, command: AdminCommand) -> (String, u32) {
+        self.span.in_scope(|| {
+            tracing::warn!(?command, "Admin command");
+        });
+        match self.admin_output(command).await {
+            Ok(output) => (output, 0),
+            Err(e) => (format!("{e:#}\n"), 1),
+        }
+    }
+
+    async fn admin_output(&self, command: AdminCommand) -> anyhow::Result<String> {
+        match command {
+            AdminCommand::Sessions => {
+                let now = Instant::now();
+                let mut rows: Vec<_> = self
+                    .active_sessions
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(fp, info)| SessionRow {
+                        fingerprint: fp.clone(),
+                        name: info.name.clone(),
+                        addr: info.addr,
+                        country: info.country,
+                        connected: now.duration_since(info.connected_at),
+                        spectators: info.stream.viewers(),
+                    })
+                    .collect();
+                Ok(commands::sessions(&mut rows))
+            }
+            AdminCommand::Kick(fp) => {
+                if self
+                    .kick(&fp, "You were disconnected by an operator.")
+                    .await
+                {
+                    return Ok(format!("Kicked {fp}\n"));
+                }
+                anyhow::ensure!(self.clustered, "No session for {fp}");
+                self.tell_cluster(ClusterEvent::Kicked(fp.clone())).await?;
+                Ok(format!("No session for {fp} here; asked the other nodes\n"))
+            }
+            AdminCommand::Ban(fp) => {
+                self.admin_request(|reply_tx| DbRequest::SetKeyBan {
+                    fingerprint: fp.clone(),
+                    banned: true,
+                    reply_tx,
+                })
+                .await?;
+                let kicked = self.kick(&fp, "This key has been banned.").await;
+                if !kicked && self.clustered {
+                    self.tell_cluster(ClusterEvent::Kicked(fp.clone())).await?;
+                }
+                Ok(format!(
+                    "Banned {fp}{}\n",
+                    if kicked { ", session closed" } else { "" }
+                ))
+            }
+            AdminCommand::Unban(fp) => {
+                self.admin_request(|reply_tx| DbRequest::SetKeyBan {
+                    fingerprint: fp.clone(),
+                    banned: false,
+                    reply_tx,
+                })
+                .await?;
+                Ok(format!("Unbanned {fp}\n"))
+            }
+            AdminCommand::Motd(message) => {
+                let cleared = message.is_none();
+                self.admin_request(|reply_tx| DbRequest::SetMotd { message, reply_tx })
+                    .await?;
+                Ok(if cleared {
+                    "MOTD cleared\n"
+                } else {
+                    "MOTD updated\n"
+                }
+                .to_string())
+            }
+            AdminCommand::Broadcast(text) => {
+                let sessions = self.active_sessions.lock().unwrap();
+                for info in sessions.values() {
+                    *info.announcement.lock().unwrap() = Some(text.clone());
+                }
+                Ok(format!("Sent to {} sessions\n", sessions.len()))
+            }
+            AdminCommand::Modifier { week, modifier } => {
+                self.admin_request(|reply_tx| DbRequest::SetWeeklyModifier {
+                    week: week.clone(),
+                    modifier,
+                    reply_tx,
+                })
+                .await?;
+                Ok(match modifier {
+                    Some(m) => format!("{} scheduled for {week}\n", m.name()),
+                    None => format!("Modifier cleared for {week}\n"),
+                })
+            }
+            AdminCommand::Refresh => {
+                self.db_tx
+                    .send(DbRequest::RefreshCache)
+                    .await
+                    .map_err(|_| anyhow::anyhow!("The DB worker is not running"))?;
+                Ok("Cache rebuild queued\n".to_string())
+            }
+            AdminCommand::Help => Ok(commands::ADMIN_HELP.to_string()),
+        }
+    }
+
+    /// Sends an admin change to the DB worker and waits for it to be stored.
+    async fn admin_request(
+        &self,
+        request: impl FnOnce(oneshot::Sender<anyhow::Result<()>>) -> DbRequest,
+    ) -> anyhow::Result<()> {
+        let (reply_tx, rx) = oneshot::channel();
+        self.db_tx
+            .send(request(reply_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("The DB worker is not running"))?;
+        tokio::time::timeout(Duration::from_secs(5), rx)
+            .await
+            .map_err(|_| anyhow::anyhow!("The DB worker did not answer in time"))?
+            .map_err(|_| anyhow::anyhow!("The DB worker dropped the request"))?
+    }
+
+    /// Ends the session playing as `fp`, if any.
+    async fn kick(&self, fp: &str, msg: &str) -> bool {
+        kick_session(&self.active_sessions, fp, msg).await
+    }
+
+    async fn tell_cluster(&self, event: ClusterEvent) -> anyhow::Result<()> {
+        self.db_tx
+            .send(DbRequest::Broadcast(event))
+            .await
+            .map_err(|_| anyhow::anyhow!("The DB worker is not running"))
+    }
+
+    fn color_support(&self) -> ColorSupport {
+        if let Some(support) = self.color_override {
+            return support;
+        }
+        if self.no_color {
+            return ColorSupport::Monochrome;
+        }
+        ColorSupport::detect(&self.term, self.colorterm.as_deref())
+    }
+
+    /// Same precedence as POSIX: `LC_ALL`, then `LC_MESSAGES`, then `LANG`.
+    fn locale_language(&self) -> Language {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| self.locale_vars.get(*name))
+            .find(|value| !value.is_empty())
+            .and_then(|value| Language::from_locale(value))
+            .unwrap_or_default()
     }
 
     async fn kick_existing_session(
@@ -173,6 +766,12 @@ impl ClientHandler {
                 SessionInfo {
                     handle: current_handle,
                     channel_id: channel,
+                    stream: self.stream.clone(),
+                    name: None,
+                    addr: self.peer_ip,
+                    country: self.country,
+                    connected_at: Instant::now(),
+                    announcement: self.announcement.clone(),
                 },
             )
         };
@@ -186,6 +785,54 @@ impl ClientHandler {
         }
     }
 
+    /// `None` when the DB worker didn't answer in time.
+    async fn find_user(
+        &self,
+        fp: &str,
+    ) -> Option<anyhow::Result<Option<shootsh_core::db::UserContext>>> {
+        let (reply_tx, rx) = tokio::sync::oneshot::channel();
+        let req = DbRequest::FindUser {
+            fingerprint: fp.to_string(),
+            reply_tx,
+        };
+        self.db_tx.send(req).await.ok()?;
+        tokio::time::timeout(Duration::from_secs(2), rx)
+            .await
+            .ok()?
+            .ok()
+    }
+
+    /// Ranks print as `-` when missing, so a slow store just leaves them out.
+    async fn fetch_user_ranks(&self, user_id: i64) -> HashMap<RankingPeriod, UserRank> {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let req = DbRequest::GetUserRanks {
+            user_id,
+            reply_tx: tx,
+        };
+        if self.db_tx.send(req).await.is_err() {
+            return HashMap::new();
+        }
+        tokio::time::timeout(Duration::from_secs(2), rx)
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+    }
+
+    async fn is_name_available(&self, name: &str) -> bool {
+        let (reply_tx, rx) = tokio::sync::oneshot::channel();
+        let req = DbRequest::CheckUsername {
+            name: name.to_string(),
+            reply_tx,
+        };
+        if self.db_tx.send(req).await.is_err() {
+            return false;
+        }
+        tokio::time::timeout(Duration::from_secs(2), rx)
+            .await
+            .is_ok_and(|reply| reply.unwrap_or(false))
+    }
+
     async fn fetch_user_context(
         &self,
         fp: &str,
@@ -195,6 +842,7 @@ impl ClientHandler {
         self.db_tx
             .send(DbRequest::GetOrCreateUser {
                 fingerprint: fp.to_string(),
+                region: self.country.filter(|_| self.geoip_regions),
                 reply_tx: tx,
             })
             .await
@@ -212,9 +860,30 @@ impl ClientHandler {
             }) // oneshot recv error
     }
 
+    fn start_recording(&self) -> Option<CastRecorder> {
+        let policy = self.recording.as_deref()?;
+        let fp = self.fingerprint.as_deref()?;
+        let size = *self.terminal_size.lock().unwrap();
+        let _enter = self.span.enter();
+        match CastRecorder::create(policy, fp, (size.width, size.height), &self.term) {
+            Ok(recorder) => {
+                tracing::info!(path = %recorder.path().display(), "Recording session");
+                Some(recorder)
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Cannot record session");
+                None
+            }
+        }
+    }
+
+    /// Frames go out through `writer`, which waits while the client's window
+    /// is full: a stalled client holds up its own loop rather than piling
+    /// frames up in russh, and the next frame drawn is of the latest state.
     fn run_render_loop(
         &mut self,
         channel: ChannelId,
+        writer: ChannelWriteHalf<Msg>,
         session_handle: russh::server::Handle,
         app: Arc<Mutex<App>>,
     ) {
@@ -228,6 +897,13 @@ impl ClientHandler {
         let terminal_size = self.terminal_size.clone();
         let shared_cache = self.shared_cache.clone();
         let output_buffer = self.output_buffer.clone();
+        let stream = self.stream.clone();
+        let announcement = self.announcement.clone();
+        let tick_rate = self.tick_rate;
+        let idle_timeout = self.idle_timeout;
+        let last_input = self.last_input.clone();
+        let mouse_seen = self.mouse_seen.clone();
+        let mut recorder = self.start_recording();
 
         tokio::spawn(
             async move {
@@ -254,13 +930,32 @@ impl ClientHandler {
                     chan: channel,
                 };
 
-                let mut interval = tokio::time::interval(Duration::from_millis(33));
+                let mut pacer = FramePacer::new(tick_rate, Instant::now());
+                let mut last_frame_hash = None;
+                let mut mouse_deadline = Some(Instant::now() + MOUSE_GRACE);
+                let mut interval = tokio::time::interval(tick_rate);
+                // a frame that is late anyway shouldn't be followed by a burst
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
                 loop {
-                    tokio::select! {
-                        _ = interval.tick() => {},
+                    let ticked = tokio::select! {
+                        _ = interval.tick() => true,
                         res = rx.recv() => {
                             if res.is_none() { break; }
+                            false
                         },
+                    };
+
+                    let idle = last_input.lock().unwrap().elapsed();
+                    if let Some(timeout) = idle_timeout.filter(|&timeout| idle >= timeout) {
+                        tracing::info!(idle_secs = idle.as_secs(), "Idle session closed");
+                        let goodbye = format!(
+                            "No input for {} minutes, so we let you go. Come back any time!",
+                            timeout.as_secs().div_ceil(60)
+                        );
+                        let mut payload = Vec::from(CLEANUP_SEQ);
+                        payload.extend_from_slice(format!("\r\n{goodbye}\r\n").as_bytes());
+                        let _ = session_handle.data(channel, payload.into()).await;
+                        break;
                     }
 
                     let render_result = {
@@ -268,10 +963,28 @@ impl ClientHandler {
                         if app.should_quit {
                             break;
                         }
+                        if mouse_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                            mouse_deadline = None;
+                            if !mouse_seen.load(Ordering::Relaxed) && !app.keyboard_aim {
+                                tracing::info!("No mouse events, switching to keyboard aiming");
+                                app.keyboard_aim = true;
+                            }
+                        }
+                        app.idle_warning = idle_timeout.and_then(|timeout| {
+                            let left = timeout - idle;
+                            (left <= IDLE_WARNING.min(timeout / 2)).then_some(left)
+                        });
 
                         let sz = *terminal_size.lock().unwrap();
-                        app.db_cache = shared_cache.load_full();
+                        app.set_db_cache(shared_cache.load_full());
+                        app.spectators = stream.viewers();
+                        if let Some(text) = announcement.lock().unwrap().take() {
+                            app.announcement = Some(text);
+                        }
 
+                        if ticked {
+                            app.perf.record_tick(pacer.interval(), Instant::now());
+                        }
                         app.update_state(Action::Tick).0.ok();
 
                         let t = term.get_or_insert_with(|| {
@@ -288,16 +1001,69 @@ impl ClientHandler {
                         let current_area = Rect::new(0, 0, sz.width, sz.height);
                         if t.size().unwrap() != current_area.into() {
                             t.resize(current_area).ok();
+                            if recorder
+                                .as_mut()
+                                .is_some_and(|r| !r.resize(sz.width, sz.height))
+                            {
+                                recorder = None;
+                            }
                         }
 
-                        (Self::render_frame(&app, t, &output_buffer), app.should_quit)
+                        // a new viewer has none of the screen the diffs build on
+                        if stream.take_redraw() {
+                            t.clear().ok();
+                        }
+                        let mut frame = remote::render_frame(&app, t, &output_buffer);
+                        // an idle screen diffs to the same cursor bookkeeping every frame
+                        let mut hasher = DefaultHasher::new();
+                        frame.hash(&mut hasher);
+                        let hash = hasher.finish();
+                        if last_frame_hash.replace(hash) == Some(hash) {
+                            frame.clear();
+                        }
+                        let now = Instant::now();
+                        app.perf.record_frame(now);
+                        if app.show_debug && app.perf.start_probe(now) {
+                            frame.extend_from_slice(CURSOR_POSITION_QUERY);
+                        }
+                        if app.feedback.take_bell() {
+                            frame.extend_from_slice(BELL);
+                        }
+
+                        (frame, app.should_quit, app.takeout.take())
                     };
 
-                    let (buffer, should_quit) = render_result;
-                    if session_handle.data(channel, buffer.into()).await.is_err() || should_quit {
+                    let (buffer, should_quit, takeout) = render_result;
+                    if buffer.is_empty() && !should_quit {
+                        continue;
+                    }
+                    let sent_at = Instant::now();
+                    let sent = writer.data(&buffer[..]).await;
+                    stream.publish(&buffer);
+                    if sent.is_ok() && recorder.as_mut().is_some_and(|r| !r.output(&buffer)) {
+                        tracing::info!("Session recording stopped at its size limit");
+                        recorder = None;
+                    }
+                    if let Some(period) = pacer.record_send(sent_at.elapsed(), Instant::now()) {
+                        tracing::debug!(
+                            fps = 1000 / period.as_millis().max(1),
+                            "Frame rate changed"
+                        );
+                        interval =
+                            tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                        interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+                    }
+                    if sent.is_err() || should_quit {
+                        // the data export goes to the plain terminal, after the TUI is torn down
+                        if let Some(takeout) = takeout {
+                            let mut payload = Vec::from(CLEANUP_SEQ);
+                            payload.extend_from_slice(takeout.replace('\n', "\r\n").as_bytes());
+                            let _ = session_handle.data(channel, payload.into()).await;
+                        }
                         break;
                     }
                 }
+                stream.end();
                 tracing::debug!("Render loop finished");
             }
             .instrument(span),
@@ -308,37 +1074,84 @@ impl ClientHandler {
 impl Handler for ClientHandler {
     type Error = russh::Error;
 
+    async fn authentication_banner(&mut self) -> Result<Option<String>, Self::Error> {
+        if self.is_banned() {
+            return Ok(None);
+        }
+        Ok(self.banner.as_deref().map(str::to_string))
+    }
+
+    /// Let in only to be told to use a key, but scanners guess passwords.
     async fn auth_password(&mut self, _user: &str, _pass: &str) -> Result<Auth, Self::Error> {
+        if self.is_banned() || !self.penalize().await {
+            return Ok(reject());
+        }
         Ok(Auth::Accept)
     }
 
-    async fn auth_publickey(&mut self, _user: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
-        let fp = key
-            .fingerprint(russh::keys::ssh_key::HashAlg::Sha256)
-            .to_string();
-
-        self.span.record("fp", &fp);
-        self.span.in_scope(|| {
-            tracing::info!("Public key authenticated");
-        });
+    /// Refusing here spares banned addresses the signature check.
+    async fn auth_publickey_offered(
+        &mut self,
+        _user: &str,
+        _key: &PublicKey,
+    ) -> Result<Auth, Self::Error> {
+        if self.is_banned() {
+            return Ok(reject());
+        }
+        Ok(Auth::Accept)
+    }
 
-        self.fingerprint = Some(fp);
+    async fn auth_publickey(&mut self, user: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
+        if self.is_banned() {
+            return Ok(reject());
+        }
+        let fp = key.fingerprint(HashAlg::Sha256).to_string();
+        Ok(self.log_in(user, fp).await)
+    }
 
-        Ok(Auth::Accept)
+    /// A certificate from a trusted CA is the account of its key ID, whatever
+    /// key it certifies, so short-lived keys keep one account. Principals
+    /// aren't checked; login names mean nothing here.
+    async fn auth_openssh_certificate(
+        &mut self,
+        user: &str,
+        certificate: &Certificate,
+    ) -> Result<Auth, Self::Error> {
+        if self.is_banned() || self.user_cas.is_empty() {
+            return Ok(reject());
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let valid = certificate.cert_type() == CertType::User
+            && !certificate.key_id().trim().is_empty()
+            && certificate.validate_at(now, self.user_cas.iter()).is_ok();
+        if !valid {
+            self.span.in_scope(|| {
+                tracing::info!(key_id = certificate.key_id(), "Certificate refused");
+            });
+            // the client falls back to the plain key
+            return Ok(reject());
+        }
+        let ca = certificate.signature_key().fingerprint(HashAlg::Sha256);
+        Ok(self
+            .log_in(user, cert_identity(&ca, certificate.key_id()))
+            .await)
     }
 
     async fn channel_open_session(
         &mut self,
-        _channel: Channel<Msg>,
+        channel: Channel<Msg>,
         _session: &mut Session,
     ) -> std::result::Result<bool, Self::Error> {
+        self.channels.insert(channel.id(), channel);
         Ok(true)
     }
 
     async fn pty_request(
         &mut self,
         channel: ChannelId,
-        _term: &str,
+        term: &str,
         col_width: u32,
         row_height: u32,
         _pix_width: u32,
@@ -346,6 +1159,7 @@ impl Handler for ClientHandler {
         _modes: &[(Pty, u32)],
         session: &mut Session,
     ) -> std::result::Result<(), Self::Error> {
+        self.term = term.to_string();
         if let Ok(mut sz) = self.terminal_size.lock() {
             *sz = domain::Size {
                 width: col_width as u16,
@@ -356,6 +1170,30 @@ impl Handler for ClientHandler {
         Ok(())
     }
 
+    /// Only honoured if the client sends them (`SendEnv COLORTERM NO_COLOR LANG LC_*`).
+    async fn env_request(
+        &mut self,
+        channel: ChannelId,
+        variable_name: &str,
+        variable_value: &str,
+        session: &mut Session,
+    ) -> std::result::Result<(), Self::Error> {
+        match variable_name {
+            "COLORTERM" => self.colorterm = Some(variable_value.to_string()),
+            "NO_COLOR" => self.no_color = true,
+            "LANG" | "LC_ALL" | "LC_MESSAGES" => {
+                self.locale_vars
+                    .insert(variable_name.to_string(), variable_value.to_string());
+            }
+            _ => {
+                let _ = session.channel_failure(channel);
+                return Ok(());
+            }
+        }
+        let _ = session.channel_success(channel);
+        Ok(())
+    }
+
     async fn window_change_request(
         &mut self,
         _channel: ChannelId,
@@ -380,6 +1218,10 @@ impl Handler for ClientHandler {
         channel: ChannelId,
         session: &mut Session,
     ) -> std::result::Result<(), Self::Error> {
+        if self.refuse(channel, session) {
+            return Ok(());
+        }
+
         let fp = match self.fingerprint.clone() {
             Some(fp) => fp,
             None => {
@@ -405,8 +1247,19 @@ impl Handler for ClientHandler {
             }
         };
 
+        let Some(opened) = self.channels.remove(&channel) else {
+            return Err(russh::Error::Inconsistent);
+        };
+        // input reaches us through `data`, so only the writing half is kept
+        let (_, writer) = opened.split();
+
         self.kick_existing_session(&fp, channel, session.handle())
             .await;
+        if self.clustered {
+            let _ = self
+                .tell_cluster(ClusterEvent::SessionStarted(fp.clone()))
+                .await;
+        }
 
         let user_context = self.fetch_user_context(&fp).await?;
 
@@ -415,13 +1268,47 @@ impl Handler for ClientHandler {
             SessionInfo {
                 handle: session.handle(),
                 channel_id: channel,
+                stream: self.stream.clone(),
+                name: user_context.name.clone(),
+                addr: self.peer_ip,
+                country: self.country,
+                connected_at: Instant::now(),
+                announcement: self.announcement.clone(),
             },
         );
 
+        // only offered if it would be accepted, so the first screen isn't an error
+        let suggested_name = match self
+            .login_user
+            .as_deref()
+            .and_then(crate::input::login_name_suggestion)
+        {
+            Some(name) if user_context.name.is_none() && self.is_name_available(name).await => {
+                Some(name.to_string())
+            }
+            _ => None,
+        };
+
         let initial_cache = self.shared_cache.load_full();
-        let mut app = App::new(user_context, self.db_tx.clone(), initial_cache);
+        let mut app = App::new(
+            user_context,
+            self.db_tx.clone(),
+            initial_cache,
+            self.game_config.clone(),
+        )
+        .with_anticheat(self.anticheat.clone())
+        .with_chat(self.chat.clone());
         let initial_size = *self.terminal_size.lock().unwrap();
         app.screen_size = initial_size;
+        app.color_support = self.color_support();
+        app.locale_language = self.locale_language();
+        app.signing_key = Some(self.signing_key.clone());
+        app.peer_ip = self.peer_ip;
+        app.keyboard_aim = crate::input::term_lacks_mouse(&self.term);
+        app.duel_tx = Some(self.duel_tx.clone());
+        if let Some(name) = &suggested_name {
+            app.suggest_name(name);
+        }
 
         let app_arc = Arc::new(Mutex::new(app));
         self.app = Some(app_arc.clone());
@@ -429,33 +1316,124 @@ impl Handler for ClientHandler {
         let _ = session.channel_success(channel);
         let _ = session.data(channel, SETUP_SEQ.into());
 
-        self.run_render_loop(channel, session.handle(), app_arc);
+        self.run_render_loop(channel, writer, session.handle(), app_arc);
+
+        Ok(())
+    }
+
+    /// `ssh <host> top` and friends print and exit without the TUI.
+    async fn exec_request(
+        &mut self,
+        channel: ChannelId,
+        data: &[u8],
+        session: &mut Session,
+    ) -> std::result::Result<(), Self::Error> {
+        if self.refuse(channel, session) {
+            return Ok(());
+        }
+        self.channels.remove(&channel);
+        let command = String::from_utf8_lossy(data).into_owned();
+        self.span.in_scope(|| {
+            tracing::info!(command = %command.trim(), "Exec request");
+        });
+        if let Some(ExecCommand::Watch(name)) = ExecCommand::parse(&command) {
+            self.start_watching(channel, &name, session);
+            return Ok(());
+        }
+        if self.fingerprint.is_some() && ExecCommand::parse(&command) == Some(ExecCommand::Upload) {
+            self.upload = Some((channel, Vec::new()));
+            let _ = session.channel_success(channel);
+            return Ok(());
+        }
+        let (output, status) = self.run_command(&command).await;
+        let _ = session.channel_success(channel);
+        self.finish_command(channel, output, status, session);
+        Ok(())
+    }
 
+    /// The upload is complete once the client closes its end.
+    async fn channel_eof(
+        &mut self,
+        channel: ChannelId,
+        session: &mut Session,
+    ) -> std::result::Result<(), Self::Error> {
+        let Some((_, payload)) = self.upload.take_if(|(id, _)| *id == channel) else {
+            return Ok(());
+        };
+        let (output, status) = self.run_upload(&payload).await;
+        self.finish_command(channel, output, status, session);
         Ok(())
     }
 
     async fn data(
         &mut self,
-        _channel: ChannelId,
+        channel: ChannelId,
         data: &[u8],
-        _session: &mut Session,
+        session: &mut Session,
     ) -> std::result::Result<(), Self::Error> {
+        if self.watching.as_ref().is_some_and(|(id, _)| *id == channel) {
+            // q, or Ctrl-C from a client in raw mode
+            if data.iter().any(|&b| b == b'q' || b == 0x03)
+                && let Some((_, stop_tx)) = self.watching.take()
+            {
+                let _ = stop_tx.send(());
+            }
+            return Ok(());
+        }
+        match &mut self.upload {
+            Some((id, payload)) if *id == channel => {
+                if payload.len() + data.len() > MAX_BATCH_BYTES {
+                    self.upload = None;
+                    self.finish_command(channel, "Upload too large\n".to_string(), 1, session);
+                } else {
+                    payload.extend_from_slice(data);
+                }
+                return Ok(());
+            }
+            _ => {}
+        }
         let app_arc = match &self.app {
             Some(a) => a,
             None => return Ok(()),
         };
 
+        let stripped = {
+            let mut app = app_arc.lock().unwrap();
+            if app.perf.probe_pending() {
+                let (rest, found) = crate::input::take_cursor_report(data);
+                if found {
+                    app.perf.finish_probe(Instant::now());
+                    if let Some(round_trip) = app.perf.round_trip {
+                        app.behavior_analyzer.set_latency(round_trip);
+                    }
+                }
+                Some(rest)
+            } else {
+                None
+            }
+        };
+        let data = stripped.as_deref().unwrap_or(data);
+        if !data.is_empty() {
+            *self.last_input.lock().unwrap() = Instant::now();
+        }
+
         let event_pairs = self.input_transformer.handle_input(data);
 
         let mut actions = Vec::new();
         {
-            let app = app_arc.lock().unwrap();
+            let mut app = app_arc.lock().unwrap();
             let captured = app.input_captured();
+            // whatever made us think otherwise, this terminal has a mouse
+            if event_pairs
+                .iter()
+                .any(|(event, _)| matches!(event, InputEvent::Mouse(_)))
+            {
+                self.mouse_seen.store(true, Ordering::Relaxed);
+                app.keyboard_aim = false;
+            }
 
             for (event, prev_buttons) in event_pairs {
-                if let Some(action) =
-                    crate::input::map_input_to_action(event, captured, &prev_buttons)
-                {
+                if let Some(action) = remote::map_input_to_action(event, captured, &prev_buttons) {
                     actions.push(action);
                 }
             }
@@ -486,26 +1464,7 @@ impl Handler for ClientHandler {
 
                 tokio::spawn(async move {
                     if let Ok(result) = rx.await {
-                        let mut app_inner = app_clone.lock().unwrap();
-                        let current_scene = app_inner.scene.clone();
-                        match result {
-                            Ok(_) => match current_scene {
-                                Scene::Naming(state) => {
-                                    app_inner.user.name = Some(state.input.clone());
-                                    app_inner.change_scene(Scene::Menu);
-                                }
-                                Scene::ResetConfirmation => {
-                                    app_inner.should_quit = true;
-                                }
-                                _ => app_inner.change_scene(Scene::Menu),
-                            },
-                            Err(e) => {
-                                if let Scene::Naming(state) = &mut app_inner.scene {
-                                    state.error = Some(e.to_string());
-                                    state.is_loading = false;
-                                }
-                            }
-                        }
+                        app_clone.lock().unwrap().complete_request(result);
                         let _ = update_tx.send(());
                     }
                 });
@@ -518,11 +1477,66 @@ impl Handler for ClientHandler {
     }
 }
 
+/// Account identity of a certificate, in place of a key fingerprint:
+/// `cert:<CA fingerprint>:<key id>`, so two CAs issuing the same key ID
+/// don't share an account. Whitespace and `%` in the key ID are
+/// percent-encoded to keep the identity one word for `admin` commands.
+fn cert_identity(ca: &Fingerprint, key_id: &str) -> String {
+    let mut identity = format!("{}:", cert_authority_name(ca));
+    for c in key_id.trim().chars() {
+        if c.is_whitespace() || c == '%' {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                identity.push_str(&format!("%{byte:02X}"));
+            }
+        } else {
+            identity.push(c);
+        }
+    }
+    identity
+}
+
+/// How access lists name every certificate a CA signs.
+pub fn cert_authority_name(ca: &Fingerprint) -> String {
+    format!("cert:{ca}")
+}
+
+/// The `cert:<CA fingerprint>` part of a certificate identity.
+fn cert_authority_of(identity: &str) -> Option<&str> {
+    let rest = identity.strip_prefix("cert:")?;
+    // the fingerprint is `SHA256:<base64>`, the key ID follows the next colon
+    let end = "cert:".len() + rest.find(':')? + 1;
+    let id_start = end + identity[end..].find(':')?;
+    Some(&identity[..id_start])
+}
+
+/// Whether `list` names `identity`, either itself or, for a certificate,
+/// the CA that signed it.
+fn lists(list: &HashSet<String>, identity: &str) -> bool {
+    list.contains(identity) || cert_authority_of(identity).is_some_and(|ca| list.contains(ca))
+}
+
+fn reject() -> Auth {
+    Auth::Reject {
+        proceed_with_methods: None,
+        partial_success: false,
+    }
+}
+
 impl Drop for ClientHandler {
     fn drop(&mut self) {
         // DO NOT REMOVE SESSION HERE! (kick_existing_session handles this well)
         let _enter = self.span.enter();
         let count = self.connection_count.fetch_sub(1, Ordering::Relaxed) - 1;
+        if let Some(ip) = self.peer_ip {
+            let mut per_ip = self.connections_per_ip.lock().unwrap();
+            if let Some(n) = per_ip.get_mut(&ip) {
+                *n -= 1;
+                if *n == 0 {
+                    per_ip.remove(&ip);
+                }
+            }
+        }
         tracing::info!(active_connections = count, "Connection closed");
     }
 }
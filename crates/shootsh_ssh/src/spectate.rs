@@ -0,0 +1,123 @@
+//! `ssh <host> watch <name>`: a session's frames fanned out to viewers, who
+//! are counted for the player's HUD.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::broadcast;
+
+/// Frames a viewer may fall behind by before it is sent a full redraw.
+const BACKLOG: usize = 64;
+
+/// The frames of one session, as its render loop sends them.
+#[derive(Clone)]
+pub struct Stream {
+    frames: broadcast::Sender<Arc<[u8]>>,
+    viewers: Arc<AtomicUsize>,
+    /// Set when a viewer joins or falls behind, so the next frame is drawn in full.
+    redraw: Arc<AtomicBool>,
+    ended: Arc<AtomicBool>,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Self {
+            frames: broadcast::channel(BACKLOG).0,
+            viewers: Arc::new(AtomicUsize::new(0)),
+            redraw: Arc::new(AtomicBool::new(false)),
+            ended: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl Stream {
+    pub fn viewers(&self) -> usize {
+        self.viewers.load(Ordering::Relaxed)
+    }
+
+    /// `None` once the session stopped drawing.
+    pub fn watch(&self) -> Option<Viewer> {
+        if self.ended.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.viewers.fetch_add(1, Ordering::Relaxed);
+        self.redraw.store(true, Ordering::Relaxed);
+        Some(Viewer {
+            frames: self.frames.subscribe(),
+            viewers: Arc::clone(&self.viewers),
+            redraw: Arc::clone(&self.redraw),
+        })
+    }
+
+    pub fn publish(&self, frame: &[u8]) {
+        if !frame.is_empty() && self.frames.receiver_count() > 0 {
+            let _ = self.frames.send(frame.into());
+        }
+    }
+
+    /// Whether a viewer needs the whole screen; cleared by asking.
+    pub fn take_redraw(&self) -> bool {
+        self.redraw.swap(false, Ordering::Relaxed)
+    }
+
+    /// Lets every viewer go; an empty frame marks the end.
+    pub fn end(&self) {
+        self.ended.store(true, Ordering::Relaxed);
+        let _ = self.frames.send(Arc::from([]));
+    }
+}
+
+/// One viewer, counted until it is dropped.
+pub struct Viewer {
+    frames: broadcast::Receiver<Arc<[u8]>>,
+    viewers: Arc<AtomicUsize>,
+    redraw: Arc<AtomicBool>,
+}
+
+impl Viewer {
+    /// The next frame, or `None` once the session has ended.
+    pub async fn next(&mut self) -> Option<Arc<[u8]>> {
+        loop {
+            match self.frames.recv().await {
+                Ok(frame) if frame.is_empty() => return None,
+                Ok(frame) => return Some(frame),
+                // the skipped frames were diffs, so only a full one makes sense again
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    self.redraw.store(true, Ordering::Relaxed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+impl Drop for Viewer {
+    fn drop(&mut self) {
+        self.viewers.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_viewers_are_counted_while_attached() {
+        let stream = Stream::default();
+        let mut first = stream.watch().unwrap();
+        let second = stream.watch().unwrap();
+        assert_eq!(stream.viewers(), 2);
+        assert!(stream.take_redraw());
+        assert!(!stream.take_redraw());
+
+        stream.publish(b"frame");
+        assert_eq!(first.next().await.as_deref(), Some(&b"frame"[..]));
+        drop(second);
+        assert_eq!(stream.viewers(), 1);
+
+        stream.end();
+        assert_eq!(first.next().await, None);
+        assert!(stream.watch().is_none());
+        drop(first);
+        assert_eq!(stream.viewers(), 0);
+    }
+}
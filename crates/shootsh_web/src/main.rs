@@ -0,0 +1,134 @@
+mod session;
+
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use axum::Router;
+use axum::extract::{State, WebSocketUpgrade};
+use axum::response::{Html, IntoResponse};
+use axum::routing::get;
+use clap::Parser;
+use shootsh_core::db::{DbCache, DbRequest, IDLE_TICK_INTERVAL, Repository};
+use shootsh_core::signing::SigningKey;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tracing_subscriber::{EnvFilter, fmt, prelude::*};
+
+const INDEX_HTML: &str = include_str!("../static/index.html");
+/// Input from a browser is keystrokes and mouse reports; nothing legitimate
+/// comes close to this.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024;
+
+/// The game in a browser, for trying it before installing anything. Players
+/// are anonymous and their scores stay in this process's memory, apart from
+/// the SSH server's leaderboards.
+#[derive(Parser, Debug)]
+#[command(version, about = "shootsh in the browser")]
+struct Args {
+    /// Address to serve the page and its WebSocket on
+    #[arg(long, env = "WEB_LISTEN_ADDR", default_value = "127.0.0.1:8080")]
+    listen_addr: String,
+    /// SSH server the page points players to for ranked play, e.g. `shootsh.example.com`
+    #[arg(long, env = "SSH_HOST")]
+    ssh_host: Option<String>,
+    /// Browser sessions played at once
+    #[arg(long, env = "MAX_SESSIONS", default_value_t = 100)]
+    max_sessions: usize,
+}
+
+/// What every browser session shares.
+#[derive(Clone)]
+pub struct Shared {
+    db_tx: mpsc::Sender<DbRequest>,
+    cache: Arc<ArcSwap<DbCache>>,
+    signing_key: SigningKey,
+    sessions: Arc<AtomicUsize>,
+    max_sessions: usize,
+    index: Arc<str>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+    tracing_subscriber::registry()
+        .with(fmt::layer())
+        .with(EnvFilter::from_default_env().add_directive(tracing::Level::INFO.into()))
+        .init();
+
+    let signing_key = SigningKey::random();
+    // guests are deleted as they leave, so this only bounds concurrent ones
+    let repo =
+        Repository::in_memory(args.max_sessions as i64 * 2).with_signing_key(signing_key.clone());
+    let cache = Arc::new(ArcSwap::from_pointee(repo.get_current_cache()));
+    let (db_tx, db_rx) = mpsc::channel(100);
+    spawn_db_worker(repo, Arc::clone(&cache), db_rx);
+
+    let shared = Shared {
+        db_tx,
+        cache,
+        signing_key,
+        sessions: Arc::new(AtomicUsize::new(0)),
+        max_sessions: args.max_sessions,
+        index: index_page(args.ssh_host.as_deref()).into(),
+    };
+    let app = Router::new()
+        .route("/", get(index))
+        .route("/ws", get(websocket))
+        .with_state(shared);
+
+    let listener = TcpListener::bind(&args.listen_addr)
+        .await
+        .with_context(|| format!("Cannot listen on {}", args.listen_addr))?;
+    tracing::info!(addr = %args.listen_addr, "Serving shootsh_web");
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn index(State(shared): State<Shared>) -> Html<String> {
+    Html(shared.index.to_string())
+}
+
+async fn websocket(ws: WebSocketUpgrade, State(shared): State<Shared>) -> impl IntoResponse {
+    ws.max_message_size(MAX_MESSAGE_SIZE)
+        .on_upgrade(move |socket| session::run(socket, shared))
+}
+
+fn index_page(ssh_host: Option<&str>) -> String {
+    let ranked = match ssh_host {
+        Some(host) => format!(
+            "For the real leaderboards: <code>ssh {}</code>",
+            escape_html(host)
+        ),
+        None => String::new(),
+    };
+    INDEX_HTML.replace("{{RANKED}}", &ranked)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn spawn_db_worker(
+    repo: Repository,
+    cache: Arc<ArcSwap<DbCache>>,
+    mut rx: mpsc::Receiver<DbRequest>,
+) {
+    let runtime = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        loop {
+            let new_cache =
+                match runtime.block_on(tokio::time::timeout(IDLE_TICK_INTERVAL, rx.recv())) {
+                    Ok(Some(req)) => repo.handle_request(req),
+                    Ok(None) => break,
+                    Err(_) => repo.run_idle(),
+                };
+            if let Some(new_cache) = new_cache {
+                cache.store(Arc::new(new_cache));
+            }
+        }
+    });
+}
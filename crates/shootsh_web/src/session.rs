@@ -0,0 +1,201 @@
+use crate::Shared;
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use ratatui::layout::Rect;
+use ratatui::{Terminal, TerminalOptions, Viewport, backend::CrosstermBackend};
+use shootsh_core::db::{ClientKind, DbRequest, UserContext};
+use shootsh_core::remote::{
+    self, CLEANUP_SEQ, CURSOR_HIDE, InputTransformer, RemoteTerminal, SETUP_SEQ, SharedBuffer,
+};
+use shootsh_core::{Action, App, GameConfig, domain};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::MissedTickBehavior;
+
+const TICK_RATE: Duration = Duration::from_millis(33);
+/// Until the page reports the real size.
+const DEFAULT_SIZE: domain::Size = domain::Size {
+    width: 80,
+    height: 24,
+};
+/// Larger claims are clamped; the frame is drawn at this size at most.
+const MAX_SIZE: u16 = 500;
+
+/// One browser tab playing as an anonymous guest, from the WebSocket
+/// handshake until it closes. Binary messages carry terminal bytes both ways;
+/// the page sends `resize <cols> <rows>` as text.
+pub async fn run(socket: WebSocket, shared: Shared) {
+    let (mut sink, mut stream) = socket.split();
+    let Some(_slot) = Slot::take(&shared.sessions, shared.max_sessions) else {
+        let msg =
+            "\r\nToo many players in the browser right now. Please try again in a few minutes.\r\n";
+        let _ = sink
+            .send(Message::Binary(msg.as_bytes().to_vec().into()))
+            .await;
+        return;
+    };
+    let Some(user) = create_guest(&shared).await else {
+        return;
+    };
+    let user_id = user.id;
+    tracing::info!(user_id, "Browser session started");
+
+    let mut app = App::new(
+        user,
+        shared.db_tx.clone(),
+        shared.cache.load_full(),
+        GameConfig::default(),
+    );
+    app.client = ClientKind::Web;
+    app.signing_key = Some(shared.signing_key.clone());
+    app.screen_size = DEFAULT_SIZE;
+    app.suggest_name(&format!("guest{user_id}"));
+
+    let output = SharedBuffer::default();
+    let mut terminal: Option<RemoteTerminal> = None;
+    let mut input = InputTransformer::new();
+    // answers to requests like a name change, applied between frames
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel();
+    let mut interval = tokio::time::interval(TICK_RATE);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    if sink
+        .send(Message::Binary(SETUP_SEQ.to_vec().into()))
+        .await
+        .is_ok()
+    {
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                Some(result) = reply_rx.recv() => app.complete_request(result),
+                msg = stream.next() => match msg {
+                    Some(Ok(Message::Binary(data))) => {
+                        for action in actions(&mut app, &mut input, &data) {
+                            let (res, rx) = app.update_state(action);
+                            if let Some(rx) = rx.filter(|_| res.is_ok()) {
+                                let reply_tx = reply_tx.clone();
+                                tokio::spawn(async move {
+                                    if let Ok(result) = rx.await {
+                                        let _ = reply_tx.send(result);
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Text(text))) => {
+                        if let Some(size) = parse_resize(text.as_str()) {
+                            app.screen_size = size;
+                        }
+                    }
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+            if app.should_quit {
+                break;
+            }
+
+            app.set_db_cache(shared.cache.load_full());
+            app.update_state(Action::Tick).0.ok();
+            let size = app.screen_size;
+            let area = Rect::new(0, 0, size.width, size.height);
+            let t = terminal.get_or_insert_with(|| {
+                Terminal::with_options(
+                    CrosstermBackend::new(output.clone()),
+                    TerminalOptions {
+                        viewport: Viewport::Fixed(area),
+                    },
+                )
+                .expect("Failed to create terminal")
+            });
+            if t.size().unwrap() != area.into() {
+                t.resize(area).ok();
+            }
+            let frame = remote::render_frame(&app, t, &output);
+            if frame == CURSOR_HIDE {
+                continue;
+            }
+            if sink.send(Message::Binary(frame.into())).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    let _ = sink
+        .send(Message::Binary(CLEANUP_SEQ.to_vec().into()))
+        .await;
+    let _ = sink.close().await;
+    // guests leave nothing behind
+    let (reply_tx, _) = oneshot::channel();
+    let _ = shared
+        .db_tx
+        .send(DbRequest::DeleteUser { user_id, reply_tx })
+        .await;
+    tracing::info!(user_id, "Browser session finished");
+}
+
+fn actions(app: &mut App, input: &mut InputTransformer, data: &[u8]) -> Vec<Action> {
+    let captured = app.input_captured();
+    input
+        .handle_input(data)
+        .into_iter()
+        .filter_map(|(event, prev_buttons)| {
+            remote::map_input_to_action(event, captured, &prev_buttons)
+        })
+        .collect()
+}
+
+async fn create_guest(shared: &Shared) -> Option<UserContext> {
+    let (reply_tx, rx) = oneshot::channel();
+    let req = DbRequest::GetOrCreateUser {
+        fingerprint: format!("web:{:016x}", rand::random::<u64>()),
+        region: None,
+        reply_tx,
+    };
+    shared.db_tx.send(req).await.ok()?;
+    let user = tokio::time::timeout(Duration::from_secs(2), rx).await;
+    if user.is_err() {
+        tracing::warn!("Guest account not created in time");
+    }
+    user.ok()?.ok()
+}
+
+fn parse_resize(text: &str) -> Option<domain::Size> {
+    let mut parts = text.strip_prefix("resize ")?.split(' ');
+    let mut dimension =
+        || -> Option<u16> { Some(parts.next()?.parse::<u16>().ok()?.min(MAX_SIZE)) };
+    let (width, height) = (dimension()?, dimension()?);
+    (width > 0 && height > 0).then_some(domain::Size { width, height })
+}
+
+/// A counted place among the concurrent sessions, given back on drop.
+struct Slot<'a>(&'a AtomicUsize);
+
+impl<'a> Slot<'a> {
+    fn take(sessions: &'a AtomicUsize, max: usize) -> Option<Self> {
+        let taken = sessions.fetch_add(1, Ordering::Relaxed);
+        let slot = Self(sessions);
+        (taken < max).then_some(slot)
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resize() {
+        let size = parse_resize("resize 120 40").unwrap();
+        assert_eq!((size.width, size.height), (120, 40));
+        assert_eq!(parse_resize("resize 9999 0"), None);
+        assert_eq!(parse_resize("resize 80"), None);
+        assert_eq!(parse_resize("hello"), None);
+    }
+}